@@ -1,15 +1,144 @@
 use async_trait::async_trait;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::ai::references::verify_code_ref;
 use crate::error::LlmError;
+use crate::files::{RepoTree, TREE_MAX_CHARS};
 use crate::llm::{Message, ModelClient};
-use crate::types::{CodeRef, Criterion, CriterionResult, GradeTask};
+use crate::profile::RepoProfile;
+use crate::prompts::{
+    PromptRegistry, GRADER_CONTEXT_PROMPT, GRADER_CRITERION_PROMPT, GRADER_SYSTEM_PROMPT,
+};
+use crate::test_runner::TestRunResult;
+use crate::types::{
+    sort_diagnostics, CheckType, CodeRef, Criterion, CriterionOutcome, CriterionResult, Diagnostic,
+    EvidenceMode, GradeTask, Severity,
+};
+
+/// Evidence language when the request doesn't specify one, matching the
+/// Korean-only reviewer prompts
+pub const DEFAULT_LANGUAGE: &str = "ko";
+
+/// Path prefix of request-provided context files among `GradeContext::files`
+pub const PROVIDED_FILE_PREFIX: &str = "[PROVIDED] ";
+
+/// `files` with the request's context files (spec, starter template) placed
+/// first under `PROVIDED_FILE_PREFIX`, so they survive the file limit and
+/// the grader can tell them from student code
+pub fn merge_provided_files(
+    provided: &[(String, String)],
+    files: Vec<(String, String)>,
+) -> Vec<(String, String)> {
+    if provided.is_empty() {
+        return files;
+    }
+    provided
+        .iter()
+        .map(|(path, content)| (format!("{}{}", PROVIDED_FILE_PREFIX, path), content.clone()))
+        .chain(files)
+        .collect()
+}
+
+fn is_provided_file(path: &str) -> bool {
+    path.starts_with(PROVIDED_FILE_PREFIX)
+}
+
+/// Diagnostics listed individually in grader prompts
+pub const DIAGNOSTICS_TOP_ITEMS: usize = 20;
+
+/// Rule-based checker findings on a graded repository, condensed for the
+/// grader prompt
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticsSummary {
+    /// Checkers that ran, including those that found nothing
+    pub checks: Vec<CheckType>,
+    /// `(check, rule, severity, count)`, most severe then most frequent first
+    pub counts: Vec<(CheckType, String, Severity, usize)>,
+    /// Most severe diagnostics, at most `DIAGNOSTICS_TOP_ITEMS`
+    pub top: Vec<(CheckType, Diagnostic)>,
+    pub total: usize,
+}
+
+impl DiagnosticsSummary {
+    pub fn new(results: Vec<(CheckType, Vec<Diagnostic>)>) -> Self {
+        let checks = results.iter().map(|(check_type, _)| *check_type).collect();
+        let mut counts: Vec<(CheckType, String, Severity, usize)> = Vec::new();
+        let mut top = Vec::new();
+        for (check_type, mut diagnostics) in results {
+            for diagnostic in &diagnostics {
+                match counts.iter_mut().find(|(c, rule, severity, _)| {
+                    *c == check_type && *rule == diagnostic.rule && *severity == diagnostic.severity
+                }) {
+                    Some((.., count)) => *count += 1,
+                    None => counts.push((check_type, diagnostic.rule.clone(), diagnostic.severity, 1)),
+                }
+            }
+            sort_diagnostics(&mut diagnostics);
+            top.extend(diagnostics.into_iter().take(DIAGNOSTICS_TOP_ITEMS).map(|d| (check_type, d)));
+        }
+        counts.sort_by_key(|(_, _, severity, count)| (severity.rank(), std::cmp::Reverse(*count)));
+        // Stable, so checkers keep their run order within a severity
+        top.sort_by_key(|(_, diagnostic)| diagnostic.severity.rank());
+        top.truncate(DIAGNOSTICS_TOP_ITEMS);
+        let total = counts.iter().map(|(.., count)| count).sum();
+        Self {
+            checks,
+            counts,
+            top,
+            total,
+        }
+    }
+
+    /// Prompt section with the counts per rule and the top items
+    pub fn render(&self) -> String {
+        let checks = self.checks.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", ");
+        if self.total == 0 {
+            return format!("Checkers run: {}. They found no issues.", checks);
+        }
+        let mut lines = vec![format!("Checkers run: {}. {} issues found:", checks, self.total)];
+        lines.extend(self.counts.iter().map(|(check_type, rule, severity, count)| {
+            format!("- {}/{} ({}): {}", check_type.as_str(), rule, severity.as_str(), count)
+        }));
+        lines.push(String::new());
+        lines.push(if self.top.len() < self.total {
+            format!("Most severe {} of {}:", self.top.len(), self.total)
+        } else {
+            "All issues:".to_string()
+        });
+        lines.extend(self.top.iter().map(|(check_type, d)| {
+            format!(
+                "- {}:{}:{} {} [{}/{}] {}",
+                d.file,
+                d.line,
+                d.column,
+                d.severity.as_str(),
+                check_type.as_str(),
+                d.rule,
+                d.message
+            )
+        }));
+        lines.join("\n")
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct GradeContext {
     pub repo_url: String,
     pub task: GradeTask,
-    pub files: Vec<(String, String)>,
+    /// Source files, shared between the contexts of all tasks of a grade
+    pub files: Arc<Vec<(String, String)>>,
+    /// Every collected path, including files left out of `files`
+    pub tree: Arc<RepoTree>,
+    /// BCP-47 tag for the language evidence is written in
+    pub language: String,
+    /// Languages detected in the submitted repository
+    pub profile: RepoProfile,
+    /// Results of running the repository's tests, for tasks with `run_tests`
+    pub test_run: Option<TestRunResult>,
+    /// Rule-based checker findings, with `run_checkers`
+    pub diagnostics: Option<Arc<DiagnosticsSummary>>,
 }
 
 impl GradeContext {
@@ -17,36 +146,140 @@ impl GradeContext {
         Self {
             repo_url,
             task,
-            files: Vec::new(),
+            files: Arc::default(),
+            tree: Arc::default(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            profile: RepoProfile::default(),
+            test_run: None,
+            diagnostics: None,
         }
     }
 
-    pub fn with_files(mut self, files: Vec<(String, String)>) -> Self {
-        self.files = files;
+    pub fn with_files(mut self, files: impl Into<Arc<Vec<(String, String)>>>) -> Self {
+        self.files = files.into();
+        self
+    }
+
+    pub fn with_tree(mut self, tree: impl Into<Arc<RepoTree>>) -> Self {
+        self.tree = tree.into();
+        self
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = language.into();
+        self
+    }
+
+    pub fn with_profile(mut self, profile: RepoProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_test_run(mut self, test_run: Option<TestRunResult>) -> Self {
+        self.test_run = test_run;
         self
     }
 
-    pub fn code_summary(&self, max_files: usize, max_chars_per_file: usize) -> String {
-        self.files
+    pub fn with_diagnostics(mut self, diagnostics: Option<Arc<DiagnosticsSummary>>) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Repository tree with the first `shown` files marked
+    pub fn tree_listing(&self, shown: usize) -> String {
+        let included = self.files.iter().take(shown).map(|(path, _)| path.as_str());
+        self.tree.render(included, TREE_MAX_CHARS)
+    }
+
+    /// The first `max_files` files, each truncated to `max_chars_per_file`,
+    /// until their total would exceed `max_total_chars`. Also returns how
+    /// many of the `max_files` were left out by the total cap.
+    fn shown_files(
+        &self,
+        max_files: usize,
+        max_chars_per_file: usize,
+        max_total_chars: usize,
+    ) -> (Vec<(&str, String)>, usize) {
+        let candidates = self.files.len().min(max_files);
+        let mut shown = Vec::new();
+        let mut total_chars = 0;
+        for (path, content) in self.files.iter().take(max_files) {
+            let truncated = if content.len() > max_chars_per_file {
+                format!(
+                    "{}...\n[truncated, {} more chars]",
+                    &content[..max_chars_per_file],
+                    content.len() - max_chars_per_file
+                )
+            } else {
+                content.clone()
+            };
+            total_chars += truncated.chars().count();
+            if total_chars > max_total_chars {
+                break;
+            }
+            shown.push((path.as_str(), truncated));
+        }
+        let omitted = candidates - shown.len();
+        (shown, omitted)
+    }
+
+    /// Number of files `code_summary` shows with the same limits
+    pub fn shown_file_count(&self, max_files: usize, max_chars_per_file: usize, max_total_chars: usize) -> usize {
+        self.shown_files(max_files, max_chars_per_file, max_total_chars).0.len()
+    }
+
+    pub fn code_summary(&self, max_files: usize, max_chars_per_file: usize, max_total_chars: usize) -> String {
+        let (shown, omitted) = self.shown_files(max_files, max_chars_per_file, max_total_chars);
+        let mut files = shown
             .iter()
-            .take(max_files)
-            .map(|(path, content)| {
-                let truncated = if content.len() > max_chars_per_file {
-                    format!(
-                        "{}...\n[truncated, {} more chars]",
-                        &content[..max_chars_per_file],
-                        content.len() - max_chars_per_file
-                    )
-                } else {
-                    content.clone()
-                };
-                format!("=== {} ===\n{}", path, truncated)
-            })
+            .map(|(path, content)| format!("=== {} ===\n{}", path, content))
             .collect::<Vec<_>>()
-            .join("\n\n")
+            .join("\n\n");
+        if omitted > 0 {
+            files = format!("{}\n\n[{} additional files not shown due to context limits]", files, omitted);
+        }
+
+        let included = shown.iter().map(|(path, _)| *path);
+        let provided = included.clone().filter(|path| is_provided_file(path)).count();
+        if provided > 0 {
+            files = format!(
+                "Files marked {} are assignment materials supplied with the task, \
+                 not part of the submission; compare the student code against them.\n\n{}",
+                PROVIDED_FILE_PREFIX.trim_end(),
+                files
+            );
+        }
+
+        if self.tree.is_empty() {
+            return files;
+        }
+        format!(
+            "{} of {} files in the repository tree are included in full (marked *); \
+             the rest are listed only.\n\n{}",
+            included.count() - provided,
+            self.tree.len(),
+            files
+        )
     }
 }
 
+/// English name for common BCP-47 tags, falling back to the tag itself
+fn language_name(tag: &str) -> String {
+    let primary = tag.split(['-', '_']).next().unwrap_or(tag).to_lowercase();
+    let name = match primary.as_str() {
+        "ko" => "Korean",
+        "en" => "English",
+        "ja" => "Japanese",
+        "zh" => "Chinese",
+        "es" => "Spanish",
+        "fr" => "French",
+        "de" => "German",
+        "vi" => "Vietnamese",
+        _ => return format!("the language with BCP-47 tag '{}'", tag),
+    };
+    name.to_string()
+}
+
 #[async_trait]
 pub trait Grader: Send + Sync {
     async fn check_criterion(
@@ -62,22 +295,39 @@ pub trait Grader: Send + Sync {
 pub struct CriteriaChecker {
     max_files: usize,
     max_chars_per_file: usize,
+    max_total_chars: usize,
+    evidence_mode: EvidenceMode,
+    prompts: Arc<PromptRegistry>,
 }
 
 impl CriteriaChecker {
     pub fn new() -> Self {
-        Self {
-            max_files: 20,
-            max_chars_per_file: 4000,
-        }
+        Self::with_limits(20, 4000, 80_000)
     }
 
-    pub fn with_limits(max_files: usize, max_chars_per_file: usize) -> Self {
+    pub fn with_limits(max_files: usize, max_chars_per_file: usize, max_total_chars: usize) -> Self {
         Self {
             max_files,
             max_chars_per_file,
+            max_total_chars,
+            evidence_mode: EvidenceMode::default(),
+            prompts: Arc::default(),
         }
     }
+
+    pub fn with_evidence_mode(mut self, evidence_mode: EvidenceMode) -> Self {
+        self.evidence_mode = evidence_mode;
+        self
+    }
+
+    pub fn with_prompts(mut self, prompts: Arc<PromptRegistry>) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    fn system_prompt(&self) -> &str {
+        &self.prompts.get(GRADER_SYSTEM_PROMPT).template
+    }
 }
 
 impl Default for CriteriaChecker {
@@ -86,37 +336,16 @@ impl Default for CriteriaChecker {
     }
 }
 
-const GRADER_SYSTEM_PROMPT: &str = r#"You are a code grader evaluating student submissions against acceptance criteria.
-
-## Your Role
-Determine if the submitted code satisfies a specific acceptance criterion.
-
-## Evaluation Guidelines
-1. Be Fair: Give credit for working implementations, even if imperfect
-2. Be Thorough: Check for actual implementation, not just presence of code
-3. Be Specific: Cite exact file and line numbers as evidence
-4. Consider Intent: Partial implementations may still satisfy criteria
-
-## Scoring Rules
-- passed: true - Criterion is clearly satisfied
-- passed: false - Criterion is NOT satisfied or insufficient evidence
-- confidence: Your certainty (0.0 = guess, 1.0 = certain)
-
-## Response Format
-Respond ONLY with valid JSON (no markdown, no explanation):
-{
-    "passed": true|false,
-    "confidence": 0.0-1.0,
-    "evidence": "Detailed explanation with code references",
-    "code_references": [
-        {"file": "path/to/file", "line_start": 10, "line_end": 20, "snippet": "optional"}
-    ]
-}"#;
-
 #[derive(Debug, Deserialize)]
 struct GraderResponse {
-    passed: bool,
+    #[serde(default)]
+    outcome: Option<CriterionOutcome>,
+    /// Older response format
+    #[serde(default)]
+    passed: Option<bool>,
     confidence: f32,
+    /// Left out or empty with `EvidenceMode::None`
+    #[serde(default)]
     evidence: String,
     #[serde(default)]
     code_references: Vec<RawCodeRef>,
@@ -139,30 +368,12 @@ impl Grader for CriteriaChecker {
         context: &GradeContext,
         criterion: &Criterion,
     ) -> Result<CriterionResult, LlmError> {
-        let code_summary = context.code_summary(self.max_files, self.max_chars_per_file);
-
-        let prompt = format!(
-            r#"## Task
-{task_title}
-{task_desc}
-
-## Acceptance Criterion to Check
-{criterion}
-
-## Submitted Code
-{code}
+        let (shared, prompt) = self.build_prompt(context, criterion);
 
-Evaluate if this criterion is satisfied. Return JSON only."#,
-            task_title = context.task.title,
-            task_desc = context.task.description.as_deref().unwrap_or(""),
-            criterion = criterion.description,
-            code = code_summary
-        );
-
-        let messages = vec![Message::user(prompt)];
-        let response = client.chat(&messages, Some(GRADER_SYSTEM_PROMPT)).await?;
+        let messages = vec![Message::user_with_cached_prefix(shared, &prompt)];
+        let response = client.chat(&messages, Some(self.system_prompt())).await?;
 
-        self.parse_response(&response, criterion)
+        self.parse_response(&response, criterion, context)
     }
 
     fn name(&self) -> &'static str {
@@ -171,10 +382,84 @@ Evaluate if this criterion is satisfied. Return JSON only."#,
 }
 
 impl CriteriaChecker {
+    /// Total prompt size (system + user message) sent for a criterion check
+    pub fn prompt_chars(&self, context: &GradeContext, criterion: &Criterion) -> usize {
+        let (shared, prompt) = self.build_prompt(context, criterion);
+        self.system_prompt().chars().count() + shared.chars().count() + prompt.chars().count()
+    }
+
+    /// The repository context shared by every criterion of a grade, and the
+    /// task and criterion text that follows it
+    fn build_prompt(&self, context: &GradeContext, criterion: &Criterion) -> (String, String) {
+        let code_summary =
+            context.code_summary(self.max_files, self.max_chars_per_file, self.max_total_chars);
+        let tree = if context.tree.is_empty() {
+            String::new()
+        } else {
+            let shown =
+                context.shown_file_count(self.max_files, self.max_chars_per_file, self.max_total_chars);
+            format!("## Repository Tree\n{}\n", context.tree_listing(shown))
+        };
+        let examples = if criterion.examples.is_empty() {
+            String::new()
+        } else {
+            let blocks = criterion
+                .examples
+                .iter()
+                .map(|example| format!("```\n{}\n```", example))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                "\nExample implementations that satisfy this criterion:\n{}\n",
+                blocks
+            )
+        };
+        let diagnostics = match &context.diagnostics {
+            Some(summary) => format!("## Checker Diagnostics\n{}\n\n", summary.render()),
+            None => String::new(),
+        };
+        let tests = match &context.test_run {
+            Some(run) => format!(
+                "\n## Test Results\n{}\n```\n{}\n```\n",
+                run.summary(),
+                run.output.trim_end()
+            ),
+            None => String::new(),
+        };
+
+        let stack = context.profile.describe();
+        let language = language_name(&context.language);
+        let evidence = match self.evidence_mode {
+            EvidenceMode::None => "Set evidence to an empty string.".to_string(),
+            EvidenceMode::Brief => format!("Write evidence as one short sentence in {}.", language),
+            EvidenceMode::Detailed => format!("Write all evidence in {}.", language),
+        };
+        let shared_vars = HashMap::from([
+            ("stack", stack.as_str()),
+            ("tree", tree.as_str()),
+            ("diagnostics", diagnostics.as_str()),
+            ("code", code_summary.as_str()),
+        ]);
+        let vars = HashMap::from([
+            ("task_title", context.task.title.as_str()),
+            ("task_description", context.task.description.as_deref().unwrap_or("")),
+            ("criterion", criterion.description.as_str()),
+            ("examples", examples.as_str()),
+            ("tests", tests.as_str()),
+            ("language", language.as_str()),
+            ("evidence", evidence.as_str()),
+        ]);
+        (
+            self.prompts.get(GRADER_CONTEXT_PROMPT).render(&shared_vars),
+            self.prompts.get(GRADER_CRITERION_PROMPT).render(&vars),
+        )
+    }
+
     fn parse_response(
         &self,
         response: &str,
         criterion: &Criterion,
+        context: &GradeContext,
     ) -> Result<CriterionResult, LlmError> {
         let json_str = self.extract_json(response);
 
@@ -183,22 +468,46 @@ impl CriteriaChecker {
             LlmError::InvalidResponse(format!("JSON parse error: {}", e))
         })?;
 
+        let outcome = match (raw.outcome, raw.passed) {
+            (Some(outcome), _) => outcome,
+            (None, Some(passed)) => CriterionOutcome::from_passed(passed),
+            (None, None) => {
+                return Err(LlmError::InvalidResponse(
+                    "Grader response has neither outcome nor passed".to_string(),
+                ))
+            }
+        };
+
         Ok(CriterionResult {
             criterion: criterion.description.clone(),
-            passed: raw.passed,
+            criterion_id: criterion.id.clone(),
+            task_index: 0,
+            passed: outcome == CriterionOutcome::Passed,
+            outcome,
             confidence: raw.confidence.clamp(0.0, 1.0),
-            evidence: raw.evidence,
+            evidence: match (&context.test_run, self.evidence_mode) {
+                (_, EvidenceMode::None) => String::new(),
+                (Some(run), _) => format!("{}\n\n[Tests] {}", raw.evidence, run.summary()),
+                (None, _) => raw.evidence,
+            },
             code_references: raw
                 .code_references
                 .into_iter()
-                .map(|r| CodeRef {
-                    file: r.file,
-                    line_start: r.line_start,
-                    line_end: r.line_end,
-                    snippet: r.snippet,
+                .map(|r| {
+                    verify_code_ref(
+                        &context.files,
+                        CodeRef {
+                            file: r.file,
+                            line_start: r.line_start,
+                            line_end: r.line_end,
+                            snippet: r.snippet,
+                            verified: false,
+                        },
+                    )
                 })
                 .collect(),
             weight: criterion.weight,
+            human_override: None,
         })
     }
 
@@ -226,6 +535,23 @@ impl CriteriaChecker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct CapturingClient {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ModelClient for CapturingClient {
+        async fn chat(&self, messages: &[Message], _system: Option<&str>) -> Result<String, LlmError> {
+            self.prompts
+                .lock()
+                .unwrap()
+                .extend(messages.iter().map(|m| m.content.clone()));
+            Ok(r#"{"passed": true, "confidence": 0.8, "evidence": "ok"}"#.to_string())
+        }
+    }
 
     #[test]
     fn test_extract_json_raw() {
@@ -246,6 +572,363 @@ mod tests {
         assert!(result.contains("\"passed\": true"));
     }
 
+    #[test]
+    fn test_parse_response_verifies_references() {
+        let checker = CriteriaChecker::new();
+        let task = GradeTask {
+            title: "Test".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let ctx = GradeContext::new("https://example.com".to_string(), task)
+            .with_files(vec![("src/app.js".to_string(), "a\nb\nc\n".to_string())]);
+        let criterion = Criterion {
+            id: None,
+            description: "App exists".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+        };
+        let response = r#"{"passed": true, "confidence": 0.9, "evidence": "ok", "code_references": [
+            {"file": "app.js", "line_start": 2, "line_end": 50},
+            {"file": "src/imaginary.js", "line_start": 1, "line_end": 2}
+        ]}"#;
+
+        let result = checker.parse_response(response, &criterion, &ctx).unwrap();
+        let refs = &result.code_references;
+        assert_eq!(refs.len(), 2);
+        assert!(refs[0].verified);
+        assert_eq!(refs[0].file, "src/app.js");
+        assert_eq!((refs[0].line_start, refs[0].line_end), (2, 3));
+        assert_eq!(refs[0].snippet.as_deref(), Some("b\nc"));
+        assert!(!refs[1].verified);
+    }
+
+    #[test]
+    fn test_parse_response_outcome() {
+        let checker = CriteriaChecker::new();
+        let task = GradeTask {
+            title: "Test".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let ctx = GradeContext::new("https://example.com".to_string(), task);
+        let criterion = Criterion {
+            id: None,
+            description: "The deployed app responds within 200ms".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+        };
+        let parse = |response: &str| checker.parse_response(response, &criterion, &ctx);
+
+        let result =
+            parse(r#"{"outcome": "not_verifiable", "confidence": 0.9, "evidence": "runtime"}"#).unwrap();
+        assert_eq!(result.outcome, CriterionOutcome::NotVerifiable);
+        assert!(!result.passed);
+
+        let legacy = parse(r#"{"passed": true, "confidence": 0.9, "evidence": "ok"}"#).unwrap();
+        assert_eq!(legacy.outcome, CriterionOutcome::Passed);
+        assert!(legacy.passed);
+
+        assert!(parse(r#"{"confidence": 0.9, "evidence": "?"}"#).is_err());
+    }
+
+    #[test]
+    fn test_prompt_language_instruction() {
+        let checker = CriteriaChecker::new();
+        let task = GradeTask {
+            title: "Test".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let criterion = Criterion {
+            id: None,
+            description: "c".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+        };
+        let ctx = GradeContext::new("https://example.com".to_string(), task);
+
+        let (_, prompt) = checker.build_prompt(&ctx, &criterion);
+        assert!(prompt.contains("Write all evidence in Korean."));
+
+        let (_, prompt) = checker.build_prompt(&ctx.clone().with_language("en-US"), &criterion);
+        assert!(prompt.contains("Write all evidence in English."));
+
+        assert_eq!(language_name("tlh"), "the language with BCP-47 tag 'tlh'");
+
+        let brief = CriteriaChecker::new().with_evidence_mode(EvidenceMode::Brief);
+        assert!(brief
+            .build_prompt(&ctx, &criterion)
+            .1
+            .contains("Write evidence as one short sentence in Korean."));
+
+        let none = CriteriaChecker::new().with_evidence_mode(EvidenceMode::None);
+        assert!(none.build_prompt(&ctx, &criterion).1.contains("Set evidence to an empty string."));
+        let result = none
+            .parse_response(r#"{"outcome": "passed", "confidence": 0.9}"#, &criterion, &ctx)
+            .unwrap();
+        assert!(result.evidence.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prompt_includes_examples() {
+        let example = "const hash = await bcrypt.hash(password, 10);";
+        let task = GradeTask {
+            title: "Authentication".to_string(),
+            description: None,
+            acceptance_criteria: vec![Criterion {
+                id: Some("hash".to_string()),
+                description: "Passwords are hashed before storage".to_string(),
+                weight: 1.0,
+                examples: vec![example.to_string()],
+                model_hint: None,
+                rubric_category: None,
+            }],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let ctx = GradeContext::new("https://example.com".to_string(), task.clone());
+        let client = CapturingClient::default();
+
+        let result = CriteriaChecker::new()
+            .check_criterion(&client, &ctx, &task.acceptance_criteria[0])
+            .await
+            .unwrap();
+
+        assert!(result.passed);
+        let prompt = client.prompts.lock().unwrap().join("\n");
+        assert!(prompt.contains("Example implementations that satisfy this criterion:"));
+        assert!(prompt.contains(&format!("```\n{}\n```", example)));
+
+        let plain = Criterion {
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+            ..task.acceptance_criteria[0].clone()
+        };
+        assert!(!CriteriaChecker::new()
+            .build_prompt(&ctx, &plain)
+            .1
+            .contains("Example implementations"));
+    }
+
+    #[tokio::test]
+    async fn test_test_results_in_prompt_and_evidence() {
+        use crate::test_runner::TestFramework;
+
+        let task = GradeTask {
+            title: "Tests".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: true,
+        };
+        let criterion = Criterion {
+            id: None,
+            description: "All tests pass".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+        };
+        let ctx = GradeContext::new("https://example.com".to_string(), task).with_test_run(Some(
+            TestRunResult {
+                framework: TestFramework::Cargo,
+                command: "cargo test".to_string(),
+                success: false,
+                timed_out: false,
+                passed: Some(3),
+                failed: Some(1),
+                duration_ms: 1200,
+                output: "test parse_empty ... FAILED".to_string(),
+            },
+        ));
+        let client = CapturingClient::default();
+
+        let result = CriteriaChecker::new()
+            .check_criterion(&client, &ctx, &criterion)
+            .await
+            .unwrap();
+
+        let prompt = client.prompts.lock().unwrap().join("\n");
+        assert!(prompt.contains("## Test Results\n`cargo test`: 3 passed, 1 failed"));
+        assert!(prompt.contains("test parse_empty ... FAILED"));
+        assert!(result.evidence.ends_with("[Tests] `cargo test`: 3 passed, 1 failed"));
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_in_prompt() {
+        let diagnostic = |file: &str, line: u32, rule: &str, severity: Severity| Diagnostic {
+            file: file.to_string(),
+            line,
+            column: 1,
+            message: format!("{} violated", rule),
+            rule: rule.to_string(),
+            severity,
+            suggestion: None,
+        };
+        let mut lint: Vec<_> = (1..=30)
+            .map(|line| diagnostic("src/app.js", line, "no-unused-vars", Severity::Warning))
+            .collect();
+        lint.push(diagnostic("src/app.js", 40, "no-undef", Severity::Error));
+        let summary = DiagnosticsSummary::new(vec![
+            (CheckType::Lint, lint),
+            (CheckType::Format, vec![diagnostic("src/b.js", 2, "trailing-whitespace", Severity::Info)]),
+            (CheckType::Typos, vec![]),
+        ]);
+
+        assert_eq!(summary.total, 32);
+        assert_eq!(summary.top.len(), DIAGNOSTICS_TOP_ITEMS);
+        assert_eq!(summary.top[0].1.rule, "no-undef");
+        let rendered = summary.render();
+        assert!(rendered.starts_with(
+            "Checkers run: lint, format, typos. 32 issues found:\n\
+             - lint/no-undef (error): 1\n\
+             - lint/no-unused-vars (warning): 30\n\
+             - format/trailing-whitespace (info): 1\n\n\
+             Most severe 20 of 32:\n\
+             - src/app.js:40:1 error [lint/no-undef] no-undef violated\n"
+        ));
+
+        let task = GradeTask {
+            title: "Code quality".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let criterion = Criterion {
+            id: None,
+            description: "Code has no lint errors".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+        };
+        let ctx = GradeContext::new("https://example.com".to_string(), task)
+            .with_diagnostics(Some(Arc::new(summary)));
+        let client = CapturingClient::default();
+        CriteriaChecker::new()
+            .check_criterion(&client, &ctx, &criterion)
+            .await
+            .unwrap();
+
+        let prompt = client.prompts.lock().unwrap().join("\n");
+        assert!(prompt.contains("## Checker Diagnostics\nCheckers run: lint, format, typos."));
+        assert!(prompt.contains("[lint/no-undef] no-undef violated"));
+
+        let clean = DiagnosticsSummary::new(vec![(CheckType::Lint, vec![])]);
+        assert_eq!(clean.render(), "Checkers run: lint. They found no issues.");
+        let (shared, _) = CriteriaChecker::new().build_prompt(&ctx.with_diagnostics(None), &criterion);
+        assert!(!shared.contains("Checker Diagnostics"));
+    }
+
+    #[test]
+    fn test_prompt_states_stack() {
+        let checker = CriteriaChecker::new();
+        let task = GradeTask {
+            title: "t".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let criterion = Criterion {
+            id: None,
+            description: "c".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+        };
+        let profile = RepoProfile::from_paths([std::path::Path::new("app.py")]);
+        let ctx = GradeContext::new("https://example.com".to_string(), task).with_profile(profile);
+
+        let (shared, prompt) = checker.build_prompt(&ctx, &criterion);
+        assert!(shared.contains("Primary language: Python"));
+
+        let other = Criterion {
+            description: "d".to_string(),
+            ..criterion
+        };
+        let (other_shared, other_prompt) = checker.build_prompt(&ctx, &other);
+        assert_eq!(shared, other_shared);
+        assert_ne!(prompt, other_prompt);
+
+        let message = Message::user_with_cached_prefix(shared.clone(), &prompt);
+        assert_eq!(message.cache_breakpoint, Some(shared.len()));
+        assert_eq!(message.content, format!("{}{}", shared, prompt));
+    }
+
+    #[test]
+    fn test_prompt_lists_repository_tree() {
+        use crate::files::TreeEntry;
+
+        let task = GradeTask {
+            title: "Testing".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let entry = |path: &str, lines| TreeEntry {
+            path: path.to_string(),
+            lines,
+        };
+        let tree = RepoTree {
+            entries: vec![
+                entry("src/app.ts", 40),
+                entry("src/util.ts", 12),
+                entry("tests/app.test.ts", 25),
+            ],
+            omitted: 0,
+        };
+        let ctx = GradeContext::new("https://example.com".to_string(), task)
+            .with_files(vec![
+                ("src/app.ts".to_string(), "export const app = 1;".to_string()),
+                ("src/util.ts".to_string(), "export const util = 2;".to_string()),
+            ])
+            .with_tree(tree);
+        let criterion = Criterion {
+            id: None,
+            description: "Project has a tests directory".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+        };
+
+        let (prompt, _) = CriteriaChecker::with_limits(1, 100, 1000).build_prompt(&ctx, &criterion);
+
+        let expected = "## Repository Tree\n\
+                        src/\n\
+                        \x20 app.ts (40 lines) *\n\
+                        \x20 util.ts (12 lines)\n\
+                        tests/\n\
+                        \x20 app.test.ts (25 lines)\n\
+                        \n\
+                        ## Submitted Code\n\
+                        1 of 3 files in the repository tree are included in full (marked *); \
+                        the rest are listed only.\n\
+                        \n\
+                        === src/app.ts ===\n\
+                        export const app = 1;\n";
+        assert!(prompt.contains(expected), "{}", prompt);
+        assert!(!prompt.contains("export const util"));
+    }
+
     #[test]
     fn test_code_summary_truncation() {
         let task = GradeTask {
@@ -253,6 +936,7 @@ mod tests {
             description: None,
             acceptance_criteria: vec![],
             estimated_minutes: None,
+            run_tests: false,
         };
         let ctx = GradeContext::new("https://example.com".to_string(), task)
             .with_files(vec![
@@ -260,8 +944,73 @@ mod tests {
                 ("file2.rs".to_string(), "short".to_string()),
             ]);
 
-        let summary = ctx.code_summary(2, 100);
+        let summary = ctx.code_summary(2, 100, 1000);
         assert!(summary.contains("[truncated"));
         assert!(summary.contains("file2.rs"));
+        assert!(!summary.contains("[PROVIDED]"));
+    }
+
+    #[test]
+    fn test_code_summary_provided_files() {
+        let task = GradeTask {
+            title: "Test".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let files = merge_provided_files(
+            &[("spec.md".to_string(), "# Spec\nBuild a counter".to_string())],
+            vec![("src/main.rs".to_string(), "fn main() {}".to_string())],
+        );
+        let tree = RepoTree {
+            entries: vec![crate::files::TreeEntry {
+                path: "src/main.rs".to_string(),
+                lines: 1,
+            }],
+            omitted: 0,
+        };
+        let ctx = GradeContext::new("https://example.com".to_string(), task)
+            .with_files(files)
+            .with_tree(tree);
+
+        let summary = ctx.code_summary(10, 1000, 10_000);
+        assert!(summary.starts_with("1 of 1 files in the repository tree"));
+        assert!(summary.contains("=== [PROVIDED] spec.md ===\n# Spec\nBuild a counter"));
+        assert!(summary.contains("=== src/main.rs ===\nfn main() {}"));
+        assert!(summary.find("[PROVIDED] spec.md").unwrap() < summary.find("src/main.rs").unwrap());
+    }
+
+    #[test]
+    fn test_code_summary_total_cap() {
+        let task = GradeTask {
+            title: "Test".to_string(),
+            description: None,
+            acceptance_criteria: vec![],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let files = (0..30)
+            .map(|i| (format!("src/file{:02}.rs", i), "x".repeat(5000)))
+            .collect::<Vec<_>>();
+        let ctx = GradeContext::new("https://example.com".to_string(), task).with_files(files);
+
+        // Per-file truncation applies first: 30 files of 1000 chars each
+        let summary = ctx.code_summary(30, 1000, 12_500);
+        assert_eq!(summary.matches("=== src/file").count(), 12);
+        assert!(summary.contains("=== src/file11.rs ==="));
+        assert!(!summary.contains("src/file12.rs"));
+        assert!(summary.ends_with("\n\n[18 additional files not shown due to context limits]"));
+        assert_eq!(ctx.shown_file_count(30, 1000, 12_500), 12);
+
+        // A cap the files fit exactly leaves nothing out
+        let summary = ctx.code_summary(5, 5000, 25_000);
+        assert_eq!(summary.matches("=== src/file").count(), 5);
+        assert!(!summary.contains("additional files"));
+
+        // Files beyond max_files aren't counted as left out by the cap
+        let summary = ctx.code_summary(5, 5000, 24_999);
+        assert_eq!(summary.matches("=== src/file").count(), 4);
+        assert!(summary.ends_with("[1 additional files not shown due to context limits]"));
     }
 }
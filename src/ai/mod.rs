@@ -1,9 +1,13 @@
 pub mod graders;
+pub mod references;
 pub mod reviewers;
 pub mod validators;
 
 use crate::error::LlmError;
+use crate::files::{RepoTree, TREE_MAX_CHARS};
+use crate::imports::{DependencyGraph, DEPENDENCY_SUMMARY_MAX_CHARS};
 use crate::llm::ModelClient;
+use crate::profile::RepoProfile;
 use crate::types::{Diagnostic, Suggestion};
 use async_trait::async_trait;
 
@@ -35,6 +39,22 @@ pub trait Reviewer: Send + Sync {
     fn name(&self) -> &'static str;
 }
 
+/// How much of each file AI reviewers include in their prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreviewLimits {
+    pub max_files: usize,
+    pub max_chars_per_file: usize,
+}
+
+impl Default for PreviewLimits {
+    fn default() -> Self {
+        Self {
+            max_files: 10,
+            max_chars_per_file: 2000,
+        }
+    }
+}
+
 /// Context provided to reviewers for code analysis
 #[derive(Debug, Clone)]
 pub struct CodeContext {
@@ -42,8 +62,18 @@ pub struct CodeContext {
     pub repo_url: String,
     /// List of files with their contents (path, content)
     pub files: Vec<(String, String)>,
+    /// Every collected path, including files left out of `files`
+    pub tree: RepoTree,
+    /// Modules imported by each JS/TS file in `files`
+    pub dependencies: DependencyGraph,
     /// Diagnostics from rule-based checkers (for context)
     pub diagnostics: Vec<Diagnostic>,
+    /// Languages detected in the repository
+    pub profile: RepoProfile,
+    /// File preview budget for reviewer prompts
+    pub preview: PreviewLimits,
+    /// README, package.json, docs/ headings and routes, for product review
+    pub product_context: Option<String>,
 }
 
 impl CodeContext {
@@ -51,7 +81,12 @@ impl CodeContext {
         Self {
             repo_url,
             files: Vec::new(),
+            tree: RepoTree::default(),
+            dependencies: DependencyGraph::default(),
             diagnostics: Vec::new(),
+            profile: RepoProfile::default(),
+            preview: PreviewLimits::default(),
+            product_context: None,
         }
     }
 
@@ -60,17 +95,62 @@ impl CodeContext {
         self
     }
 
+    pub fn with_tree(mut self, tree: RepoTree) -> Self {
+        self.tree = tree;
+        self
+    }
+
+    pub fn with_dependencies(mut self, dependencies: DependencyGraph) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Per-file import listing, empty when no imports were found
+    pub fn dependency_summary(&self) -> String {
+        self.dependencies.render(DEPENDENCY_SUMMARY_MAX_CHARS)
+    }
+
     pub fn with_diagnostics(mut self, diagnostics: Vec<Diagnostic>) -> Self {
         self.diagnostics = diagnostics;
         self
     }
 
+    pub fn with_profile(mut self, profile: RepoProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_preview(mut self, preview: PreviewLimits) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    pub fn with_product_context(mut self, product_context: Option<String>) -> Self {
+        self.product_context = product_context;
+        self
+    }
+
+    /// Repository tree with `included` files marked
+    pub fn tree_listing<'a>(&self, included: impl IntoIterator<Item = &'a str>) -> String {
+        self.tree.render(included, TREE_MAX_CHARS)
+    }
+
     /// Get a summary of the codebase for prompts
     pub fn summary(&self) -> String {
+        if !self.tree.is_empty() {
+            return format!(
+                "Repository: {}\n{}\nFiles ({}):\n{}",
+                self.repo_url,
+                self.profile.describe(),
+                self.tree.len(),
+                self.tree_listing([])
+            );
+        }
         let file_list: Vec<_> = self.files.iter().map(|(path, _)| path.as_str()).collect();
         format!(
-            "Repository: {}\nFiles ({}):\n- {}",
+            "Repository: {}\n{}\nFiles ({}):\n- {}",
             self.repo_url,
+            self.profile.describe(),
             self.files.len(),
             file_list.join("\n- ")
         )
@@ -0,0 +1,158 @@
+//! Validation of LLM-provided file references against the collected files
+//!
+//! Models sometimes cite files that don't exist, omit a leading directory,
+//! or point past the end of a file. These helpers map a cited path to a real
+//! file and clamp line numbers to its actual length.
+
+use crate::types::CodeRef;
+
+/// Max lines copied into a snippet filled in from the real file
+const SNIPPET_LINES: usize = 5;
+
+fn normalize(path: &str) -> &str {
+    path.trim().trim_start_matches("./").trim_start_matches('/')
+}
+
+/// Find the collected file a model-cited path refers to.
+///
+/// Exact matches win; otherwise a file whose path ends with the cited path
+/// (model dropped a leading directory) or vice versa is accepted, preferring
+/// the shortest such path.
+pub fn resolve_file<'a>(files: &'a [(String, String)], path: &str) -> Option<&'a (String, String)> {
+    let wanted = normalize(path);
+    if wanted.is_empty() {
+        return None;
+    }
+
+    if let Some(file) = files.iter().find(|(p, _)| normalize(p) == wanted) {
+        return Some(file);
+    }
+
+    files
+        .iter()
+        .filter(|(p, _)| {
+            let candidate = normalize(p);
+            candidate.ends_with(&format!("/{}", wanted))
+                || wanted.ends_with(&format!("/{}", candidate))
+        })
+        .min_by_key(|(p, _)| p.len())
+}
+
+/// Clamp a 1-based inclusive line range to `[1, line_count]`
+pub fn clamp_lines(line_start: u32, line_end: u32, line_count: usize) -> (u32, u32) {
+    let max = line_count.max(1) as u32;
+    let start = line_start.clamp(1, max);
+    let end = line_end.clamp(start, max);
+    (start, end)
+}
+
+/// Check a code reference against the collected files.
+///
+/// Resolved references get the real path, clamped lines, a snippet from the
+/// file when the model omitted one, and `verified: true`. Unresolvable ones
+/// are kept as-is with `verified: false`.
+pub fn verify_code_ref(files: &[(String, String)], code_ref: CodeRef) -> CodeRef {
+    let Some((path, content)) = resolve_file(files, &code_ref.file) else {
+        return CodeRef {
+            verified: false,
+            ..code_ref
+        };
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
+    let (line_start, line_end) = clamp_lines(code_ref.line_start, code_ref.line_end, lines.len());
+
+    let snippet = code_ref.snippet.or_else(|| {
+        let start = line_start as usize - 1;
+        let end = (line_end as usize)
+            .min(start + SNIPPET_LINES)
+            .min(lines.len());
+        (start < end).then(|| lines[start..end].join("\n"))
+    });
+
+    CodeRef {
+        file: path.clone(),
+        line_start,
+        line_end,
+        snippet,
+        verified: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<(String, String)> {
+        vec![
+            (
+                "src/main.rs".to_string(),
+                "fn main() {\n    run();\n}\n".to_string(),
+            ),
+            (
+                "src/lib/auth.ts".to_string(),
+                (1..=10).map(|i| format!("line {}\n", i)).collect(),
+            ),
+        ]
+    }
+
+    fn code_ref(file: &str, line_start: u32, line_end: u32) -> CodeRef {
+        CodeRef {
+            file: file.to_string(),
+            line_start,
+            line_end,
+            snippet: None,
+            verified: false,
+        }
+    }
+
+    #[test]
+    fn test_resolve_fuzzy_path() {
+        let files = files();
+        assert_eq!(
+            resolve_file(&files, "./src/main.rs").unwrap().0,
+            "src/main.rs"
+        );
+        assert_eq!(
+            resolve_file(&files, "lib/auth.ts").unwrap().0,
+            "src/lib/auth.ts"
+        );
+        assert_eq!(
+            resolve_file(&files, "repo/src/main.rs").unwrap().0,
+            "src/main.rs"
+        );
+        assert!(resolve_file(&files, "src/missing.rs").is_none());
+        assert!(resolve_file(&files, "").is_none());
+    }
+
+    #[test]
+    fn test_verify_hallucinated_path() {
+        let verified = verify_code_ref(&files(), code_ref("src/does_not_exist.rs", 1, 5));
+        assert!(!verified.verified);
+        assert_eq!(verified.file, "src/does_not_exist.rs");
+        assert!(verified.snippet.is_none());
+    }
+
+    #[test]
+    fn test_verify_clamps_out_of_range_lines() {
+        let verified = verify_code_ref(&files(), code_ref("main.rs", 2, 400));
+        assert!(verified.verified);
+        assert_eq!(verified.file, "src/main.rs");
+        assert_eq!((verified.line_start, verified.line_end), (2, 3));
+        assert_eq!(verified.snippet.as_deref(), Some("    run();\n}"));
+
+        let past_eof = verify_code_ref(&files(), code_ref("src/main.rs", 90, 95));
+        assert_eq!((past_eof.line_start, past_eof.line_end), (3, 3));
+    }
+
+    #[test]
+    fn test_verify_snippet_limited_and_preserved() {
+        let verified = verify_code_ref(&files(), code_ref("src/lib/auth.ts", 1, 10));
+        assert_eq!(verified.snippet.unwrap().lines().count(), SNIPPET_LINES);
+
+        let mut with_snippet = code_ref("src/lib/auth.ts", 1, 2);
+        with_snippet.snippet = Some("model snippet".to_string());
+        let verified = verify_code_ref(&files(), with_snippet);
+        assert_eq!(verified.snippet.as_deref(), Some("model snippet"));
+    }
+}
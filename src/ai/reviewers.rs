@@ -1,15 +1,28 @@
-use crate::ai::{CodeContext, Reviewer};
+use crate::ai::references::{clamp_lines, resolve_file};
+use crate::ai::{CodeContext, PreviewLimits, Reviewer};
 use crate::error::LlmError;
 use crate::llm::{Message, ModelClient};
-use crate::types::{Priority, Suggestion, SuggestionCategory};
+use crate::prompts::{PromptRegistry, CODE_ORACLE_PROMPT, CODE_ORACLE_SYSTEM_PROMPT};
+use crate::types::{Priority, Suggestion, SuggestionCategory, NEUTRAL_SUGGESTION_CONFIDENCE};
 use async_trait::async_trait;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
 
-pub struct CodeOracle;
+pub struct CodeOracle {
+    prompts: Arc<PromptRegistry>,
+}
 
 impl CodeOracle {
     pub fn new() -> Self {
-        Self
+        Self {
+            prompts: Arc::default(),
+        }
+    }
+
+    pub fn with_prompts(mut self, prompts: Arc<PromptRegistry>) -> Self {
+        self.prompts = prompts;
+        self
     }
 }
 
@@ -30,48 +43,22 @@ impl Reviewer for CodeOracle {
             return Ok(Vec::new());
         }
 
-        let files_content = context
-            .files
-            .iter()
-            .take(10)
-            .map(|(path, content)| {
-                let preview = if content.len() > 2000 {
-                    format!("{}...(truncated)", &content[..2000])
-                } else {
-                    content.clone()
-                };
-                format!("=== {} ===\n{}", path, preview)
-            })
-            .collect::<Vec<_>>()
-            .join("\n\n");
+        let files_content = file_previews(context.files.iter(), context.preview);
+        let tree = tree_section(context, context.files.iter());
+        let dependencies = dependency_section(context);
 
-        let prompt = format!(
-            "Analyze this codebase and provide architectural and code quality suggestions.\n\n\
-             {}\n\n\
-             Provide suggestions in this JSON format:\n\
-             [{{\n\
-               \"category\": \"architecture\"|\"performance\"|\"security\"|\"code_quality\",\n\
-               \"title\": \"Brief title\",\n\
-               \"description\": \"Detailed description\",\n\
-               \"file\": \"path/to/file.rs\" (optional),\n\
-               \"line\": 42 (optional),\n\
-               \"priority\": \"high\"|\"medium\"|\"low\",\n\
-               \"rationale\": \"Why this matters\"\n\
-             }}]\n\n\
-             Focus on:\n\
-             - Architectural patterns and anti-patterns\n\
-             - Error handling improvements\n\
-             - Performance optimizations\n\
-             - Security concerns\n\
-             - Code organization\n\n\
-             Return ONLY the JSON array.",
-            files_content
-        );
+        let vars = HashMap::from([
+            ("tree", tree.as_str()),
+            ("dependencies", dependencies.as_str()),
+            ("files", files_content.as_str()),
+        ]);
+        let prompt = self.prompts.get(CODE_ORACLE_PROMPT).render(&vars);
 
         let messages = vec![Message::user(prompt)];
-        let response = client.chat(&messages, Some(CODE_ORACLE_SYSTEM)).await?;
+        let system = &self.prompts.get(CODE_ORACLE_SYSTEM_PROMPT).template;
+        let response = client.chat(&messages, Some(system)).await?;
 
-        parse_suggestions(&response)
+        parse_suggestions(&response, &context.files)
     }
 
     fn name(&self) -> &'static str {
@@ -105,6 +92,13 @@ impl Reviewer for ProductIdeasReviewer {
         }
 
         let summary = context.summary();
+        let product_context = match &context.product_context {
+            Some(product_context) => format!(
+                "What the project says about itself (README, package.json, docs, routes):\n{}\n\n",
+                product_context
+            ),
+            None => String::new(),
+        };
         let diagnostics_summary = if context.diagnostics.is_empty() {
             "No issues detected.".to_string()
         } else {
@@ -114,6 +108,7 @@ impl Reviewer for ProductIdeasReviewer {
         let prompt = format!(
             "Analyze this codebase from a PRODUCT perspective.\n\n\
              {}\n\n\
+             {}\
              Current issues: {}\n\n\
              Provide suggestions in this JSON format:\n\
              [{{\n\
@@ -121,22 +116,25 @@ impl Reviewer for ProductIdeasReviewer {
                \"title\": \"Brief title\",\n\
                \"description\": \"Detailed description\",\n\
                \"priority\": \"high\"|\"medium\"|\"low\",\n\
-               \"rationale\": \"Why this matters for the product\"\n\
+               \"rationale\": \"Why this matters for the product\",\n\
+               \"confidence\": 0.0-1.0\n\
              }}]\n\n\
+             Set confidence to how sure you are that the suggestion is worth acting on.\n\n\
              Focus on:\n\
-             - Feature suggestions based on code structure\n\
+             - Features that fit the product's stated purpose and existing routes\n\
              - Production hardening (logging, monitoring, error recovery)\n\
              - Deployment considerations\n\
              - User experience improvements\n\
              - Reliability and resilience\n\n\
+             Ground suggestions in what this project actually does; avoid generic advice.\n\n\
              Return ONLY the JSON array.",
-            summary, diagnostics_summary
+            summary, product_context, diagnostics_summary
         );
 
         let messages = vec![Message::user(prompt)];
         let response = client.chat(&messages, Some(PRODUCT_REVIEWER_SYSTEM)).await?;
 
-        parse_suggestions(&response)
+        parse_suggestions(&response, &context.files)
     }
 
     fn name(&self) -> &'static str {
@@ -144,14 +142,255 @@ impl Reviewer for ProductIdeasReviewer {
     }
 }
 
-const CODE_ORACLE_SYSTEM: &str = "You are a senior software architect reviewing code. \
-    Focus on actionable improvements. Respond ONLY with JSON. \
-    All text content (title, description, rationale) MUST be written in Korean.";
+/// Path fragments that mark a file as a test
+const TEST_PATH_PATTERNS: &[&str] = &[
+    "/tests/", "/test/", "__tests__", ".test.", ".spec.", "_test.", "/test_", "pytest",
+];
+
+/// Source patterns that declare test cases
+const TEST_CONTENT_PATTERNS: &[&str] = &["#[test]", "describe(", "it(", "test(", "def test_", "import pytest"];
+
+/// Whether a collected file is (or contains) tests
+fn is_test_file(path: &str, content: &str) -> bool {
+    let path = format!("/{}", path.to_lowercase());
+    TEST_PATH_PATTERNS.iter().any(|p| path.contains(p))
+        || TEST_CONTENT_PATTERNS.iter().any(|p| content.contains(p))
+}
+
+/// Reviewer that finds code without corresponding tests
+pub struct TestCoverageReviewer;
+
+impl TestCoverageReviewer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for TestCoverageReviewer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Reviewer for TestCoverageReviewer {
+    async fn review(
+        &self,
+        client: &dyn ModelClient,
+        context: &CodeContext,
+    ) -> Result<Vec<Suggestion>, LlmError> {
+        if context.files.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (tests, sources): (Vec<_>, Vec<_>) = context
+            .files
+            .iter()
+            .partition(|(path, content)| is_test_file(path, content));
+        let test_files = if tests.is_empty() {
+            "(none)".to_string()
+        } else {
+            tests
+                .iter()
+                .map(|(path, _)| format!("- {}", path))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let prompt = format!(
+            "Identify functions, modules and branches in this codebase that have no \
+             corresponding test files or test cases.\n\n\
+             {}\
+             Test files:\n{}\n\n\
+             Source files:\n{}\n\n\
+             Provide suggestions in this JSON format:\n\
+             [{{\n\
+               \"category\": \"test_coverage\",\n\
+               \"title\": \"Brief title\",\n\
+               \"description\": \"What is untested and which cases to cover\",\n\
+               \"file\": \"path/to/file.rs\" (optional),\n\
+               \"line\": 42 (optional),\n\
+               \"visibility\": \"public\"|\"private\",\n\
+               \"priority\": \"high\"|\"medium\"|\"low\",\n\
+               \"rationale\": \"Why this needs a test\",\n\
+               \"confidence\": 0.0-1.0\n\
+             }}]\n\n\
+             Use visibility \"public\" for exported API functions, handlers and entry points, \
+             and \"private\" for internal utility functions. Set confidence to how sure you are \
+             that the code is really untested.\n\n\
+             Return ONLY the JSON array.",
+            tree_section(context, sources.iter().copied()),
+            test_files,
+            file_previews(sources.iter().copied(), context.preview)
+        );
+
+        let messages = vec![Message::user(prompt)];
+        let response = client.chat(&messages, Some(TEST_COVERAGE_SYSTEM)).await?;
+
+        Ok(parse_raw_suggestions(&response)?
+            .into_iter()
+            .map(|raw| {
+                let priority = match raw.visibility.as_deref().map(str::to_lowercase).as_deref() {
+                    Some("public") => Priority::High,
+                    Some("private") => Priority::Low,
+                    _ => parse_priority(&raw.priority),
+                };
+                Suggestion {
+                    category: SuggestionCategory::TestCoverage,
+                    priority,
+                    ..to_suggestion(raw, &context.files)
+                }
+            })
+            .collect())
+    }
+
+    fn name(&self) -> &'static str {
+        "test_coverage_reviewer"
+    }
+}
+
+/// Extensions of the markup files the accessibility reviewer reads
+const MARKUP_EXTENSIONS: &[&str] = &["html", "jsx", "tsx"];
+
+fn is_markup_file(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| MARKUP_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Reviewer that finds accessibility problems in HTML and JSX
+pub struct AccessibilityReviewer;
+
+impl AccessibilityReviewer {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Whether the repository has any HTML/JSX/TSX files to review
+    pub fn applies_to(context: &CodeContext) -> bool {
+        context.files.iter().any(|(path, _)| is_markup_file(path))
+            || context.tree.entries.iter().any(|entry| is_markup_file(&entry.path))
+    }
+}
+
+impl Default for AccessibilityReviewer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Reviewer for AccessibilityReviewer {
+    async fn review(
+        &self,
+        client: &dyn ModelClient,
+        context: &CodeContext,
+    ) -> Result<Vec<Suggestion>, LlmError> {
+        let markup: Vec<_> = context
+            .files
+            .iter()
+            .filter(|(path, _)| is_markup_file(path))
+            .collect();
+        if markup.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let prompt = format!(
+            "Review these HTML/JSX files for accessibility problems.\n\n\
+             {}\n\n\
+             Look for:\n\
+             - Images (<img>, <Image>) without meaningful alt text\n\
+             - Form inputs, selects and textareas without an associated label or aria-label\n\
+             - Headings that skip levels (e.g. <h1> followed by <h3>) or pages without an <h1>\n\n\
+             Provide suggestions in this JSON format:\n\
+             [{{\n\
+               \"category\": \"accessibility\",\n\
+               \"title\": \"Brief title\",\n\
+               \"description\": \"What is inaccessible and how to fix it\",\n\
+               \"file\": \"path/to/Component.tsx\" (optional),\n\
+               \"line\": 42 (optional),\n\
+               \"priority\": \"high\"|\"medium\"|\"low\",\n\
+               \"rationale\": \"Who is affected, e.g. screen reader users\",\n\
+               \"confidence\": 0.0-1.0\n\
+             }}]\n\n\
+             Set confidence to how sure you are that the problem is real.\n\n\
+             Return ONLY the JSON array.",
+            file_previews(markup.iter().copied(), context.preview)
+        );
+
+        let messages = vec![Message::user(prompt)];
+        let response = client.chat(&messages, Some(ACCESSIBILITY_SYSTEM)).await?;
+
+        Ok(parse_raw_suggestions(&response)?
+            .into_iter()
+            .map(|raw| Suggestion {
+                category: SuggestionCategory::Accessibility,
+                ..to_suggestion(raw, &context.files)
+            })
+            .collect())
+    }
+
+    fn name(&self) -> &'static str {
+        "accessibility_reviewer"
+    }
+}
+
+/// Repository tree ahead of the previews of `shown` files, which are marked
+fn tree_section<'a>(
+    context: &CodeContext,
+    shown: impl Iterator<Item = &'a (String, String)>,
+) -> String {
+    if context.tree.is_empty() {
+        return String::new();
+    }
+    let shown = shown.take(context.preview.max_files).map(|(path, _)| path.as_str());
+    format!(
+        "Repository tree (files marked * are shown below, the rest are listed only):\n{}\n",
+        context.tree_listing(shown)
+    )
+}
+
+fn dependency_section(context: &CodeContext) -> String {
+    if context.dependencies.is_empty() {
+        return String::new();
+    }
+    format!(
+        "Module imports (file -> imported modules):\n{}\n",
+        context.dependency_summary()
+    )
+}
+
+/// File contents for reviewer prompts, within the preview budget
+fn file_previews<'a>(
+    files: impl Iterator<Item = &'a (String, String)>,
+    limits: PreviewLimits,
+) -> String {
+    files
+        .take(limits.max_files)
+        .map(|(path, content)| {
+            let preview = if content.len() > limits.max_chars_per_file {
+                let end = content.floor_char_boundary(limits.max_chars_per_file);
+                format!("{}...(truncated)", &content[..end])
+            } else {
+                content.clone()
+            };
+            format!("=== {} ===\n{}", path, preview)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
 
 const PRODUCT_REVIEWER_SYSTEM: &str = "You are a product engineer reviewing code for production readiness. \
     Focus on reliability, user experience, and operational excellence. Respond ONLY with JSON. \
     All text content (title, description, rationale) MUST be written in Korean.";
 
+const TEST_COVERAGE_SYSTEM: &str = "You are a test engineer reviewing a student's code for missing tests. \
+    Point to concrete untested code and the cases a test should cover. Respond ONLY with JSON. \
+    All text content (title, description, rationale) MUST be written in Korean.";
+
+const ACCESSIBILITY_SYSTEM: &str = "You are a frontend engineer reviewing a student's markup for accessibility. \
+    Point to concrete elements and the attribute or structure that fixes them. Respond ONLY with JSON. \
+    All text content (title, description, rationale) MUST be written in Korean.";
+
 #[derive(Debug, Deserialize)]
 struct RawSuggestion {
     category: String,
@@ -163,9 +402,40 @@ struct RawSuggestion {
     line: Option<u32>,
     priority: String,
     rationale: String,
+    /// Whether the referenced code is public API (test coverage reviewer only)
+    #[serde(default)]
+    visibility: Option<String>,
+    #[serde(default)]
+    confidence: Option<f32>,
+}
+
+fn parse_suggestions(
+    response: &str,
+    files: &[(String, String)],
+) -> Result<Vec<Suggestion>, LlmError> {
+    Ok(parse_raw_suggestions(response)?
+        .into_iter()
+        .map(|raw| to_suggestion(raw, files))
+        .collect())
+}
+
+fn to_suggestion(raw: RawSuggestion, files: &[(String, String)]) -> Suggestion {
+    let (file, line) = verify_location(files, raw.file, raw.line);
+    Suggestion {
+        category: parse_category(&raw.category),
+        title: raw.title,
+        description: raw.description,
+        file,
+        line,
+        priority: parse_priority(&raw.priority),
+        rationale: raw.rationale,
+        confidence: raw
+            .confidence
+            .map_or(NEUTRAL_SUGGESTION_CONFIDENCE, |c| c.clamp(0.0, 1.0)),
+    }
 }
 
-fn parse_suggestions(response: &str) -> Result<Vec<Suggestion>, LlmError> {
+fn parse_raw_suggestions(response: &str) -> Result<Vec<RawSuggestion>, LlmError> {
     let trimmed = response.trim();
     let json_str = if let Some(start) = trimmed.find('[') {
         if let Some(end) = trimmed.rfind(']') {
@@ -177,22 +447,23 @@ fn parse_suggestions(response: &str) -> Result<Vec<Suggestion>, LlmError> {
         trimmed
     };
 
-    let raw: Vec<RawSuggestion> = serde_json::from_str(json_str).map_err(|e| {
+    serde_json::from_str(json_str).map_err(|e| {
         LlmError::InvalidResponse(format!("Failed to parse suggestions: {} - Response: {}", e, json_str))
-    })?;
+    })
+}
 
-    Ok(raw
-        .into_iter()
-        .map(|r| Suggestion {
-            category: parse_category(&r.category),
-            title: r.title,
-            description: r.description,
-            file: r.file,
-            line: r.line,
-            priority: parse_priority(&r.priority),
-            rationale: r.rationale,
-        })
-        .collect())
+/// Map a suggested file to a real collected file and clamp its line.
+/// References to files that don't exist are dropped.
+fn verify_location(
+    files: &[(String, String)],
+    file: Option<String>,
+    line: Option<u32>,
+) -> (Option<String>, Option<u32>) {
+    let Some((path, content)) = file.as_deref().and_then(|f| resolve_file(files, f)) else {
+        return (None, None);
+    };
+    let line = line.map(|l| clamp_lines(l, l, content.lines().count()).0);
+    (Some(path.clone()), line)
 }
 
 fn parse_category(s: &str) -> SuggestionCategory {
@@ -203,6 +474,8 @@ fn parse_category(s: &str) -> SuggestionCategory {
         "code_quality" => SuggestionCategory::CodeQuality,
         "product_idea" => SuggestionCategory::ProductIdea,
         "hardening" => SuggestionCategory::Hardening,
+        "test_coverage" => SuggestionCategory::TestCoverage,
+        "accessibility" => SuggestionCategory::Accessibility,
         _ => SuggestionCategory::CodeQuality,
     }
 }
@@ -219,6 +492,121 @@ fn parse_priority(s: &str) -> Priority {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ai::PreviewLimits;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct CapturingClient {
+        prompts: Mutex<Vec<String>>,
+        response: Option<String>,
+    }
+
+    #[async_trait]
+    impl ModelClient for CapturingClient {
+        async fn chat(&self, messages: &[Message], _system: Option<&str>) -> Result<String, LlmError> {
+            self.prompts
+                .lock()
+                .unwrap()
+                .extend(messages.iter().map(|m| m.content.clone()));
+            Ok(self.response.clone().unwrap_or_else(|| "[]".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_code_oracle_respects_preview_limits() {
+        let files = (0..3)
+            .map(|i| (format!("src/file{}.rs", i), "x".repeat(50)))
+            .collect();
+        let context = CodeContext::new("https://github.com/test/repo".to_string())
+            .with_files(files)
+            .with_preview(PreviewLimits {
+                max_files: 2,
+                max_chars_per_file: 10,
+            });
+        let client = CapturingClient::default();
+
+        CodeOracle::new().review(&client, &context).await.unwrap();
+
+        let prompt = client.prompts.lock().unwrap()[0].clone();
+        assert!(prompt.contains("=== src/file1.rs ===\nxxxxxxxxxx...(truncated)"));
+        assert!(!prompt.contains("src/file2.rs"));
+    }
+
+    #[tokio::test]
+    async fn test_code_oracle_lists_repository_tree() {
+        use crate::files::{RepoTree, TreeEntry};
+
+        let tree = RepoTree {
+            entries: ["src/main.rs", "src/store.rs", "tests/store_test.rs"]
+                .iter()
+                .map(|path| TreeEntry {
+                    path: path.to_string(),
+                    lines: 10,
+                })
+                .collect(),
+            omitted: 2,
+        };
+        let context = CodeContext::new("https://github.com/test/repo".to_string())
+            .with_files(vec![("src/main.rs".to_string(), "fn main() {}".to_string())])
+            .with_tree(tree);
+        let client = CapturingClient::default();
+
+        CodeOracle::new().review(&client, &context).await.unwrap();
+
+        let prompt = client.prompts.lock().unwrap()[0].clone();
+        let expected = "suggestions.\n\n\
+                        Repository tree (files marked * are shown below, the rest are listed only):\n\
+                        src/\n\
+                        \x20 main.rs (10 lines) *\n\
+                        \x20 store.rs (10 lines)\n\
+                        tests/\n\
+                        \x20 store_test.rs (10 lines)\n\
+                        …and 2 more files\n\
+                        \n\
+                        === src/main.rs ===\nfn main() {}\n";
+        assert!(prompt.contains(expected), "{}", prompt);
+    }
+
+    #[tokio::test]
+    async fn test_code_oracle_includes_dependency_summary() {
+        use crate::imports::DependencyGraph;
+
+        let files = vec![
+            ("src/app.ts".to_string(), "import { api } from './api';".to_string()),
+            ("src/api.ts".to_string(), "const axios = require('axios');".to_string()),
+        ];
+        let context = CodeContext::new("https://github.com/test/repo".to_string())
+            .with_dependencies(DependencyGraph::from_files(&files))
+            .with_files(files);
+        let client = CapturingClient::default();
+
+        CodeOracle::new().review(&client, &context).await.unwrap();
+
+        let prompt = client.prompts.lock().unwrap()[0].clone();
+        let expected = "Module imports (file -> imported modules):\n\
+                        src/app.ts -> ./api\n\
+                        src/api.ts -> axios\n\
+                        \n\
+                        === src/app.ts ===";
+        assert!(prompt.contains(expected), "{}", prompt);
+    }
+
+    #[tokio::test]
+    async fn test_product_reviewer_includes_product_context() {
+        let context = CodeContext::new("https://github.com/test/repo".to_string())
+            .with_files(vec![("server.js".to_string(), "app.get('/recipes', list);".to_string())]);
+        let client = CapturingClient::default();
+
+        ProductIdeasReviewer::new().review(&client, &context).await.unwrap();
+        let context = context.with_product_context(Some("### Routes\nGET /recipes (server.js)".to_string()));
+        ProductIdeasReviewer::new().review(&client, &context).await.unwrap();
+
+        let prompts = client.prompts.lock().unwrap();
+        assert!(!prompts[0].contains("What the project says about itself"));
+        assert!(prompts[1].contains(
+            "What the project says about itself (README, package.json, docs, routes):\n### Routes\nGET /recipes (server.js)\n\nCurrent issues:"
+        ));
+    }
 
     #[test]
     fn test_parse_suggestions() {
@@ -234,14 +622,49 @@ mod tests {
             }
         ]"#;
 
-        let suggestions = parse_suggestions(response).unwrap();
+        let files = vec![("src/api.rs".to_string(), "line\n".repeat(50))];
+        let suggestions = parse_suggestions(response, &files).unwrap();
         assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].file.as_deref(), Some("src/api.rs"));
+        assert_eq!(suggestions[0].line, Some(42));
         assert!(matches!(
             suggestions[0].category,
             SuggestionCategory::Architecture
         ));
         assert_eq!(suggestions[0].title, "Add caching layer");
         assert!(matches!(suggestions[0].priority, Priority::High));
+        assert_eq!(suggestions[0].confidence, NEUTRAL_SUGGESTION_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_parse_suggestion_confidence() {
+        let response = r#"[
+            {"category": "security", "title": "a", "description": "d", "priority": "high",
+             "rationale": "r", "confidence": 0.85},
+            {"category": "security", "title": "b", "description": "d", "priority": "low",
+             "rationale": "r", "confidence": 4}
+        ]"#;
+
+        let suggestions = parse_suggestions(response, &[]).unwrap();
+        assert_eq!(suggestions[0].confidence, 0.85);
+        assert_eq!(suggestions[1].confidence, 1.0);
+    }
+
+    #[test]
+    fn test_parse_suggestions_verifies_locations() {
+        let response = r#"[
+            {"category": "security", "title": "a", "description": "d", "file": "handlers/user.py",
+             "line": 900, "priority": "high", "rationale": "r"},
+            {"category": "security", "title": "b", "description": "d", "file": "src/ghost.py",
+             "line": 3, "priority": "low", "rationale": "r"}
+        ]"#;
+        let files = vec![("app/handlers/user.py".to_string(), "x\ny\n".to_string())];
+
+        let suggestions = parse_suggestions(response, &files).unwrap();
+        assert_eq!(suggestions[0].file.as_deref(), Some("app/handlers/user.py"));
+        assert_eq!(suggestions[0].line, Some(2));
+        assert_eq!(suggestions[1].file, None);
+        assert_eq!(suggestions[1].line, None);
     }
 
     #[test]
@@ -254,12 +677,99 @@ mod tests {
             parse_category("PERFORMANCE"),
             SuggestionCategory::Performance
         ));
+        assert!(matches!(
+            parse_category("test_coverage"),
+            SuggestionCategory::TestCoverage
+        ));
+        assert!(matches!(
+            parse_category("accessibility"),
+            SuggestionCategory::Accessibility
+        ));
         assert!(matches!(
             parse_category("unknown"),
             SuggestionCategory::CodeQuality
         ));
     }
 
+    #[tokio::test]
+    async fn test_coverage_reviewer_priorities() {
+        let files = vec![
+            ("src/api.js".to_string(), "export function createUser() {}\n".to_string()),
+            ("src/format.js".to_string(), "function pad() {}\n".to_string()),
+            (
+                "tests/api.test.js".to_string(),
+                "describe('api', () => { it('works', () => {}) })\n".to_string(),
+            ),
+        ];
+        let context = CodeContext::new("https://github.com/test/repo".to_string()).with_files(files);
+        let client = CapturingClient {
+            response: Some(
+                r#"[
+                {"category": "code_quality", "title": "createUser untested", "description": "d",
+                 "file": "src/api.js", "line": 1, "visibility": "public", "priority": "low", "rationale": "r"},
+                {"category": "test_coverage", "title": "pad untested", "description": "d",
+                 "visibility": "private", "priority": "high", "rationale": "r"},
+                {"category": "test_coverage", "title": "error branch", "description": "d",
+                 "priority": "medium", "rationale": "r"}
+            ]"#
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+
+        let suggestions = TestCoverageReviewer::new().review(&client, &context).await.unwrap();
+
+        let prompt = client.prompts.lock().unwrap()[0].clone();
+        assert!(prompt.contains("Test files:\n- tests/api.test.js\n"));
+        assert!(prompt.contains("=== src/format.js ==="));
+        assert!(!prompt.contains("=== tests/api.test.js ==="));
+
+        assert!(suggestions
+            .iter()
+            .all(|s| matches!(s.category, SuggestionCategory::TestCoverage)));
+        let priorities: Vec<Priority> = suggestions.iter().map(|s| s.priority).collect();
+        assert!(matches!(
+            priorities.as_slice(),
+            [Priority::High, Priority::Low, Priority::Medium]
+        ));
+        assert_eq!(suggestions[0].file.as_deref(), Some("src/api.js"));
+    }
+
+    #[tokio::test]
+    async fn test_accessibility_reviewer_reads_markup_only() {
+        let files = vec![
+            ("src/App.tsx".to_string(), "<img src=\"logo.png\" />\n".to_string()),
+            ("src/api.ts".to_string(), "export const get = () => {}\n".to_string()),
+        ];
+        let context = CodeContext::new("https://github.com/test/repo".to_string()).with_files(files);
+        assert!(AccessibilityReviewer::applies_to(&context));
+        let client = CapturingClient {
+            response: Some(
+                r#"[{"category": "code_quality", "title": "Missing alt", "description": "d",
+                     "file": "src/App.tsx", "line": 1, "priority": "high", "rationale": "r"}]"#
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
+
+        let suggestions = AccessibilityReviewer::new().review(&client, &context).await.unwrap();
+
+        let prompt = client.prompts.lock().unwrap()[0].clone();
+        assert!(prompt.contains("=== src/App.tsx ==="));
+        assert!(!prompt.contains("src/api.ts"));
+        assert_eq!(suggestions.len(), 1);
+        assert!(matches!(suggestions[0].category, SuggestionCategory::Accessibility));
+        assert_eq!(suggestions[0].file.as_deref(), Some("src/App.tsx"));
+
+        // No markup: nothing to review and no LLM call
+        let backend = CodeContext::new("https://github.com/test/repo".to_string())
+            .with_files(vec![("main.py".to_string(), "print(1)\n".to_string())]);
+        assert!(!AccessibilityReviewer::applies_to(&backend));
+        let client = CapturingClient::default();
+        assert!(AccessibilityReviewer::new().review(&client, &backend).await.unwrap().is_empty());
+        assert!(client.prompts.lock().unwrap().is_empty());
+    }
+
     #[test]
     fn test_parse_priority() {
         assert!(matches!(parse_priority("high"), Priority::High));
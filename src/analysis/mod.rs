@@ -0,0 +1,5 @@
+pub mod product;
+pub mod similarity;
+
+pub use product::extract_product_context;
+pub use similarity::SimilarityFingerprint;
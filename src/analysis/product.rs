@@ -0,0 +1,232 @@
+//! Product context for the product ideas reviewer
+//!
+//! Collects what a repository says about itself — README, package.json
+//! description and scripts, docs/ headings — plus the HTTP routes declared
+//! in its source, so product suggestions can refer to actual features
+//! instead of the file list alone.
+
+use crate::files::read_prefix;
+use crate::profile::is_ignored_dir_name;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use walkdir::WalkDir;
+
+/// Characters of product context included in reviewer prompts
+pub const PRODUCT_CONTEXT_MAX_CHARS: usize = 8000;
+
+/// Bytes of README read
+const README_MAX_BYTES: usize = 6000;
+
+const README_NAMES: &[&str] = &["README.md", "README", "README.txt", "README.rst", "readme.md", "Readme.md"];
+
+/// Markdown files under docs/ scanned for headings
+const DOCS_MAX_FILES: usize = 20;
+
+/// Headings listed per docs/ file
+const DOCS_MAX_HEADINGS: usize = 10;
+
+/// Routes listed before the rest are counted
+const MAX_ROUTES: usize = 50;
+
+/// `(method, path)` declarations: Express/Fastify calls, actix/Rocket
+/// attributes and Flask/FastAPI decorators
+static ROUTE_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        r#"\b(?:app|router|server|fastify|api)\.(get|post|put|patch|delete|all)\(\s*['"`](/[^'"`]*)['"`]"#,
+        r#"#\[(get|post|put|patch|delete)\(\s*"(/[^"]*)""#,
+        r#"@\w+\.(get|post|put|patch|delete|route)\(\s*['"](/[^'"]*)['"]"#,
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .collect()
+});
+
+/// Product context of the repository at `repo_path`, with routes harvested
+/// from `files`. `None` when nothing was found; unreadable sources are
+/// skipped.
+pub fn extract_product_context(repo_path: &Path, files: &[(String, String)]) -> Option<String> {
+    let sections: Vec<String> = [
+        readme_section(repo_path),
+        package_section(repo_path),
+        docs_section(repo_path),
+        routes_section(files),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if sections.is_empty() {
+        return None;
+    }
+    let mut context = sections.join("\n\n");
+    if context.len() > PRODUCT_CONTEXT_MAX_CHARS {
+        let mut end = PRODUCT_CONTEXT_MAX_CHARS;
+        while !context.is_char_boundary(end) {
+            end -= 1;
+        }
+        context.truncate(end);
+        context.push_str("\n…(truncated)");
+    }
+    Some(context)
+}
+
+fn readme_section(repo_path: &Path) -> Option<String> {
+    let (name, content) = README_NAMES
+        .iter()
+        .find_map(|name| Some((name, read_prefix(&repo_path.join(name), README_MAX_BYTES).ok()?)))?;
+    let content = content.trim();
+    if content.is_empty() {
+        return None;
+    }
+    Some(format!("### {}\n{}", name, content))
+}
+
+/// Name, description and scripts from package.json
+fn package_section(repo_path: &Path) -> Option<String> {
+    let content = fs::read_to_string(repo_path.join("package.json")).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+
+    let mut lines = Vec::new();
+    for field in ["name", "description"] {
+        if let Some(value) = json.get(field).and_then(|v| v.as_str()) {
+            lines.push(format!("{}: {}", field, value));
+        }
+    }
+    if let Some(scripts) = json.get("scripts").and_then(|s| s.as_object()) {
+        lines.push("scripts:".to_string());
+        for (name, command) in scripts {
+            lines.push(format!("- {}: {}", name, command.as_str().unwrap_or_default()));
+        }
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!("### package.json\n{}", lines.join("\n")))
+}
+
+/// Markdown headings of each file under docs/
+fn docs_section(repo_path: &Path) -> Option<String> {
+    let docs = repo_path.join("docs");
+    let mut paths: Vec<_> = WalkDir::new(&docs)
+        .into_iter()
+        .filter_entry(|entry| !is_ignored_dir_name(&entry.file_name().to_string_lossy()))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md" || ext == "mdx"))
+        .collect();
+    paths.sort();
+
+    let mut lines = Vec::new();
+    for path in paths.iter().take(DOCS_MAX_FILES) {
+        let Ok(content) = read_prefix(path, README_MAX_BYTES) else {
+            continue;
+        };
+        let headings: Vec<_> = content
+            .lines()
+            .filter(|line| line.starts_with('#'))
+            .map(|line| line.trim_start_matches('#').trim())
+            .filter(|heading| !heading.is_empty())
+            .take(DOCS_MAX_HEADINGS)
+            .collect();
+        if headings.is_empty() {
+            continue;
+        }
+        let relative = path.strip_prefix(repo_path).unwrap_or(path);
+        lines.push(format!("{}: {}", relative.display(), headings.join(" / ")));
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!("### docs/\n{}", lines.join("\n")))
+}
+
+/// `METHOD /path (file)` for each distinct route declared in `files`
+fn routes_section(files: &[(String, String)]) -> Option<String> {
+    let mut routes = Vec::new();
+    for (path, source) in files {
+        for pattern in ROUTE_PATTERNS.iter() {
+            for captures in pattern.captures_iter(source) {
+                let route = format!("{} {} ({})", captures[1].to_uppercase(), &captures[2], path);
+                if !routes.contains(&route) {
+                    routes.push(route);
+                }
+            }
+        }
+    }
+
+    if routes.is_empty() {
+        return None;
+    }
+    let total = routes.len();
+    routes.truncate(MAX_ROUTES);
+    if total > MAX_ROUTES {
+        routes.push(format!("…and {} more routes", total - MAX_ROUTES));
+    }
+    Some(format!("### Routes\n{}", routes.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fixture(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_extract_product_context() {
+        let repo = fixture(&[
+            ("README.md", "# Recipe Box\nSave and share recipes with friends.\n"),
+            (
+                "package.json",
+                r#"{"name": "recipe-box", "description": "Recipe sharing app", "scripts": {"start": "node server.js"}}"#,
+            ),
+            ("docs/guide.md", "# User Guide\nIntro\n## Sharing recipes\n"),
+            ("docs/node_modules/skip.md", "# Vendored\n"),
+        ]);
+        let files = vec![
+            (
+                "server.js".to_string(),
+                "app.get('/recipes', list);\nrouter.post(\"/recipes/:id/share\", share);\napp.get('/recipes', again);"
+                    .to_string(),
+            ),
+            ("src/api.rs".to_string(), "#[get(\"/health\")]\nasync fn health() {}".to_string()),
+            ("app.py".to_string(), "@app.route('/login')\ndef login(): pass".to_string()),
+        ];
+
+        let context = extract_product_context(repo.path(), &files).unwrap();
+
+        assert!(context.contains("### README.md\n# Recipe Box\nSave and share recipes with friends."));
+        assert!(context.contains("description: Recipe sharing app"));
+        assert!(context.contains("- start: node server.js"));
+        assert!(context.contains("docs/guide.md: User Guide / Sharing recipes"));
+        assert!(!context.contains("Vendored"));
+        assert!(context.contains(
+            "### Routes\nGET /recipes (server.js)\nPOST /recipes/:id/share (server.js)\nGET /health (src/api.rs)\nROUTE /login (app.py)"
+        ));
+    }
+
+    #[test]
+    fn test_extract_product_context_empty_repo() {
+        let repo = fixture(&[("package.json", "not json"), ("src/main.rs", "fn main() {}")]);
+        let files = vec![("src/main.rs".to_string(), "fn main() {}".to_string())];
+
+        assert_eq!(extract_product_context(repo.path(), &files), None);
+
+        let long = fixture(&[("README.md", &"a".repeat(README_MAX_BYTES * 2))]);
+        let context = extract_product_context(long.path(), &[]).unwrap();
+        assert!(context.len() <= PRODUCT_CONTEXT_MAX_CHARS + "\n…(truncated)".len());
+    }
+}
@@ -0,0 +1,199 @@
+//! Submission fingerprints for duplicate detection across a cohort
+//!
+//! A fingerprint holds a content hash of each graded file and a MinHash
+//! signature over shingles of normalized tokens. Matching hashes find files
+//! copied as-is; the signature estimates the Jaccard similarity of the whole
+//! submissions, so renamed files, reformatting and small edits still match.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+/// Hash functions in a MinHash signature
+pub const MINHASH_PERMUTATIONS: usize = 128;
+
+/// Consecutive tokens per shingle
+pub const SHINGLE_TOKENS: usize = 5;
+
+/// Estimated similarity at which two submissions are reported
+pub const DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.8;
+
+/// Identifiers, numbers and single punctuation characters; whitespace and
+/// layout are dropped
+static TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*|[0-9]+|[^\sA-Za-z0-9_]").unwrap());
+
+/// Normalized content hash of one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHash {
+    pub path: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimilarityFingerprint {
+    /// Files by path
+    pub files: Vec<FileHash>,
+    /// Minimum of each hash function over all shingles; empty when the
+    /// submission has fewer than `SHINGLE_TOKENS` tokens
+    pub minhash: Vec<u32>,
+}
+
+impl SimilarityFingerprint {
+    /// Fingerprint of `(path, content)` files
+    pub fn from_files(files: &[(String, String)]) -> Self {
+        let mut hashes = Vec::with_capacity(files.len());
+        let mut shingles = HashSet::new();
+
+        for (path, content) in files {
+            let tokens: Vec<&str> = TOKEN.find_iter(content).map(|m| m.as_str()).collect();
+            let mut hasher = Sha256::new();
+            for token in &tokens {
+                hasher.update(token);
+                hasher.update([0]);
+            }
+            hashes.push(FileHash {
+                path: path.clone(),
+                hash: hex::encode(&hasher.finalize()[..8]),
+            });
+            shingles.extend(tokens.windows(SHINGLE_TOKENS).map(fnv1a));
+        }
+        hashes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let minhash = if shingles.is_empty() {
+            Vec::new()
+        } else {
+            (0..MINHASH_PERMUTATIONS as u64)
+                .map(|i| {
+                    let seed = splitmix64(i);
+                    let min = shingles.iter().map(|&shingle| splitmix64(shingle ^ seed)).min();
+                    (min.unwrap_or(u64::MAX) >> 32) as u32
+                })
+                .collect()
+        };
+
+        Self { files: hashes, minhash }
+    }
+
+    /// Estimated Jaccard similarity of the two submissions' shingles, 0.0-1.0
+    pub fn similarity(&self, other: &Self) -> f32 {
+        if self.minhash.is_empty() || self.minhash.len() != other.minhash.len() {
+            return 0.0;
+        }
+        let equal = self
+            .minhash
+            .iter()
+            .zip(&other.minhash)
+            .filter(|(a, b)| a == b)
+            .count();
+        equal as f32 / self.minhash.len() as f32
+    }
+
+    /// Paths of files whose content also appears in `other`, under any path
+    pub fn shared_files(&self, other: &Self) -> Vec<String> {
+        let theirs: HashSet<&str> = other.files.iter().map(|file| file.hash.as_str()).collect();
+        self.files
+            .iter()
+            .filter(|file| theirs.contains(file.hash.as_str()))
+            .map(|file| file.path.clone())
+            .collect()
+    }
+}
+
+/// `(i, j, similarity)` for every pair of `fingerprints` at least
+/// `threshold` similar, most similar first
+pub fn similar_pairs(fingerprints: &[&SimilarityFingerprint], threshold: f32) -> Vec<(usize, usize, f32)> {
+    let mut pairs = Vec::new();
+    for (i, a) in fingerprints.iter().enumerate() {
+        for (j, b) in fingerprints.iter().enumerate().skip(i + 1) {
+            let similarity = a.similarity(b);
+            if similarity >= threshold {
+                pairs.push((i, j, similarity));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+    pairs
+}
+
+/// 64-bit FNV-1a over the tokens of a shingle, stable across builds
+fn fnv1a(tokens: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for token in tokens {
+        for byte in token.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small but non-trivial submission: a todo API
+    fn submission() -> Vec<(String, String)> {
+        let server = (0..30)
+            .map(|i| {
+                format!(
+                    "app.get('/todos/{i}', async (req, res) => {{\n  const todo = await db.find({i}, req.query.user);\n  res.json({{ id: todo.id, done: todo.done, step: {i} }});\n}});\n"
+                )
+            })
+            .collect::<String>();
+        vec![
+            ("server.js".to_string(), server),
+            (
+                "db.js".to_string(),
+                "export const db = { find: async (id, user) => ({ id, user, done: false }) };\n".to_string(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_near_duplicates_are_similar() {
+        let original = SimilarityFingerprint::from_files(&submission());
+
+        // Same code, reformatted, renamed file and a few changed lines
+        let mut copy = submission();
+        copy[0].0 = "src/index.js".to_string();
+        copy[0].1 = copy[0].1.replace("  ", "\t").replacen("step: 3", "step: 33", 1);
+        copy[1].1 = copy[1].1.replace(" = { ", " =\n{\n");
+        let copy = SimilarityFingerprint::from_files(&copy);
+
+        let unrelated = SimilarityFingerprint::from_files(&[(
+            "main.py".to_string(),
+            (0..30)
+                .map(|i| format!("def handler_{i}(event):\n    return sum(x * {i} for x in event['values'])\n"))
+                .collect(),
+        )]);
+
+        assert!(original.similarity(&copy) >= DEFAULT_SIMILARITY_THRESHOLD);
+        assert!(original.similarity(&unrelated) < 0.1);
+        assert_eq!(original.similarity(&original), 1.0);
+        // Whitespace-only changes keep the file hash
+        assert_eq!(original.shared_files(&copy), vec!["db.js".to_string()]);
+        assert!(original.shared_files(&unrelated).is_empty());
+    }
+
+    #[test]
+    fn test_similar_pairs() {
+        let a = SimilarityFingerprint::from_files(&submission());
+        let b = a.clone();
+        let empty = SimilarityFingerprint::from_files(&[("README.md".to_string(), "hi".to_string())]);
+
+        assert!(empty.minhash.is_empty());
+        assert_eq!(empty.similarity(&empty), 0.0);
+        assert_eq!(similar_pairs(&[&a, &empty, &b], 0.8), vec![(0, 2, 1.0)]);
+        assert!(similar_pairs(&[&a], 0.0).is_empty());
+    }
+}
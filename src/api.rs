@@ -1,42 +1,257 @@
-use actix_web::{web, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use actix_web_lab::sse::{self, Event, Sse};
-use futures::stream::StreamExt;
-use std::time::Duration;
+use futures::future::ready;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use serde::Serialize;
+use std::time::{Duration, Instant};
 
+use crate::checkers::{all_checkers, applies_to};
+use crate::db::AuditEntry;
+use crate::diff::{compare_diagnostics, diff_diagnostics, diff_suggestions, ReviewComparison, ReviewDiff};
 use crate::error::ApiError;
-use crate::grade_orchestrator::GradeStore;
-use crate::orchestrator::ReviewStore;
+use crate::git::ClonedRepo;
+use crate::grade_orchestrator::{unix_now, GradeStore};
+use crate::orchestrator::{unix_millis, ReviewState, ReviewStore};
+use crate::profile::Language;
 use crate::types::{
-    CreateGradeResponse, CreateReviewResponse, GradeRequest, GradeResponse, GradeStatus,
-    ReviewRequest, ReviewResponse,
+    BatchGradeFailure, BatchGradeRequest, BatchGradeResponse, BatchGradeStatus, BatchStatusResponse, CapabilitiesQuery, CapabilitiesResponse,
+    CheckType, CheckerCapability, CreateGradeResponse, CreateReviewResponse,
+    CriterionOverrideRequest, DryRunRequest, EvaluateResponse, EventEnvelope,
+    GradeEvent, GradeReport, GradeRequest, GradeResponse, GradeStatus, GradeStreamEnvelope, GradeStreamQuery,
+    RegradeRequest, ReviewByRepoQuery, ReviewCompareQuery, ReviewHistoryQuery,
+    ReviewRequest, ReviewResponse, ReviewStatus, SimilarityQuery,
 };
+use crate::idempotency::{self, request_hash, Claim, Idempotent, IDEMPOTENT_REPLAYED_HEADER};
+use crate::webhook::{GithubWebhook, PushEvent};
+
+/// Serialize events to JSON payloads
+fn event_payloads<E: Serialize>(events: impl Stream<Item = E>) -> impl Stream<Item = String> {
+    events.filter_map(|event| ready(serde_json::to_string(&event).ok()))
+}
+
+/// How often idle streams send a keep-alive
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// `events` with `ping` interleaved every `KEEP_ALIVE_INTERVAL`, ending when
+/// `events` ends
+fn with_pings<E>(events: impl Stream<Item = E>, ping: fn() -> E) -> impl Stream<Item = E> {
+    let start = tokio::time::Instant::now() + KEEP_ALIVE_INTERVAL;
+    let pings = stream::unfold(tokio::time::interval_at(start, KEEP_ALIVE_INTERVAL), move |mut interval| async move {
+        interval.tick().await;
+        Some((Some(ping()), interval))
+    });
+    // `None` marks the end of `events`, since the pings never end
+    let events = events.map(Some).chain(stream::once(ready(None)));
+    stream::select(events, pings)
+        .take_while(|event| ready(event.is_some()))
+        .filter_map(ready)
+}
+
+/// Grades one merged stream may follow
+const MAX_STREAMED_GRADES: usize = 100;
+
+/// Grade requests one batch may contain
+const MAX_BATCH_GRADES: usize = 50;
+
+/// Events of every grade in `ids` merged in arrival order. The stream ends
+/// once each grade has sent its terminal event.
+async fn merged_grade_payloads(store: &GradeStore, ids: &[String]) -> impl Stream<Item = String> {
+    let mut streams: Vec<BoxStream<'static, GradeStreamEnvelope>> = Vec::with_capacity(ids.len());
+    for id in ids {
+        let grade_id = id.clone();
+        let grade_stream = match store.watch_grade(id).await {
+            Ok(events) => events
+                .map(move |event| GradeStreamEnvelope {
+                    grade_id: grade_id.clone(),
+                    event: Some(event),
+                    error: None,
+                })
+                .boxed(),
+            Err(_) => stream::once(ready(GradeStreamEnvelope {
+                error: Some(format!("Grade {} not found", grade_id)),
+                grade_id,
+                event: None,
+            }))
+            .boxed(),
+        };
+        streams.push(grade_stream);
+    }
+
+    stream::select_all(streams).filter_map(|envelope| async move { serde_json::to_string(&envelope).ok() })
+}
+
+/// Client IP for audit entries, honouring `Forwarded`/`X-Forwarded-For`
+fn client_ip(req: &HttpRequest) -> Option<String> {
+    req.connection_info().realip_remote_addr().map(str::to_string)
+}
+
+fn review_response(state: ReviewState) -> ReviewResponse {
+    ReviewResponse {
+        id: state.id,
+        status: state.status,
+        repo_url: state.repo_url,
+        results: state.results,
+        suggestions: state.suggestions,
+        error: state.error,
+        queue_position: state.queue_position,
+        cached: state.cached,
+        refreshing: state.refreshing,
+        languages: state.profile.map(|profile| profile.languages).unwrap_or_default(),
+    }
+}
+
+fn grade_response(report: GradeReport) -> GradeResponse {
+    GradeResponse {
+        id: report.id,
+        status: report.status,
+        repo_url: report.repo_url,
+        overall_score: report.overall_score,
+        percentage: report.percentage,
+        grade: report.grade,
+        tasks: report.tasks,
+        rubric_breakdown: report.rubric_breakdown,
+        summary: report.summary,
+        language: report.language,
+        is_late: report.is_late,
+        minutes_late: report.minutes_late,
+        commit_sha: report.commit_sha,
+        partial: report.partial,
+        regrade_of: report.regrade_of,
+        model: report.model,
+        queue_position: report.queue_position,
+        created_at: report.created_at,
+        started_grading_at: report.started_grading_at,
+        completed_at: report.completed_at,
+        persisted: report.persisted,
+        batch_id: report.batch_id,
+        error: report.error,
+    }
+}
 
 pub async fn health() -> impl Responder {
     web::Json(serde_json::json!({"status": "ok"}))
 }
 
+/// Detectable languages and the checkers that run for them. With
+/// `review_id`, that review's detected languages and the checkers that
+/// applied to it.
+pub async fn capabilities(
+    query: web::Query<CapabilitiesQuery>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let profile = match &query.review_id {
+        Some(review_id) => {
+            let state = store
+                .get_review(review_id)
+                .await
+                .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", review_id)))?;
+            let profile = state.profile.ok_or_else(|| {
+                ApiError::Conflict(format!("Review {} has not been analyzed yet", review_id))
+            })?;
+            Some(profile)
+        }
+        None => None,
+    };
+
+    let checkers = all_checkers()
+        .into_iter()
+        .filter(|checker| profile.as_ref().is_none_or(|profile| applies_to(checker.as_ref(), profile)))
+        .map(|checker| CheckerCapability {
+            check_type: checker.check_type(),
+            languages: checker.languages().to_vec(),
+        })
+        .collect();
+
+    Ok(web::Json(CapabilitiesResponse {
+        languages: Language::ALL.to_vec(),
+        checkers,
+        profile,
+    }))
+}
+
 pub async fn create_review(
-    body: web::Json<ReviewRequest>,
+    req: HttpRequest,
+    body: web::Json<Idempotent<ReviewRequest>>,
     store: web::Data<ReviewStore>,
 ) -> Result<impl Responder, ApiError> {
-    let request = body.into_inner();
+    let Idempotent { request, idempotency_key } = body.into_inner();
 
     if request.repo_url.is_empty() {
         return Err(ApiError::BadRequest("repo_url is required".to_string()));
     }
+    if request.max_total_duration_secs == Some(0) {
+        return Err(ApiError::BadRequest(
+            "max_total_duration_secs must be greater than 0".to_string(),
+        ));
+    }
 
-    let review_id = store.create_review(request.repo_url.clone()).await;
+    let key = idempotency::idempotency_key(&req, idempotency_key.as_deref())?;
+    let preview = store.preview_limits(request.preview_files, request.preview_chars);
+    let review_id = match &key {
+        Some(key) => {
+            let store = &store;
+            let claim = store
+                .idempotency_keys()
+                .claim(
+                    key,
+                    &request_hash(&request),
+                    |id| async move { store.get_review(&id).await.is_some() },
+                    |id| store.create_review_with_id(id, request.repo_url.clone(), preview),
+                )
+                .await?;
+            match claim {
+                Claim::New(id) => id,
+                Claim::Existing(id) => {
+                    let state = store
+                        .get_review(&id)
+                        .await
+                        .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", id)))?;
+                    return Ok(replayed(CreateReviewResponse {
+                        review_id: id,
+                        queue_position: state.queue_position.unwrap_or(0),
+                    }));
+                }
+            }
+        }
+        None => {
+            store
+                .create_review_with_preview(request.repo_url.clone(), preview)
+                .await
+        }
+    };
+    if let Some(secs) = request.max_total_duration_secs {
+        store.set_max_duration(&review_id, Duration::from_secs(secs)).await;
+    }
+    store.set_cache_mode(&review_id, request.cache_mode).await;
+    if let Some(min_source_lines) = request.min_source_lines {
+        store.set_min_source_lines(&review_id, min_source_lines).await;
+    }
+    store.audit(
+        AuditEntry::new("create_review", "review", &review_id, client_ip(&req))
+            .with_metadata(bson::doc! { "repo_url": &request.repo_url }),
+    );
 
-    let store_clone = store.get_ref().clone();
-    let review_id_clone = review_id.clone();
-    tokio::spawn(async move {
-        if let Err(e) = store_clone.run_review(&review_id_clone).await {
-            tracing::error!("Review {} failed: {}", review_id_clone, e);
-            store_clone.mark_failed(&review_id_clone, e.to_string()).await;
+    let queue_position = match store.enqueue_review(&review_id).await {
+        Ok(position) => position,
+        Err(e) => {
+            if let Some(key) = &key {
+                store.idempotency_keys().release(key).await;
+            }
+            return Err(e);
         }
-    });
+    };
+
+    Ok(HttpResponse::Ok().json(CreateReviewResponse {
+        review_id,
+        queue_position,
+    }))
+}
 
-    Ok(web::Json(CreateReviewResponse { review_id }))
+/// Response to a create request repeated with the same idempotency key
+fn replayed(body: impl serde::Serialize) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"))
+        .json(body)
 }
 
 pub async fn get_review(
@@ -50,48 +265,240 @@ pub async fn get_review(
         .await
         .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", review_id)))?;
 
-    Ok(web::Json(ReviewResponse {
-        id: state.id.clone(),
-        status: state.status,
-        repo_url: state.repo_url.clone(),
-        results: state.results.clone(),
-        suggestions: state.suggestions.clone(),
-        error: state.error.clone(),
-    }))
+    Ok(web::Json(review_response(state)))
 }
 
-pub async fn stream_review(
+/// Ordered event log of a finished review
+pub async fn get_review_events(
     path: web::Path<String>,
     store: web::Data<ReviewStore>,
 ) -> Result<impl Responder, ApiError> {
     let review_id = path.into_inner();
+    Ok(web::Json(store.review_events(&review_id).await?))
+}
 
-    let receiver = store
-        .subscribe(&review_id)
+pub async fn get_review_by_repo(
+    query: web::Query<ReviewByRepoQuery>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let repo_url = query.into_inner().url;
+
+    let state = store
+        .get_latest_review_for_repo(&repo_url)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("No review found for {}", repo_url)))?;
+
+    Ok(web::Json(review_response(state)))
+}
+
+pub async fn list_reviews(
+    query: web::Query<ReviewHistoryQuery>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let repo_url = query.into_inner().repo_url;
+
+    let reviews = store.list_reviews(&repo_url).await?;
+
+    Ok(web::Json(
+        reviews.into_iter().map(review_response).collect::<Vec<_>>(),
+    ))
+}
+
+pub async fn revalidate_review(
+    req: HttpRequest,
+    path: web::Path<String>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let review_id = path.into_inner();
+
+    store.rerun_validation(&review_id).await?;
+    store.audit(AuditEntry::new(
+        "revalidate_review",
+        "review",
+        &review_id,
+        client_ip(&req),
+    ));
+
+    let state = store
+        .get_review(&review_id)
         .await
         .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", review_id)))?;
 
-    let stream = tokio_stream::wrappers::BroadcastStream::new(receiver).filter_map(|result| async move {
-        match result {
-            Ok(event) => {
-                let data = serde_json::to_string(&event).ok()?;
-                Some(Ok::<_, std::convert::Infallible>(Event::Data(
-                    sse::Data::new(data),
-                )))
-            }
-            Err(_) => None,
+    Ok(web::Json(review_response(state)))
+}
+
+pub async fn cancel_review(
+    req: HttpRequest,
+    path: web::Path<String>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let review_id = path.into_inner();
+
+    store.cancel_review(&review_id).await?;
+    store.audit(AuditEntry::new(
+        "cancel_review",
+        "review",
+        &review_id,
+        client_ip(&req),
+    ));
+
+    let state = store
+        .get_review(&review_id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", review_id)))?;
+
+    Ok(web::Json(review_response(state)))
+}
+
+/// Abort one checker of a running review; the review continues without it
+pub async fn abort_checker(
+    req: HttpRequest,
+    path: web::Path<(String, CheckType)>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let (review_id, check_type) = path.into_inner();
+
+    store.abort_checker(&review_id, check_type).await?;
+    store.audit(AuditEntry::new("abort_checker", "review", &review_id, client_ip(&req)));
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn compare_reviews(
+    path: web::Path<(String, String)>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let (base_id, review_id) = path.into_inner();
+
+    let base = store
+        .get_review(&base_id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", base_id)))?;
+    let review = store
+        .get_review(&review_id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", review_id)))?;
+
+    if base.repo_url != review.repo_url {
+        return Err(ApiError::Unprocessable(format!(
+            "Reviews are of different repositories: {} and {}",
+            base.repo_url, review.repo_url
+        )));
+    }
+
+    Ok(web::Json(ReviewDiff {
+        diagnostics: diff_diagnostics(&base.results, &review.results),
+        suggestions: diff_suggestions(&base.suggestions, &review.suggestions),
+        base_review_id: base_id,
+        review_id,
+    }))
+}
+
+/// Issues fixed, introduced and persisted between review `id_a` and a later
+/// review `id_b`, e.g. a student's resubmission
+pub async fn compare_review_pair(
+    query: web::Query<ReviewCompareQuery>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let ReviewCompareQuery { id_a, id_b } = query.into_inner();
+
+    let mut reviews = Vec::with_capacity(2);
+    for id in [&id_a, &id_b] {
+        let review = store
+            .get_review(id)
+            .await
+            .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", id)))?;
+        if review.status != ReviewStatus::Completed {
+            return Err(ApiError::Conflict(format!("Review {} is not completed", id)));
         }
-    });
+        reviews.push(review);
+    }
 
-    Ok(Sse::from_stream(stream).with_keep_alive(Duration::from_secs(15)))
+    Ok(web::Json(ReviewComparison {
+        diagnostics: compare_diagnostics(&reviews[0].results, &reviews[1].results),
+        id_a,
+        id_b,
+    }))
+}
+
+pub async fn stream_review(
+    path: web::Path<String>,
+    store: web::Data<ReviewStore>,
+) -> Result<impl Responder, ApiError> {
+    let review_id = path.into_inner();
+
+    let events = store.watch_review(&review_id).await?;
+
+    let stream = event_payloads(events)
+        .map(|data| Ok::<_, std::convert::Infallible>(Event::Data(sse::Data::new(data))));
+
+    Ok(Sse::from_stream(stream).with_keep_alive(KEEP_ALIVE_INTERVAL))
 }
 
 pub async fn create_grade(
-    body: web::Json<GradeRequest>,
+    req: HttpRequest,
+    body: web::Json<Idempotent<GradeRequest>>,
     store: web::Data<GradeStore>,
 ) -> Result<impl Responder, ApiError> {
-    let request = body.into_inner();
+    let Idempotent { request, idempotency_key } = body.into_inner();
+    validate_grade_request(&store, &request)?;
+
+    let key = idempotency::idempotency_key(&req, idempotency_key.as_deref())?;
+    let grade_id = match &key {
+        Some(key) => {
+            let store = &store;
+            let claim = store
+                .idempotency_keys()
+                .claim(
+                    key,
+                    &request_hash(&request),
+                    |id| async move { store.grade_status(&id).await.is_some() },
+                    |id| store.create_grade_with_id(id, request.clone()),
+                )
+                .await?;
+            match claim {
+                Claim::New(id) => id,
+                Claim::Existing(id) => {
+                    let (status, queue_position) = store
+                        .grade_status(&id)
+                        .await
+                        .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", id)))?;
+                    return Ok(replayed(CreateGradeResponse {
+                        grade_id: id,
+                        status,
+                        queue_position: queue_position.unwrap_or(0),
+                        estimated_duration_ms: None,
+                    }));
+                }
+            }
+        }
+        None => store.create_grade(request.clone()).await,
+    };
+    store.audit(
+        AuditEntry::new("create_grade", "grade", &grade_id, client_ip(&req))
+            .with_metadata(bson::doc! { "repo_url": &request.repo_url }),
+    );
+
+    let estimated_duration_ms = store.estimate_duration_ms(&request).await;
+    let queue_position = match store.enqueue_grade(&grade_id, request).await {
+        Ok(position) => position,
+        Err(e) => {
+            if let Some(key) = &key {
+                store.idempotency_keys().release(key).await;
+            }
+            return Err(e);
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(CreateGradeResponse {
+        grade_id,
+        status: GradeStatus::Queued,
+        queue_position,
+        estimated_duration_ms,
+    }))
+}
 
+fn validate_grade_request(store: &GradeStore, request: &GradeRequest) -> Result<(), ApiError> {
     if request.repo_url.is_empty() {
         return Err(ApiError::BadRequest("repo_url is required".to_string()));
     }
@@ -100,22 +507,292 @@ pub async fn create_grade(
         return Err(ApiError::BadRequest("tasks cannot be empty".to_string()));
     }
 
-    let grade_id = store.create_grade(request.clone()).await;
+    let errors = store.config_errors(request);
+    if !errors.is_empty() {
+        return Err(ApiError::BadRequest(errors.join("; ")));
+    }
+
+    store.validate_model(request.model.as_deref())
+}
+
+/// Create and queue several grades at once. Nothing is created unless every
+/// request is valid and the queue has room for all of them; a grade that
+/// still can't be queued is reported in `failed` instead of `grade_ids`.
+pub async fn create_grade_batch(
+    req: HttpRequest,
+    body: web::Json<BatchGradeRequest>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let requests = body.into_inner().requests;
+
+    if requests.is_empty() {
+        return Err(ApiError::BadRequest("requests cannot be empty".to_string()));
+    }
+    if requests.len() > MAX_BATCH_GRADES {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} grades can be created in one batch",
+            MAX_BATCH_GRADES
+        )));
+    }
+
+    for (index, request) in requests.iter().enumerate() {
+        validate_grade_request(&store, request).map_err(|e| match e {
+            ApiError::BadRequest(message) => {
+                ApiError::BadRequest(format!("requests[{}]: {}", index, message))
+            }
+            other => other,
+        })?;
+    }
+    if store.queue_free_slots() < requests.len() {
+        return Err(ApiError::ServiceUnavailable(
+            "job queue has no room for the batch, try again later".to_string(),
+        ));
+    }
 
-    let store_clone = store.get_ref().clone();
-    let grade_id_clone = grade_id.clone();
-    tokio::spawn(async move {
-        if let Err(e) = store_clone.run_grade(&grade_id_clone, request).await {
-            tracing::error!("Grade {} failed: {}", grade_id_clone, e);
+    let (batch_id, created) = store.create_grade_batch(&requests).await;
+    let mut grade_ids = Vec::with_capacity(created.len());
+    let mut failed = Vec::new();
+    for (index, (grade_id, request)) in created.into_iter().zip(requests).enumerate() {
+        // Only a grade queued concurrently can take the room checked above.
+        // A grade that isn't queued is failed and dropped, so its id is left out.
+        match store.enqueue_grade(&grade_id, request).await {
+            Ok(_) => grade_ids.push(grade_id),
+            Err(e) => {
+                tracing::warn!("Grade {} of batch {} was not queued: {}", grade_id, batch_id, e);
+                failed.push(BatchGradeFailure { index, error: e.to_string() });
+            }
         }
-    });
+    }
+    store.audit(
+        AuditEntry::new("create_grade_batch", "grade_batch", &batch_id, client_ip(&req))
+            .with_metadata(bson::doc! { "grade_count": grade_ids.len() as i64 }),
+    );
+
+    Ok(web::Json(BatchGradeResponse {
+        batch_id,
+        grade_ids,
+        failed,
+        created_at: unix_now(),
+    }))
+}
+
+pub async fn get_batch_status(
+    path: web::Path<String>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let batch_id = path.into_inner();
+
+    let reports = store.batch_grades(&batch_id).await;
+    if reports.is_empty() {
+        return Err(ApiError::NotFound(format!("Batch {} not found", batch_id)));
+    }
+
+    Ok(web::Json(batch_status(batch_id, reports)))
+}
+
+fn batch_status(batch_id: String, reports: Vec<GradeReport>) -> BatchStatusResponse {
+    let count = |status: GradeStatus| reports.iter().filter(|r| r.status == status).count();
+    let completed = count(GradeStatus::Completed);
+    let failed = count(GradeStatus::Failed);
+
+    BatchStatusResponse {
+        batch_id,
+        total: reports.len(),
+        completed,
+        failed,
+        in_progress: reports.len() - completed - failed,
+        grades: reports
+            .into_iter()
+            .map(|report| BatchGradeStatus {
+                grade_id: report.id,
+                repo_url: report.repo_url,
+                status: report.status,
+                percentage: report.percentage,
+                error: report.error,
+            })
+            .collect(),
+    }
+}
+
+pub async fn regrade(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<RegradeRequest>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let original_id = path.into_inner();
+    let tasks = body.into_inner().tasks;
+
+    if tasks.is_empty() {
+        return Err(ApiError::BadRequest("tasks cannot be empty".to_string()));
+    }
+
+    let (grade_id, request) = store.create_regrade(&original_id, tasks).await?;
+    store.audit(
+        AuditEntry::new("regrade", "grade", &grade_id, client_ip(&req))
+            .with_metadata(bson::doc! { "regrade_of": &original_id }),
+    );
+
+    let estimated_duration_ms = store.estimate_duration_ms(&request).await;
+    let queue_position = store.enqueue_grade(&grade_id, request).await?;
 
     Ok(web::Json(CreateGradeResponse {
         grade_id,
-        status: GradeStatus::Pending,
+        status: GradeStatus::Queued,
+        queue_position,
+        estimated_duration_ms,
     }))
 }
 
+/// Record an instructor's verdict on one criterion and rescore the grade
+pub async fn override_criterion(
+    req: HttpRequest,
+    path: web::Path<(String, usize, usize)>,
+    body: web::Json<CriterionOverrideRequest>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let (grade_id, task_index, criterion_index) = path.into_inner();
+    let verdict = body.into_inner();
+    let passed = verdict.passed;
+
+    let report = store
+        .override_criterion(&grade_id, task_index, criterion_index, verdict)
+        .await?;
+    store.audit(
+        AuditEntry::new("override_criterion", "grade", &grade_id, client_ip(&req)).with_metadata(
+            bson::doc! {
+                "task_index": task_index as i64,
+                "criterion_index": criterion_index as i64,
+                "passed": passed,
+            },
+        ),
+    );
+
+    Ok(web::Json(grade_response(report)))
+}
+
+/// Agreement between instructor overrides and grader confidence
+pub async fn grade_calibration(store: web::Data<GradeStore>) -> Result<impl Responder, ApiError> {
+    Ok(web::Json(store.calibration().await?))
+}
+
+/// Pairs of submissions in a course whose fingerprints look alike
+pub async fn grade_similarity(
+    store: web::Data<GradeStore>,
+    query: web::Query<SimilarityQuery>,
+) -> Result<impl Responder, ApiError> {
+    let query = query.into_inner();
+    Ok(web::Json(
+        store
+            .similarity(&query.course_title, query.threshold, query.limit)
+            .await?,
+    ))
+}
+
+/// Re-grade this grade's branch whenever it is pushed to
+pub async fn subscribe_grade(
+    req: HttpRequest,
+    path: web::Path<String>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let grade_id = path.into_inner();
+
+    store.subscribe_regrades(&grade_id).await?;
+    store.audit(AuditEntry::new("subscribe_grade", "grade", &grade_id, client_ip(&req)));
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn unsubscribe_grade(
+    req: HttpRequest,
+    path: web::Path<String>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let grade_id = path.into_inner();
+
+    store.unsubscribe_regrades(&grade_id).await?;
+    store.audit(AuditEntry::new("unsubscribe_grade", "grade", &grade_id, client_ip(&req)));
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// GitHub webhook deliveries. Pushes re-grade subscribed grades of the pushed
+/// branch; every other event, and pushes nothing follows, get 204.
+pub async fn github_webhook(
+    req: HttpRequest,
+    body: web::Bytes,
+    webhook: Option<web::Data<GithubWebhook>>,
+    store: web::Data<GradeStore>,
+) -> Result<HttpResponse, ApiError> {
+    let webhook =
+        webhook.ok_or_else(|| ApiError::NotFound("GitHub webhooks are not configured".to_string()))?;
+
+    let signature = req
+        .headers()
+        .get("x-hub-signature-256")
+        .and_then(|value| value.to_str().ok());
+    if !webhook.verify(&body, signature) {
+        return Err(ApiError::Unauthorized("invalid webhook signature".to_string()));
+    }
+
+    let event = req.headers().get("x-github-event").and_then(|value| value.to_str().ok());
+    if event != Some("push") {
+        return Ok(HttpResponse::NoContent().finish());
+    }
+
+    let push: PushEvent = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::BadRequest(format!("invalid push event: {}", e)))?;
+    let regrades = store.regrade_on_push(&push).await?;
+    if regrades.is_empty() {
+        return Ok(HttpResponse::NoContent().finish());
+    }
+
+    let mut grade_ids = Vec::new();
+    for (grade_id, request) in regrades {
+        store.audit(
+            AuditEntry::new("webhook_regrade", "grade", &grade_id, client_ip(&req))
+                .with_metadata(bson::doc! { "commit_sha": &push.after }),
+        );
+        match store.enqueue_grade(&grade_id, request).await {
+            Ok(_) => grade_ids.push(grade_id),
+            Err(e) => tracing::warn!("Push re-grade {} was not queued: {}", grade_id, e),
+        }
+    }
+
+    Ok(HttpResponse::Accepted().json(serde_json::json!({ "grade_ids": grade_ids })))
+}
+
+pub async fn cancel_grade(
+    req: HttpRequest,
+    path: web::Path<String>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let grade_id = path.into_inner();
+
+    store.cancel_grade(&grade_id).await?;
+    store.audit(AuditEntry::new(
+        "cancel_grade",
+        "grade",
+        &grade_id,
+        client_ip(&req),
+    ));
+
+    let report = store
+        .get_grade(&grade_id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", grade_id)))?;
+
+    Ok(web::Json(grade_response(report)))
+}
+
+pub async fn dry_run_grade(
+    body: web::Json<DryRunRequest>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let result = store.dry_run_with_context(&body).await?;
+    Ok(web::Json(result))
+}
+
 pub async fn get_grade(
     path: web::Path<String>,
     store: web::Data<GradeStore>,
@@ -127,17 +804,27 @@ pub async fn get_grade(
         .await
         .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", grade_id)))?;
 
-    Ok(web::Json(GradeResponse {
-        id: report.id,
-        status: report.status,
-        repo_url: report.repo_url,
-        overall_score: report.overall_score,
-        percentage: report.percentage,
-        grade: report.grade,
-        tasks: report.tasks,
-        summary: report.summary,
-        error: report.error,
-    }))
+    Ok(web::Json(grade_response(report)))
+}
+
+/// One task's result, for views that don't need the whole report
+pub async fn get_grade_task(
+    path: web::Path<(String, usize)>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let (grade_id, task_index) = path.into_inner();
+
+    let report = store
+        .get_grade(&grade_id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", grade_id)))?;
+    let task = report
+        .tasks
+        .into_iter()
+        .nth(task_index)
+        .ok_or_else(|| ApiError::NotFound(format!("Grade {} has no task {}", grade_id, task_index)))?;
+
+    Ok(web::Json(task))
 }
 
 pub async fn stream_grade(
@@ -146,35 +833,682 @@ pub async fn stream_grade(
 ) -> Result<impl Responder, ApiError> {
     let grade_id = path.into_inner();
 
-    let receiver = store
-        .subscribe(&grade_id)
+    let events = store.watch_grade(&grade_id).await?;
+
+    let stream = event_payloads(events)
+        .map(|data| Ok::<_, std::convert::Infallible>(Event::Data(sse::Data::new(data))));
+
+    Ok(Sse::from_stream(stream).with_keep_alive(KEEP_ALIVE_INTERVAL))
+}
+
+/// Ordered event log of a finished grade
+pub async fn get_grade_events(
+    path: web::Path<String>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let grade_id = path.into_inner();
+    Ok(web::Json(store.grade_events(&grade_id).await?))
+}
+
+/// Grade events as JSON Lines, for clients that don't speak SSE
+pub async fn stream_grade_jsonl(
+    path: web::Path<String>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let grade_id = path.into_inner();
+
+    let events = store.watch_grade(&grade_id).await?;
+
+    let lines = event_payloads(with_pings(events, || EventEnvelope::new(0, unix_millis(), GradeEvent::Ping)))
+        .map(|line| Ok::<_, std::convert::Infallible>(web::Bytes::from(line + "\n")));
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(lines))
+}
+
+/// Follow several grades on one SSE stream, e.g. a whole cohort
+pub async fn stream_grades(
+    query: web::Query<GradeStreamQuery>,
+    store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let mut ids: Vec<String> = Vec::new();
+    for id in query.ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+        if !ids.iter().any(|known| known == id) {
+            ids.push(id.to_string());
+        }
+    }
+    if ids.is_empty() {
+        return Err(ApiError::BadRequest("ids must list at least one grade id".to_string()));
+    }
+    if ids.len() > MAX_STREAMED_GRADES {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} grades can be streamed at once",
+            MAX_STREAMED_GRADES
+        )));
+    }
+
+    let stream = merged_grade_payloads(&store, &ids)
         .await
-        .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", grade_id)))?;
+        .map(|data| Ok::<_, std::convert::Infallible>(Event::Data(sse::Data::new(data))));
 
-    let stream = tokio_stream::wrappers::BroadcastStream::new(receiver).filter_map(|result| async move {
-        match result {
-            Ok(event) => {
-                let data = serde_json::to_string(&event).ok()?;
-                Some(Ok::<_, std::convert::Infallible>(Event::Data(
-                    sse::Data::new(data),
-                )))
-            }
-            Err(_) => None,
+    Ok(Sse::from_stream(stream).with_keep_alive(KEEP_ALIVE_INTERVAL))
+}
+
+/// Queue a review and a grade of a repository that run from a single clone.
+/// Progress can be followed on each job's stream endpoint.
+pub async fn evaluate(
+    req: HttpRequest,
+    body: web::Json<GradeRequest>,
+    review_store: web::Data<ReviewStore>,
+    grade_store: web::Data<GradeStore>,
+) -> Result<impl Responder, ApiError> {
+    let request = body.into_inner();
+    validate_grade_request(&grade_store, &request)?;
+
+    let review_id = review_store.create_review(request.repo_url.clone()).await;
+    if request.config.is_some() {
+        let config = grade_store.config_for(&request);
+        review_store.set_min_source_lines(&review_id, config.min_source_lines).await;
+    }
+    let grade_id = grade_store.create_grade(request.clone()).await;
+    let ip = client_ip(&req);
+    review_store.audit(
+        AuditEntry::new("evaluate", "review", &review_id, ip.clone())
+            .with_metadata(bson::doc! { "repo_url": &request.repo_url, "grade_id": &grade_id }),
+    );
+    grade_store.audit(
+        AuditEntry::new("evaluate", "grade", &grade_id, ip)
+            .with_metadata(bson::doc! { "repo_url": &request.repo_url, "review_id": &review_id }),
+    );
+
+    // Runs on the grade queue, so it counts against the grade workers
+    let job = run_evaluation(
+        review_store.get_ref().clone(),
+        grade_store.get_ref().clone(),
+        review_id.clone(),
+        grade_id.clone(),
+        request,
+    );
+    let queue_position = match grade_store.enqueue_job(&grade_id, job).await {
+        Ok(position) => position,
+        Err(e) => {
+            review_store.mark_failed(&review_id, e.to_string()).await;
+            return Err(e);
         }
-    });
+    };
 
-    Ok(Sse::from_stream(stream).with_keep_alive(Duration::from_secs(15)))
+    Ok(HttpResponse::Ok().json(EvaluateResponse {
+        review_id,
+        grade_id,
+        queue_position,
+    }))
+}
+
+/// Clone the repository once and run the review and grade against it
+async fn run_evaluation(
+    review_store: ReviewStore,
+    grade_store: GradeStore,
+    review_id: String,
+    grade_id: String,
+    request: GradeRequest,
+) {
+    let fail_both = |e: ApiError| {
+        let (review_store, grade_store) = (&review_store, &grade_store);
+        let (review_id, grade_id) = (&review_id, &grade_id);
+        async move {
+            review_store.mark_failed(review_id, e.to_string()).await;
+            grade_store.mark_failed(grade_id, e.to_string()).await;
+        }
+    };
+
+    let start = Instant::now();
+    let cloning = async {
+        review_store.begin_cloning(&review_id).await?;
+        grade_store.begin_cloning(&grade_id).await?;
+        let options = grade_store.clone_options(&request);
+        ClonedRepo::from_url_with_options(&request.repo_url, options).await
+    };
+    let cloned_repo = match cloning.await {
+        Ok(repo) => repo,
+        Err(e) => return fail_both(e).await,
+    };
+
+    let (review_result, grade_result) = tokio::join!(
+        review_store.run_review_with_repo(&review_id, &cloned_repo, start),
+        grade_store.run_grade_with_repo(&grade_id, request, &cloned_repo, start),
+    );
+
+    if let Err(e) = review_result {
+        tracing::error!("Review {} failed: {}", review_id, e);
+        review_store.mark_failed(&review_id, e.to_string()).await;
+    }
+    if let Err(e) = grade_result {
+        tracing::error!("Grade {} failed: {}", grade_id, e);
+        grade_store.mark_failed(&grade_id, e.to_string()).await;
+    }
 }
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
             .route("/health", web::get().to(health))
+            .route("/capabilities", web::get().to(capabilities))
             .route("/review", web::post().to(create_review))
+            .route("/reviews", web::get().to(list_reviews))
+            .route("/review/by-repo", web::get().to(get_review_by_repo))
+            .route("/review/compare", web::get().to(compare_review_pair))
             .route("/review/{id}", web::get().to(get_review))
             .route("/review/{id}/stream", web::get().to(stream_review))
+            .route("/review/{id}/events", web::get().to(get_review_events))
+            .route("/review/{id}/revalidate", web::post().to(revalidate_review))
+            .route("/review/{id}/cancel", web::post().to(cancel_review))
+            .route("/review/{id}/checker/{check_type}", web::delete().to(abort_checker))
+            .route("/review/{id}/compare/{other_id}", web::get().to(compare_reviews))
             .route("/grade", web::post().to(create_grade))
+            .route("/grade/dry-run", web::post().to(dry_run_grade))
+            .route("/grade/stream", web::get().to(stream_grades))
+            .route("/grade/batch", web::post().to(create_grade_batch))
+            .route("/grade/batch/{batch_id}/status", web::get().to(get_batch_status))
             .route("/grade/{id}", web::get().to(get_grade))
-            .route("/grade/{id}/stream", web::get().to(stream_grade)),
+            .route("/grade/{id}/stream", web::get().to(stream_grade))
+            .route("/grade/{id}/stream.jsonl", web::get().to(stream_grade_jsonl))
+            .route("/grade/{id}/events", web::get().to(get_grade_events))
+            .route("/grade/{id}/tasks/{task_index}", web::get().to(get_grade_task))
+            .route("/grade/{id}/regrade", web::post().to(regrade))
+            .route("/grade/{id}/cancel", web::post().to(cancel_grade))
+            .route(
+                "/grade/{id}/criteria/{task_index}/{criterion_index}/override",
+                web::post().to(override_criterion),
+            )
+            .route("/grades/calibration", web::get().to(grade_calibration))
+            .route("/grades/similarity", web::get().to(grade_similarity))
+            .route("/grade/{id}/subscribe", web::post().to(subscribe_grade))
+            .route("/grade/{id}/subscribe", web::delete().to(unsubscribe_grade))
+            .route("/webhooks/github", web::post().to(github_webhook))
+            .route("/evaluate", web::post().to(evaluate)),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grade_orchestrator::tests::grade_request;
+    use crate::orchestrator::{watch_events, EventLog};
+    use crate::types::{CriterionOutcome, GradeConfigOverrides, ReviewCacheMode};
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_client_ip_prefers_forwarded_header() {
+        let req = TestRequest::default()
+            .insert_header(("x-forwarded-for", "203.0.113.7"))
+            .peer_addr("10.0.0.1:5000".parse().unwrap())
+            .to_http_request();
+        assert_eq!(client_ip(&req).as_deref(), Some("203.0.113.7"));
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:5000".parse().unwrap())
+            .to_http_request();
+        assert_eq!(client_ip(&req).as_deref(), Some("10.0.0.1"));
+    }
+
+    #[tokio::test]
+    async fn test_lagged_subscriber_receives_events_dropped() {
+        // broadcast capacities are rounded up to a power of two
+        let log = EventLog::new(128);
+        let receiver = log.subscribe();
+
+        // Slow consumer: nothing is read until 200 events have been sent
+        for criterion_index in 0..200 {
+            log.send(GradeEvent::CriterionChecked {
+                    task_index: 0,
+                    criterion_index,
+                    criterion: "c".to_string(),
+                    criterion_id: None,
+                    passed: true,
+                    outcome: CriterionOutcome::Passed,
+                    confidence: 1.0,
+                });
+        }
+        drop(log);
+
+        let events = watch_events(receiver, |count| GradeEvent::EventsDropped { count }, GradeEvent::is_terminal);
+        let payloads: Vec<String> = event_payloads(events).collect().await;
+
+        assert_eq!(payloads.len(), 129);
+        assert!(payloads[0].starts_with(r#"{"seq":0,"timestamp_ms":"#));
+        assert!(payloads[0].ends_with(r#""type":"events_dropped","count":72}"#));
+        // The missed events' numbers are skipped
+        assert!(payloads[1].starts_with(r#"{"seq":73,"#));
+        assert!(payloads[1].contains(r#""criterion_index":72"#));
+        assert!(payloads[128].contains(r#""criterion_index":199"#));
+    }
+
+    #[tokio::test]
+    async fn test_merged_grade_stream() {
+        let store = GradeStore::new(3600, None, Default::default(), None);
+        let request = grade_request(vec![]);
+        let running = store.create_grade(request.clone()).await;
+        let finished = store.create_grade(request).await;
+        store.mark_failed(&finished, "boom".to_string()).await;
+
+        let ids = vec![running.clone(), finished.clone(), "missing".to_string()];
+        let payloads = merged_grade_payloads(&store, &ids).await;
+
+        store.begin_cloning(&running).await.unwrap();
+        store.mark_failed(&running, "clone failed".to_string()).await;
+        // Events after the terminal one are not forwarded
+        store.mark_failed(&running, "again".to_string()).await;
+
+        let envelopes: Vec<serde_json::Value> = payloads
+            .map(|payload| serde_json::from_str(&payload).unwrap())
+            .collect()
+            .await;
+        let of = |id: &str| -> Vec<String> {
+            envelopes
+                .iter()
+                .filter(|e| e["grade_id"] == id)
+                .map(|e| match e.get("event") {
+                    Some(event) => event["type"].as_str().unwrap().to_string(),
+                    None => e["error"].as_str().unwrap().to_string(),
+                })
+                .collect()
+        };
+
+        assert_eq!(of(&running), vec!["cloning_started", "grade_failed"]);
+        assert_eq!(of(&finished), vec!["grade_failed"]);
+        assert_eq!(of("missing"), vec!["Grade missing not found"]);
+    }
+
+    #[actix_web::test]
+    async fn test_grade_stream_jsonl() {
+        let store = web::Data::new(GradeStore::new(3600, None, Default::default(), None));
+        let request = grade_request(vec![]);
+        let id = store.create_grade(request).await;
+        store.mark_failed(&id, "clone failed".to_string()).await;
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+
+        let request = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/grade/{}/stream.jsonl", id))
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        assert_eq!(response.headers().get("content-type").unwrap(), "application/x-ndjson");
+        let body = actix_web::test::read_body(response).await;
+        let lines: Vec<serde_json::Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["type"], "grade_failed");
+        assert!(lines[0]["seq"].as_u64().unwrap() > 0);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/grade/missing/stream.jsonl")
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, request).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_grade_events() {
+        let store = web::Data::new(GradeStore::new(3600, None, Default::default(), None));
+        let request = grade_request(vec![]);
+        let id = store.create_grade(request).await;
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+        let get_events = |id: &str| actix_web::test::TestRequest::get().uri(&format!("/api/grade/{}/events", id)).to_request();
+
+        // Running grades have no final log yet
+        store.begin_cloning(&id).await.unwrap();
+        assert_eq!(actix_web::test::call_service(&app, get_events(&id)).await.status(), 409);
+
+        store.mark_failed(&id, "clone failed".to_string()).await;
+        let events: Vec<serde_json::Value> = actix_web::test::call_and_read_body_json(&app, get_events(&id)).await;
+        assert_eq!(events.first().unwrap()["type"], "grade_started");
+        assert_eq!(events.last().unwrap()["type"], "grade_failed");
+        let seqs: Vec<u64> = events.iter().map(|e| e["seq"].as_u64().unwrap()).collect();
+        assert_eq!(seqs, (1..=events.len() as u64).collect::<Vec<_>>());
+        assert!(events.iter().all(|e| e["timestamp_ms"].as_u64().unwrap() > 0));
+
+        assert_eq!(actix_web::test::call_service(&app, get_events("missing")).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_grade_task() {
+        use crate::types::{Criterion, GradeConfig, GradeTask, NoSourcePolicy};
+
+        // A repository without source code is graded 0% without an LLM
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("README.md"), "# Notes\n").unwrap();
+        let repo = crate::git::ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
+        let task = |title: &str| GradeTask {
+            title: title.to_string(),
+            description: None,
+            acceptance_criteria: vec![Criterion {
+                id: None,
+                description: format!("{} works", title),
+                weight: 1.0,
+                examples: vec![],
+                model_hint: None,
+                rubric_category: None,
+            }],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let request = GradeRequest {
+            config: Some(GradeConfigOverrides::from(GradeConfig {
+                no_source_policy: NoSourcePolicy::ZeroGrade,
+                ..GradeConfig::default()
+            })),
+            ..grade_request(vec![task("Login"), task("Signup")])
+        };
+        let store = web::Data::new(GradeStore::new(3600, None, Default::default(), None));
+        let id = store.create_grade(request.clone()).await;
+        store
+            .run_grade_with_repo(&id, request, &repo, std::time::Instant::now())
+            .await
+            .unwrap();
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+        let get_task = |id: &str, index: &str| {
+            actix_web::test::TestRequest::get()
+                .uri(&format!("/api/grade/{}/tasks/{}", id, index))
+                .to_request()
+        };
+
+        let task: serde_json::Value = actix_web::test::call_and_read_body_json(&app, get_task(&id, "1")).await;
+        assert_eq!(task["task_title"], "Signup");
+        assert_eq!(task["criteria_results"][0]["criterion"], "Signup works");
+
+        assert_eq!(actix_web::test::call_service(&app, get_task(&id, "2")).await.status(), 404);
+        assert_eq!(actix_web::test::call_service(&app, get_task("missing", "0")).await.status(), 404);
+        assert_eq!(actix_web::test::call_service(&app, get_task(&id, "first")).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_create_with_idempotency_key() {
+        let grades = web::Data::new(GradeStore::new(3600, None, Default::default(), None));
+        let reviews = web::Data::new(ReviewStore::new(3600, None, None));
+        let app = actix_web::test::init_service(
+            actix_web::App::new()
+                .app_data(grades.clone())
+                .app_data(reviews.clone())
+                .configure(configure),
+        )
+        .await;
+        let grade = |key: Option<&str>, title: &str| {
+            let mut request = actix_web::test::TestRequest::post().uri("/api/grade").set_json(serde_json::json!({
+                "repo_url": "not-a-repo",
+                "tasks": [{"title": title, "acceptance_criteria": [{"description": "Works", "weight": 1.0}]}],
+            }));
+            if let Some(key) = key {
+                request = request.insert_header((crate::idempotency::IDEMPOTENCY_KEY_HEADER, key));
+            }
+            request.to_request()
+        };
+        let grade_id = |response: actix_web::dev::ServiceResponse| async {
+            let replayed = response.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER);
+            let body: CreateGradeResponse = actix_web::test::read_body_json(response).await;
+            (body.grade_id, replayed)
+        };
+
+        // Simultaneous retries create one grade
+        let (first, second) = futures::join!(
+            actix_web::test::call_service(&app, grade(Some("k1"), "Login")),
+            actix_web::test::call_service(&app, grade(Some("k1"), "Login")),
+        );
+        let (first, second) = (grade_id(first).await, grade_id(second).await);
+        assert_eq!(first.0, second.0);
+        assert!(first.1 != second.1);
+
+        let (retry, replayed) = grade_id(actix_web::test::call_service(&app, grade(Some("k1"), "Login")).await).await;
+        assert_eq!(retry, first.0);
+        assert!(replayed);
+        let response = actix_web::test::call_service(&app, grade(Some("k1"), "Signup")).await;
+        assert_eq!(response.status(), 409);
+        let (other, _) = grade_id(actix_web::test::call_service(&app, grade(None, "Login")).await).await;
+        assert_ne!(other, first.0);
+
+        let review = || {
+            actix_web::test::TestRequest::post()
+                .uri("/api/review")
+                .set_json(serde_json::json!({"repo_url": "not-a-repo", "idempotency_key": "r1"}))
+                .to_request()
+        };
+        let created: CreateReviewResponse = actix_web::test::call_and_read_body_json(&app, review()).await;
+        let repeated = actix_web::test::call_service(&app, review()).await;
+        assert!(repeated.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        let repeated: CreateReviewResponse = actix_web::test::read_body_json(repeated).await;
+        assert_eq!(repeated.review_id, created.review_id);
+    }
+
+    #[actix_web::test]
+    async fn test_grade_batch() {
+        let store = web::Data::new(GradeStore::new(3600, None, Default::default(), None));
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+
+        let request = |tasks: serde_json::Value| {
+            serde_json::json!({"repo_url": "https://github.com/test/repo", "tasks": tasks})
+        };
+        let task = serde_json::json!([{"title": "t", "acceptance_criteria": []}]);
+
+        // One invalid request rejects the whole batch
+        let invalid = actix_web::test::TestRequest::post()
+            .uri("/api/grade/batch")
+            .set_json(serde_json::json!({"requests": [request(task.clone()), request(serde_json::json!([]))]}))
+            .to_request();
+        let response = actix_web::test::call_service(&app, invalid).await;
+        assert_eq!(response.status(), 400);
+        let body: serde_json::Value = actix_web::test::read_body_json(response).await;
+        assert!(body.to_string().contains("requests[1]: tasks cannot be empty"));
+
+        let requests: Vec<GradeRequest> =
+            serde_json::from_value(serde_json::json!([request(task.clone()), request(task)])).unwrap();
+        let (batch_id, grade_ids) = store.create_grade_batch(&requests).await;
+        assert_eq!(grade_ids.len(), 2);
+        store.mark_failed(&grade_ids[1], "clone failed".to_string()).await;
+        store.create_grade(requests[0].clone()).await;
+
+        let status = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/grade/batch/{}/status", batch_id))
+            .to_request();
+        let status: BatchStatusResponse = actix_web::test::call_and_read_body_json(&app, status).await;
+        assert_eq!((status.total, status.failed, status.in_progress), (2, 1, 1));
+        assert_eq!(status.grades.len(), 2);
+
+        let missing = actix_web::test::TestRequest::get()
+            .uri("/api/grade/batch/nope/status")
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, missing).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_compare_review_pair_requires_completed_reviews() {
+        let store = web::Data::new(ReviewStore::new(3600, None, None));
+        let pending = store.create_review("https://github.com/test/repo".to_string()).await;
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+
+        let request = actix_web::test::TestRequest::get()
+            .uri(&format!("/api/review/compare?id_a={}&id_b=missing", pending))
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, request).await.status(), 409);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/review/compare?id_a=missing&id_b=other")
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, request).await.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_capabilities() {
+        let store = web::Data::new(ReviewStore::new(3600, None, None));
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+        let get = |uri: String| actix_web::test::TestRequest::get().uri(&uri).to_request();
+
+        let all: serde_json::Value = actix_web::test::call_and_read_body_json(&app, get("/api/capabilities".to_string())).await;
+        assert_eq!(all["checkers"].as_array().unwrap().len(), all_checkers().len());
+        assert!(all["languages"].as_array().unwrap().contains(&"python".into()));
+        assert!(all.get("profile").is_none());
+
+        let id = store.create_review("https://github.com/test/repo".to_string()).await;
+        let response = actix_web::test::call_service(&app, get(format!("/api/capabilities?review_id={}", id))).await;
+        assert_eq!(response.status(), 409);
+        let response = actix_web::test::call_service(&app, get("/api/capabilities?review_id=missing".to_string())).await;
+        assert_eq!(response.status(), 404);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("app.py"), "def main():\n    x = 1\n    y = 2\n    print(x + y)\n\nmain()\n").unwrap();
+        let repo = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
+        store.run_review_with_repo(&id, &repo, Instant::now()).await.unwrap();
+
+        let review: serde_json::Value = actix_web::test::call_and_read_body_json(&app, get(format!("/api/capabilities?review_id={}", id))).await;
+        assert_eq!(review["profile"]["primary"], "python");
+        let checkers = review["checkers"].as_array().unwrap();
+        assert_eq!(checkers.len(), all_checkers().len() - 1);
+        assert!(checkers.contains(&serde_json::json!({"check_type": "python_types", "languages": ["python"]})));
+        assert!(checkers.contains(&serde_json::json!({"check_type": "typos", "languages": []})));
+
+        let state: serde_json::Value = actix_web::test::call_and_read_body_json(&app, get(format!("/api/review/{}", id))).await;
+        assert_eq!(state["languages"], serde_json::json!([["python", 1]]));
+    }
+
+    #[actix_web::test]
+    async fn test_abort_checker() {
+        let store = web::Data::new(ReviewStore::new(3600, None, None));
+        let id = store.create_review("https://github.com/test/repo".to_string()).await;
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+
+        let abort = |uri: String| actix_web::test::TestRequest::delete().uri(&uri).to_request();
+        // Nothing is running yet
+        let response = actix_web::test::call_service(&app, abort(format!("/api/review/{}/checker/lint", id))).await;
+        assert_eq!(response.status(), 409);
+        let response = actix_web::test::call_service(&app, abort("/api/review/missing/checker/lint".to_string())).await;
+        assert_eq!(response.status(), 404);
+        let response = actix_web::test::call_service(&app, abort(format!("/api/review/{}/checker/nope", id))).await;
+        assert_eq!(response.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_create_review_cache_modes() {
+        let store = web::Data::new(ReviewStore::new(3600, None, None));
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+
+        for (mode, expected) in [
+            (None, ReviewCacheMode::Use),
+            (Some("use"), ReviewCacheMode::Use),
+            (Some("bypass"), ReviewCacheMode::Bypass),
+            (Some("refresh"), ReviewCacheMode::Refresh),
+        ] {
+            let mut body = serde_json::json!({"repo_url": "not-a-repo"});
+            if let Some(mode) = mode {
+                body["cache_mode"] = mode.into();
+            }
+            let request = actix_web::test::TestRequest::post()
+                .uri("/api/review")
+                .set_json(body)
+                .to_request();
+            let created: CreateReviewResponse =
+                actix_web::test::call_and_read_body_json(&app, request).await;
+            let state = store.get_review(&created.review_id).await.unwrap();
+            assert_eq!(state.cache_mode, expected);
+        }
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/review")
+            .set_json(serde_json::json!({"repo_url": "not-a-repo", "cache_mode": "sometimes"}))
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, request).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_overrides_require_mongodb() {
+        let store = web::Data::new(GradeStore::new(3600, None, Default::default(), None));
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+
+        let request = actix_web::test::TestRequest::post()
+            .uri("/api/grade/g1/criteria/0/1/override")
+            .set_json(serde_json::json!({"passed": false, "note": "README is empty"}))
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, request).await.status(), 400);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/grades/calibration")
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, request).await.status(), 400);
+
+        let request = actix_web::test::TestRequest::get()
+            .uri("/api/grades/similarity?metadata.course_title=Web%20101&threshold=0.9")
+            .to_request();
+        assert_eq!(actix_web::test::call_service(&app, request).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_github_webhook() {
+        use hmac::{Hmac, Mac};
+
+        const PUSH: &str = include_str!("../tests/fixtures/github/push.json");
+        const PING: &str = include_str!("../tests/fixtures/github/ping.json");
+        let sign = |body: &str| {
+            let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"webhook-secret").unwrap();
+            mac.update(body.as_bytes());
+            format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+        };
+        let delivery = |event: &str, body: &'static str, signature: String| {
+            actix_web::test::TestRequest::post()
+                .uri("/api/webhooks/github")
+                .insert_header(("x-github-event", event))
+                .insert_header(("x-hub-signature-256", signature))
+                .set_payload(body)
+                .to_request()
+        };
+
+        let store = web::Data::new(GradeStore::new(3600, None, Default::default(), None));
+        let unconfigured = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).configure(configure),
+        )
+        .await;
+        let response = actix_web::test::call_service(&unconfigured, delivery("push", PUSH, sign(PUSH))).await;
+        assert_eq!(response.status(), 404);
+
+        let webhook = web::Data::new(GithubWebhook::new("webhook-secret".to_string().into()));
+        let app = actix_web::test::init_service(
+            actix_web::App::new().app_data(store.clone()).app_data(webhook).configure(configure),
+        )
+        .await;
+
+        let response = actix_web::test::call_service(&app, delivery("push", PUSH, sign(PING))).await;
+        assert_eq!(response.status(), 401);
+
+        let response = actix_web::test::call_service(&app, delivery("ping", PING, sign(PING))).await;
+        assert_eq!(response.status(), 204);
+
+        // Without MongoDB there are no subscriptions to re-grade
+        let response = actix_web::test::call_service(&app, delivery("push", PUSH, sign(PUSH))).await;
+        assert_eq!(response.status(), 204);
+    }
+}
@@ -0,0 +1,170 @@
+//! Bearer token authentication for `/api/*`
+//!
+//! When `API_AUTH_TOKEN` is set, every API route except `/api/health` and the
+//! GitHub webhook (which checks its own signature) requires
+//! `Authorization: Bearer <token>`. Without a token the API stays open for
+//! local development.
+
+use crate::error::ApiError;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method};
+use actix_web::{Error, ResponseError};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use secrecy::{ExposeSecret, SecretString};
+use std::sync::Arc;
+
+/// Routes reachable without a token
+const PUBLIC_PATHS: &[&str] = &["/api/health", "/api/webhooks/github"];
+
+/// Middleware requiring the configured bearer token, or a no-op without one
+#[derive(Clone, Default)]
+pub struct BearerAuth {
+    token: Option<Arc<SecretString>>,
+}
+
+impl BearerAuth {
+    pub fn new(token: Option<SecretString>) -> Self {
+        Self {
+            token: token.map(Arc::new),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for BearerAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = BearerAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(BearerAuthMiddleware {
+            service,
+            token: self.token.clone(),
+        }))
+    }
+}
+
+pub struct BearerAuthMiddleware<S> {
+    service: S,
+    token: Option<Arc<SecretString>>,
+}
+
+impl<S> BearerAuthMiddleware<S> {
+    fn is_authorized(&self, req: &ServiceRequest) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let path = req.path();
+        // CORS preflights never carry credentials
+        if req.method() == Method::OPTIONS
+            || !path.starts_with("/api/")
+            || PUBLIC_PATHS.contains(&path)
+        {
+            return true;
+        }
+
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(bearer_token)
+            .is_some_and(|given| constant_time_eq(given.as_bytes(), token.expose_secret().as_bytes()))
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for BearerAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if !self.is_authorized(&req) {
+            let mut response =
+                ApiError::Unauthorized("missing or invalid bearer token".to_string()).error_response();
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+            return Box::pin(ready(Ok(req.into_response(response).map_into_right_body())));
+        }
+
+        let response = self.service.call(req);
+        Box::pin(async move { Ok(response.await?.map_into_left_body()) })
+    }
+}
+
+/// Token from an `Authorization: Bearer <token>` value
+fn bearer_token(value: &str) -> Option<&str> {
+    let (scheme, token) = value.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+/// Comparison whose duration doesn't reveal how much of the token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    async fn status(token: Option<&str>, path: &str, authorization: Option<&str>) -> StatusCode {
+        let app = init_service(
+            App::new()
+                .wrap(BearerAuth::new(token.map(|t| SecretString::from(t.to_string()))))
+                .route("/api/health", web::get().to(HttpResponse::Ok))
+                .route("/api/grade/{id}", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let mut request = TestRequest::get().uri(path);
+        if let Some(authorization) = authorization {
+            request = request.insert_header((header::AUTHORIZATION, authorization));
+        }
+        call_service(&app, request.to_request()).await.status()
+    }
+
+    #[actix_web::test]
+    async fn test_bearer_auth() {
+        let token = Some("s3cret");
+        assert_eq!(status(token, "/api/grade/1", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(token, "/api/grade/1", Some("Bearer wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(token, "/api/grade/1", Some("bearer s3cret")).await,
+            StatusCode::OK
+        );
+        assert_eq!(status(token, "/api/health", None).await, StatusCode::OK);
+
+        // No token configured: open for local development
+        assert_eq!(status(None, "/api/grade/1", None).await, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_bearer_token_parsing() {
+        assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("Basic abc"), None);
+        assert_eq!(bearer_token("Bearer "), None);
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+    }
+}
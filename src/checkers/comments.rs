@@ -1,14 +1,26 @@
 //! Comment checker for TODO, FIXME, HACK, and other markers
 //!
-//! Detects actionable comments that should be addressed.
+//! Detects actionable comments that should be addressed, and unresolved
+//! merge-conflict markers left in committed files. Markers are only matched
+//! inside comments (outside string literals), and never inside URLs.
+//! Optionally, TODOs referencing GitHub issues (`TODO(#123)`) are checked
+//! against the repository's issues.
 
+use crate::checkers::notebook::{cell_path, is_notebook, CellKind, Notebook};
 use crate::checkers::Checker;
+use crate::config::AuthorizationHeader;
+use crate::files::{relative_path, FileFilter};
 use crate::types::{CheckType, Diagnostic, Severity};
+use futures::future::join_all;
 use rayon::prelude::*;
 use regex::Regex;
+use secrecy::SecretString;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::runtime::Handle;
 
 /// Patterns for detecting actionable comments
 static COMMENT_PATTERNS: LazyLock<Vec<CommentPattern>> = LazyLock::new(|| {
@@ -58,6 +70,128 @@ static COMMENT_PATTERNS: LazyLock<Vec<CommentPattern>> = LazyLock::new(|| {
     ]
 });
 
+/// Conflict markers git writes at the start of a line, always 7 characters
+const CONFLICT_OURS: &str = "<<<<<<<";
+const CONFLICT_BASE: &str = "|||||||";
+const CONFLICT_SEPARATOR: &str = "=======";
+const CONFLICT_THEIRS: &str = ">>>>>>>";
+
+/// Whether `line` starts with `marker` followed by a space or the line end
+fn is_conflict_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Find unresolved merge conflicts. A bare `=======` line only counts inside
+/// an open conflict, so Markdown-style dividers are not reported.
+fn conflict_marker_diagnostics(filename: &str, content: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut in_conflict = false;
+
+    for (line_num, line) in content.lines().enumerate() {
+        let is_marker = if is_conflict_marker(line, CONFLICT_OURS) {
+            in_conflict = true;
+            true
+        } else if is_conflict_marker(line, CONFLICT_THEIRS) {
+            in_conflict = false;
+            true
+        } else {
+            in_conflict
+                && (is_conflict_marker(line, CONFLICT_BASE) || line == CONFLICT_SEPARATOR)
+        };
+
+        if is_marker {
+            diagnostics.push(Diagnostic {
+                file: filename.to_string(),
+                line: (line_num + 1) as u32,
+                column: 1,
+                message: format!("Unresolved merge-conflict marker: {}", line.trim_end()),
+                rule: "merge-conflict-marker".to_string(),
+                severity: Severity::Error,
+                suggestion: Some(
+                    "Resolve the conflict, keep the intended changes and remove the markers"
+                        .to_string(),
+                ),
+            });
+        }
+    }
+
+    diagnostics
+}
+
+/// Markers reported by default; NOTE fires on ordinary documentation and is opt-in
+pub const DEFAULT_MARKERS: &[&str] = &["TODO", "FIXME", "HACK", "XXX", "BUG", "DEPRECATED"];
+
+static URL_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[A-Za-z][A-Za-z0-9+.-]*://\S+").unwrap());
+
+static ISSUE_REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"#(\d+)\b").unwrap());
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+const ISSUE_LOOKUP_TIMEOUT_SECS: u64 = 10;
+
+/// Issues looked up per file; further references in the file go unchecked
+const MAX_ISSUE_CHECKS_PER_FILE: usize = 10;
+
+/// Issues looked up per repository, across all its files
+const MAX_ISSUE_CHECKS_PER_REPO: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IssueState {
+    Open,
+    Closed,
+    Missing,
+}
+
+/// Repository whose issues TODO references are checked against
+struct GithubIssues {
+    owner: String,
+    repo: String,
+    token: Option<SecretString>,
+    api_url: String,
+    client: reqwest::Client,
+}
+
+impl GithubIssues {
+    /// State of issue `number`, or `None` if GitHub couldn't be asked (rate
+    /// limits, network errors)
+    async fn issue_state(&self, number: u64) -> Option<IssueState> {
+        let url = format!("{}/repos/{}/{}/issues/{}", self.api_url, self.owner, self.repo, number);
+        let mut request = self
+            .client
+            .get(&url)
+            .header("User-Agent", "code-review-api")
+            .header("Accept", "application/vnd.github+json");
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", token.to_authorization_header("Bearer"));
+        }
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::debug!("Issue lookup {} failed: {}", url, e);
+                return None;
+            }
+        };
+        match response.status().as_u16() {
+            410 => Some(IssueState::Missing),
+            // Without a token a private repository's issues all look missing
+            404 if self.token.is_some() => Some(IssueState::Missing),
+            status if (200..300).contains(&status) => {
+                let issue: serde_json::Value = response.json().await.ok()?;
+                match issue["state"].as_str()? {
+                    "closed" => Some(IssueState::Closed),
+                    _ => Some(IssueState::Open),
+                }
+            }
+            status => {
+                tracing::debug!("Issue lookup {} returned {}", url, status);
+                None
+            }
+        }
+    }
+}
+
 struct CommentPattern {
     regex: Regex,
     marker: &'static str,
@@ -65,8 +199,132 @@ struct CommentPattern {
     message: &'static str,
 }
 
+/// How comments are written in a file type
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    /// Characters that open and close string literals
+    quotes: &'static [char],
+}
+
+const C_STYLE: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    quotes: &['"'],
+};
+const JS_STYLE: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+};
+const PHP_STYLE: CommentSyntax = CommentSyntax {
+    line: &["//", "#"],
+    block: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+};
+const HASH_STYLE: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: None,
+    quotes: &['"', '\''],
+};
+
+/// Comment syntax of notebook code cells by kernel language
+fn notebook_syntax(language: &str) -> &'static CommentSyntax {
+    match language {
+        "javascript" | "typescript" => &JS_STYLE,
+        "c" | "c++" | "java" | "scala" | "kotlin" | "rust" | "go" | "c#" => &C_STYLE,
+        // Python, R, Julia and most other kernels use `#`
+        _ => &HASH_STYLE,
+    }
+}
+
+/// Comment syntax by extension; `None` means the whole file is prose
+fn comment_syntax(path: &Path) -> Option<&'static CommentSyntax> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match ext {
+        "js" | "jsx" | "ts" | "tsx" => Some(&JS_STYLE),
+        "php" => Some(&PHP_STYLE),
+        "py" | "rb" => Some(&HASH_STYLE),
+        // Markdown, plain text and unknown types are treated as all comment
+        "md" | "txt" => None,
+        _ if is_source_file(path) => Some(&C_STYLE),
+        _ => None,
+    }
+}
+
+/// Comment parts of `line` as `(byte offset, text)`, tracking whether a
+/// block comment is still open at the end of the line
+fn comment_segments<'a>(
+    line: &'a str,
+    syntax: &CommentSyntax,
+    in_block: &mut bool,
+) -> Vec<(usize, &'a str)> {
+    let mut segments = Vec::new();
+    let mut i = 0;
+
+    if *in_block {
+        let close = syntax.block.map_or("*/", |(_, close)| close);
+        match line.find(close) {
+            Some(end) => {
+                segments.push((0, &line[..end]));
+                i = end + close.len();
+                *in_block = false;
+            }
+            None => return vec![(0, line)],
+        }
+    }
+
+    let mut quote: Option<char> = None;
+    while let Some(c) = line[i..].chars().next() {
+        let rest = &line[i..];
+        if let Some(q) = quote {
+            if c == '\\' {
+                // Skip the escaped character
+                i += c.len_utf8();
+                i += line[i..].chars().next().map_or(0, char::len_utf8);
+                continue;
+            }
+            if c == q {
+                quote = None;
+            }
+        } else if syntax.quotes.contains(&c) {
+            quote = Some(c);
+        } else if syntax.line.iter().any(|prefix| rest.starts_with(prefix)) {
+            segments.push((i, rest));
+            break;
+        } else if let Some((open, close)) = syntax.block.filter(|(open, _)| rest.starts_with(open)) {
+            let body = i + open.len();
+            match line[body..].find(close) {
+                Some(end) => {
+                    segments.push((i, &line[i..body + end]));
+                    i = body + end + close.len();
+                    continue;
+                }
+                None => {
+                    segments.push((i, rest));
+                    *in_block = true;
+                    break;
+                }
+            }
+        }
+        i += c.len_utf8();
+    }
+
+    segments
+}
+
+/// Blank out URLs, keeping byte offsets, so markers in links are not matched
+fn without_urls(text: &str) -> String {
+    URL_PATTERN
+        .replace_all(text, |caps: &regex::Captures| " ".repeat(caps[0].len()))
+        .into_owned()
+}
+
 /// Comment checker that finds TODO, FIXME, HACK, etc.
-pub struct CommentChecker;
+pub struct CommentChecker {
+    markers: Vec<String>,
+    github: Option<GithubIssues>,
+}
 
 impl Default for CommentChecker {
     fn default() -> Self {
@@ -76,34 +334,167 @@ impl Default for CommentChecker {
 
 impl CommentChecker {
     pub fn new() -> Self {
-        Self
+        Self::with_markers(DEFAULT_MARKERS)
+    }
+
+    /// Only report the given markers (case-insensitive), e.g. to enable NOTE
+    pub fn with_markers(markers: &[&str]) -> Self {
+        Self {
+            markers: markers.iter().map(|m| m.to_uppercase()).collect(),
+            github: None,
+        }
+    }
+
+    /// Check TODOs referencing `#N` against the issues of `owner/repo`. The
+    /// token raises GitHub's rate limit and gives access to private repos.
+    pub fn with_github_validation(mut self, owner: String, repo: String, token: Option<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(ISSUE_LOOKUP_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        self.github = Some(GithubIssues {
+            owner,
+            repo,
+            token: token.map(SecretString::from),
+            api_url: GITHUB_API_URL.to_string(),
+            client,
+        });
+        self
+    }
+
+    /// Flag TODOs whose referenced issue is closed (warning) or doesn't exist
+    /// (error), looking up at most `MAX_ISSUE_CHECKS_PER_REPO` issues. Blocks
+    /// on the current tokio runtime, so it must run on a blocking thread;
+    /// without a runtime or GitHub validation it does nothing.
+    pub fn check_orphaned_todos(&self, diagnostics: &[Diagnostic]) -> Vec<Diagnostic> {
+        let (Some(github), Ok(runtime)) = (&self.github, Handle::try_current()) else {
+            return vec![];
+        };
+
+        let mut references: BTreeMap<&str, Vec<(&Diagnostic, u64)>> = BTreeMap::new();
+        for todo in diagnostics.iter().filter(|d| d.rule == "comment-todo") {
+            for captures in ISSUE_REFERENCE.captures_iter(&todo.message) {
+                if let Ok(number) = captures[1].parse() {
+                    references.entry(&todo.file).or_default().push((todo, number));
+                }
+            }
+        }
+
+        let mut states: HashMap<u64, Option<IssueState>> = HashMap::new();
+        let mut remaining = MAX_ISSUE_CHECKS_PER_REPO;
+        let mut orphaned = Vec::new();
+        for refs in references.values() {
+            let mut batch: Vec<u64> = Vec::new();
+            for (_, number) in refs {
+                if !states.contains_key(number) && !batch.contains(number) {
+                    batch.push(*number);
+                }
+            }
+            batch.truncate(MAX_ISSUE_CHECKS_PER_FILE.min(remaining));
+            remaining -= batch.len();
+
+            let results = runtime.block_on(join_all(batch.iter().map(|n| github.issue_state(*n))));
+            states.extend(batch.into_iter().zip(results));
+
+            for (todo, number) in refs {
+                let (severity, message) = match states.get(number) {
+                    Some(Some(IssueState::Closed)) => {
+                        (Severity::Warning, format!("Referenced issue #{} is closed", number))
+                    }
+                    Some(Some(IssueState::Missing)) => {
+                        (Severity::Error, format!("Referenced issue #{} does not exist", number))
+                    }
+                    _ => continue,
+                };
+                orphaned.push(Diagnostic {
+                    file: todo.file.clone(),
+                    line: todo.line,
+                    column: todo.column,
+                    message,
+                    rule: "orphaned-todo".to_string(),
+                    severity,
+                    suggestion: Some(format!(
+                        "Finish the TODO or point it at an open issue instead of #{}",
+                        number
+                    )),
+                });
+            }
+        }
+
+        orphaned
+    }
+
+    fn active_patterns(&self) -> impl Iterator<Item = &'static CommentPattern> + '_ {
+        COMMENT_PATTERNS
+            .iter()
+            .filter(|pattern| self.markers.iter().any(|m| m == pattern.marker))
     }
 
-    fn check_file(&self, path: &Path) -> Vec<Diagnostic> {
+    fn check_file(&self, repo_path: &Path, path: &Path) -> Vec<Diagnostic> {
         let content = match fs::read_to_string(path) {
             Ok(c) => c,
             Err(_) => return vec![],
         };
 
-        let filename = path.to_string_lossy().to_string();
-        let mut diagnostics = Vec::new();
+        let filename = relative_path(repo_path, path);
+        if is_notebook(path) {
+            return self.check_notebook(&filename, &content);
+        }
+        self.check_content(&filename, &content, comment_syntax(path))
+    }
+
+    /// Check each code and markdown cell, reported as `path#cell-N`
+    fn check_notebook(&self, filename: &str, content: &str) -> Vec<Diagnostic> {
+        let Some(notebook) = Notebook::parse(content) else {
+            return vec![];
+        };
+        let code_syntax = notebook_syntax(&notebook.language);
+
+        notebook
+            .cells
+            .iter()
+            .flat_map(|cell| {
+                let syntax = match cell.kind {
+                    CellKind::Code => Some(code_syntax),
+                    CellKind::Markdown => None,
+                };
+                self.check_content(&cell_path(filename, cell.number), &cell.source, syntax)
+            })
+            .collect()
+    }
+
+    fn check_content(
+        &self,
+        filename: &str,
+        content: &str,
+        syntax: Option<&CommentSyntax>,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = conflict_marker_diagnostics(filename, content);
+        let mut in_block = false;
 
         for (line_num, line) in content.lines().enumerate() {
             let line_number = (line_num + 1) as u32;
+            let segments = match syntax {
+                Some(syntax) => comment_segments(line, syntax, &mut in_block),
+                None => vec![(0, line)],
+            };
 
             // Check for comment markers
-            for pattern in COMMENT_PATTERNS.iter() {
-                if let Some(captures) = pattern.regex.captures(line) {
+            for (offset, segment) in segments {
+                let text = without_urls(segment);
+                for pattern in self.active_patterns() {
+                    let Some(captures) = pattern.regex.captures(&text) else {
+                        continue;
+                    };
                     let description = captures
                         .get(1)
                         .map(|m| m.as_str().trim())
                         .unwrap_or("")
                         .to_string();
 
-                    let column = line
-                        .find(pattern.marker)
-                        .map(|i| (i + 1) as u32)
-                        .unwrap_or(1);
+                    let column = captures
+                        .get(0)
+                        .map_or(1, |m| (offset + m.start() + 1) as u32);
 
                     let message = if description.is_empty() {
                         pattern.message.to_string()
@@ -112,7 +503,7 @@ impl CommentChecker {
                     };
 
                     diagnostics.push(Diagnostic {
-                        file: filename.clone(),
+                        file: filename.to_string(),
                         line: line_number,
                         column,
                         message,
@@ -136,28 +527,31 @@ impl Checker for CommentChecker {
         CheckType::Comments
     }
 
-    fn check(&self, repo_path: &Path) -> Vec<Diagnostic> {
-        let files = collect_source_files(repo_path);
+    fn check(&self, repo_path: &Path, filter: FileFilter) -> Vec<Diagnostic> {
+        let files = collect_source_files(repo_path, filter);
 
         if files.is_empty() {
             return vec![];
         }
 
-        files
+        let mut diagnostics: Vec<Diagnostic> = files
             .par_iter()
-            .flat_map(|file| self.check_file(file))
-            .collect()
+            .flat_map(|file| self.check_file(repo_path, file))
+            .collect();
+        let orphaned = self.check_orphaned_todos(&diagnostics);
+        diagnostics.extend(orphaned);
+        diagnostics
     }
 }
 
 /// Collect all source files from a directory
-fn collect_source_files(dir: &Path) -> Vec<std::path::PathBuf> {
+fn collect_source_files(dir: &Path, filter: FileFilter) -> Vec<std::path::PathBuf> {
     let mut files = Vec::new();
-    collect_source_files_recursive(dir, &mut files);
+    collect_source_files_recursive(dir, filter, &mut files);
     files
 }
 
-fn collect_source_files_recursive(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+fn collect_source_files_recursive(dir: &Path, filter: FileFilter, files: &mut Vec<std::path::PathBuf>) {
     let entries = match fs::read_dir(dir) {
         Ok(e) => e,
         Err(_) => return,
@@ -181,8 +575,8 @@ fn collect_source_files_recursive(dir: &Path, files: &mut Vec<std::path::PathBuf
         }
 
         if path.is_dir() {
-            collect_source_files_recursive(&path, files);
-        } else if is_source_file(&path) {
+            collect_source_files_recursive(&path, filter, files);
+        } else if (is_source_file(&path) || is_notebook(&path)) && !filter.skips(&path) {
             files.push(path);
         }
     }
@@ -231,7 +625,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.js", "// TODO: implement this feature");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].rule, "comment-todo");
@@ -244,7 +638,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.rs", "// FIXME: this is broken");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].rule, "comment-fixme");
@@ -257,7 +651,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.py", "# HACK: temporary workaround");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].rule, "comment-hack");
@@ -269,7 +663,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.js", "/* BUG: race condition */");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].rule, "comment-bug");
@@ -290,7 +684,7 @@ function foo() {
 "#;
         let path = create_test_file(&dir, "test.js", content);
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 3);
     }
@@ -301,20 +695,209 @@ function foo() {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.js", "// todo: lowercase works too");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].rule, "comment-todo");
     }
 
+    #[test]
+    fn test_ignores_markers_in_strings() {
+        let checker = CommentChecker::new();
+        let dir = TempDir::new().unwrap();
+        let content = "const label = \"TODO list\"; // FIXME: rename\nlet s = 'a // TODO';\n";
+        let path = create_test_file(&dir, "test.js", content);
+
+        let diagnostics = checker.check_file(dir.path(), &path);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "comment-fixme");
+        assert_eq!(diagnostics[0].column, 31);
+    }
+
+    #[test]
+    fn test_ignores_markers_in_urls() {
+        let checker = CommentChecker::new();
+        let dir = TempDir::new().unwrap();
+        let path = create_test_file(&dir, "test.rs", "// see https://example.com/TODO-list\n");
+
+        assert!(checker.check_file(dir.path(), &path).is_empty());
+    }
+
+    #[test]
+    fn test_ignores_identifiers() {
+        let checker = CommentChecker::new();
+        let dir = TempDir::new().unwrap();
+        let content = "// render the todoList and myTodo items\nconst todoList = [];\n";
+        let path = create_test_file(&dir, "test.ts", content);
+
+        assert!(checker.check_file(dir.path(), &path).is_empty());
+    }
+
+    #[test]
+    fn test_block_comment_spans_lines() {
+        let checker = CommentChecker::new();
+        let dir = TempDir::new().unwrap();
+        let content = "/**\n * TODO: document\n */\nconst TODO = 1;\n";
+        let path = create_test_file(&dir, "test.java", content);
+
+        let diagnostics = checker.check_file(dir.path(), &path);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 2);
+    }
+
+    #[test]
+    fn test_markdown_prose_and_note_opt_in() {
+        let dir = TempDir::new().unwrap();
+        let content = "NOTE: run npm install first\nSee https://example.com/TODO\nTODO: write docs\n";
+        let path = create_test_file(&dir, "README.md", content);
+
+        let diagnostics = CommentChecker::new().check_file(dir.path(), &path);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].rule.as_str()), (3, "comment-todo"));
+
+        let diagnostics = CommentChecker::with_markers(&["note"]).check_file(dir.path(), &path);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "comment-note");
+    }
+
+    #[test]
+    fn test_merge_conflict_markers() {
+        let checker = CommentChecker::new();
+        let dir = TempDir::new().unwrap();
+        let content = "const a = 1;\n<<<<<<< HEAD\nconst b = 2;\n=======\nconst b = 3;\n>>>>>>> feature\n";
+        let path = create_test_file(&dir, "test.js", content);
+
+        let diagnostics = checker.check_file(dir.path(), &path);
+
+        let lines: Vec<u32> = diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![2, 4, 6]);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.rule == "merge-conflict-marker" && d.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_divider_outside_conflict_ignored() {
+        let content = "Title\n=======\n\n# <<<<<<<< not a marker\nx = a >>>>>>> b\n";
+
+        assert!(conflict_marker_diagnostics("notes.md", content).is_empty());
+    }
+
     #[test]
     fn test_no_comments() {
         let checker = CommentChecker::new();
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.js", "const x = 1;");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert!(diagnostics.is_empty());
     }
+
+    #[actix_web::test]
+    async fn test_orphaned_todos() {
+        use actix_web::{web, App, HttpResponse};
+
+        let server = actix_test::start(|| {
+            App::new().route(
+                "/repos/{owner}/{repo}/issues/{number}",
+                web::get().to(|path: web::Path<(String, String, u64)>| async move {
+                    match path.2 {
+                        1 => HttpResponse::Ok().json(serde_json::json!({ "state": "open" })),
+                        2 => HttpResponse::Ok().json(serde_json::json!({ "state": "closed" })),
+                        4 => HttpResponse::Gone().finish(),
+                        _ => HttpResponse::NotFound().finish(),
+                    }
+                }),
+            )
+        });
+        let dir = TempDir::new().unwrap();
+        create_test_file(
+            &dir,
+            "app.ts",
+            "// TODO(#1): still open\n// TODO(#2): fix race condition\n// TODO(#3): gone\n\
+             // FIXME(#3): not a TODO\n// TODO(#4): deleted\n",
+        );
+
+        for token in [Some("token".to_string()), None] {
+            let with_token = token.is_some();
+            let mut checker = CommentChecker::new().with_github_validation("o".into(), "r".into(), token);
+            checker.github.as_mut().unwrap().api_url = server.url("").trim_end_matches('/').to_string();
+
+            let path = dir.path().to_path_buf();
+            let diagnostics = tokio::task::spawn_blocking(move || checker.check(&path, FileFilter::default()))
+                .await
+                .unwrap();
+            let orphaned: Vec<_> = diagnostics
+                .iter()
+                .filter(|d| d.rule == "orphaned-todo")
+                .map(|d| (d.line, d.severity, d.message.clone()))
+                .collect();
+
+            let mut expected = vec![(2, Severity::Warning, "Referenced issue #2 is closed".to_string())];
+            // Without a token a 404 may be a private repository
+            if with_token {
+                expected.push((3, Severity::Error, "Referenced issue #3 does not exist".to_string()));
+            }
+            expected.push((5, Severity::Error, "Referenced issue #4 does not exist".to_string()));
+            assert_eq!(orphaned, expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_issue_lookups_capped_per_repo() {
+        use actix_web::{web, App, HttpResponse};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let server = actix_test::start({
+            let lookups = Arc::clone(&lookups);
+            move || {
+                let lookups = Arc::clone(&lookups);
+                App::new().default_service(web::to(move || {
+                    lookups.fetch_add(1, Ordering::SeqCst);
+                    async { HttpResponse::Ok().json(serde_json::json!({ "state": "open" })) }
+                }))
+            }
+        });
+
+        let mut checker = CommentChecker::new().with_github_validation("o".into(), "r".into(), None);
+        checker.github.as_mut().unwrap().api_url = server.url("").trim_end_matches('/').to_string();
+        let dir = TempDir::new().unwrap();
+        for file in 0..5 {
+            let todos: String = (0..10).map(|i| format!("// TODO(#{}): later\n", file * 10 + i + 1)).collect();
+            create_test_file(&dir, &format!("file{}.ts", file), &todos);
+        }
+
+        let path = dir.path().to_path_buf();
+        tokio::task::spawn_blocking(move || checker.check(&path, FileFilter::default()))
+            .await
+            .unwrap();
+
+        assert_eq!(lookups.load(Ordering::SeqCst), MAX_ISSUE_CHECKS_PER_REPO);
+    }
+
+    #[test]
+    fn test_notebook_cells() {
+        let checker = CommentChecker::new();
+        let dir = TempDir::new().unwrap();
+        let path = create_test_file(&dir, "analysis.ipynb", r##"{"cells": [{"cell_type": "markdown", "source": ["# Analysis\n", "TODO: describe teh dataset\n"]}, {"cell_type": "code", "source": ["import pandas as pd\n", "df = pd.read_csv(\"data.csv\")  # FIXME: hardcoded path\n", "label = \"TODO not a comment\"\n"]}], "metadata": {"kernelspec": {"language": "python"}}, "nbformat": 4}"##);
+
+        let diagnostics = checker.check_file(dir.path(), &path);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.file.as_str(), d.line, d.rule.as_str()))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                ("analysis.ipynb#cell-1", 2, "comment-todo"),
+                ("analysis.ipynb#cell-2", 2, "comment-fixme"),
+            ]
+        );
+    }
 }
@@ -4,6 +4,8 @@
 //! missing newlines, inconsistent indentation, etc.
 
 use crate::checkers::Checker;
+use crate::files::{relative_path, FileFilter};
+use crate::profile::is_ignored_dir_name;
 use crate::types::{CheckType, Diagnostic, Severity};
 use rayon::prelude::*;
 use std::fs;
@@ -22,12 +24,28 @@ pub enum FormatRule {
     LineTooLong,
     /// Consecutive blank lines
     MultipleBlankLines,
+    /// Cargo dependency sections out of conventional order
+    TomlSectionOrder,
+    /// Dependency names not sorted alphabetically
+    UnsortedDependencies,
+    /// Source files exceeding max line count
+    FileTooLong,
 }
 
+/// Cargo dependency sections in their conventional order
+const DEPENDENCY_SECTIONS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Line count above which a source file is flagged as too long
+pub const DEFAULT_MAX_FILE_LINES: usize = 800;
+
+/// Formattable extensions that hold docs, data or config rather than code
+const NON_SOURCE_EXTENSIONS: &[&str] = &["md", "json", "yaml", "yml", "toml"];
+
 /// Format checker that finds style issues
 pub struct FormatChecker {
     max_line_length: usize,
     max_blank_lines: usize,
+    max_file_lines: usize,
 }
 
 impl Default for FormatChecker {
@@ -41,6 +59,7 @@ impl FormatChecker {
         Self {
             max_line_length: 120,
             max_blank_lines: 2,
+            max_file_lines: DEFAULT_MAX_FILE_LINES,
         }
     }
 
@@ -49,25 +68,34 @@ impl FormatChecker {
         Self {
             max_line_length,
             max_blank_lines,
+            max_file_lines: DEFAULT_MAX_FILE_LINES,
         }
     }
 
-    fn check_file(&self, path: &Path) -> Vec<Diagnostic> {
+    /// Flag source files with more than `max_file_lines` lines
+    pub fn with_max_file_lines(mut self, max_file_lines: usize) -> Self {
+        self.max_file_lines = max_file_lines;
+        self
+    }
+
+    fn check_file(&self, repo_path: &Path, path: &Path) -> Vec<Diagnostic> {
         let content = match fs::read_to_string(path) {
             Ok(c) => c,
             Err(_) => return vec![],
         };
 
-        let filename = path.to_string_lossy().to_string();
+        let filename = relative_path(repo_path, path);
         let mut diagnostics = Vec::new();
 
         // Check trailing whitespace and line length
         let mut consecutive_blank_lines = 0;
         let mut has_tabs = false;
         let mut has_spaces = false;
+        let mut line_count = 0;
 
         for (line_num, line) in content.lines().enumerate() {
             let line_number = (line_num + 1) as u32;
+            line_count = line_num + 1;
 
             // Trailing whitespace
             if line.ends_with(' ') || line.ends_with('\t') {
@@ -150,10 +178,9 @@ impl FormatChecker {
 
         // Check for final newline
         if !content.is_empty() && !content.ends_with('\n') {
-            let last_line = content.lines().count() as u32;
             diagnostics.push(Diagnostic {
                 file: filename.clone(),
-                line: last_line,
+                line: line_count as u32,
                 column: 1,
                 message: "File should end with a newline".to_string(),
                 rule: "missing-final-newline".to_string(),
@@ -162,17 +189,147 @@ impl FormatChecker {
             });
         }
 
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if line_count > self.max_file_lines && !NON_SOURCE_EXTENSIONS.contains(&extension) {
+            diagnostics.push(Diagnostic {
+                file: filename.clone(),
+                line: 1,
+                column: 1,
+                message: format!(
+                    "File has {} lines (more than {})",
+                    line_count, self.max_file_lines
+                ),
+                rule: "file-too-long".to_string(),
+                severity: Severity::Warning,
+                suggestion: Some("Split the file into smaller modules".to_string()),
+            });
+        }
+
+        if extension == "toml" {
+            diagnostics.extend(Self::check_toml(&filename, &content));
+        }
+
+        diagnostics
+    }
+
+    /// Section order and dependency sorting for Cargo-style manifests
+    fn check_toml(filename: &str, content: &str) -> Vec<Diagnostic> {
+        // Invalid TOML is left to the build tooling
+        let table: toml::Table = match content.parse() {
+            Ok(t) => t,
+            Err(_) => return vec![],
+        };
+
+        let mut diagnostics = Vec::new();
+        let sections: Vec<&str> = table
+            .keys()
+            .map(String::as_str)
+            .filter(|k| DEPENDENCY_SECTIONS.contains(k))
+            .collect();
+
+        for pair in sections.windows(2) {
+            let rank = |name| DEPENDENCY_SECTIONS.iter().position(|s| *s == name);
+            if rank(pair[1]) < rank(pair[0]) {
+                diagnostics.push(Diagnostic {
+                    file: filename.to_string(),
+                    line: section_line(content, pair[1]),
+                    column: 1,
+                    message: format!("[{}] should come before [{}]", pair[1], pair[0]),
+                    rule: "toml-section-order".to_string(),
+                    severity: Severity::Info,
+                    suggestion: Some(format!(
+                        "Order sections as {}",
+                        DEPENDENCY_SECTIONS
+                            .iter()
+                            .map(|s| format!("[{}]", s))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
+                });
+            }
+        }
+
+        for section in sections {
+            let Some(dependencies) = table[section].as_table() else {
+                continue;
+            };
+            let names: Vec<&str> = dependencies.keys().map(String::as_str).collect();
+            for pair in names.windows(2) {
+                if pair[1] < pair[0] {
+                    diagnostics.push(Diagnostic {
+                        file: filename.to_string(),
+                        line: key_line(content, section, pair[1]),
+                        column: 1,
+                        message: format!(
+                            "Dependency '{}' in [{}] is not sorted alphabetically",
+                            pair[1], section
+                        ),
+                        rule: "unsorted-dependencies".to_string(),
+                        severity: Severity::Info,
+                        suggestion: Some(format!("Move '{}' before '{}'", pair[1], pair[0])),
+                    });
+                }
+            }
+        }
+
         diagnostics
     }
 }
 
+fn is_header(line: &str, name: &str) -> bool {
+    line.trim()
+        .strip_prefix('[')
+        .and_then(|rest| rest.split(']').next())
+        .is_some_and(|header| header.trim() == name)
+}
+
+/// 1-based line of a `[section]` header (or its first `[section.x]` table)
+fn section_line(content: &str, section: &str) -> u32 {
+    let dotted = format!("{}.", section);
+    content
+        .lines()
+        .position(|line| {
+            is_header(line, section)
+                || line
+                    .trim()
+                    .strip_prefix('[')
+                    .is_some_and(|rest| rest.starts_with(&dotted))
+        })
+        .map(|i| i as u32 + 1)
+        .unwrap_or(1)
+}
+
+/// 1-based line where `key` is defined in `section`, either as `key = ...`
+/// under the header or as its own `[section.key]` table
+fn key_line(content: &str, section: &str, key: &str) -> u32 {
+    let dotted_header = format!("{}.{}", section, key);
+    let mut in_section = false;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if is_header(line, &dotted_header) {
+                return i as u32 + 1;
+            }
+            in_section = is_header(line, section);
+        } else if in_section {
+            let name = trimmed.split(['=', '.']).next().unwrap_or("").trim();
+            if name.trim_matches('"') == key {
+                return i as u32 + 1;
+            }
+        }
+    }
+
+    section_line(content, section)
+}
+
 impl Checker for FormatChecker {
     fn check_type(&self) -> CheckType {
         CheckType::Format
     }
 
-    fn check(&self, repo_path: &Path) -> Vec<Diagnostic> {
-        let files = collect_formattable_files(repo_path);
+    fn check(&self, repo_path: &Path, filter: FileFilter) -> Vec<Diagnostic> {
+        let files = collect_formattable_files(repo_path, filter);
 
         if files.is_empty() {
             return vec![];
@@ -180,19 +337,19 @@ impl Checker for FormatChecker {
 
         files
             .par_iter()
-            .flat_map(|file| self.check_file(file))
+            .flat_map(|file| self.check_file(repo_path, file))
             .collect()
     }
 }
 
 /// Collect all formattable files from a directory
-fn collect_formattable_files(dir: &Path) -> Vec<std::path::PathBuf> {
+fn collect_formattable_files(dir: &Path, filter: FileFilter) -> Vec<std::path::PathBuf> {
     let mut files = Vec::new();
-    collect_formattable_files_recursive(dir, &mut files);
+    collect_formattable_files_recursive(dir, filter, &mut files);
     files
 }
 
-fn collect_formattable_files_recursive(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+fn collect_formattable_files_recursive(dir: &Path, filter: FileFilter, files: &mut Vec<std::path::PathBuf>) {
     let entries = match fs::read_dir(dir) {
         Ok(e) => e,
         Err(_) => return,
@@ -201,23 +358,16 @@ fn collect_formattable_files_recursive(dir: &Path, files: &mut Vec<std::path::Pa
     for entry in entries.flatten() {
         let path = entry.path();
 
-        // Skip common non-source directories
+        // Skip hidden, build output and vendored directories
         if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-            if name.starts_with('.')
-                || name == "node_modules"
-                || name == "target"
-                || name == "dist"
-                || name == "build"
-                || name == "vendor"
-                || name == "__pycache__"
-            {
+            if is_ignored_dir_name(name) || name == "vendor" {
                 continue;
             }
         }
 
         if path.is_dir() {
-            collect_formattable_files_recursive(&path, files);
-        } else if is_formattable_file(&path) {
+            collect_formattable_files_recursive(&path, filter, files);
+        } else if is_formattable_file(&path) && !filter.skips(&path) {
             files.push(path);
         }
     }
@@ -271,13 +421,65 @@ mod tests {
         path
     }
 
+    #[test]
+    fn test_cargo_toml_section_order() {
+        let checker = FormatChecker::new();
+        let dir = TempDir::new().unwrap();
+        let content = "[package]\nname = \"demo\"\n\n[dev-dependencies]\ntempfile = \"3\"\n\n[dependencies]\nserde = \"1\"\n";
+        let path = create_test_file(&dir, "Cargo.toml", content);
+
+        let diagnostics = checker.check_file(dir.path(), &path);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "toml-section-order");
+        assert_eq!(diagnostics[0].line, 7);
+        assert_eq!(diagnostics[0].severity, Severity::Info);
+    }
+
+    #[test]
+    fn test_cargo_toml_unsorted_dependencies() {
+        let checker = FormatChecker::new();
+        let dir = TempDir::new().unwrap();
+        let content = "[dependencies]\ntokio = { version = \"1\" }\nanyhow = \"1\"\nserde = \"1\"\n\n[dependencies.regex]\nversion = \"1\"\n\n[build-dependencies]\ncc = \"1\"\n";
+        let path = create_test_file(&dir, "Cargo.toml", content);
+
+        let diagnostics = checker.check_file(dir.path(), &path);
+        let unsorted: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.rule == "unsorted-dependencies")
+            .map(|d| (d.line, d.message.as_str()))
+            .collect();
+
+        assert_eq!(
+            unsorted,
+            vec![
+                (3, "Dependency 'anyhow' in [dependencies] is not sorted alphabetically"),
+                (6, "Dependency 'regex' in [dependencies] is not sorted alphabetically"),
+            ]
+        );
+        assert!(!diagnostics.iter().any(|d| d.rule == "toml-section-order"));
+    }
+
+    #[test]
+    fn test_toml_keeps_character_checks() {
+        let checker = FormatChecker::new();
+        let dir = TempDir::new().unwrap();
+        let content = "[dependencies]  \nserde = \"1\"\n";
+        let path = create_test_file(&dir, "Cargo.toml", content);
+
+        let diagnostics = checker.check_file(dir.path(), &path);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "trailing-whitespace");
+    }
+
     #[test]
     fn test_trailing_whitespace() {
         let checker = FormatChecker::new();
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.js", "const x = 1;   \nconst y = 2;\n");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].rule, "trailing-whitespace");
@@ -290,7 +492,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.js", "const x = 1;");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert!(diagnostics
             .iter()
@@ -305,11 +507,30 @@ mod tests {
             "const x = 'this is a very long line that exceeds the maximum allowed length of 80 characters';\n";
         let path = create_test_file(&dir, "test.js", long_line);
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert!(diagnostics.iter().any(|d| d.rule == "line-too-long"));
     }
 
+    #[test]
+    fn test_file_too_long() {
+        let checker = FormatChecker::new().with_max_file_lines(3);
+        let dir = TempDir::new().unwrap();
+        let content = "const a = 1;\nconst b = 2;\nconst c = 3;\n";
+        let short = create_test_file(&dir, "short.js", content);
+        let long = create_test_file(&dir, "long.js", &format!("{}const d = 4;\n", content));
+        let data = create_test_file(&dir, "data.json", "[\n1,\n2,\n3\n]\n");
+
+        assert!(checker.check_file(dir.path(), &short).is_empty());
+        assert!(checker.check_file(dir.path(), &data).is_empty());
+        let diagnostics = checker.check_file(dir.path(), &long);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "file-too-long");
+        assert_eq!(diagnostics[0].line, 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].message, "File has 4 lines (more than 3)");
+    }
+
     #[test]
     fn test_multiple_blank_lines() {
         let checker = FormatChecker::new();
@@ -317,7 +538,7 @@ mod tests {
         let content = "const x = 1;\n\n\n\nconst y = 2;\n";
         let path = create_test_file(&dir, "test.js", content);
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert!(diagnostics.iter().any(|d| d.rule == "multiple-blank-lines"));
     }
@@ -329,7 +550,7 @@ mod tests {
         let content = "\tfunction foo() {\n    return 1;\n}\n";
         let path = create_test_file(&dir, "test.js", content);
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert!(diagnostics.iter().any(|d| d.rule == "mixed-indentation"));
     }
@@ -340,7 +561,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.js", "const x = 1;\nconst y = 2;\n");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert!(diagnostics.is_empty());
     }
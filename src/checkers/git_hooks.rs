@@ -0,0 +1,203 @@
+//! Git hooks checker
+//!
+//! Verifies that the repository sets up pre-commit hooks (git, husky,
+//! lefthook or pre-commit) so linting runs before code is committed.
+
+use crate::checkers::Checker;
+use crate::files::{relative_path, FileFilter};
+use crate::types::{CheckType, Diagnostic, Severity};
+use std::fs;
+use std::path::Path;
+
+/// Hook manager config files checked at the repo root
+const HOOK_CONFIG_FILES: &[&str] = &["lefthook.yml", ".pre-commit-config.yaml"];
+
+/// Checker for recommended git hook setup
+pub struct GitHooksChecker;
+
+impl Default for GitHooksChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitHooksChecker {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn diagnostic(
+        repo_path: &Path,
+        path: &Path,
+        message: &str,
+        rule: &str,
+        severity: Severity,
+        suggestion: &str,
+    ) -> Diagnostic {
+        Diagnostic {
+            file: relative_path(repo_path, path),
+            line: 1,
+            column: 1,
+            message: message.to_string(),
+            rule: rule.to_string(),
+            severity,
+            suggestion: Some(suggestion.to_string()),
+        }
+    }
+}
+
+fn is_file(path: &Path) -> bool {
+    fs::metadata(path).map(|m| m.is_file()).unwrap_or(false)
+}
+
+fn is_dir(path: &Path) -> bool {
+    fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false)
+}
+
+/// Whether `package.json` has a `prepare` script that installs husky
+fn has_husky_prepare_script(package_json: &Path) -> bool {
+    let content = match fs::read_to_string(package_json) {
+        Ok(c) => c,
+        Err(_) => return false,
+    };
+
+    serde_json::from_str::<serde_json::Value>(&content)
+        .ok()
+        .and_then(|json| {
+            json.get("scripts")?
+                .get("prepare")?
+                .as_str()
+                .map(|script| script.contains("husky"))
+        })
+        .unwrap_or(false)
+}
+
+impl Checker for GitHooksChecker {
+    fn check_type(&self) -> CheckType {
+        CheckType::GitHooks
+    }
+
+    fn check(&self, repo_path: &Path, _filter: FileFilter) -> Vec<Diagnostic> {
+        let husky_dir = repo_path.join(".husky");
+        let has_git_hook = is_file(&repo_path.join(".git").join("hooks").join("pre-commit"));
+        let has_husky = is_dir(&husky_dir);
+        let has_hook_config = HOOK_CONFIG_FILES
+            .iter()
+            .any(|name| is_file(&repo_path.join(name)));
+
+        if !has_git_hook && !has_husky && !has_hook_config {
+            return vec![Self::diagnostic(
+                repo_path,
+                repo_path,
+                "No pre-commit hooks configured",
+                "git-hooks-missing",
+                Severity::Info,
+                "Set up pre-commit hooks (husky, lefthook or pre-commit) to run linters before each commit",
+            )];
+        }
+
+        let mut diagnostics = Vec::new();
+
+        if has_husky {
+            if !is_file(&husky_dir.join("pre-commit")) {
+                diagnostics.push(Self::diagnostic(
+                    repo_path,
+                    &husky_dir,
+                    ".husky directory exists but has no pre-commit hook",
+                    "husky-missing-pre-commit",
+                    Severity::Warning,
+                    "Add a .husky/pre-commit script that runs your linters",
+                ));
+            }
+
+            let package_json = repo_path.join("package.json");
+            if is_file(&package_json) && !has_husky_prepare_script(&package_json) {
+                diagnostics.push(Self::diagnostic(
+                    repo_path,
+                    &package_json,
+                    "package.json has no prepare script installing husky",
+                    "husky-missing-prepare",
+                    Severity::Info,
+                    "Add \"prepare\": \"husky install\" to package.json scripts so hooks are installed automatically",
+                ));
+            }
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_no_hooks() {
+        let dir = TempDir::new().unwrap();
+
+        let diagnostics = GitHooksChecker::new().check(dir.path(), FileFilter::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "git-hooks-missing");
+        assert_eq!(diagnostics[0].severity, Severity::Info);
+    }
+
+    #[test]
+    fn test_sample_git_hooks_ignored() {
+        let dir = TempDir::new().unwrap();
+        let hooks = dir.path().join(".git").join("hooks");
+        fs::create_dir_all(&hooks).unwrap();
+        fs::write(hooks.join("pre-commit.sample"), "#!/bin/sh").unwrap();
+
+        let diagnostics = GitHooksChecker::new().check(dir.path(), FileFilter::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "git-hooks-missing");
+    }
+
+    #[test]
+    fn test_pre_commit_config() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".pre-commit-config.yaml"), "repos: []").unwrap();
+
+        let diagnostics = GitHooksChecker::new().check(dir.path(), FileFilter::default());
+
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_husky_without_pre_commit() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join(".husky")).unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"scripts": {"prepare": "husky install"}}"#,
+        )
+        .unwrap();
+
+        let diagnostics = GitHooksChecker::new().check(dir.path(), FileFilter::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "husky-missing-pre-commit");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_husky_missing_prepare_script() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join(".husky")).unwrap();
+        fs::write(dir.path().join(".husky").join("pre-commit"), "npm run lint").unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"scripts": {"test": "jest"}}"#,
+        )
+        .unwrap();
+
+        let diagnostics = GitHooksChecker::new().check(dir.path(), FileFilter::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "husky-missing-prepare");
+        assert_eq!(diagnostics[0].severity, Severity::Info);
+    }
+}
@@ -3,13 +3,18 @@
 //! Fast AST-based linting with customizable rules.
 
 use crate::checkers::Checker;
+use crate::files::{relative_path, FileFilter};
+use crate::profile::Language;
 use crate::types::{CheckType, Diagnostic, Severity};
 use oxc_allocator::Allocator;
-use oxc_ast::ast::{CallExpression, Expression, VariableDeclarationKind};
+use oxc_ast::ast::{
+    Argument, ArrayExpression, BinaryExpression, BinaryOperator, BindingPatternKind,
+    CallExpression, ChainElement, Expression, VariableDeclarationKind,
+};
 use oxc_ast::visit::walk;
-use oxc_ast::Visit;
+use oxc_ast::{AstKind, Visit};
 use oxc_parser::Parser;
-use oxc_span::SourceType;
+use oxc_span::{GetSpan, SourceType};
 use rayon::prelude::*;
 use std::collections::HashSet;
 use std::fs;
@@ -30,6 +35,13 @@ pub enum LintRule {
     NoVar,
     /// Disallow duplicate keys in objects
     NoDuplicateKeys,
+    /// Disallow `.then`/`.catch` callbacks (prefer async/await)
+    NoPromiseCallback,
+    /// Disallow `==`/`!=` (prefer `===`/`!==`)
+    StrictEquality,
+    /// Require `useEffect`/`useCallback`/`useMemo` dependency arrays to list
+    /// the component values their callback uses
+    ReactHooksOrder,
 }
 
 impl LintRule {
@@ -40,20 +52,32 @@ impl LintRule {
             LintRule::NoEval,
             LintRule::NoVar,
             LintRule::NoDuplicateKeys,
+            LintRule::StrictEquality,
+            LintRule::ReactHooksOrder,
         ]
     }
 }
 
+/// Options that tune individual rules
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinterConfig {
+    /// Let no-promise-callback accept `.catch(handler)` when it isn't
+    /// chained onto a `.then`
+    pub allow_catch_only: bool,
+    /// Let strict-equality accept `x == null`/`x != null`, which also
+    /// match `undefined`
+    pub strict_equality_allow_null: bool,
+}
+
 /// The linter configuration and executor
 pub struct Linter {
     rules: HashSet<LintRule>,
+    options: LinterConfig,
 }
 
 impl Default for Linter {
     fn default() -> Self {
-        Self {
-            rules: LintRule::recommended().into_iter().collect(),
-        }
+        Self::with_rules(LintRule::recommended())
     }
 }
 
@@ -67,22 +91,28 @@ impl Linter {
     pub fn with_rules(rules: Vec<LintRule>) -> Self {
         Self {
             rules: rules.into_iter().collect(),
+            options: LinterConfig::default(),
         }
     }
 
+    pub fn with_config(mut self, options: LinterConfig) -> Self {
+        self.options = options;
+        self
+    }
+
     /// Check if a rule is enabled
     pub fn has_rule(&self, rule: LintRule) -> bool {
         self.rules.contains(&rule)
     }
 
     /// Lint a single file
-    fn lint_file(&self, path: &Path) -> Vec<Diagnostic> {
+    fn lint_file(&self, repo_path: &Path, path: &Path) -> Vec<Diagnostic> {
         let source = match fs::read_to_string(path) {
             Ok(s) => s,
             Err(_) => return vec![],
         };
 
-        let filename = path.to_string_lossy().to_string();
+        let filename = relative_path(repo_path, path);
         self.lint_source(&filename, &source)
     }
 
@@ -94,24 +124,40 @@ impl Linter {
         let parser = Parser::new(&allocator, source, source_type);
         let ret = parser.parse();
 
-        // Return parse errors as diagnostics
+        let lines = LineIndex::new(source);
+
+        // Return parse errors as diagnostics, located at their primary label
         if !ret.errors.is_empty() {
             return ret
                 .errors
                 .iter()
-                .map(|e| Diagnostic {
-                    file: filename.to_string(),
-                    line: 1,
-                    column: 1,
-                    message: e.to_string(),
-                    rule: "parse-error".to_string(),
-                    severity: Severity::Error,
-                    suggestion: None,
+                .map(|e| {
+                    let offset = e
+                        .labels
+                        .as_ref()
+                        .and_then(|labels| labels.first())
+                        .map(|label| label.offset() as u32)
+                        .unwrap_or(0);
+                    let (line, column) = lines.line_col(offset);
+                    let mut suggestion = lines.code_frame(line, column);
+                    if let Some(help) = &e.help {
+                        suggestion.push_str(&format!("\nhelp: {}", help));
+                    }
+
+                    Diagnostic {
+                        file: filename.to_string(),
+                        line,
+                        column,
+                        message: e.to_string(),
+                        rule: "parse-error".to_string(),
+                        severity: Severity::Error,
+                        suggestion: Some(suggestion),
+                    }
                 })
                 .collect();
         }
 
-        let mut visitor = LintVisitor::new(filename.to_string(), source, self);
+        let mut visitor = LintVisitor::new(filename.to_string(), lines, self);
         visitor.visit_program(&ret.program);
 
         visitor.diagnostics
@@ -123,9 +169,13 @@ impl Checker for Linter {
         CheckType::Lint
     }
 
-    fn check(&self, repo_path: &Path) -> Vec<Diagnostic> {
+    fn languages(&self) -> &'static [Language] {
+        &[Language::JavaScript, Language::TypeScript]
+    }
+
+    fn check(&self, repo_path: &Path, filter: FileFilter) -> Vec<Diagnostic> {
         // Find all JS/TS files
-        let files = collect_js_ts_files(repo_path);
+        let files = collect_js_ts_files(repo_path, filter);
 
         if files.is_empty() {
             return vec![];
@@ -134,47 +184,159 @@ impl Checker for Linter {
         // Lint in parallel
         files
             .par_iter()
-            .flat_map(|file| self.lint_file(file))
+            .flat_map(|file| self.lint_file(repo_path, file))
             .collect()
     }
 }
 
+/// Line start offsets of a source file, for mapping spans to line/column
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    /// 1-based line and character column of a byte offset
+    fn line_col(&self, offset: u32) -> (u32, u32) {
+        let offset = (offset as usize).min(self.source.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.source[self.starts[line]..]
+            .char_indices()
+            .take_while(|(i, _)| self.starts[line] + i < offset)
+            .count();
+        (line as u32 + 1, column as u32 + 1)
+    }
+
+    fn line_text(&self, line: u32) -> &'a str {
+        let index = (line as usize).saturating_sub(1);
+        let start = self.starts.get(index).copied().unwrap_or(self.source.len());
+        let end = self
+            .starts
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.source.len());
+        self.source[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    /// The offending line with a caret under the given column
+    fn code_frame(&self, line: u32, column: u32) -> String {
+        let number = line.to_string();
+        let gutter = " ".repeat(number.len());
+        format!(
+            "{} | {}\n{} | {}^",
+            number,
+            self.line_text(line),
+            gutter,
+            " ".repeat(column.saturating_sub(1) as usize)
+        )
+    }
+}
+
 struct LintVisitor<'a> {
     file: String,
-    source: &'a str,
+    lines: LineIndex<'a>,
     config: &'a Linter,
     diagnostics: Vec<Diagnostic>,
     current_object_keys: Vec<HashSet<String>>,
+    /// Names bound in each enclosing function, innermost last; values that
+    /// never change between renders (state setters, refs) are left out
+    function_bindings: Vec<HashSet<String>>,
 }
 
 impl<'a> LintVisitor<'a> {
-    fn new(file: String, source: &'a str, config: &'a Linter) -> Self {
+    fn new(file: String, lines: LineIndex<'a>, config: &'a Linter) -> Self {
         Self {
             file,
-            source,
+            lines,
             config,
             diagnostics: Vec::new(),
             current_object_keys: Vec::new(),
+            function_bindings: Vec::new(),
         }
     }
 
-    fn get_line_col(&self, offset: u32) -> (u32, u32) {
-        let mut line = 1u32;
-        let mut col = 1u32;
+    /// react-hooks-order: flag a hook whose dependency array leaves out
+    /// values of the enclosing component its callback uses. Only literal
+    /// arrays of names and member chains are checked.
+    fn check_hook_dependencies(&mut self, expr: &CallExpression<'a>) {
+        let Some(hook) = hook_name(&expr.callee) else {
+            return;
+        };
+        let (Some(callback), Some(Argument::ArrayExpression(deps))) =
+            (expr.arguments.first(), expr.arguments.get(1))
+        else {
+            return;
+        };
 
-        for (i, ch) in self.source.char_indices() {
-            if i as u32 >= offset {
-                break;
+        let mut collector = ReferenceCollector::default();
+        match callback {
+            Argument::ArrowFunctionExpression(arrow) => {
+                collector.visit_formal_parameters(&arrow.params);
+                collector.visit_function_body(&arrow.body);
             }
-            if ch == '\n' {
-                line += 1;
-                col = 1;
-            } else {
-                col += 1;
+            Argument::FunctionExpression(function) => {
+                collector.visit_formal_parameters(&function.params);
+                if let Some(body) = &function.body {
+                    collector.visit_function_body(body);
+                }
             }
+            _ => return,
         }
 
-        (line, col)
+        let Some(listed) = dependency_roots(deps) else {
+            return;
+        };
+        let mut missing: Vec<String> = Vec::new();
+        for name in collector.references {
+            if !collector.declared.contains(&name)
+                && !listed.contains(name.as_str())
+                && !missing.contains(&name)
+                && self.function_bindings.iter().any(|scope| scope.contains(&name))
+            {
+                missing.push(name);
+            }
+        }
+        if missing.is_empty() {
+            return;
+        }
+
+        let names = missing
+            .iter()
+            .map(|name| format!("'{}'", name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = if deps.elements.is_empty() {
+            format!("{} has an empty dependency array but uses {}", hook, names)
+        } else {
+            format!("{} is missing dependencies: {}", hook, names)
+        };
+        let mut all: Vec<&str> = deps
+            .elements
+            .iter()
+            .map(|element| {
+                let span = element.span();
+                &self.lines.source[span.start as usize..span.end as usize]
+            })
+            .collect();
+        all.extend(missing.iter().map(String::as_str));
+        let suggestion = format!(
+            "List every prop, state value and local the callback uses: [{}]",
+            all.join(", ")
+        );
+        self.add_diagnostic(
+            deps.span.start,
+            &message,
+            "react-hooks-order",
+            Severity::Warning,
+            Some(&suggestion),
+        );
     }
 
     fn add_diagnostic(
@@ -185,7 +347,7 @@ impl<'a> LintVisitor<'a> {
         severity: Severity,
         suggestion: Option<&str>,
     ) {
-        let (line, column) = self.get_line_col(offset);
+        let (line, column) = self.lines.line_col(offset);
         self.diagnostics.push(Diagnostic {
             file: self.file.clone(),
             line,
@@ -199,6 +361,50 @@ impl<'a> LintVisitor<'a> {
 }
 
 impl<'a> Visit<'a> for LintVisitor<'a> {
+    fn enter_node(&mut self, kind: AstKind<'a>) {
+        if matches!(kind, AstKind::Function(_) | AstKind::ArrowFunctionExpression(_)) {
+            self.function_bindings.push(HashSet::new());
+        }
+    }
+
+    fn leave_node(&mut self, kind: AstKind<'a>) {
+        if matches!(kind, AstKind::Function(_) | AstKind::ArrowFunctionExpression(_)) {
+            self.function_bindings.pop();
+        }
+    }
+
+    fn visit_binding_identifier(&mut self, ident: &oxc_ast::ast::BindingIdentifier<'a>) {
+        if let Some(scope) = self.function_bindings.last_mut() {
+            scope.insert(ident.name.to_string());
+        }
+        walk::walk_binding_identifier(self, ident);
+    }
+
+    fn visit_variable_declarator(&mut self, decl: &oxc_ast::ast::VariableDeclarator<'a>) {
+        walk::walk_variable_declarator(self, decl);
+
+        // `const [state, setState] = useState()` and `const ref = useRef()`
+        // are the same on every render, so they're never dependencies
+        let Some(Expression::CallExpression(init)) = &decl.init else {
+            return;
+        };
+        let Expression::Identifier(callee) = &init.callee else {
+            return;
+        };
+        let stable = match (callee.name.as_str(), &decl.id.kind) {
+            ("useState" | "useReducer", BindingPatternKind::ArrayPattern(pattern)) => pattern
+                .elements
+                .get(1)
+                .and_then(|element| element.as_ref())
+                .and_then(|element| element.get_binding_identifier()),
+            ("useRef", BindingPatternKind::BindingIdentifier(ident)) => Some(&**ident),
+            _ => None,
+        };
+        if let (Some(ident), Some(scope)) = (stable, self.function_bindings.last_mut()) {
+            scope.remove(ident.name.as_str());
+        }
+    }
+
     fn visit_debugger_statement(&mut self, stmt: &oxc_ast::ast::DebuggerStatement) {
         if self.config.has_rule(LintRule::NoDebugger) {
             self.add_diagnostic(
@@ -259,9 +465,55 @@ impl<'a> Visit<'a> for LintVisitor<'a> {
             }
         }
 
+        // no-promise-callback
+        if self.config.has_rule(LintRule::NoPromiseCallback) {
+            if let Expression::StaticMemberExpression(member) = &expr.callee {
+                let method = member.property.name.as_str();
+                let catch_only = method == "catch" && !is_then_call(&member.object);
+                if matches!(method, "then" | "catch")
+                    && expr.arguments.iter().any(is_sync_callback)
+                    && !(catch_only && self.config.options.allow_catch_only)
+                {
+                    self.add_diagnostic(
+                        member.property.span.start,
+                        &format!("Promise callback passed to .{}()", method),
+                        "no-promise-callback",
+                        Severity::Info,
+                        Some("Convert the chain to async/await with try/catch for errors"),
+                    );
+                }
+            }
+        }
+
+        if self.config.has_rule(LintRule::ReactHooksOrder) {
+            self.check_hook_dependencies(expr);
+        }
+
         walk::walk_call_expression(self, expr);
     }
 
+    fn visit_binary_expression(&mut self, expr: &BinaryExpression<'a>) {
+        // strict-equality
+        if self.config.has_rule(LintRule::StrictEquality)
+            && matches!(expr.operator, BinaryOperator::Equality | BinaryOperator::Inequality)
+        {
+            let null_check = matches!(expr.left, Expression::NullLiteral(_))
+                || matches!(expr.right, Expression::NullLiteral(_));
+            if !(null_check && self.config.options.strict_equality_allow_null) {
+                let operator = expr.operator.as_str();
+                self.add_diagnostic(
+                    expr.span.start,
+                    &format!("Expected '{}=' and instead saw '{}'", operator, operator),
+                    "strict-equality",
+                    Severity::Warning,
+                    Some("Use === for strict equality comparison"),
+                );
+            }
+        }
+
+        walk::walk_binary_expression(self, expr);
+    }
+
     fn visit_variable_declaration(&mut self, decl: &oxc_ast::ast::VariableDeclaration<'a>) {
         // no-var
         if self.config.has_rule(LintRule::NoVar) && decl.kind == VariableDeclarationKind::Var {
@@ -310,14 +562,85 @@ impl<'a> Visit<'a> for LintVisitor<'a> {
     }
 }
 
+/// Whether `expr` is a `.then(...)` call
+fn is_then_call(expr: &Expression) -> bool {
+    let Expression::CallExpression(call) = expr else {
+        return false;
+    };
+    matches!(&call.callee, Expression::StaticMemberExpression(m) if m.property.name == "then")
+}
+
+/// Identifiers a hook callback reads and the names it declares itself
+#[derive(Default)]
+struct ReferenceCollector {
+    references: Vec<String>,
+    declared: HashSet<String>,
+}
+
+impl<'a> Visit<'a> for ReferenceCollector {
+    fn visit_identifier_reference(&mut self, ident: &oxc_ast::ast::IdentifierReference<'a>) {
+        self.references.push(ident.name.to_string());
+    }
+
+    fn visit_binding_identifier(&mut self, ident: &oxc_ast::ast::BindingIdentifier<'a>) {
+        self.declared.insert(ident.name.to_string());
+    }
+}
+
+/// `useEffect`, `useCallback` or `useMemo`, called directly or on `React`
+fn hook_name<'b>(callee: &'b Expression) -> Option<&'b str> {
+    let name = match callee {
+        Expression::Identifier(id) => id.name.as_str(),
+        Expression::StaticMemberExpression(member) => match &member.object {
+            Expression::Identifier(object) if object.name == "React" => member.property.name.as_str(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    matches!(name, "useEffect" | "useCallback" | "useMemo").then_some(name)
+}
+
+/// Root names of a dependency array, e.g. `props` for `props.user.id`, or
+/// `None` when an element is anything else
+fn dependency_roots<'b>(deps: &'b ArrayExpression) -> Option<HashSet<&'b str>> {
+    deps.elements
+        .iter()
+        .map(|element| element.as_expression().and_then(dependency_root))
+        .collect()
+}
+
+fn dependency_root<'b>(expr: &'b Expression) -> Option<&'b str> {
+    match expr {
+        Expression::Identifier(id) => Some(id.name.as_str()),
+        Expression::StaticMemberExpression(member) => dependency_root(&member.object),
+        Expression::ComputedMemberExpression(member) => dependency_root(&member.object),
+        Expression::ChainExpression(chain) => match &chain.expression {
+            ChainElement::StaticMemberExpression(member) => dependency_root(&member.object),
+            ChainElement::ComputedMemberExpression(member) => dependency_root(&member.object),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Function expressions and non-async arrows; an async arrow already uses
+/// async/await for its own body
+fn is_sync_callback(arg: &Argument) -> bool {
+    match arg {
+        Argument::FunctionExpression(_) => true,
+        Argument::ArrowFunctionExpression(arrow) => !arrow.r#async,
+        _ => false,
+    }
+}
+
 /// Collect all JS/TS files from a directory
-fn collect_js_ts_files(dir: &Path) -> Vec<std::path::PathBuf> {
+fn collect_js_ts_files(dir: &Path, filter: FileFilter) -> Vec<std::path::PathBuf> {
     let mut files = Vec::new();
-    collect_js_ts_files_recursive(dir, &mut files);
+    collect_js_ts_files_recursive(dir, filter, &mut files);
     files
 }
 
-fn collect_js_ts_files_recursive(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+fn collect_js_ts_files_recursive(dir: &Path, filter: FileFilter, files: &mut Vec<std::path::PathBuf>) {
     let entries = match fs::read_dir(dir) {
         Ok(e) => e,
         Err(_) => return,
@@ -335,8 +658,8 @@ fn collect_js_ts_files_recursive(dir: &Path, files: &mut Vec<std::path::PathBuf>
         }
 
         if path.is_dir() {
-            collect_js_ts_files_recursive(&path, files);
-        } else if is_js_ts_file(&path) {
+            collect_js_ts_files_recursive(&path, filter, files);
+        } else if is_js_ts_file(&path) && !filter.skips(&path) {
             files.push(path);
         }
     }
@@ -390,6 +713,104 @@ mod tests {
         assert_eq!(diagnostics[0].rule, "no-duplicate-keys");
     }
 
+    #[test]
+    fn test_strict_equality() {
+        let linter = Linter::with_rules(vec![LintRule::StrictEquality]);
+        let source = "if (a == b) {}\nif (a != b) {}\nif (a === b) {}\nif (a !== b) {}";
+        let diagnostics = linter.lint_source("test.js", source);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.rule == "strict-equality"));
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+        assert_eq!(
+            diagnostics[0].suggestion.as_deref(),
+            Some("Use === for strict equality comparison")
+        );
+        let mut lines: Vec<_> = diagnostics.iter().map(|d| (d.line, d.column)).collect();
+        lines.sort();
+        assert_eq!(lines, vec![(1, 5), (2, 5)]);
+    }
+
+    #[test]
+    fn test_strict_equality_allow_null() {
+        let source = "if (x == null) {}\nif (null != y) {}";
+        let linter = Linter::with_rules(vec![LintRule::StrictEquality]);
+        assert_eq!(linter.lint_source("test.js", source).len(), 2);
+
+        let allowed = linter.with_config(LinterConfig {
+            strict_equality_allow_null: true,
+            ..LinterConfig::default()
+        });
+        assert!(allowed.lint_source("test.js", source).is_empty());
+        assert_eq!(allowed.lint_source("test.js", "if (x == 0) {}").len(), 1);
+    }
+
+    #[test]
+    fn test_no_promise_callback_chain() {
+        let linter = Linter::with_rules(vec![LintRule::NoPromiseCallback]);
+        let source = "fetch(url)\n  .then(function (r) { return r.json(); })\n  .catch(e => log(e));";
+        let diagnostics = linter.lint_source("test.js", source);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.rule == "no-promise-callback"));
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Info));
+        let mut lines: Vec<_> = diagnostics.iter().map(|d| (d.line, d.column)).collect();
+        lines.sort();
+        assert_eq!(lines, vec![(2, 4), (3, 4)]);
+
+        // A .catch after .then is still part of the chain
+        let allowed = linter.with_config(LinterConfig {
+            allow_catch_only: true,
+            ..LinterConfig::default()
+        });
+        assert_eq!(allowed.lint_source("test.js", source).len(), 2);
+    }
+
+    #[test]
+    fn test_no_promise_callback_catch_only() {
+        let source = "save(data).catch(err => log(err));";
+        let linter = Linter::with_rules(vec![LintRule::NoPromiseCallback]);
+        assert_eq!(linter.lint_source("test.js", source).len(), 1);
+
+        let allowed = linter.with_config(LinterConfig {
+            allow_catch_only: true,
+            ..LinterConfig::default()
+        });
+        assert!(allowed.lint_source("test.js", source).is_empty());
+    }
+
+    #[test]
+    fn test_no_promise_callback_async_arrow() {
+        let linter = Linter::with_rules(vec![LintRule::NoPromiseCallback]);
+        let source = "ready.then(async () => { await start(); });\npromise.then(handler);";
+        assert!(linter.lint_source("test.js", source).is_empty());
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let linter = Linter::new();
+        let source = "const a = 1;\nfunction f() {\n  const b = ;\n}\n";
+        let diagnostics = linter.lint_source("test.js", source);
+
+        assert!(!diagnostics.is_empty());
+        let error = &diagnostics[0];
+        assert_eq!(error.rule, "parse-error");
+        assert_eq!((error.line, error.column), (3, 13));
+        assert!(error
+            .suggestion
+            .as_deref()
+            .unwrap()
+            .starts_with("3 |   const b = ;\n  |             ^"));
+    }
+
+    #[test]
+    fn test_line_index_multibyte() {
+        let lines = LineIndex::new("let é = 1;\nx");
+        assert_eq!(lines.line_col(6), (1, 6));
+        assert_eq!(lines.line_col(12), (2, 1));
+        assert_eq!(lines.line_text(2), "x");
+    }
+
     #[test]
     fn test_clean_code() {
         let linter = Linter::new();
@@ -397,4 +818,63 @@ mod tests {
 
         assert!(diagnostics.is_empty());
     }
+
+    #[test]
+    fn test_react_hooks_empty_deps() {
+        let linter = Linter::with_rules(vec![LintRule::ReactHooksOrder]);
+        let source = "import { useEffect, useState } from 'react';\n\
+                      function Profile({ userId }) {\n\
+                      \x20 const [user, setUser] = useState(null);\n\
+                      \x20 useEffect(() => {\n\
+                      \x20   fetchUser(userId).then(setUser);\n\
+                      \x20 }, []);\n\
+                      \x20 return <p>{user}</p>;\n\
+                      }\n";
+
+        let diagnostics = linter.lint_source("Profile.jsx", source);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "react-hooks-order");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].message, "useEffect has an empty dependency array but uses 'userId'");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (6, 6));
+        assert!(diagnostics[0].suggestion.as_deref().unwrap().ends_with("[userId]"));
+    }
+
+    #[test]
+    fn test_react_hooks_missing_deps() {
+        let linter = Linter::with_rules(vec![LintRule::ReactHooksOrder]);
+        let source = "function Editor({ onSave }) {\n\
+                      \x20 const [draft, setDraft] = useState('');\n\
+                      \x20 const title = draft.trim();\n\
+                      \x20 const save = React.useCallback(() => onSave(draft, title), [draft]);\n\
+                      \x20 return save;\n\
+                      }\n";
+
+        let diagnostics = linter.lint_source("Editor.js", source);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "useCallback is missing dependencies: 'onSave', 'title'");
+        assert!(diagnostics[0].suggestion.as_deref().unwrap().ends_with("[draft, onSave, title]"));
+    }
+
+    #[test]
+    fn test_react_hooks_correct_deps() {
+        let linter = Linter::with_rules(vec![LintRule::ReactHooksOrder]);
+        let source = "const API = '/api';\n\
+                      function List({ items, user }) {\n\
+                      \x20 const [count, setCount] = useState(0);\n\
+                      \x20 const input = useRef(null);\n\
+                      \x20 const done = useMemo(() => items.filter((item) => item.done).length, [items]);\n\
+                      \x20 useEffect(() => { input.current.focus(); setCount(0); fetch(API); }, []);\n\
+                      \x20 useEffect(() => { document.title = user.name; }, [user.name]);\n\
+                      \x20 useEffect(() => { const local = 1; console.log(local); });\n\
+                      \x20 useEffect(() => console.log(count), deps());\n\
+                      \x20 return done;\n\
+                      }\n";
+
+        let diagnostics = linter.lint_source("List.js", source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
 }
@@ -1,30 +1,101 @@
 pub mod comments;
 pub mod format;
+pub mod git_hooks;
 pub mod linter;
+pub mod notebook;
+pub mod python_linter;
+pub mod python_types;
+pub mod sql;
 pub mod typos;
 
+use crate::files::FileFilter;
+use crate::profile::{Language, RepoProfile};
 use crate::types::{CheckType, Diagnostic};
 use std::path::Path;
 
 pub trait Checker: Send + Sync {
     fn check_type(&self) -> CheckType;
-    fn check(&self, repo_path: &Path) -> Vec<Diagnostic>;
+    /// Diagnostics for the files of `repo_path` that `filter` keeps
+    fn check(&self, repo_path: &Path, filter: FileFilter) -> Vec<Diagnostic>;
+
+    /// Languages this checker applies to; empty means language-agnostic
+    fn languages(&self) -> &'static [Language] {
+        &[]
+    }
+}
+
+/// Whether a checker is relevant for a repository with the given profile
+pub fn applies_to(checker: &dyn Checker, profile: &RepoProfile) -> bool {
+    let languages = checker.languages();
+    languages.is_empty() || languages.iter().any(|l| profile.has(*l))
 }
 
-pub fn run_all_checkers(repo_path: &Path) -> Vec<(CheckType, Vec<Diagnostic>)> {
-    let checkers: Vec<Box<dyn Checker>> = vec![
+/// Every checker, in the order they should run
+pub fn all_checkers() -> Vec<Box<dyn Checker>> {
+    vec![
         Box::new(linter::Linter::new()),
+        Box::new(python_linter::PythonLinter::new()),
+        Box::new(python_types::PythonTypeChecker::new()),
+        Box::new(sql::SqlInjectionChecker::new()),
         Box::new(comments::CommentChecker::new()),
         Box::new(typos::TyposChecker::new()),
         Box::new(format::FormatChecker::new()),
-    ];
+        Box::new(git_hooks::GitHooksChecker::new()),
+    ]
+}
+
+/// Checkers that apply to a repository, in the order they should run.
+/// Language-agnostic ones (comments, typos, format, git hooks) always do.
+pub fn checkers_for(profile: &RepoProfile) -> Vec<Box<dyn Checker>> {
+    all_checkers()
+        .into_iter()
+        .filter(|checker| applies_to(checker.as_ref(), profile))
+        .collect()
+}
 
-    checkers
+pub fn run_all_checkers(
+    repo_path: &Path,
+    profile: &RepoProfile,
+    filter: FileFilter,
+) -> Vec<(CheckType, Vec<Diagnostic>)> {
+    checkers_for(profile)
         .into_iter()
         .map(|checker| {
             let check_type = checker.check_type();
-            let diagnostics = checker.check(repo_path);
+            let diagnostics = checker.check(repo_path, filter);
             (check_type, diagnostics)
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_js_linter_skipped_for_rust_repo() {
+        let rust = RepoProfile::from_paths([Path::new("src/main.rs")]);
+        let mixed = RepoProfile::from_paths([Path::new("src/main.rs"), Path::new("web/app.ts")]);
+
+        assert!(!applies_to(&linter::Linter::new(), &rust));
+        assert!(applies_to(&linter::Linter::new(), &mixed));
+        assert!(applies_to(&comments::CommentChecker::new(), &rust));
+    }
+
+    #[test]
+    fn test_checkers_for_python_repo() {
+        let python = RepoProfile::from_paths([Path::new("app.py"), Path::new("README.md")]);
+        let checkers = checkers_for(&python);
+        let check_types: Vec<CheckType> = checkers.iter().map(|c| c.check_type()).collect();
+
+        // The JS/TS linter is left out; the Python linter reports as Lint too
+        assert_eq!(checkers.len(), all_checkers().len() - 1);
+        assert!(checkers
+            .iter()
+            .all(|c| c.languages().is_empty() || c.languages().contains(&Language::Python)));
+        assert!(check_types.contains(&CheckType::PythonTypes));
+        for always in [CheckType::Comments, CheckType::Typos, CheckType::Format] {
+            assert!(check_types.contains(&always));
+        }
+    }
+}
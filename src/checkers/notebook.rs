@@ -0,0 +1,121 @@
+//! Jupyter notebook support for the text-based checkers
+//!
+//! Notebooks are JSON, so checkers run on each cell's source instead of the
+//! raw file. Diagnostics point at `path#cell-N` with lines relative to the
+//! cell, so they can be located in the notebook UI.
+
+use serde_json::Value;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellKind {
+    Code,
+    Markdown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotebookCell {
+    /// 1-based position among all cells of the notebook
+    pub number: usize,
+    pub kind: CellKind,
+    pub source: String,
+}
+
+/// Code and markdown cells of a notebook plus its kernel language
+#[derive(Debug, Clone, Default)]
+pub struct Notebook {
+    /// Lowercase kernel language, e.g. `python`; empty if not recorded
+    pub language: String,
+    pub cells: Vec<NotebookCell>,
+}
+
+pub fn is_notebook(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("ipynb")
+}
+
+/// Path reported for diagnostics in a cell
+pub fn cell_path(path: &str, number: usize) -> String {
+    format!("{}#cell-{}", path, number)
+}
+
+impl Notebook {
+    /// Parse notebook JSON; `None` if it isn't a notebook. Raw cells and
+    /// outputs are skipped.
+    pub fn parse(content: &str) -> Option<Self> {
+        let json: Value = serde_json::from_str(content).ok()?;
+        let cells = json.get("cells")?.as_array()?;
+
+        let metadata = &json["metadata"];
+        let language = metadata["kernelspec"]["language"]
+            .as_str()
+            .or_else(|| metadata["language_info"]["name"].as_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let cells = cells
+            .iter()
+            .enumerate()
+            .filter_map(|(index, cell)| {
+                let kind = match cell["cell_type"].as_str()? {
+                    "code" => CellKind::Code,
+                    "markdown" => CellKind::Markdown,
+                    _ => return None,
+                };
+                Some(NotebookCell {
+                    number: index + 1,
+                    kind,
+                    source: cell_source(&cell["source"]),
+                })
+            })
+            .collect();
+
+        Some(Self { language, cells })
+    }
+}
+
+/// nbformat stores source as a string or a list of lines with their newlines
+fn cell_source(source: &Value) -> String {
+    match source {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notebook() {
+        let content = r##"{
+            "cells": [
+                {"cell_type": "markdown", "source": ["# Title\n", "Some text"]},
+                {"cell_type": "raw", "source": "ignored"},
+                {"cell_type": "code", "source": "x = 1\n# TODO: plot", "outputs": [{"text": "teh"}]}
+            ],
+            "metadata": {"kernelspec": {"language": "Python"}},
+            "nbformat": 4
+        }"##;
+        let notebook = Notebook::parse(content).unwrap();
+
+        assert_eq!(notebook.language, "python");
+        assert_eq!(
+            notebook.cells,
+            vec![
+                NotebookCell {
+                    number: 1,
+                    kind: CellKind::Markdown,
+                    source: "# Title\nSome text".to_string(),
+                },
+                NotebookCell {
+                    number: 3,
+                    kind: CellKind::Code,
+                    source: "x = 1\n# TODO: plot".to_string(),
+                },
+            ]
+        );
+        assert!(Notebook::parse("not json").is_none());
+        assert_eq!(cell_path("a.ipynb", 3), "a.ipynb#cell-3");
+    }
+}
@@ -0,0 +1,664 @@
+//! Python linting with source heuristics
+//!
+//! Rules run over a masked copy of the source where string literals and
+//! comments are blanked out, so `"eval("` in a docstring or `# print(x)` never
+//! match. Analysis sits behind [`PythonBackend`] so a real parser backend can
+//! replace the heuristics without changing the checker.
+
+use crate::checkers::Checker;
+use crate::files::{relative_path, FileFilter};
+use crate::profile::Language;
+use crate::types::{CheckType, Diagnostic, Severity};
+use rayon::prelude::*;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+static BARE_EXCEPT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^[ \t]*(except)[ \t]*:").unwrap());
+static WILDCARD_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^[ \t]*(from)[ \t]+[\w.]+[ \t]+import[ \t]+\*").unwrap());
+static BUILTIN_CALL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(print|eval|exec)\s*\(").unwrap());
+static NONE_COMPARISON: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(==|!=)\s*None\b|\bNone\s*(==|!=)").unwrap());
+static DEF_PARAMS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bdef\s+\w+\s*(\()").unwrap());
+static MUTABLE_CONSTRUCTOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(list|dict|set|defaultdict|OrderedDict|collections\.\w+)\s*\(").unwrap()
+});
+
+/// Available Python lint rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PyLintRule {
+    /// Disallow `except:` without an exception type
+    BareExcept,
+    /// Disallow print() outside test files
+    NoPrint,
+    /// Disallow mutable default arguments (`def f(x=[])`)
+    MutableDefault,
+    /// Disallow eval()/exec()
+    NoEval,
+    /// Disallow `from module import *`
+    WildcardImport,
+    /// Disallow `== None` / `!= None`
+    NoneComparison,
+}
+
+impl PyLintRule {
+    /// Get recommended rules (good defaults)
+    pub fn recommended() -> Vec<PyLintRule> {
+        vec![
+            PyLintRule::BareExcept,
+            PyLintRule::NoPrint,
+            PyLintRule::MutableDefault,
+            PyLintRule::NoEval,
+            PyLintRule::WildcardImport,
+            PyLintRule::NoneComparison,
+        ]
+    }
+
+    /// Rule name used in diagnostics, namespaced to avoid clashing with JS rules
+    pub fn name(&self) -> &'static str {
+        match self {
+            PyLintRule::BareExcept => "py-bare-except",
+            PyLintRule::NoPrint => "py-no-print",
+            PyLintRule::MutableDefault => "py-mutable-default",
+            PyLintRule::NoEval => "py-no-eval",
+            PyLintRule::WildcardImport => "py-wildcard-import",
+            PyLintRule::NoneComparison => "py-none-comparison",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            PyLintRule::NoEval => Severity::Error,
+            PyLintRule::NoPrint => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// A rule violation reported by a backend (1-based line/column)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule: PyLintRule,
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+    pub suggestion: String,
+}
+
+/// Source analysis backend for [`PythonLinter`]
+pub trait PythonBackend: Send + Sync {
+    /// Report violations of the enabled `rules` in `source`.
+    /// `is_test` is set for test files, where print() is allowed.
+    fn analyze(&self, source: &str, rules: &HashSet<PyLintRule>, is_test: bool) -> Vec<Finding>;
+}
+
+/// Line/regex heuristics over comment- and string-masked source
+pub struct HeuristicBackend;
+
+impl PythonBackend for HeuristicBackend {
+    fn analyze(&self, source: &str, rules: &HashSet<PyLintRule>, is_test: bool) -> Vec<Finding> {
+        let masked = mask_source(source);
+        let positions = Positions::new(&masked);
+        let mut findings = Vec::new();
+
+        let mut push = |rule: PyLintRule, offset: usize, message: String, suggestion: &str| {
+            let (line, column) = positions.line_col(offset);
+            findings.push(Finding {
+                rule,
+                line,
+                column,
+                message,
+                suggestion: suggestion.to_string(),
+            });
+        };
+
+        if rules.contains(&PyLintRule::BareExcept) {
+            for caps in BARE_EXCEPT.captures_iter(&masked) {
+                push(
+                    PyLintRule::BareExcept,
+                    caps.get(1).unwrap().start(),
+                    "Bare 'except:' catches every exception, including KeyboardInterrupt"
+                        .to_string(),
+                    "Catch a specific exception, e.g. 'except ValueError:' or 'except Exception:'",
+                );
+            }
+        }
+
+        if rules.contains(&PyLintRule::WildcardImport) {
+            for caps in WILDCARD_IMPORT.captures_iter(&masked) {
+                push(
+                    PyLintRule::WildcardImport,
+                    caps.get(1).unwrap().start(),
+                    "Wildcard import pollutes the namespace".to_string(),
+                    "Import the names you need explicitly",
+                );
+            }
+        }
+
+        for caps in BUILTIN_CALL.captures_iter(&masked) {
+            let name = caps.get(1).unwrap();
+            if !is_builtin_call(&masked, name.start()) {
+                continue;
+            }
+            match name.as_str() {
+                "print" if rules.contains(&PyLintRule::NoPrint) && !is_test => push(
+                    PyLintRule::NoPrint,
+                    name.start(),
+                    "Unexpected print() call".to_string(),
+                    "Use the logging module instead of print()",
+                ),
+                "eval" | "exec" if rules.contains(&PyLintRule::NoEval) => push(
+                    PyLintRule::NoEval,
+                    name.start(),
+                    format!(
+                        "{}() is a security risk and should be avoided",
+                        name.as_str()
+                    ),
+                    "Use ast.literal_eval() for data or call the code directly",
+                ),
+                _ => {}
+            }
+        }
+
+        if rules.contains(&PyLintRule::NoneComparison) {
+            for caps in NONE_COMPARISON.captures_iter(&masked) {
+                let op = caps.get(1).or_else(|| caps.get(2)).unwrap();
+                let (message, suggestion) = if op.as_str() == "==" {
+                    ("Comparison to None with '=='", "Use 'is None' instead")
+                } else {
+                    ("Comparison to None with '!='", "Use 'is not None' instead")
+                };
+                push(
+                    PyLintRule::NoneComparison,
+                    caps.get(0).unwrap().start(),
+                    message.to_string(),
+                    suggestion,
+                );
+            }
+        }
+
+        if rules.contains(&PyLintRule::MutableDefault) {
+            for caps in DEF_PARAMS.captures_iter(&masked) {
+                for offset in mutable_defaults(&masked, caps.get(1).unwrap().start()) {
+                    push(
+                        PyLintRule::MutableDefault,
+                        offset,
+                        "Mutable default argument is shared between calls".to_string(),
+                        "Default to None and create the value inside the function",
+                    );
+                }
+            }
+        }
+
+        findings.sort_by_key(|f| (f.line, f.column));
+        findings
+    }
+}
+
+/// Blank out comments and string contents, keeping quotes, newlines and the
+/// character count of every line so positions map back to the original.
+pub(crate) fn mask_source(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let blank = |c: char| if c == '\n' { '\n' } else { ' ' };
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                out.push(' ');
+                i += 1;
+            }
+        } else if c == '"' || c == '\'' {
+            let is_triple_at =
+                |j: usize| j + 2 < chars.len() && chars[j + 1] == c && chars[j + 2] == c;
+            let triple = is_triple_at(i);
+            let delim_len = if triple { 3 } else { 1 };
+            out.extend(std::iter::repeat_n(c, delim_len));
+            i += delim_len;
+
+            while i < chars.len() {
+                let ch = chars[i];
+                if ch == '\\' && i + 1 < chars.len() {
+                    out.push(' ');
+                    out.push(blank(chars[i + 1]));
+                    i += 2;
+                } else if ch == '\n' && !triple {
+                    // Unterminated single-line string
+                    break;
+                } else if ch == c && (!triple || is_triple_at(i)) {
+                    out.extend(std::iter::repeat_n(c, delim_len));
+                    i += delim_len;
+                    break;
+                } else {
+                    out.push(blank(ch));
+                    i += 1;
+                }
+            }
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+
+    out
+}
+
+/// Byte offset to 1-based line/column (in characters) lookup
+pub(crate) struct Positions<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Positions<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    pub(crate) fn line_col(&self, offset: usize) -> (u32, u32) {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.text[self.line_starts[line]..offset].chars().count() + 1;
+        (line as u32 + 1, column as u32)
+    }
+}
+
+/// Whether a name match is a call to the builtin rather than a method call
+/// (`obj.print(`) or a definition (`def print(`)
+fn is_builtin_call(masked: &str, start: usize) -> bool {
+    let before = &masked[..start];
+    if before.ends_with('.') {
+        return false;
+    }
+    let before = before.trim_end_matches([' ', '\t']);
+    !(before.ends_with("def") && before.len() < start)
+}
+
+/// Byte spans of the parameters in the list opening at `open`, plus the
+/// offset of the closing parenthesis. `None` if the list is unterminated.
+pub(crate) fn parameter_spans(masked: &str, open: usize) -> Option<(Vec<(usize, usize)>, usize)> {
+    let mut spans = Vec::new();
+    let mut depth = 0i32;
+    let mut segment_start = open + 1;
+
+    for (i, &b) in masked.as_bytes().iter().enumerate().skip(open) {
+        match b {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    spans.push((segment_start, i));
+                    spans.retain(|&(start, end)| !masked[start..end].trim().is_empty());
+                    return Some((spans, i));
+                }
+            }
+            b',' if depth == 1 => {
+                spans.push((segment_start, i));
+                segment_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Offset of the `=` separating a parameter from its default value, ignoring
+/// comparison operators and anything nested in brackets
+pub(crate) fn default_separator(masked: &str, start: usize, end: usize) -> Option<usize> {
+    let bytes = masked.as_bytes();
+    let mut depth = 0i32;
+
+    for (i, &b) in bytes.iter().enumerate().take(end).skip(start) {
+        match b {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'=' if depth == 0 => {
+                let prev = if i > start { bytes[i - 1] } else { b' ' };
+                let next = if i + 1 < end { bytes[i + 1] } else { b' ' };
+                if next != b'=' && !matches!(prev, b'=' | b'!' | b'<' | b'>') {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Offsets of mutable default values in the parameter list opening at `open`
+fn mutable_defaults(masked: &str, open: usize) -> Vec<usize> {
+    let Some((spans, _)) = parameter_spans(masked, open) else {
+        return vec![];
+    };
+
+    spans
+        .into_iter()
+        .filter_map(|(start, end)| mutable_default_in(masked, start, end))
+        .collect()
+}
+
+/// Check a single parameter (`name[: annotation][= default]`) spanning `start..end`
+fn mutable_default_in(masked: &str, start: usize, end: usize) -> Option<usize> {
+    let value_start = default_separator(masked, start, end)? + 1;
+    let value = &masked[value_start..end];
+    let trimmed = value.trim_start();
+    let offset = value_start + (value.len() - trimmed.len());
+
+    let is_mutable = trimmed.starts_with('[')
+        || trimmed.starts_with('{')
+        || MUTABLE_CONSTRUCTOR.is_match(trimmed);
+    is_mutable.then_some(offset)
+}
+
+/// Test files may print freely
+fn is_test_file(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    file_name.starts_with("test_")
+        || file_name.ends_with("_test.py")
+        || file_name == "conftest.py"
+        || path
+            .components()
+            .any(|c| matches!(c.as_os_str().to_str(), Some("tests" | "test")))
+}
+
+/// Python linter configuration and executor
+pub struct PythonLinter {
+    rules: HashSet<PyLintRule>,
+    backend: Box<dyn PythonBackend>,
+}
+
+impl Default for PythonLinter {
+    fn default() -> Self {
+        Self::with_rules(PyLintRule::recommended())
+    }
+}
+
+impl PythonLinter {
+    /// Create a new linter with default rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a linter with specified rules
+    pub fn with_rules(rules: Vec<PyLintRule>) -> Self {
+        Self {
+            rules: rules.into_iter().collect(),
+            backend: Box::new(HeuristicBackend),
+        }
+    }
+
+    /// Replace the analysis backend
+    pub fn with_backend(mut self, backend: Box<dyn PythonBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Check if a rule is enabled
+    pub fn has_rule(&self, rule: PyLintRule) -> bool {
+        self.rules.contains(&rule)
+    }
+
+    /// Lint a single file
+    fn lint_file(&self, repo_path: &Path, path: &Path) -> Vec<Diagnostic> {
+        let source = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(_) => return vec![],
+        };
+
+        let filename = relative_path(repo_path, path);
+        self.lint_source(&filename, &source)
+    }
+
+    /// Lint source code directly
+    fn lint_source(&self, filename: &str, source: &str) -> Vec<Diagnostic> {
+        let is_test = is_test_file(Path::new(filename));
+
+        self.backend
+            .analyze(source, &self.rules, is_test)
+            .into_iter()
+            .map(|finding| Diagnostic {
+                file: filename.to_string(),
+                line: finding.line,
+                column: finding.column,
+                message: finding.message,
+                rule: finding.rule.name().to_string(),
+                severity: finding.rule.severity(),
+                suggestion: Some(finding.suggestion),
+            })
+            .collect()
+    }
+}
+
+impl Checker for PythonLinter {
+    fn check_type(&self) -> CheckType {
+        CheckType::Lint
+    }
+
+    fn languages(&self) -> &'static [Language] {
+        &[Language::Python]
+    }
+
+    fn check(&self, repo_path: &Path, filter: FileFilter) -> Vec<Diagnostic> {
+        let files = collect_python_files(repo_path, filter);
+
+        if files.is_empty() {
+            return vec![];
+        }
+
+        // Lint in parallel
+        files
+            .par_iter()
+            .flat_map(|file| self.lint_file(repo_path, file))
+            .collect()
+    }
+}
+
+/// Collect all Python files from a directory
+pub(crate) fn collect_python_files(dir: &Path, filter: FileFilter) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    collect_python_files_recursive(dir, filter, &mut files);
+    files
+}
+
+fn collect_python_files_recursive(dir: &Path, filter: FileFilter, files: &mut Vec<std::path::PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        // Skip hidden directories, virtualenvs and caches
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            if name.starts_with('.')
+                || matches!(
+                    name,
+                    "venv" | "env" | "__pycache__" | "site-packages" | "build" | "dist"
+                )
+            {
+                continue;
+            }
+        }
+
+        if path.is_dir() {
+            collect_python_files_recursive(&path, filter, files);
+        } else if path.extension().and_then(|e| e.to_str()) == Some("py")
+            && !filter.skips(&path)
+        {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn lint(source: &str) -> Vec<Diagnostic> {
+        PythonLinter::new().lint_source("app.py", source)
+    }
+
+    fn positions(diagnostics: &[Diagnostic], rule: &str) -> Vec<(u32, u32)> {
+        diagnostics
+            .iter()
+            .filter(|d| d.rule == rule)
+            .map(|d| (d.line, d.column))
+            .collect()
+    }
+
+    #[test]
+    fn test_bare_except() {
+        let source =
+            "try:\n    run()\nexcept:\n    pass\ntry:\n    run()\nexcept Exception:\n    pass\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(positions(&diagnostics, "py-bare-except"), vec![(3, 1)]);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0].suggestion.is_some());
+    }
+
+    #[test]
+    fn test_print_outside_tests() {
+        let source = "def main():\n    print('hi')\n    logger.print('x')\n    # print(debug)\n";
+        assert_eq!(positions(&lint(source), "py-no-print"), vec![(2, 5)]);
+
+        let in_tests = PythonLinter::new().lint_source("tests/test_app.py", source);
+        assert!(positions(&in_tests, "py-no-print").is_empty());
+    }
+
+    #[test]
+    fn test_mutable_default_multiline_def_with_decorator() {
+        let source = r#"@app.route("/", methods=["GET"])
+def handler(
+    request,
+    items: list[int] = [],
+    opts={"a": 1},
+    name="x",
+    flag: bool = a == b,
+    factory=dict(),
+):
+    return request
+"#;
+        assert_eq!(
+            positions(&lint(source), "py-mutable-default"),
+            vec![(4, 24), (5, 10), (8, 13)]
+        );
+    }
+
+    #[test]
+    fn test_mutable_default_clean_and_async() {
+        assert!(positions(
+            &lint("def f(x=None, y=(1, 2), z='[]'):\n    pass\n"),
+            "py-mutable-default"
+        )
+        .is_empty());
+        assert_eq!(
+            positions(
+                &lint("async def f(cache={}):\n    pass\n"),
+                "py-mutable-default"
+            ),
+            vec![(1, 19)]
+        );
+    }
+
+    #[test]
+    fn test_eval_and_exec() {
+        let source =
+            "x = eval(expr)\nexec(code)\nself.eval(x)\ndef eval(x):\n    pass\ns = 'eval(x)'\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(positions(&diagnostics, "py-no-eval"), vec![(1, 5), (2, 1)]);
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.rule != "py-no-eval" || d.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_wildcard_import() {
+        let source = "from os.path import *\nfrom . import *\nfrom x import (a, b)\nimport os\n";
+        assert_eq!(
+            positions(&lint(source), "py-wildcard-import"),
+            vec![(1, 1), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_none_comparison() {
+        let source = "if x == None:\n    pass\nif None != y:\n    pass\nif x is None:\n    pass\nmsg = '== None'\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(
+            positions(&diagnostics, "py-none-comparison"),
+            vec![(1, 6), (3, 4)]
+        );
+        assert!(diagnostics[1]
+            .suggestion
+            .as_deref()
+            .unwrap()
+            .contains("is not None"));
+    }
+
+    #[test]
+    fn test_strings_and_comments_are_masked() {
+        let source = "DOC = \"\"\"\nexcept:\nprint(x)\nfrom a import *\n\"\"\"\ns = 'it\\'s'; print(s)  # eval(x)\n";
+        let diagnostics = lint(source);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "py-no-print");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (6, 14));
+    }
+
+    #[test]
+    fn test_unicode_columns() {
+        let diagnostics = lint("s = '한글'; print(s)\n");
+        assert_eq!(positions(&diagnostics, "py-no-print"), vec![(1, 11)]);
+    }
+
+    #[test]
+    fn test_rule_selection() {
+        let linter = PythonLinter::with_rules(vec![PyLintRule::BareExcept]);
+        let diagnostics =
+            linter.lint_source("app.py", "print(1)\ntry:\n    x()\nexcept:\n    pass\n");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "py-bare-except");
+    }
+
+    #[test]
+    fn test_custom_backend() {
+        struct NoFindings;
+        impl PythonBackend for NoFindings {
+            fn analyze(&self, _: &str, _: &HashSet<PyLintRule>, _: bool) -> Vec<Finding> {
+                vec![]
+            }
+        }
+
+        let linter = PythonLinter::new().with_backend(Box::new(NoFindings));
+        assert!(linter.lint_source("app.py", "print(1)\n").is_empty());
+    }
+
+    #[test]
+    fn test_check_skips_virtualenv() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("venv/lib")).unwrap();
+        fs::write(dir.path().join("venv/lib/six.py"), "print(1)\n").unwrap();
+        fs::write(dir.path().join("main.py"), "print(1)\n").unwrap();
+
+        let diagnostics = PythonLinter::new().check(dir.path(), FileFilter::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "main.py");
+    }
+}
@@ -0,0 +1,250 @@
+//! Python type annotation checker
+//!
+//! Flags functions whose parameters or return value lack type annotations.
+//! Works on the same comment- and string-masked source as the Python linter.
+
+use crate::checkers::python_linter::{
+    collect_python_files, default_separator, mask_source, parameter_spans, Positions,
+};
+use crate::checkers::Checker;
+use crate::files::{relative_path, FileFilter};
+use crate::profile::Language;
+use crate::types::{CheckType, Diagnostic, Severity};
+use rayon::prelude::*;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+static FUNCTION_DEF: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^[ \t]*((?:async[ \t]+)?def)\s+(\w+)\s*(\()").unwrap());
+
+/// Methods whose signatures are conventional enough to skip
+const SKIPPED_FUNCTIONS: &[&str] = &["__init__", "__str__", "__repr__"];
+
+/// Checker for missing type annotations on Python functions
+pub struct PythonTypeChecker {
+    /// Severity reported for private (`_`-prefixed) functions
+    private_function_min_severity: Severity,
+}
+
+impl Default for PythonTypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PythonTypeChecker {
+    pub fn new() -> Self {
+        Self {
+            private_function_min_severity: Severity::Info,
+        }
+    }
+
+    pub fn with_private_function_min_severity(mut self, severity: Severity) -> Self {
+        self.private_function_min_severity = severity;
+        self
+    }
+
+    fn check_file(&self, repo_path: &Path, path: &Path) -> Vec<Diagnostic> {
+        let source = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(_) => return vec![],
+        };
+
+        self.check_source(&relative_path(repo_path, path), &source)
+    }
+
+    fn check_source(&self, filename: &str, source: &str) -> Vec<Diagnostic> {
+        let masked = mask_source(source);
+        let positions = Positions::new(&masked);
+        let mut diagnostics = Vec::new();
+
+        for caps in FUNCTION_DEF.captures_iter(&masked) {
+            let name = caps.get(2).unwrap().as_str();
+            if SKIPPED_FUNCTIONS.contains(&name) {
+                continue;
+            }
+
+            let Some((params, close)) = parameter_spans(&masked, caps.get(3).unwrap().start())
+            else {
+                continue;
+            };
+
+            let (line, column) = positions.line_col(caps.get(1).unwrap().start());
+            let is_private = name.starts_with('_');
+            let severity = |public: Severity| {
+                if is_private {
+                    self.private_function_min_severity
+                } else {
+                    public
+                }
+            };
+
+            let missing = unannotated_params(&masked, &params);
+            if !missing.is_empty() {
+                diagnostics.push(Diagnostic {
+                    file: filename.to_string(),
+                    line,
+                    column,
+                    message: format!(
+                        "Function '{}' has parameters without type annotations: {}",
+                        name,
+                        missing.join(", ")
+                    ),
+                    rule: "py-missing-param-types".to_string(),
+                    severity: severity(Severity::Warning),
+                    suggestion: Some(format!("Annotate parameters, e.g. '{}: str'", missing[0])),
+                });
+            }
+
+            if !masked[close + 1..].trim_start().starts_with("->") {
+                diagnostics.push(Diagnostic {
+                    file: filename.to_string(),
+                    line,
+                    column,
+                    message: format!("Function '{}' has no return type annotation", name),
+                    rule: "py-missing-return-type".to_string(),
+                    severity: severity(Severity::Info),
+                    suggestion: Some("Add a return type, e.g. '-> None'".to_string()),
+                });
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Names of parameters lacking an annotation. `self`/`cls` in first position
+/// and the bare `*` and `/` markers are ignored.
+fn unannotated_params(masked: &str, params: &[(usize, usize)]) -> Vec<String> {
+    params
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &(start, end))| {
+            let separator = default_separator(masked, start, end).unwrap_or(end);
+            let head = &masked[start..separator];
+            let name = head
+                .split(':')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .trim_start_matches('*');
+
+            if name.is_empty() || name == "/" || (index == 0 && matches!(name, "self" | "cls")) {
+                return None;
+            }
+
+            (!has_annotation(head)).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Whether a parameter (without its default) has a depth-0 `:` annotation
+fn has_annotation(param: &str) -> bool {
+    let mut depth = 0i32;
+    for b in param.bytes() {
+        match b {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b':' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+impl Checker for PythonTypeChecker {
+    fn check_type(&self) -> CheckType {
+        CheckType::PythonTypes
+    }
+
+    fn languages(&self) -> &'static [Language] {
+        &[Language::Python]
+    }
+
+    fn check(&self, repo_path: &Path, filter: FileFilter) -> Vec<Diagnostic> {
+        collect_python_files(repo_path, filter)
+            .par_iter()
+            .flat_map(|file| self.check_file(repo_path, file))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Vec<Diagnostic> {
+        PythonTypeChecker::new().check_source("app.py", source)
+    }
+
+    fn rules(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics.iter().map(|d| d.rule.as_str()).collect()
+    }
+
+    #[test]
+    fn test_unannotated_function() {
+        let diagnostics = check("def add(a, b):\n    return a + b\n");
+
+        assert_eq!(
+            rules(&diagnostics),
+            vec!["py-missing-param-types", "py-missing-return-type"]
+        );
+        assert!(diagnostics[0].message.contains("a, b"));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].severity, Severity::Info);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 1));
+    }
+
+    #[test]
+    fn test_fully_annotated() {
+        let source = r#"
+class Repo:
+    def __init__(self, path):
+        self.path = path
+
+    def __repr__(self):
+        return "Repo"
+
+    @classmethod
+    def load(cls, path: str, *args: int, key: str = "x", **kwargs: dict[str, int]) -> "Repo":
+        return cls(path)
+
+    async def fetch(self, *, timeout: float = 1.0) -> None:
+        pass
+"#;
+        assert!(check(source).is_empty());
+    }
+
+    #[test]
+    fn test_multiline_signature_and_defaults() {
+        let source = "async def fetch(\n    url: str,\n    retries=3,\n    hook=lambda r: r,\n    mapping: dict[str, int] = {},\n) -> bytes:\n    pass\n";
+        let diagnostics = check(source);
+
+        assert_eq!(rules(&diagnostics), vec!["py-missing-param-types"]);
+        assert!(diagnostics[0].message.ends_with("retries, hook"));
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 1));
+    }
+
+    #[test]
+    fn test_private_function_severity() {
+        let source = "    def _helper(x):\n        pass\n";
+        let diagnostics = check(source);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Info));
+        assert_eq!(diagnostics[0].column, 5);
+
+        let strict = PythonTypeChecker::new()
+            .with_private_function_min_severity(Severity::Warning)
+            .check_source("app.py", source);
+        assert!(strict.iter().all(|d| d.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn test_ignores_strings_and_comments() {
+        let source = "DOC = \"\"\"\ndef fake(x):\n\"\"\"\n# def other(y):\ndef real() -> int:\n    return 1\n";
+        assert!(check(source).is_empty());
+    }
+}
@@ -0,0 +1,353 @@
+//! SQL injection checker
+//!
+//! Flags SQL statements built from variables in raw query strings: string
+//! concatenation, Python f-strings and `%`/`.format()` formatting, JS/TS
+//! template literals, `Sprintf`/`String.format`, and Ruby/PHP interpolation.
+//! Patterns run line by line and only report string literals that contain a
+//! SQL statement, so constant queries and parameterized calls such as
+//! `cursor.execute("... WHERE id = %s", (id,))` are never flagged.
+
+use crate::checkers::Checker;
+use crate::files::{relative_path, FileFilter};
+use crate::profile::Language;
+use crate::types::{CheckType, Diagnostic, Severity};
+use rayon::prelude::*;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Extensions scanned for raw SQL
+const SQL_SOURCE_EXTENSIONS: &[&str] = &["py", "js", "ts", "java", "go", "rb", "php"];
+
+/// A SQL statement (or the WHERE clause of one) inside a string literal
+static SQL_STATEMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\bSELECT\s+(\*|DISTINCT\b|\w+\(|[\w.]+\s*,|[\w.]+\s+FROM\b)|\bINSERT\s+INTO\b|\bUPDATE\s+\w+\s+SET\b|\bDELETE\s+FROM\b|\bWHERE\s+\w+\s*(=|<|>|!=|\bLIKE\b|\bIN\b)",
+    )
+    .unwrap()
+});
+
+/// Regex for a single- or double-quoted string literal whose content matches
+/// `body`, captured as `dq` or `sq`
+fn quoted(body: &str) -> String {
+    format!(
+        r#"(?:"(?P<dq>{})"|'(?P<sq>{})')"#,
+        body.replace("QUOTE", "\""),
+        body.replace("QUOTE", "'")
+    )
+}
+
+/// Ways of building a query from variables
+static INJECTION_PATTERNS: LazyLock<Vec<InjectionPattern>> = LazyLock::new(|| {
+    vec![
+        InjectionPattern {
+            regex: Regex::new(&format!(
+                r"{}\s*(?:\+\s*(?P<var>[A-Za-z_$][\w$.]*)|\.\s*\$\w)",
+                quoted(r"[^QUOTE\n]*")
+            ))
+            .unwrap(),
+            description: "string concatenation",
+            extensions: &[],
+        },
+        InjectionPattern {
+            regex: Regex::new(r#"\b(?:[rR]?[fF]|[fF][rR])(?:"""|'''|"|')(?P<sql>[^\n]*\{[^}\n]+\}[^\n]*)"#)
+                .unwrap(),
+            description: "an f-string",
+            extensions: &["py"],
+        },
+        InjectionPattern {
+            regex: Regex::new(&format!(r"{}\s*%\s*[\w(]", quoted(r"[^QUOTE\n]*%[sd][^QUOTE\n]*"))).unwrap(),
+            description: "% formatting",
+            extensions: &["py"],
+        },
+        InjectionPattern {
+            regex: Regex::new(&format!(r"{}\s*\.format\(", quoted(r"[^QUOTE\n]*\{\w*\}[^QUOTE\n]*"))).unwrap(),
+            description: "str.format()",
+            extensions: &["py"],
+        },
+        InjectionPattern {
+            regex: Regex::new(r"`(?P<sql>[^`\n]*\$\{[^`\n]*)").unwrap(),
+            description: "a template literal",
+            extensions: &["js", "ts"],
+        },
+        InjectionPattern {
+            regex: Regex::new(r#"\b(?:Sprintf|String\.format|formatted)\(\s*"(?P<sql>[^"\n]*%[sdv][^"\n]*)""#)
+                .unwrap(),
+            description: "a format string",
+            extensions: &["go", "java"],
+        },
+        InjectionPattern {
+            regex: Regex::new(r#""(?P<sql>[^"\n]*(?:#\{|\$[A-Za-z_{])[^"\n]*)""#).unwrap(),
+            description: "string interpolation",
+            extensions: &["rb", "php"],
+        },
+    ]
+});
+
+struct InjectionPattern {
+    regex: Regex,
+    /// How the query is built, for the diagnostic message
+    description: &'static str,
+    /// Extensions the pattern applies to; empty means all scanned files
+    extensions: &'static [&'static str],
+}
+
+/// Whether a concatenated identifier is a constant (`TABLE_NAME`), which
+/// can't carry user input
+fn is_constant(identifier: &str) -> bool {
+    let name = identifier.rsplit('.').next().unwrap_or(identifier);
+    name.chars().any(|c| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Whether a line is only a comment
+fn is_comment_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    ["//", "#", "/*", "*", "--"]
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix))
+}
+
+/// Checker for SQL queries built from variables
+pub struct SqlInjectionChecker;
+
+impl Default for SqlInjectionChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SqlInjectionChecker {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn check_file(&self, repo_path: &Path, path: &Path) -> Vec<Diagnostic> {
+        let source = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(_) => return vec![],
+        };
+
+        let filename = relative_path(repo_path, path);
+        self.check_source(&filename, &source)
+    }
+
+    /// Check source code directly
+    fn check_source(&self, filename: &str, source: &str) -> Vec<Diagnostic> {
+        let ext = Path::new(filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+        let patterns: Vec<&InjectionPattern> = INJECTION_PATTERNS
+            .iter()
+            .filter(|p| p.extensions.is_empty() || p.extensions.contains(&ext))
+            .collect();
+
+        let mut diagnostics = Vec::new();
+        for (line_num, line) in source.lines().enumerate() {
+            if is_comment_line(line) {
+                continue;
+            }
+
+            // One report per line, for the first pattern that matches
+            let found = patterns.iter().find_map(|pattern| {
+                pattern.regex.captures_iter(line).find_map(|caps| {
+                    let sql = caps
+                        .name("sql")
+                        .or_else(|| caps.name("dq"))
+                        .or_else(|| caps.name("sq"))?;
+                    if !SQL_STATEMENT.is_match(sql.as_str()) {
+                        return None;
+                    }
+                    if caps.name("var").is_some_and(|var| is_constant(var.as_str())) {
+                        return None;
+                    }
+                    Some((caps.get(0).unwrap().start(), pattern.description))
+                })
+            });
+
+            if let Some((offset, description)) = found {
+                diagnostics.push(Diagnostic {
+                    file: filename.to_string(),
+                    line: (line_num + 1) as u32,
+                    column: (line[..offset].chars().count() + 1) as u32,
+                    message: format!("SQL query built with {} may allow SQL injection", description),
+                    rule: "sql-injection".to_string(),
+                    severity: Severity::Error,
+                    suggestion: Some(
+                        "Use a parameterized query (placeholders with bound parameters) or an ORM instead of inserting variables into SQL"
+                            .to_string(),
+                    ),
+                });
+            }
+        }
+
+        diagnostics
+    }
+}
+
+impl Checker for SqlInjectionChecker {
+    fn check_type(&self) -> CheckType {
+        CheckType::SqlInjection
+    }
+
+    fn languages(&self) -> &'static [Language] {
+        &[
+            Language::Python,
+            Language::JavaScript,
+            Language::TypeScript,
+            Language::Java,
+            Language::Go,
+            Language::Ruby,
+            Language::Php,
+        ]
+    }
+
+    fn check(&self, repo_path: &Path, filter: FileFilter) -> Vec<Diagnostic> {
+        let files = collect_sql_source_files(repo_path, filter);
+
+        if files.is_empty() {
+            return vec![];
+        }
+
+        files
+            .par_iter()
+            .flat_map(|file| self.check_file(repo_path, file))
+            .collect()
+    }
+}
+
+/// Collect files that may contain raw SQL from a directory
+fn collect_sql_source_files(dir: &Path, filter: FileFilter) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    collect_sql_source_files_recursive(dir, filter, &mut files);
+    files
+}
+
+fn collect_sql_source_files_recursive(dir: &Path, filter: FileFilter, files: &mut Vec<std::path::PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        // Skip hidden directories, dependencies and build output
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            if name.starts_with('.')
+                || matches!(
+                    name,
+                    "node_modules" | "target" | "dist" | "build" | "vendor" | "venv" | "__pycache__"
+                )
+            {
+                continue;
+            }
+        }
+
+        if path.is_dir() {
+            collect_sql_source_files_recursive(&path, filter, files);
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| SQL_SOURCE_EXTENSIONS.contains(&ext))
+            && !filter.skips(&path)
+        {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn lines(filename: &str, source: &str) -> Vec<u32> {
+        SqlInjectionChecker::new()
+            .check_source(filename, source)
+            .iter()
+            .map(|d| d.line)
+            .collect()
+    }
+
+    #[test]
+    fn test_python_f_strings() {
+        let source = r#"cursor.execute(f"SELECT * FROM users WHERE id = {user_id}")
+cursor.execute("SELECT * FROM users WHERE id = %s", (user_id,))
+cursor.execute("SELECT * FROM users WHERE name = '%s'" % name)
+label = f"Selected {count} items"
+# cursor.execute(f"DELETE FROM users WHERE id = {user_id}")
+"#;
+        let diagnostics = SqlInjectionChecker::new().check_source("app.py", source);
+
+        assert_eq!(diagnostics.iter().map(|d| d.line).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].column, 16);
+        assert!(diagnostics[0].message.contains("f-string"));
+        assert!(diagnostics[0].suggestion.as_deref().unwrap().contains("parameterized"));
+    }
+
+    #[test]
+    fn test_javascript_template_literals() {
+        let source = r#"db.query(`SELECT * FROM orders WHERE user_id = ${userId}`);
+db.query(`UPDATE users SET name = '${name}' WHERE id = 1`);
+db.query("SELECT * FROM orders WHERE user_id = $1", [userId]);
+const greeting = `Hello ${name}`;
+"#;
+        assert_eq!(lines("db.js", source), vec![1, 2]);
+        assert_eq!(lines("db.ts", source), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_java_string_concatenation() {
+        let source = r#"String sql = "SELECT * FROM users WHERE email = '" + email + "'";
+PreparedStatement ps = conn.prepareStatement("SELECT * FROM users WHERE email = ?");
+String sql = "SELECT * FROM " + TABLE_NAME;
+String all = "SELECT * FROM users" + " ORDER BY id";
+String msg = "Please select a file from " + folder;
+"#;
+        assert_eq!(lines("UserDao.java", source), vec![1]);
+    }
+
+    #[test]
+    fn test_format_and_interpolation() {
+        assert_eq!(
+            lines("db.go", "q := fmt.Sprintf(\"DELETE FROM items WHERE id = %s\", id)\n"),
+            vec![1]
+        );
+        assert_eq!(
+            lines("user.rb", "User.where(\"SELECT * FROM users WHERE name = '#{name}'\")\n"),
+            vec![1]
+        );
+        assert_eq!(
+            lines("user.php", "$q = \"SELECT * FROM users WHERE id = \" . $id;\n"),
+            vec![1]
+        );
+        // Interpolation patterns are language-specific
+        assert!(lines("app.js", "const s = \"SELECT * FROM t WHERE a = #{x}\";\n").is_empty());
+    }
+
+    #[test]
+    fn test_check_scans_supported_extensions() {
+        let dir = TempDir::new().unwrap();
+        let query = "q = f\"SELECT * FROM users WHERE id = {uid}\"\n";
+        fs::write(dir.path().join("app.py"), query).unwrap();
+        fs::write(dir.path().join("notes.md"), query).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        fs::write(
+            dir.path().join("node_modules/pkg/index.js"),
+            "db.query(`SELECT * FROM t WHERE id = ${id}`)\n",
+        )
+        .unwrap();
+
+        let diagnostics = SqlInjectionChecker::new().check(dir.path(), FileFilter::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "app.py");
+        assert_eq!(diagnostics[0].rule, "sql-injection");
+    }
+}
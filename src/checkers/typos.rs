@@ -1,6 +1,8 @@
 //! Typo detection for common spelling mistakes
 
+use crate::checkers::notebook::{cell_path, is_notebook, Notebook};
 use crate::checkers::Checker;
+use crate::files::{relative_path, FileFilter};
 use crate::types::{CheckType, Diagnostic, Severity};
 use rayon::prelude::*;
 use std::collections::HashMap;
@@ -126,38 +128,55 @@ impl TyposChecker {
         Self
     }
 
-    fn check_file(&self, path: &Path) -> Vec<Diagnostic> {
+    fn check_file(&self, repo_path: &Path, path: &Path) -> Vec<Diagnostic> {
         let content = match fs::read_to_string(path) {
             Ok(c) => c,
             Err(_) => return vec![],
         };
 
-        let filename = path.to_string_lossy().to_string();
-        let mut diagnostics = Vec::new();
-
-        for (line_num, line) in content.lines().enumerate() {
-            let line_number = (line_num + 1) as u32;
-
-            for word in extract_words(line) {
-                let lower = word.text.to_lowercase();
-                if let Some(&correction) = COMMON_TYPOS.get(lower.as_str()) {
-                    diagnostics.push(Diagnostic {
-                        file: filename.clone(),
-                        line: line_number,
-                        column: (word.start + 1) as u32,
-                        message: format!("Possible typo: '{}' -> '{}'", word.text, correction),
-                        rule: "typo".to_string(),
-                        severity: Severity::Info,
-                        suggestion: Some(format!("Did you mean '{}'?", correction)),
-                    });
-                }
-            }
+        let filename = relative_path(repo_path, path);
+        if !is_notebook(path) {
+            return check_content(&filename, &content);
         }
 
-        diagnostics
+        // Markdown and code cells, reported as `path#cell-N`
+        let Some(notebook) = Notebook::parse(&content) else {
+            return vec![];
+        };
+        notebook
+            .cells
+            .iter()
+            .flat_map(|cell| check_content(&cell_path(&filename, cell.number), &cell.source))
+            .collect()
     }
 }
 
+/// Typos in `content`, reported against `filename`
+fn check_content(filename: &str, content: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        let line_number = (line_num + 1) as u32;
+
+        for word in extract_words(line) {
+            let lower = word.text.to_lowercase();
+            if let Some(&correction) = COMMON_TYPOS.get(lower.as_str()) {
+                diagnostics.push(Diagnostic {
+                    file: filename.to_string(),
+                    line: line_number,
+                    column: (word.start + 1) as u32,
+                    message: format!("Possible typo: '{}' -> '{}'", word.text, correction),
+                    rule: "typo".to_string(),
+                    severity: Severity::Info,
+                    suggestion: Some(format!("Did you mean '{}'?", correction)),
+                });
+            }
+        }
+    }
+
+    diagnostics
+}
+
 struct Word<'a> {
     text: &'a str,
     start: usize,
@@ -202,8 +221,8 @@ impl Checker for TyposChecker {
         CheckType::Typos
     }
 
-    fn check(&self, repo_path: &Path) -> Vec<Diagnostic> {
-        let files = collect_checkable_files(repo_path);
+    fn check(&self, repo_path: &Path, filter: FileFilter) -> Vec<Diagnostic> {
+        let files = collect_checkable_files(repo_path, filter);
 
         if files.is_empty() {
             return vec![];
@@ -211,18 +230,18 @@ impl Checker for TyposChecker {
 
         files
             .par_iter()
-            .flat_map(|file| self.check_file(file))
+            .flat_map(|file| self.check_file(repo_path, file))
             .collect()
     }
 }
 
-fn collect_checkable_files(dir: &Path) -> Vec<std::path::PathBuf> {
+fn collect_checkable_files(dir: &Path, filter: FileFilter) -> Vec<std::path::PathBuf> {
     let mut files = Vec::new();
-    collect_checkable_files_recursive(dir, &mut files);
+    collect_checkable_files_recursive(dir, filter, &mut files);
     files
 }
 
-fn collect_checkable_files_recursive(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+fn collect_checkable_files_recursive(dir: &Path, filter: FileFilter, files: &mut Vec<std::path::PathBuf>) {
     let entries = match fs::read_dir(dir) {
         Ok(e) => e,
         Err(_) => return,
@@ -245,8 +264,8 @@ fn collect_checkable_files_recursive(dir: &Path, files: &mut Vec<std::path::Path
         }
 
         if path.is_dir() {
-            collect_checkable_files_recursive(&path, files);
-        } else if is_checkable_file(&path) {
+            collect_checkable_files_recursive(&path, filter, files);
+        } else if is_checkable_file(&path) && !filter.skips(&path) {
             files.push(path);
         }
     }
@@ -275,6 +294,7 @@ fn is_checkable_file(path: &Path) -> bool {
             | "cs"
             | "md"
             | "txt"
+            | "ipynb"
     )
 }
 
@@ -297,7 +317,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.txt", "teh quick brown fox");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 1);
         assert_eq!(diagnostics[0].rule, "typo");
@@ -310,7 +330,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.js", "funciton foo() { retrun 1; }");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert_eq!(diagnostics.len(), 2);
     }
@@ -321,7 +341,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = create_test_file(&dir, "test.txt", "the quick brown fox");
 
-        let diagnostics = checker.check_file(&path);
+        let diagnostics = checker.check_file(dir.path(), &path);
 
         assert!(diagnostics.is_empty());
     }
@@ -334,4 +354,17 @@ mod tests {
         assert_eq!(words[1].text, "foo");
         assert_eq!(words[2].text, "bar");
     }
+
+    #[test]
+    fn test_notebook_cells() {
+        let checker = TyposChecker::new();
+        let dir = TempDir::new().unwrap();
+        let path = create_test_file(&dir, "analysis.ipynb", r##"{"cells": [{"cell_type": "markdown", "source": ["# Analysis\n", "TODO: describe teh dataset\n"]}, {"cell_type": "code", "source": ["import pandas as pd\n", "df = pd.read_csv(\"data.csv\")  # FIXME: hardcoded path\n", "label = \"TODO not a comment\"\n"]}], "metadata": {"kernelspec": {"language": "python"}}, "nbformat": 4}"##);
+
+        let diagnostics = checker.check_file(dir.path(), &path);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "analysis.ipynb#cell-1");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 16));
+    }
 }
@@ -1,5 +1,30 @@
 use crate::error::ConfigError;
-use secrecy::SecretString;
+use crate::queue::{DEFAULT_MAX_CONCURRENT_JOBS, DEFAULT_MAX_QUEUED_JOBS};
+use crate::test_runner::{TestRunnerConfig, DEFAULT_MAX_TEST_TIMEOUT_SECS};
+use crate::types::GradeConfig;
+use secrecy::{ExposeSecret, SecretString};
+use std::collections::HashMap;
+
+/// Seconds an LLM request may take when `LLM_TIMEOUT_SECS` is unset
+pub const DEFAULT_LLM_TIMEOUT_SECS: u64 = 120;
+
+/// LLM requests in flight across all grade jobs when unset
+pub const DEFAULT_MAX_CONCURRENT_LLM_REQUESTS: usize = 20;
+
+/// AI suggestions below this confidence are dropped when
+/// `MIN_SUGGESTION_CONFIDENCE` is unset
+pub const DEFAULT_MIN_SUGGESTION_CONFIDENCE: f32 = 0.3;
+
+/// AI suggestions kept per review when `MAX_SUGGESTIONS` is unset
+pub const DEFAULT_MAX_SUGGESTIONS: usize = 15;
+
+/// Reviews (and, separately, grades) kept in memory when `MAX_STORED_JOBS`
+/// is unset
+pub const DEFAULT_MAX_STORED_JOBS: usize = 10_000;
+
+/// Seconds browsers may cache CORS preflight responses when
+/// `CORS_MAX_AGE_SECS` is unset
+pub const DEFAULT_CORS_MAX_AGE_SECS: u64 = 86_400;
 
 // MongoDB configuration
 #[derive(Clone)]
@@ -22,6 +47,10 @@ pub struct AppConfig {
     pub server: ServerConfig,
     pub providers: ProvidersConfig,
     pub review: ReviewConfig,
+    /// Grading defaults for fields a request's `config` doesn't set
+    pub grade: GradeConfig,
+    /// Whether and how long submissions' own tests may run
+    pub test_runner: TestRunnerConfig,
     pub mongo: MongoConfig,
 }
 
@@ -30,27 +59,125 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub cors_origins: Vec<String>,
+    /// `Access-Control-Max-Age` of preflight responses
+    pub cors_max_age_secs: u64,
+    /// Bearer token required on API routes; open when unset
+    pub api_auth_token: Option<SecretString>,
+    /// Secret of the GitHub push webhook; the receiver is disabled when unset
+    pub github_webhook_secret: Option<SecretString>,
 }
 
 #[derive(Clone)]
 pub struct ProvidersConfig {
     pub openai_api_key: Option<SecretString>,
     pub anthropic_api_key: Option<SecretString>,
+    /// Messages endpoint used instead of the public Anthropic API
+    pub anthropic_base_url: Option<String>,
     pub opencode_api_key: Option<SecretString>,
     pub opencode_base_url: Option<String>,
+    /// Extra headers sent with every OpenCode request
+    pub opencode_extra_headers: HashMap<String, String>,
+    /// Model for criteria with `model_hint: "cheap"`
+    pub cheap_model: Option<String>,
+    /// Model for criteria with `model_hint: "strong"`
+    pub strong_model: Option<String>,
+    /// Model for AI validators filtering checker false positives
+    pub validator_model: Option<String>,
+    /// Model for AI reviewers writing suggestions
+    pub reviewer_model: Option<String>,
+    /// Default model for graders checking acceptance criteria
+    pub grader_model: Option<String>,
     pub default_timeout_secs: u64,
+    /// LLM requests in flight across all grade jobs
+    pub max_concurrent_llm_requests: usize,
+    pub llm_log: LlmLogConfig,
+}
+
+impl ProvidersConfig {
+    /// Configured API keys, scrubbed from logged prompts and responses
+    pub fn secrets(&self) -> Vec<String> {
+        [
+            &self.openai_api_key,
+            &self.anthropic_api_key,
+            &self.opencode_api_key,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|key| key.expose_secret().to_string())
+        .collect()
+    }
+}
+
+/// How much of each LLM call is logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LlmLogLevel {
+    #[default]
+    Off,
+    /// Provider, model, sizes and latency
+    Metadata,
+    /// Metadata plus redacted, truncated prompts and responses
+    Full,
+}
+
+impl std::str::FromStr for LlmLogLevel {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "metadata" => Ok(Self::Metadata),
+            "full" => Ok(Self::Full),
+            _ => Err(ConfigError::InvalidValue("LLM_LOG_LEVEL".into())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LlmLogConfig {
+    pub level: LlmLogLevel,
+    /// Characters of each prompt or response logged at `full`
+    pub max_chars: usize,
+}
+
+impl Default for LlmLogConfig {
+    fn default() -> Self {
+        Self {
+            level: LlmLogLevel::Off,
+            max_chars: 2000,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct ReviewConfig {
     pub max_concurrent_checks: usize,
+    /// Reviews (and, separately, grades) run at once
+    pub max_concurrent_jobs: usize,
+    /// Reviews (and, separately, grades) waiting for a worker before new ones get 503
+    pub max_queued_jobs: usize,
     pub review_ttl_secs: u64,
+    /// Reviews (and, separately, grades) kept in memory regardless of TTL
+    pub max_stored_jobs: usize,
     pub max_repo_size_mb: u64,
+    /// Files included in AI reviewer prompts
+    pub ai_preview_files: usize,
+    /// Characters of each file included in AI reviewer prompts
+    pub ai_preview_chars: usize,
+    /// Skip generated, minified and vendored files (disable for debugging)
+    pub skip_generated_files: bool,
+    /// Bytes read from each source file loaded into LLM context
+    pub max_file_bytes: usize,
+    /// Bytes of source held per review or grade context
+    pub max_context_bytes: usize,
+    /// AI suggestions below this confidence are left out of reviews
+    pub min_suggestion_confidence: f32,
+    /// AI suggestions kept per review, highest priority first
+    pub max_suggestions: usize,
 }
 
 impl AppConfig {
     pub fn from_env() -> Result<Self, ConfigError> {
-        Ok(Self {
+        let mut config = Self {
             server: ServerConfig {
                 host: std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
                 port: std::env::var("PORT")
@@ -62,47 +189,202 @@ impl AppConfig {
                     .split(',')
                     .map(|s| s.trim().to_string())
                     .collect(),
+                cors_max_age_secs: std::env::var("CORS_MAX_AGE_SECS")
+                    .unwrap_or_else(|_| DEFAULT_CORS_MAX_AGE_SECS.to_string())
+                    .parse()
+                    .map_err(|_| ConfigError::InvalidValue("CORS_MAX_AGE_SECS".into()))?,
+                api_auth_token: std::env::var("API_AUTH_TOKEN")
+                    .ok()
+                    .filter(|token| !token.trim().is_empty())
+                    .map(SecretString::from),
+                github_webhook_secret: std::env::var("GITHUB_WEBHOOK_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty())
+                    .map(SecretString::from),
             },
             providers: ProvidersConfig {
                 openai_api_key: std::env::var("OPENAI_API_KEY").ok().map(SecretString::from),
                 anthropic_api_key: std::env::var("ANTHROPIC_API_KEY")
                     .ok()
                     .map(SecretString::from),
+                anthropic_base_url: std::env::var("ANTHROPIC_BASE_URL").ok().filter(|url| !url.is_empty()),
                 opencode_api_key: std::env::var("OPENCODE_API_KEY")
                     .ok()
                     .map(SecretString::from),
                 opencode_base_url: std::env::var("OPENCODE_BASE_URL").ok(),
+                opencode_extra_headers: parse_header_pairs(
+                    &std::env::var("OPENCODE_EXTRA_HEADERS").unwrap_or_default(),
+                )
+                .ok_or_else(|| ConfigError::InvalidValue("OPENCODE_EXTRA_HEADERS".into()))?,
+                cheap_model: std::env::var("LLM_CHEAP_MODEL").ok().filter(|m| !m.is_empty()),
+                strong_model: std::env::var("LLM_STRONG_MODEL").ok().filter(|m| !m.is_empty()),
+                validator_model: std::env::var("LLM_MODEL_VALIDATOR").ok().filter(|m| !m.is_empty()),
+                reviewer_model: std::env::var("LLM_MODEL_REVIEWER").ok().filter(|m| !m.is_empty()),
+                grader_model: std::env::var("LLM_MODEL_GRADER").ok().filter(|m| !m.is_empty()),
                 default_timeout_secs: std::env::var("LLM_TIMEOUT_SECS")
-                    .unwrap_or_else(|_| "120".to_string())
+                    .unwrap_or_else(|_| DEFAULT_LLM_TIMEOUT_SECS.to_string())
+                    .parse()
+                    .unwrap_or(DEFAULT_LLM_TIMEOUT_SECS),
+                max_concurrent_llm_requests: std::env::var("MAX_CONCURRENT_LLM_REQUESTS")
+                    .unwrap_or_else(|_| DEFAULT_MAX_CONCURRENT_LLM_REQUESTS.to_string())
                     .parse()
-                    .unwrap_or(120),
+                    .unwrap_or(DEFAULT_MAX_CONCURRENT_LLM_REQUESTS),
+                llm_log: LlmLogConfig {
+                    level: std::env::var("LLM_LOG_LEVEL")
+                        .unwrap_or_else(|_| "off".to_string())
+                        .parse()?,
+                    max_chars: std::env::var("LLM_LOG_MAX_CHARS")
+                        .unwrap_or_else(|_| "2000".to_string())
+                        .parse()
+                        .unwrap_or(2000),
+                },
             },
             review: ReviewConfig {
                 max_concurrent_checks: std::env::var("MAX_CONCURRENT_CHECKS")
                     .unwrap_or_else(|_| "4".to_string())
                     .parse()
                     .unwrap_or(4),
+                max_concurrent_jobs: std::env::var("MAX_CONCURRENT_JOBS")
+                    .unwrap_or_else(|_| DEFAULT_MAX_CONCURRENT_JOBS.to_string())
+                    .parse()
+                    .unwrap_or(DEFAULT_MAX_CONCURRENT_JOBS),
+                max_queued_jobs: std::env::var("MAX_QUEUED_JOBS")
+                    .unwrap_or_else(|_| DEFAULT_MAX_QUEUED_JOBS.to_string())
+                    .parse()
+                    .unwrap_or(DEFAULT_MAX_QUEUED_JOBS),
                 review_ttl_secs: std::env::var("REVIEW_TTL_SECS")
                     .unwrap_or_else(|_| "3600".to_string())
                     .parse()
                     .unwrap_or(3600),
+                max_stored_jobs: std::env::var("MAX_STORED_JOBS")
+                    .unwrap_or_else(|_| DEFAULT_MAX_STORED_JOBS.to_string())
+                    .parse()
+                    .unwrap_or(DEFAULT_MAX_STORED_JOBS),
                 max_repo_size_mb: 100,
+                ai_preview_files: std::env::var("AI_PREVIEW_FILES")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse()
+                    .unwrap_or(10),
+                ai_preview_chars: std::env::var("AI_PREVIEW_CHARS")
+                    .unwrap_or_else(|_| "2000".to_string())
+                    .parse()
+                    .unwrap_or(2000),
+                skip_generated_files: std::env::var("SKIP_GENERATED_FILES")
+                    .map(|v| v != "false" && v != "0")
+                    .unwrap_or(true),
+                max_file_bytes: std::env::var("MAX_FILE_BYTES")
+                    .unwrap_or_else(|_| "262144".to_string())
+                    .parse()
+                    .unwrap_or(262144),
+                max_context_bytes: std::env::var("MAX_CONTEXT_BYTES")
+                    .unwrap_or_else(|_| "4194304".to_string())
+                    .parse()
+                    .unwrap_or(4194304),
+                min_suggestion_confidence: std::env::var("MIN_SUGGESTION_CONFIDENCE")
+                    .unwrap_or_else(|_| DEFAULT_MIN_SUGGESTION_CONFIDENCE.to_string())
+                    .parse()
+                    .unwrap_or(DEFAULT_MIN_SUGGESTION_CONFIDENCE),
+                max_suggestions: std::env::var("MAX_SUGGESTIONS")
+                    .unwrap_or_else(|_| DEFAULT_MAX_SUGGESTIONS.to_string())
+                    .parse()
+                    .unwrap_or(DEFAULT_MAX_SUGGESTIONS),
+            },
+            grade: GradeConfig::default(),
+            test_runner: TestRunnerConfig {
+                enabled: std::env::var("ENABLE_TEST_RUNNER").is_ok_and(|v| v == "true" || v == "1"),
+                max_timeout_secs: std::env::var("MAX_TEST_TIMEOUT_SECS")
+                    .unwrap_or_else(|_| DEFAULT_MAX_TEST_TIMEOUT_SECS.to_string())
+                    .parse()
+                    .unwrap_or(DEFAULT_MAX_TEST_TIMEOUT_SECS),
             },
             mongo: MongoConfig {
                 mongodb_url: std::env::var("MONGODB_URL").ok().map(SecretString::from),
                 mongodb_db_name: std::env::var("MONGODB_DB_NAME")
                     .unwrap_or_else(|_| "omakasem".to_string()),
             },
+        };
+        config.grade = grade_config(|name| std::env::var(name).ok(), &config.review)?;
+        Ok(config)
+    }
+}
+
+/// Default `GradeConfig` from `GRADE_*` variables, looked up with `var`,
+/// sharing the file read limits of reviews
+fn grade_config(
+    var: impl Fn(&str) -> Option<String>,
+    review: &ReviewConfig,
+) -> Result<GradeConfig, ConfigError> {
+    let defaults = GradeConfig::default();
+    let positive = |name: &str, default: usize| -> Result<usize, ConfigError> {
+        let Some(raw) = var(name) else {
+            return Ok(default);
+        };
+        match raw.trim().parse() {
+            Ok(value) if value > 0 => Ok(value),
+            _ => Err(ConfigError::InvalidValue(format!(
+                "{} (expected a positive integer, got {:?})",
+                name, raw
+            ))),
+        }
+    };
+    Ok(GradeConfig {
+        max_files: positive("GRADE_MAX_FILES", defaults.max_files)?,
+        max_chars_per_file: positive("GRADE_MAX_CHARS_PER_FILE", defaults.max_chars_per_file)?,
+        max_total_chars: positive("GRADE_MAX_TOTAL_CHARS", defaults.max_total_chars)?,
+        max_parallel_tasks: positive("GRADE_MAX_PARALLEL_TASKS", defaults.max_parallel_tasks)?,
+        max_parallel_criteria: positive("GRADE_MAX_PARALLEL_CRITERIA", defaults.max_parallel_criteria)?,
+        criterion_timeout_secs: positive(
+            "GRADE_CRITERION_TIMEOUT_SECS",
+            defaults.criterion_timeout_secs as usize,
+        )? as u64,
+        max_file_bytes: review.max_file_bytes,
+        max_context_bytes: review.max_context_bytes,
+        ..defaults
+    })
+}
+
+/// Parse comma-separated `name=value` pairs; `None` if a pair has no `=` or
+/// an empty name
+fn parse_header_pairs(raw: &str) -> Option<HashMap<String, String>> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
         })
+        .collect()
+}
+
+/// `Authorization` header values for secrets, so clients don't format
+/// `expose_secret()` into headers themselves
+pub trait AuthorizationHeader {
+    /// `<token_type> <secret>`, e.g. `Bearer abc`
+    fn to_authorization_header(&self, token_type: &str) -> String;
+}
+
+impl AuthorizationHeader for SecretString {
+    fn to_authorization_header(&self, token_type: &str) -> String {
+        authorization_header(token_type, self.expose_secret())
     }
 }
 
+/// Token servers often answer `token_type: "bearer"`; APIs expect `Bearer`
+pub(crate) fn authorization_header(token_type: &str, token: &str) -> String {
+    let token_type = if token_type.eq_ignore_ascii_case("bearer") { "Bearer" } else { token_type };
+    format!("{} {}", token_type, token)
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             host: "0.0.0.0".to_string(),
             port: 8080,
             cors_origins: vec!["*".to_string()],
+            cors_max_age_secs: DEFAULT_CORS_MAX_AGE_SECS,
+            api_auth_token: None,
+            github_webhook_secret: None,
         }
     }
 }
@@ -112,9 +394,18 @@ impl Default for ProvidersConfig {
         Self {
             openai_api_key: None,
             anthropic_api_key: None,
+            anthropic_base_url: None,
             opencode_api_key: None,
             opencode_base_url: None,
-            default_timeout_secs: 120,
+            opencode_extra_headers: HashMap::new(),
+            cheap_model: None,
+            strong_model: None,
+            validator_model: None,
+            reviewer_model: None,
+            grader_model: None,
+            default_timeout_secs: DEFAULT_LLM_TIMEOUT_SECS,
+            max_concurrent_llm_requests: DEFAULT_MAX_CONCURRENT_LLM_REQUESTS,
+            llm_log: LlmLogConfig::default(),
         }
     }
 }
@@ -123,8 +414,18 @@ impl Default for ReviewConfig {
     fn default() -> Self {
         Self {
             max_concurrent_checks: 4,
+            max_concurrent_jobs: DEFAULT_MAX_CONCURRENT_JOBS,
+            max_queued_jobs: DEFAULT_MAX_QUEUED_JOBS,
             review_ttl_secs: 3600,
+            max_stored_jobs: DEFAULT_MAX_STORED_JOBS,
             max_repo_size_mb: 100,
+            ai_preview_files: 10,
+            ai_preview_chars: 2000,
+            skip_generated_files: true,
+            max_file_bytes: 262144,
+            max_context_bytes: 4194304,
+            min_suggestion_confidence: DEFAULT_MIN_SUGGESTION_CONFIDENCE,
+            max_suggestions: DEFAULT_MAX_SUGGESTIONS,
         }
     }
 }
@@ -146,4 +447,54 @@ mod tests {
         assert!(providers.openai_api_key.is_none());
         assert_eq!(providers.default_timeout_secs, 120);
     }
+
+    #[test]
+    fn test_parse_header_pairs() {
+        let headers = parse_header_pairs("X-Org-Id=org-42, X-Route = fast ,").unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["X-Org-Id"], "org-42");
+        assert_eq!(headers["X-Route"], "fast");
+        assert!(parse_header_pairs("").unwrap().is_empty());
+        assert!(parse_header_pairs("X-Org-Id").is_none());
+        assert!(parse_header_pairs("=value").is_none());
+    }
+
+    #[test]
+    fn test_llm_log_level_parse() {
+        assert_eq!("Metadata".parse::<LlmLogLevel>().unwrap(), LlmLogLevel::Metadata);
+        assert_eq!("full".parse::<LlmLogLevel>().unwrap(), LlmLogLevel::Full);
+        assert!("verbose".parse::<LlmLogLevel>().is_err());
+    }
+
+    #[test]
+    fn test_grade_config_from_env() {
+        let review = ReviewConfig {
+            max_file_bytes: 1024,
+            ..ReviewConfig::default()
+        };
+        let from = |pairs: &[(&str, &str)]| {
+            let vars: HashMap<String, String> =
+                pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            grade_config(|name| vars.get(name).cloned(), &review)
+        };
+
+        let config = from(&[("GRADE_MAX_FILES", "80"), ("GRADE_CRITERION_TIMEOUT_SECS", " 90 ")]).unwrap();
+        assert_eq!(config.max_files, 80);
+        assert_eq!(config.criterion_timeout_secs, 90);
+        assert_eq!(config.max_chars_per_file, GradeConfig::default().max_chars_per_file);
+        assert_eq!(config.max_file_bytes, 1024);
+
+        for bad in [("GRADE_MAX_FILES", "0"), ("GRADE_MAX_PARALLEL_TASKS", "many"), ("GRADE_MAX_CHARS_PER_FILE", "-1")] {
+            let err = from(&[bad]).unwrap_err();
+            assert!(err.to_string().contains(bad.0), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_secret_authorization_header() {
+        let secret = SecretString::from("abc123".to_string());
+        assert_eq!(secret.to_authorization_header("Bearer"), "Bearer abc123");
+        assert_eq!(secret.to_authorization_header("bearer"), "Bearer abc123");
+        assert_eq!(secret.to_authorization_header("MAC"), "MAC abc123");
+    }
 }
@@ -0,0 +1,77 @@
+//! CORS policy built from `CORS_ORIGINS` and `CORS_MAX_AGE_SECS`
+
+use actix_cors::Cors;
+use actix_web::http::{header, Method};
+
+/// Headers browser clients send: JSON bodies, bearer auth, SSE reconnects
+/// and idempotent retries
+const ALLOWED_HEADERS: [header::HeaderName; 6] = [
+    header::CONTENT_TYPE,
+    header::AUTHORIZATION,
+    header::ACCEPT,
+    header::CACHE_CONTROL,
+    header::HeaderName::from_static("last-event-id"),
+    header::HeaderName::from_static("idempotency-key"),
+];
+
+/// CORS middleware allowing `origins`. A `*` entry allows any origin.
+/// Preflights are cached for `max_age_secs`, and every response varies on
+/// `Origin` so shared caches don't serve one origin's headers to another.
+pub fn build_cors(origins: &[String], max_age_secs: u64) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
+        .allowed_headers(ALLOWED_HEADERS)
+        .max_age(usize::try_from(max_age_secs).unwrap_or(usize::MAX));
+
+    if origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin().send_wildcard();
+    }
+    origins
+        .iter()
+        .filter(|origin| !origin.is_empty())
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_CORS_MAX_AGE_SECS;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn allow_origin(origins: &[&str], origin: &str) -> Option<String> {
+        let origins: Vec<String> = origins.iter().map(|o| o.to_string()).collect();
+        let app = test::init_service(
+            App::new()
+                .wrap(build_cors(&origins, DEFAULT_CORS_MAX_AGE_SECS))
+                .route("/api/health", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let request = test::TestRequest::get()
+            .uri("/api/health")
+            .insert_header((header::ORIGIN, origin))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[actix_web::test]
+    async fn test_cors_origins() {
+        assert_eq!(
+            allow_origin(&["*"], "https://app.example.com").await.as_deref(),
+            Some("*")
+        );
+        assert_eq!(
+            allow_origin(&["https://app.example.com", ""], "https://app.example.com")
+                .await
+                .as_deref(),
+            Some("https://app.example.com")
+        );
+        assert_eq!(
+            allow_origin(&["https://app.example.com"], "https://evil.example.com").await,
+            None
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use bson::{doc, oid::ObjectId, DateTime as BsonDateTime};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::db::MongoClient;
+
+/// How long audit entries are kept before MongoDB expires them
+const AUDIT_RETENTION: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: String,
+    pub ip_address: Option<String>,
+    pub timestamp: BsonDateTime,
+    pub metadata: bson::Document,
+}
+
+impl AuditEntry {
+    pub fn new(
+        action: impl Into<String>,
+        resource_type: impl Into<String>,
+        resource_id: impl Into<String>,
+        ip_address: Option<String>,
+    ) -> Self {
+        Self {
+            id: ObjectId::new(),
+            action: action.into(),
+            resource_type: resource_type.into(),
+            resource_id: resource_id.into(),
+            ip_address,
+            timestamp: BsonDateTime::now(),
+            metadata: bson::Document::new(),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: bson::Document) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+pub struct AuditRepository {
+    client: MongoClient,
+}
+
+impl AuditRepository {
+    pub fn new(client: MongoClient) -> Self {
+        Self { client }
+    }
+
+    fn collection(&self) -> Collection<AuditEntry> {
+        self.client.database().collection("audit_log")
+    }
+
+    /// Create the TTL index that expires entries after 90 days
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "timestamp": 1 })
+            .options(
+                IndexOptions::builder()
+                    .expire_after(AUDIT_RETENTION)
+                    .build(),
+            )
+            .build();
+
+        self.collection().create_index(index).await?;
+        Ok(())
+    }
+
+    pub async fn record(&self, entry: AuditEntry) -> Result<(), mongodb::error::Error> {
+        self.collection().insert_one(entry).await?;
+        Ok(())
+    }
+}
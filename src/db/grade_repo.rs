@@ -1,16 +1,27 @@
-use bson::{doc, oid::ObjectId, DateTime as BsonDateTime};
+use bson::{doc, oid::ObjectId, Bson, DateTime as BsonDateTime};
+use futures::TryStreamExt;
 use mongodb::Collection;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    analysis::SimilarityFingerprint,
     db::MongoClient,
-    types::{GradeReport, GradeRequest, GradeStatus},
+    types::{CriterionResult, EventEnvelope, GradeEvent, GradeReport, GradeRequest, GradeStatus, CALIBRATION_BUCKETS},
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GradeJob {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
+    /// Grade id used by the API
+    #[serde(default)]
+    pub grade_id: Option<String>,
+    /// Grade id of the job this one re-grades
+    #[serde(default)]
+    pub regrade_of: Option<String>,
+    /// Commit that was graded, set on completion
+    #[serde(default)]
+    pub commit_sha: Option<String>,
     pub curriculum_id: Option<String>,
     pub task_id: Option<String>,
     pub repo_url: String,
@@ -18,9 +29,31 @@ pub struct GradeJob {
     pub status: GradeStatus,
     pub request: bson::Document,
     pub result: Option<bson::Document>,
+    /// Event log, saved with each report
+    #[serde(default)]
+    pub events: Vec<EventEnvelope<GradeEvent>>,
     pub error: Option<String>,
     pub created_at: BsonDateTime,
     pub completed_at: Option<BsonDateTime>,
+    /// Graded files' fingerprint, when `similarity_fingerprint` was set
+    #[serde(default)]
+    pub fingerprint: Option<SimilarityFingerprint>,
+}
+
+/// Jobs loaded for one similarity comparison, which compares every pair of
+/// them
+pub const MAX_SIMILARITY_JOBS: i64 = 1000;
+
+/// Completed grades needed before a duration estimate is given
+pub const MIN_DURATION_SAMPLES: u64 = 3;
+
+/// Most recent grades averaged for a duration estimate
+const DURATION_SAMPLE_LIMIT: i64 = 100;
+
+/// Mean of `samples` durations adding up to `total_ms`; `None` with fewer
+/// than [`MIN_DURATION_SAMPLES`]
+pub fn average_duration(total_ms: u64, samples: u64) -> Option<u64> {
+    (samples >= MIN_DURATION_SAMPLES).then(|| total_ms / samples)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,11 +62,43 @@ pub struct TaskGradeUpdate {
     pub score: f32,
     pub percentage: u32,
     pub grade: String,
-    pub criteria_results: Vec<bson::Document>,
+    /// Criterion results grouped by the task they belong to
+    pub criteria_results: Vec<TaskCriteriaResults>,
     pub repo_url: String,
     pub graded_at: BsonDateTime,
 }
 
+/// One graded task's criterion results, each carrying its criterion id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskCriteriaResults {
+    pub task_index: usize,
+    pub task_title: String,
+    pub criteria: Vec<CriterionResult>,
+}
+
+impl TaskGradeUpdate {
+    pub fn from_report(report: &GradeReport) -> Self {
+        Self {
+            grade_job_id: report.id.clone(),
+            score: report.overall_score,
+            percentage: report.percentage,
+            grade: report.grade.clone(),
+            criteria_results: report
+                .tasks
+                .iter()
+                .enumerate()
+                .map(|(task_index, task)| TaskCriteriaResults {
+                    task_index,
+                    task_title: task.task_title.clone(),
+                    criteria: task.criteria_results.clone(),
+                })
+                .collect(),
+            repo_url: report.repo_url.clone(),
+            graded_at: BsonDateTime::now(),
+        }
+    }
+}
+
 pub struct GradeRepository {
     client: MongoClient,
 }
@@ -53,6 +118,8 @@ impl GradeRepository {
 
     pub async fn save_grade_job(
         &self,
+        grade_id: &str,
+        regrade_of: Option<String>,
         request: &GradeRequest,
         curriculum_id: Option<String>,
         task_id: Option<String>,
@@ -62,6 +129,9 @@ impl GradeRepository {
 
         let job = GradeJob {
             id: None,
+            grade_id: Some(grade_id.to_string()),
+            regrade_of,
+            commit_sha: None,
             curriculum_id,
             task_id,
             repo_url: request.repo_url.clone(),
@@ -69,9 +139,11 @@ impl GradeRepository {
             status: GradeStatus::Pending,
             request: request_doc,
             result: None,
+            events: Vec::new(),
             error: None,
             created_at: BsonDateTime::now(),
             completed_at: None,
+            fingerprint: None,
         };
 
         let result = self.grade_jobs_collection().insert_one(job).await?;
@@ -80,29 +152,48 @@ impl GradeRepository {
 
     pub async fn update_grade_job(
         &self,
-        id: &str,
+        grade_id: &str,
         report: &GradeReport,
+        events: &[EventEnvelope<GradeEvent>],
+        fingerprint: Option<&SimilarityFingerprint>,
     ) -> Result<(), mongodb::error::Error> {
-        let oid = ObjectId::parse_str(id)
-            .map_err(|e| mongodb::error::Error::custom(format!("Invalid ObjectId: {}", e)))?;
-
         let result_doc = bson::to_document(report)
             .map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize report: {}", e)))?;
+        let events = bson::to_bson(events)
+            .map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize events: {}", e)))?;
 
-        self.grade_jobs_collection()
-            .update_one(
-                doc! { "_id": oid },
-                doc! {
-                    "$set": {
-                        "status": bson::to_bson(&report.status).unwrap(),
-                        "result": result_doc,
-                        "error": &report.error,
-                        "completed_at": BsonDateTime::now(),
-                    }
-                },
-            )
+        let mut set = doc! {
+            "status": bson::to_bson(&report.status).unwrap(),
+            "result": result_doc,
+            "events": events,
+            "error": &report.error,
+            "commit_sha": &report.commit_sha,
+            "branch": &report.branch,
+            "completed_at": report
+                .completed_at
+                .map_or_else(BsonDateTime::now, |secs| {
+                    BsonDateTime::from_millis(secs as i64 * 1000)
+                }),
+        };
+        // Later updates (overrides, retries) keep the stored fingerprint
+        if let Some(fingerprint) = fingerprint {
+            let fingerprint = bson::to_bson(fingerprint)
+                .map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize fingerprint: {}", e)))?;
+            set.insert("fingerprint", fingerprint);
+        }
+
+        let result = self
+            .grade_jobs_collection()
+            .update_one(doc! { "grade_id": grade_id }, doc! { "$set": set })
             .await?;
 
+        // The job's insert may itself be waiting to be retried
+        if result.matched_count == 0 {
+            return Err(mongodb::error::Error::custom(format!(
+                "grade job {} not found",
+                grade_id
+            )));
+        }
         Ok(())
     }
 
@@ -118,6 +209,132 @@ impl GradeRepository {
             .await
     }
 
+    /// Find a job by the grade id returned from the API
+    pub async fn find_by_grade_id(
+        &self,
+        grade_id: &str,
+    ) -> Result<Option<GradeJob>, mongodb::error::Error> {
+        self.grade_jobs_collection()
+            .find_one(doc! { "grade_id": grade_id })
+            .await
+    }
+
+    /// Fingerprinted jobs of the course titled `course_title`, newest first,
+    /// at most [`MAX_SIMILARITY_JOBS`]
+    pub async fn find_fingerprinted(&self, course_title: &str) -> Result<Vec<GradeJob>, mongodb::error::Error> {
+        self.grade_jobs_collection()
+            .find(doc! {
+                "request.metadata.course_title": course_title,
+                "fingerprint": { "$type": "object" },
+            })
+            .projection(doc! { "events": 0, "result": 0 })
+            .sort(doc! { "created_at": -1 })
+            .limit(MAX_SIMILARITY_JOBS)
+            .await?
+            .try_collect()
+            .await
+    }
+
+    /// Average `duration_ms` of recent complete (not partial) grades with
+    /// `task_count` tasks; `None` without enough of them
+    pub async fn average_duration_by_task_count(
+        &self,
+        task_count: usize,
+    ) -> Result<Option<u64>, mongodb::error::Error> {
+        let pipeline = vec![
+            doc! {
+                "$match": {
+                    "status": "completed",
+                    "request.tasks": { "$size": task_count as i64 },
+                    "result.partial": { "$ne": true },
+                }
+            },
+            doc! { "$sort": { "created_at": -1 } },
+            doc! { "$limit": DURATION_SAMPLE_LIMIT },
+            doc! {
+                "$group": {
+                    "_id": Bson::Null,
+                    "total_ms": { "$sum": "$result.duration_ms" },
+                    "samples": { "$sum": 1 },
+                }
+            },
+        ];
+
+        let group: Option<bson::Document> = self
+            .grade_jobs_collection()
+            .aggregate(pipeline)
+            .await?
+            .try_next()
+            .await?;
+        Ok(group.and_then(|group| {
+            average_duration(bson_count(group.get("total_ms")), bson_count(group.get("samples")))
+        }))
+    }
+
+    /// `(bucket, overrides, agreed)` for every confidence bucket with
+    /// overridden criteria, where `agreed` counts instructor verdicts that
+    /// matched the grader's
+    pub async fn override_calibration(&self) -> Result<Vec<(usize, u64, u64)>, mongodb::error::Error> {
+        let overridden = doc! {
+            "result.tasks.criteria_results.human_override": { "$exists": true, "$ne": null },
+        };
+        let pipeline = vec![
+            doc! { "$match": overridden.clone() },
+            doc! { "$unwind": "$result.tasks" },
+            doc! { "$unwind": "$result.tasks.criteria_results" },
+            doc! { "$match": overridden },
+            doc! {
+                "$group": {
+                    "_id": {
+                        "$min": [
+                            {
+                                "$floor": {
+                                    "$multiply": [
+                                        "$result.tasks.criteria_results.confidence",
+                                        CALIBRATION_BUCKETS as i32,
+                                    ]
+                                }
+                            },
+                            CALIBRATION_BUCKETS as i32 - 1,
+                        ]
+                    },
+                    "overrides": { "$sum": 1 },
+                    "agreed": {
+                        "$sum": {
+                            "$cond": [
+                                {
+                                    "$eq": [
+                                        "$result.tasks.criteria_results.passed",
+                                        "$result.tasks.criteria_results.human_override.passed",
+                                    ]
+                                },
+                                1,
+                                0,
+                            ]
+                        }
+                    },
+                }
+            },
+        ];
+
+        let groups: Vec<bson::Document> = self
+            .grade_jobs_collection()
+            .aggregate(pipeline)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(groups
+            .iter()
+            .map(|group| {
+                (
+                    bson_count(group.get("_id")) as usize,
+                    bson_count(group.get("overrides")),
+                    bson_count(group.get("agreed")),
+                )
+            })
+            .collect())
+    }
+
     pub async fn update_task_grade(
         &self,
         curriculum_id: &str,
@@ -130,23 +347,7 @@ impl GradeRepository {
         let task_oid = ObjectId::parse_str(task_id)
             .map_err(|e| mongodb::error::Error::custom(format!("Invalid task_id: {}", e)))?;
 
-        let grade_result = TaskGradeUpdate {
-            grade_job_id: report.id.clone(),
-            score: report.overall_score,
-            percentage: report.percentage,
-            grade: report.grade.clone(),
-            criteria_results: report
-                .tasks
-                .iter()
-                .flat_map(|t| {
-                    t.criteria_results
-                        .iter()
-                        .filter_map(|c| bson::to_document(c).ok())
-                })
-                .collect(),
-            repo_url: report.repo_url.clone(),
-            graded_at: BsonDateTime::now(),
-        };
+        let grade_result = TaskGradeUpdate::from_report(report);
 
         let grade_result_doc = bson::to_document(&grade_result)
             .map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize grade_result: {}", e)))?;
@@ -178,3 +379,81 @@ impl GradeRepository {
         Ok(())
     }
 }
+
+/// Non-negative whole number from an aggregation result, whichever numeric
+/// type the server returned it as
+fn bson_count(value: Option<&Bson>) -> u64 {
+    match value {
+        Some(Bson::Int32(n)) => (*n).max(0) as u64,
+        Some(Bson::Int64(n)) => (*n).max(0) as u64,
+        Some(Bson::Double(n)) => n.max(0.0) as u64,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_grade_update_round_trip() {
+        let criterion = |id: Option<&str>| {
+            serde_json::json!({
+                "criterion": "Login form exists",
+                "criterion_id": id,
+                "outcome": "passed",
+                "confidence": 0.5,
+                "evidence": "",
+                "weight": 1.0,
+            })
+        };
+        let task = |title: &str, criteria: Vec<serde_json::Value>| {
+            serde_json::json!({
+                "task_title": title,
+                "score": 1.0,
+                "status": "passed",
+                "criteria_results": criteria,
+                "passed_count": criteria.len(),
+                "total_count": criteria.len(),
+            })
+        };
+        let report: GradeReport = serde_json::from_value(serde_json::json!({
+            "id": "grade-1",
+            "repo_url": "https://github.com/user/repo",
+            "status": "completed",
+            "overall_score": 1.0,
+            "percentage": 100,
+            "grade": "우수",
+            "tasks": [
+                task("Auth", vec![criterion(Some("login-form"))]),
+                task("Profile", vec![criterion(None)]),
+            ],
+            "summary": "",
+            "duration_ms": 0,
+            "language": "ko",
+        }))
+        .unwrap();
+
+        let document = bson::to_document(&TaskGradeUpdate::from_report(&report)).unwrap();
+        let groups = document.get_array("criteria_results").unwrap();
+        assert_eq!(groups.len(), 2);
+
+        let update: TaskGradeUpdate = bson::from_document(document).unwrap();
+        let auth = &update.criteria_results[0];
+        assert_eq!((auth.task_index, auth.task_title.as_str()), (0, "Auth"));
+        assert_eq!(auth.criteria[0].criterion_id.as_deref(), Some("login-form"));
+        let profile = &update.criteria_results[1];
+        assert_eq!((profile.task_index, profile.task_title.as_str()), (1, "Profile"));
+        assert_eq!(profile.criteria[0].criterion_id, None);
+    }
+
+    #[test]
+    fn test_average_duration() {
+        assert_eq!(average_duration(0, 0), None);
+        assert_eq!(average_duration(90_000, MIN_DURATION_SAMPLES - 1), None);
+        assert_eq!(average_duration(90_000, 3), Some(30_000));
+        // Rounds down to whole milliseconds
+        assert_eq!(average_duration(100, 3), Some(33));
+        assert_eq!(bson_count(Some(&Bson::Double(1.5e5))), 150_000);
+    }
+}
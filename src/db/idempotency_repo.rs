@@ -0,0 +1,73 @@
+use bson::{doc, DateTime as BsonDateTime};
+use mongodb::{
+    options::{IndexOptions, ReturnDocument},
+    Collection, IndexModel,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::db::MongoClient;
+
+/// Job created for an `Idempotency-Key`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    /// `<scope>:<key>`, unique so only one request can claim a key
+    #[serde(rename = "_id")]
+    pub id: String,
+    /// SHA-256 of the request body the key was first used with
+    pub request_hash: String,
+    pub job_id: String,
+    pub created_at: BsonDateTime,
+}
+
+pub struct IdempotencyRepository {
+    client: MongoClient,
+}
+
+impl IdempotencyRepository {
+    pub fn new(client: MongoClient) -> Self {
+        Self { client }
+    }
+
+    fn collection(&self) -> Collection<IdempotencyRecord> {
+        self.client.database().collection("idempotency_keys")
+    }
+
+    /// Create the TTL index that expires keys after `ttl`
+    pub async fn ensure_indexes(&self, ttl: Duration) -> Result<(), mongodb::error::Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "created_at": 1 })
+            .options(IndexOptions::builder().expire_after(ttl).build())
+            .build();
+
+        self.collection().create_index(index).await?;
+        Ok(())
+    }
+
+    /// Atomically store `record` unless its key is taken. Returns the record
+    /// already holding the key, or `None` when `record` was stored.
+    pub async fn claim(
+        &self,
+        record: &IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>, mongodb::error::Error> {
+        self.collection()
+            .find_one_and_update(
+                doc! { "_id": &record.id },
+                doc! {
+                    "$setOnInsert": {
+                        "request_hash": &record.request_hash,
+                        "job_id": &record.job_id,
+                        "created_at": record.created_at,
+                    }
+                },
+            )
+            .upsert(true)
+            .return_document(ReturnDocument::Before)
+            .await
+    }
+
+    pub async fn release(&self, id: &str) -> Result<(), mongodb::error::Error> {
+        self.collection().delete_one(doc! { "_id": id }).await?;
+        Ok(())
+    }
+}
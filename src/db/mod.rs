@@ -1,7 +1,17 @@
+pub mod audit_repo;
 pub mod client;
 pub mod grade_repo;
+pub mod idempotency_repo;
 pub mod review_cache_repo;
+pub mod review_repo;
+pub mod subscription_repo;
+pub mod write_retry;
 
+pub use audit_repo::{AuditEntry, AuditRepository};
 pub use client::MongoClient;
-pub use grade_repo::{GradeJob, GradeRepository, TaskGradeUpdate};
+pub use grade_repo::{GradeJob, GradeRepository, TaskCriteriaResults, TaskGradeUpdate};
+pub use idempotency_repo::{IdempotencyRecord, IdempotencyRepository};
 pub use review_cache_repo::{CachedReview, ReviewCacheRepository};
+pub use review_repo::{ReviewJob, ReviewRepository};
+pub use subscription_repo::{RegradeSubscription, SubscriptionRepository};
+pub use write_retry::{PendingWrite, WriteRetryQueue};
@@ -14,6 +14,10 @@ pub struct CachedReview {
     pub results: Vec<Diagnostic>,
     pub suggestions: Vec<Suggestion>,
     pub created_at: BsonDateTime,
+    /// Prompt version the review ran with; missing on entries saved before
+    /// versions were recorded
+    #[serde(default)]
+    pub prompt_version: Option<String>,
 }
 
 pub struct ReviewCacheRepository {
@@ -35,6 +39,16 @@ impl ReviewCacheRepository {
             .await
     }
 
+    pub async fn find_latest_for_repo(
+        &self,
+        repo_url: &str,
+    ) -> Result<Option<CachedReview>, mongodb::error::Error> {
+        self.collection()
+            .find_one(doc! { "repo_url": repo_url })
+            .sort(doc! { "created_at": -1 })
+            .await
+    }
+
     pub async fn save(
         &self,
         cache_key: &str,
@@ -42,6 +56,7 @@ impl ReviewCacheRepository {
         commit_sha: &str,
         results: &[Diagnostic],
         suggestions: &[Suggestion],
+        prompt_version: &str,
     ) -> Result<(), mongodb::error::Error> {
         let cached = CachedReview {
             cache_key: cache_key.to_string(),
@@ -50,6 +65,7 @@ impl ReviewCacheRepository {
             results: results.to_vec(),
             suggestions: suggestions.to_vec(),
             created_at: BsonDateTime::now(),
+            prompt_version: Some(prompt_version.to_string()),
         };
 
         self.collection()
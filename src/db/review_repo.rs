@@ -0,0 +1,143 @@
+use bson::{doc, oid::ObjectId, DateTime as BsonDateTime};
+use futures::TryStreamExt;
+use mongodb::{Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+
+use crate::db::MongoClient;
+use crate::types::{Diagnostic, EventEnvelope, ReviewEvent, ReviewStatus, Suggestion};
+
+/// Review history keyed by review id. Unlike `review_cache`, which dedups by
+/// commit, every review gets its own job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewJob {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// Review id used by the API
+    pub review_id: String,
+    pub repo_url: String,
+    pub status: ReviewStatus,
+    #[serde(default)]
+    pub results: Vec<Diagnostic>,
+    /// Checker output before AI validation, so re-validation works after expiry
+    #[serde(default)]
+    pub checker_results: Vec<Diagnostic>,
+    #[serde(default)]
+    pub suggestions: Vec<Suggestion>,
+    /// Event log, saved when the review finishes
+    #[serde(default)]
+    pub events: Vec<EventEnvelope<ReviewEvent>>,
+    pub error: Option<String>,
+    pub created_at: BsonDateTime,
+    pub completed_at: Option<BsonDateTime>,
+}
+
+pub struct ReviewRepository {
+    client: MongoClient,
+}
+
+impl ReviewRepository {
+    pub fn new(client: MongoClient) -> Self {
+        Self { client }
+    }
+
+    fn collection(&self) -> Collection<ReviewJob> {
+        self.client.database().collection("review_jobs")
+    }
+
+    /// Index lookups by review id and history queries by repository
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let indexes = [
+            IndexModel::builder().keys(doc! { "review_id": 1 }).build(),
+            IndexModel::builder()
+                .keys(doc! { "repo_url": 1, "created_at": -1 })
+                .build(),
+        ];
+
+        self.collection().create_indexes(indexes).await?;
+        Ok(())
+    }
+
+    pub async fn save_review_job(
+        &self,
+        review_id: &str,
+        repo_url: &str,
+        created_at: BsonDateTime,
+    ) -> Result<(), mongodb::error::Error> {
+        let job = ReviewJob {
+            id: None,
+            review_id: review_id.to_string(),
+            repo_url: repo_url.to_string(),
+            status: ReviewStatus::Pending,
+            results: Vec::new(),
+            checker_results: Vec::new(),
+            suggestions: Vec::new(),
+            events: Vec::new(),
+            error: None,
+            created_at,
+            completed_at: None,
+        };
+
+        self.collection().insert_one(job).await?;
+        Ok(())
+    }
+
+    /// Record a finished review's status, results and event log
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_review_job(
+        &self,
+        review_id: &str,
+        status: ReviewStatus,
+        results: &[Diagnostic],
+        checker_results: &[Diagnostic],
+        suggestions: &[Suggestion],
+        error: Option<&str>,
+        events: &[EventEnvelope<ReviewEvent>],
+    ) -> Result<(), mongodb::error::Error> {
+        let serialize = |e: bson::ser::Error| {
+            mongodb::error::Error::custom(format!("Failed to serialize review: {}", e))
+        };
+
+        self.collection()
+            .update_one(
+                doc! { "review_id": review_id },
+                doc! {
+                    "$set": {
+                        "status": bson::to_bson(&status).map_err(serialize)?,
+                        "results": bson::to_bson(results).map_err(serialize)?,
+                        "checker_results": bson::to_bson(checker_results).map_err(serialize)?,
+                        "suggestions": bson::to_bson(suggestions).map_err(serialize)?,
+                        "error": error,
+                        "events": bson::to_bson(events).map_err(serialize)?,
+                        "completed_at": BsonDateTime::now(),
+                    }
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_by_review_id(
+        &self,
+        review_id: &str,
+    ) -> Result<Option<ReviewJob>, mongodb::error::Error> {
+        self.collection()
+            .find_one(doc! { "review_id": review_id })
+            .await
+    }
+
+    /// Most recent reviews of `repo_url`, newest first
+    pub async fn find_by_repo(
+        &self,
+        repo_url: &str,
+        limit: i64,
+    ) -> Result<Vec<ReviewJob>, mongodb::error::Error> {
+        self.collection()
+            .find(doc! { "repo_url": repo_url })
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?
+            .try_collect()
+            .await
+    }
+}
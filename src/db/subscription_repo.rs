@@ -0,0 +1,101 @@
+use bson::{doc, oid::ObjectId, DateTime as BsonDateTime};
+use futures::TryStreamExt;
+use mongodb::{options::IndexOptions, Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+
+use crate::db::MongoClient;
+
+/// A grade re-run whenever its GitHub branch is pushed to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegradeSubscription {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// Lowercase `owner/repo` of the GitHub repository
+    pub repo: String,
+    /// Branch graded; `None` follows the repository's default branch
+    pub branch: Option<String>,
+    /// Grade that was subscribed
+    pub grade_id: String,
+    /// Newest grade of the chain; the next re-grade links to it
+    pub latest_grade_id: String,
+    /// Grade request re-used for every re-grade
+    pub request: bson::Document,
+    pub created_at: BsonDateTime,
+}
+
+pub struct SubscriptionRepository {
+    client: MongoClient,
+}
+
+impl SubscriptionRepository {
+    pub fn new(client: MongoClient) -> Self {
+        Self { client }
+    }
+
+    fn collection(&self) -> Collection<RegradeSubscription> {
+        self.client.database().collection("grade_subscriptions")
+    }
+
+    /// Index lookups by repository and keep one subscription per grade
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let indexes = [
+            IndexModel::builder().keys(doc! { "repo": 1 }).build(),
+            IndexModel::builder()
+                .keys(doc! { "grade_id": 1 })
+                .options(IndexOptions::builder().unique(true).build())
+                .build(),
+        ];
+
+        self.collection().create_indexes(indexes).await?;
+        Ok(())
+    }
+
+    /// Create or replace the subscription of `subscription.grade_id`
+    pub async fn subscribe(
+        &self,
+        subscription: &RegradeSubscription,
+    ) -> Result<(), mongodb::error::Error> {
+        self.collection()
+            .replace_one(doc! { "grade_id": &subscription.grade_id }, subscription)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    /// Remove the subscription that `grade_id` started or last re-graded.
+    /// Returns whether one existed.
+    pub async fn unsubscribe(&self, grade_id: &str) -> Result<bool, mongodb::error::Error> {
+        let result = self
+            .collection()
+            .delete_one(doc! {
+                "$or": [{ "grade_id": grade_id }, { "latest_grade_id": grade_id }]
+            })
+            .await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    pub async fn find_by_repo(
+        &self,
+        repo: &str,
+    ) -> Result<Vec<RegradeSubscription>, mongodb::error::Error> {
+        self.collection()
+            .find(doc! { "repo": repo })
+            .await?
+            .try_collect()
+            .await
+    }
+
+    pub async fn set_latest_grade(
+        &self,
+        id: ObjectId,
+        grade_id: &str,
+    ) -> Result<(), mongodb::error::Error> {
+        self.collection()
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": { "latest_grade_id": grade_id } },
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+//! Background retries for grade writes that failed while MongoDB was down
+//!
+//! Grade persistence is best-effort: a failed write is logged and queued here
+//! instead of failing the grade, then retried in order until it lands or runs
+//! out of attempts.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::analysis::SimilarityFingerprint;
+use crate::db::GradeRepository;
+use crate::types::{EventEnvelope, GradeEvent, GradeReport, GradeRequest};
+
+/// Seconds between retry rounds
+pub const WRITE_RETRY_INTERVAL_SECS: u64 = 30;
+
+/// Attempts after which a queued write is dropped
+pub const MAX_WRITE_ATTEMPTS: u32 = 20;
+
+#[derive(Debug, Clone)]
+pub enum PendingWrite {
+    SaveGradeJob {
+        grade_id: String,
+        regrade_of: Option<String>,
+        request: Box<GradeRequest>,
+    },
+    UpdateGradeJob {
+        report: Box<GradeReport>,
+        events: Vec<EventEnvelope<GradeEvent>>,
+        fingerprint: Option<Box<SimilarityFingerprint>>,
+    },
+    UpdateTaskGrade {
+        curriculum_id: String,
+        task_id: String,
+        report: Box<GradeReport>,
+    },
+}
+
+impl PendingWrite {
+    pub fn grade_id(&self) -> &str {
+        match self {
+            Self::SaveGradeJob { grade_id, .. } => grade_id,
+            Self::UpdateGradeJob { report, .. } | Self::UpdateTaskGrade { report, .. } => &report.id,
+        }
+    }
+
+    /// Whether this write stores the grade's report in its job
+    pub fn stores_report(&self) -> bool {
+        matches!(self, Self::UpdateGradeJob { .. })
+    }
+
+    pub async fn apply(&self, repo: &GradeRepository) -> Result<(), mongodb::error::Error> {
+        match self {
+            Self::SaveGradeJob {
+                grade_id,
+                regrade_of,
+                request,
+            } => repo
+                .save_grade_job(
+                    grade_id,
+                    regrade_of.clone(),
+                    request,
+                    request.curriculum_id.clone(),
+                    request.task_id.clone(),
+                )
+                .await
+                .map(|_| ()),
+            Self::UpdateGradeJob {
+                report,
+                events,
+                fingerprint,
+            } => {
+                repo.update_grade_job(&report.id, report, events, fingerprint.as_deref())
+                    .await
+            }
+            Self::UpdateTaskGrade {
+                curriculum_id,
+                task_id,
+                report,
+            } => repo.update_task_grade(curriculum_id, task_id, report).await,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WriteRetryQueue {
+    /// Queued writes with the attempts made so far, oldest first
+    pending: Arc<Mutex<VecDeque<(PendingWrite, u32)>>>,
+}
+
+impl WriteRetryQueue {
+    /// Retry queued writes against `repo` every `interval`. Ids of grades
+    /// whose report was stored by a retry are sent to `persisted`.
+    pub fn start(
+        repo: Arc<GradeRepository>,
+        interval: Duration,
+        persisted: mpsc::UnboundedSender<String>,
+    ) -> Self {
+        let queue = Self {
+            pending: Arc::default(),
+        };
+
+        let pending = Arc::clone(&queue.pending);
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                Self::retry_round(&pending, &repo, &persisted).await;
+            }
+        });
+        queue
+    }
+
+    /// Queue a write that failed once
+    pub fn push(&self, write: PendingWrite) {
+        self.pending.lock().unwrap().push_back((write, 1));
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Apply every queued write in order; failures keep their place ahead of
+    /// writes queued during the round
+    async fn retry_round(
+        pending: &Mutex<VecDeque<(PendingWrite, u32)>>,
+        repo: &GradeRepository,
+        persisted: &mpsc::UnboundedSender<String>,
+    ) {
+        let batch: Vec<_> = pending.lock().unwrap().drain(..).collect();
+        let mut failed = Vec::new();
+
+        for (write, attempts) in batch {
+            match write.apply(repo).await {
+                Ok(()) => {
+                    tracing::info!("Retried MongoDB write for grade {}", write.grade_id());
+                    if write.stores_report() {
+                        let _ = persisted.send(write.grade_id().to_string());
+                    }
+                }
+                Err(e) if attempts + 1 >= MAX_WRITE_ATTEMPTS => {
+                    tracing::error!(
+                        "Dropping MongoDB write for grade {} after {} attempts: {}",
+                        write.grade_id(),
+                        attempts + 1,
+                        e
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!("MongoDB write for grade {} still failing: {}", write.grade_id(), e);
+                    failed.push((write, attempts + 1));
+                }
+            }
+        }
+
+        let mut pending = pending.lock().unwrap();
+        for entry in failed.into_iter().rev() {
+            pending.push_front(entry);
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Comparison of two reviews of the same repository
+//!
+//! Diagnostics are matched by file, rule and message prefix, allowing the line
+//! to drift within a window since edits elsewhere in a file shift line numbers.
+//! A renamed file shows up as resolved diagnostics plus new ones.
+//!
+//! [`compare_diagnostics`] is the stricter comparison used for resubmissions:
+//! diagnostics match only on identical file, rule and message, at any line.
+
+use crate::types::{Diagnostic, Severity, SeverityCounts, Suggestion};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Max line shift for a diagnostic to count as the same issue
+pub const LINE_TOLERANCE: u32 = 10;
+
+/// Characters of the message compared; the tail often embeds names or counts
+const MESSAGE_PREFIX_CHARS: usize = 40;
+
+/// Minimum word overlap (Jaccard) for two suggestion titles to match
+const TITLE_SIMILARITY_THRESHOLD: f32 = 0.6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticDiff {
+    /// Only in the earlier review
+    pub resolved: Vec<Diagnostic>,
+    /// Only in the later review
+    pub new: Vec<Diagnostic>,
+    /// In both reviews, as reported by the later one
+    pub persisting: Vec<Diagnostic>,
+    pub resolved_by_severity: SeverityCounts,
+    pub new_by_severity: SeverityCounts,
+    pub persisting_by_severity: SeverityCounts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestionDiff {
+    pub resolved: Vec<Suggestion>,
+    pub new: Vec<Suggestion>,
+    pub persisting: Vec<Suggestion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewDiff {
+    pub base_review_id: String,
+    pub review_id: String,
+    pub diagnostics: DiagnosticDiff,
+    pub suggestions: SuggestionDiff,
+}
+
+/// Diagnostics of two reviews matched exactly on file, rule and message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticComparison {
+    /// Only in the first review
+    pub fixed: Vec<Diagnostic>,
+    /// Only in the second review
+    pub introduced: Vec<Diagnostic>,
+    /// In both reviews, as reported by the second one
+    pub persisted: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewComparison {
+    pub id_a: String,
+    pub id_b: String,
+    #[serde(flatten)]
+    pub diagnostics: DiagnosticComparison,
+}
+
+fn severity_counts(diagnostics: &[Diagnostic]) -> SeverityCounts {
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    SeverityCounts {
+        error: count(Severity::Error),
+        warning: count(Severity::Warning),
+        info: count(Severity::Info),
+        filtered: 0,
+    }
+}
+
+fn message_prefix(message: &str) -> &str {
+    match message.char_indices().nth(MESSAGE_PREFIX_CHARS) {
+        Some((end, _)) => &message[..end],
+        None => message,
+    }
+}
+
+fn same_issue(a: &Diagnostic, b: &Diagnostic) -> bool {
+    a.file == b.file
+        && a.rule == b.rule
+        && message_prefix(&a.message) == message_prefix(&b.message)
+        && a.line.abs_diff(b.line) <= LINE_TOLERANCE
+}
+
+/// Pair up items of `after` with unmatched items of `before`, returning
+/// `(resolved, new, persisting)`. `distance` ranks candidate matches.
+fn match_items<T: Clone>(
+    before: &[T],
+    after: &[T],
+    matches: impl Fn(&T, &T) -> bool,
+    distance: impl Fn(&T, &T) -> u32,
+) -> (Vec<T>, Vec<T>, Vec<T>) {
+    let mut matched: HashSet<usize> = HashSet::new();
+    let mut new = Vec::new();
+    let mut persisting = Vec::new();
+
+    for item in after {
+        let best = before
+            .iter()
+            .enumerate()
+            .filter(|(i, old)| !matched.contains(i) && matches(old, item))
+            .min_by_key(|(_, old)| distance(old, item))
+            .map(|(i, _)| i);
+
+        match best {
+            Some(i) => {
+                matched.insert(i);
+                persisting.push(item.clone());
+            }
+            None => new.push(item.clone()),
+        }
+    }
+
+    let resolved = before
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !matched.contains(i))
+        .map(|(_, old)| old.clone())
+        .collect();
+
+    (resolved, new, persisting)
+}
+
+pub fn diff_diagnostics(before: &[Diagnostic], after: &[Diagnostic]) -> DiagnosticDiff {
+    let (resolved, new, persisting) =
+        match_items(before, after, same_issue, |a, b| a.line.abs_diff(b.line));
+
+    DiagnosticDiff {
+        resolved_by_severity: severity_counts(&resolved),
+        new_by_severity: severity_counts(&new),
+        persisting_by_severity: severity_counts(&persisting),
+        resolved,
+        new,
+        persisting,
+    }
+}
+
+/// Compare review `a` with a later review `b`. Each diagnostic matches at
+/// most one other, the closest by line when a file repeats an issue.
+pub fn compare_diagnostics(a: &[Diagnostic], b: &[Diagnostic]) -> DiagnosticComparison {
+    let (fixed, introduced, persisted) = match_items(
+        a,
+        b,
+        |x, y| x.file == y.file && x.rule == y.rule && x.message == y.message,
+        |x, y| x.line.abs_diff(y.line),
+    );
+
+    DiagnosticComparison {
+        fixed,
+        introduced,
+        persisted,
+    }
+}
+
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Word-level Jaccard similarity of two titles
+fn title_similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (title_words(a), title_words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+pub fn diff_suggestions(before: &[Suggestion], after: &[Suggestion]) -> SuggestionDiff {
+    let (resolved, new, persisting) = match_items(
+        before,
+        after,
+        |a, b| title_similarity(&a.title, &b.title) >= TITLE_SIMILARITY_THRESHOLD,
+        // Prefer the most similar title
+        |a, b| ((1.0 - title_similarity(&a.title, &b.title)) * 1000.0) as u32,
+    );
+
+    SuggestionDiff {
+        resolved,
+        new,
+        persisting,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Priority, SuggestionCategory};
+
+    fn diag(file: &str, line: u32, rule: &str, message: &str) -> Diagnostic {
+        Diagnostic {
+            file: file.to_string(),
+            line,
+            column: 1,
+            message: message.to_string(),
+            rule: rule.to_string(),
+            severity: Severity::Warning,
+            suggestion: None,
+        }
+    }
+
+    fn suggestion(title: &str) -> Suggestion {
+        Suggestion {
+            category: SuggestionCategory::CodeQuality,
+            title: title.to_string(),
+            description: String::new(),
+            file: None,
+            line: None,
+            priority: Priority::Medium,
+            rationale: String::new(),
+            confidence: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_line_drift_within_tolerance() {
+        let before = vec![
+            diag(
+                "src/app.js",
+                10,
+                "no-var",
+                "Use let or const instead of var",
+            ),
+            diag(
+                "src/app.js",
+                40,
+                "no-console",
+                "Unexpected console statement",
+            ),
+        ];
+        let after = vec![
+            diag(
+                "src/app.js",
+                14,
+                "no-var",
+                "Use let or const instead of var",
+            ),
+            diag(
+                "src/app.js",
+                90,
+                "no-console",
+                "Unexpected console statement",
+            ),
+        ];
+
+        let diff = diff_diagnostics(&before, &after);
+
+        assert_eq!(diff.persisting.len(), 1);
+        assert_eq!(diff.persisting[0].line, 14);
+        assert_eq!(diff.resolved.len(), 1);
+        assert_eq!(diff.resolved[0].line, 40);
+        assert_eq!(diff.new.len(), 1);
+        assert_eq!(diff.new[0].line, 90);
+        assert_eq!(diff.new_by_severity.warning, 1);
+    }
+
+    #[test]
+    fn test_duplicates_match_closest_once() {
+        let before = vec![
+            diag("a.py", 5, "py-no-print", "print() call"),
+            diag("a.py", 8, "py-no-print", "print() call"),
+        ];
+        let after = vec![diag("a.py", 9, "py-no-print", "print() call")];
+
+        let diff = diff_diagnostics(&before, &after);
+
+        assert_eq!(diff.persisting.len(), 1);
+        assert_eq!(diff.resolved.len(), 1);
+        assert_eq!(diff.resolved[0].line, 5);
+        assert!(diff.new.is_empty());
+    }
+
+    #[test]
+    fn test_message_prefix_and_rename() {
+        let long = "Function 'handler' has parameters without type annotations: a";
+        let before = vec![
+            diag("src/old_name.py", 3, "py-missing-param-types", long),
+            diag("src/util.py", 1, "py-missing-param-types", long),
+        ];
+        let after = vec![
+            diag("src/new_name.py", 3, "py-missing-param-types", long),
+            diag(
+                "src/util.py",
+                1,
+                "py-missing-param-types",
+                "Function 'handler' has parameters without type annotations: a, b",
+            ),
+        ];
+
+        let diff = diff_diagnostics(&before, &after);
+
+        assert_eq!(diff.persisting.len(), 1);
+        assert_eq!(diff.persisting[0].file, "src/util.py");
+        assert_eq!(diff.resolved[0].file, "src/old_name.py");
+        assert_eq!(diff.new[0].file, "src/new_name.py");
+    }
+
+    #[test]
+    fn test_compare_fixed_introduced_persisted() {
+        let a = vec![
+            diag("src/app.js", 3, "no-var", "Unexpected var"),
+            diag("src/app.js", 10, "no-console", "Unexpected console statement"),
+        ];
+        let b = vec![
+            // Moved far beyond the diff tolerance, still the same issue
+            diag("src/app.js", 80, "no-console", "Unexpected console statement"),
+            diag("src/api.js", 1, "no-debugger", "Unexpected debugger statement"),
+        ];
+
+        let comparison = compare_diagnostics(&a, &b);
+
+        assert_eq!(comparison.fixed.len(), 1);
+        assert_eq!(comparison.fixed[0].rule, "no-var");
+        assert_eq!(comparison.introduced.len(), 1);
+        assert_eq!(comparison.introduced[0].file, "src/api.js");
+        assert_eq!(comparison.persisted.len(), 1);
+        assert_eq!(comparison.persisted[0].line, 80);
+    }
+
+    #[test]
+    fn test_compare_requires_exact_key() {
+        let a = vec![
+            diag("a.py", 1, "py-no-print", "Unexpected print() call"),
+            diag("a.py", 2, "py-no-eval", "eval() is a security risk"),
+            diag("a.py", 3, "typo", "'teh' should be 'the'"),
+        ];
+        let b = vec![
+            // Same rule and message in another file
+            diag("b.py", 1, "py-no-print", "Unexpected print() call"),
+            // Same file and message, different rule
+            diag("a.py", 2, "py-no-exec", "eval() is a security risk"),
+            // Message differs past any prefix
+            diag("a.py", 3, "typo", "'teh' should be 'then'"),
+        ];
+
+        let comparison = compare_diagnostics(&a, &b);
+
+        assert!(comparison.persisted.is_empty());
+        assert_eq!(comparison.fixed.len(), 3);
+        assert_eq!(comparison.introduced.len(), 3);
+    }
+
+    #[test]
+    fn test_compare_duplicates_pair_once() {
+        let a = vec![
+            diag("a.py", 5, "py-no-print", "Unexpected print() call"),
+            diag("a.py", 40, "py-no-print", "Unexpected print() call"),
+        ];
+        let b = vec![
+            diag("a.py", 41, "py-no-print", "Unexpected print() call"),
+            diag("a.py", 42, "py-no-print", "Unexpected print() call"),
+            diag("a.py", 43, "py-no-print", "Unexpected print() call"),
+        ];
+
+        let comparison = compare_diagnostics(&a, &b);
+
+        assert_eq!(comparison.persisted.len(), 2);
+        assert_eq!(comparison.introduced.len(), 1);
+        assert!(comparison.fixed.is_empty());
+    }
+
+    #[test]
+    fn test_compare_empty_sides() {
+        let a = vec![diag("a.py", 1, "py-no-print", "Unexpected print() call")];
+
+        let all_fixed = compare_diagnostics(&a, &[]);
+        assert_eq!(all_fixed.fixed.len(), 1);
+        assert!(all_fixed.introduced.is_empty() && all_fixed.persisted.is_empty());
+
+        let all_introduced = compare_diagnostics(&[], &a);
+        assert_eq!(all_introduced.introduced.len(), 1);
+        assert!(all_introduced.fixed.is_empty() && all_introduced.persisted.is_empty());
+
+        let json = serde_json::to_value(ReviewComparison {
+            id_a: "a".to_string(),
+            id_b: "b".to_string(),
+            diagnostics: all_fixed,
+        })
+        .unwrap();
+        assert_eq!(json["fixed"].as_array().unwrap().len(), 1);
+        assert!(json["persisted"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_suggestion_title_similarity() {
+        let before = vec![
+            suggestion("Add input validation to login form"),
+            suggestion("Cache GitHub API responses"),
+        ];
+        let after = vec![
+            suggestion("Add input validation to the login form"),
+            suggestion("Split the monolithic App component"),
+        ];
+
+        let diff = diff_suggestions(&before, &after);
+
+        assert_eq!(diff.persisting.len(), 1);
+        assert_eq!(diff.resolved[0].title, "Cache GitHub API responses");
+        assert_eq!(diff.new[0].title, "Split the monolithic App component");
+    }
+}
@@ -1,4 +1,7 @@
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use actix_web::{
+    http::{header, StatusCode},
+    HttpResponse, ResponseError,
+};
 use serde::Serialize;
 use thiserror::Error;
 
@@ -10,12 +13,27 @@ pub enum ApiError {
     #[error("invalid request: {0}")]
     BadRequest(String),
 
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("conflict: {0}")]
+    Conflict(String),
+
+    #[error("unprocessable request: {0}")]
+    Unprocessable(String),
+
+    #[error("service unavailable: {0}")]
+    ServiceUnavailable(String),
+
     #[error("git error: {0}")]
     GitError(String),
 
     #[error("checker error: {0}")]
     CheckerError(String),
 
+    #[error("LLM error: {0}")]
+    LlmError(#[from] LlmError),
+
     #[error("internal error: {0}")]
     InternalError(String),
 }
@@ -87,6 +105,20 @@ pub struct ErrorResponse {
     pub code: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<String>,
+    /// How long to wait before retrying, when the error is transient
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+}
+
+impl ApiError {
+    /// Milliseconds a client should wait before retrying, when known
+    pub fn retry_after_ms(&self) -> Option<u64> {
+        match self {
+            ApiError::ServiceUnavailable(_) => Some(crate::queue::QUEUE_FULL_RETRY_AFTER_SECS * 1000),
+            ApiError::LlmError(e) => e.retry_after_ms(),
+            _ => None,
+        }
+    }
 }
 
 impl ResponseError for ApiError {
@@ -94,8 +126,20 @@ impl ResponseError for ApiError {
         match self {
             ApiError::NotFound(_) => StatusCode::NOT_FOUND,
             ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             ApiError::GitError(_) => StatusCode::UNPROCESSABLE_ENTITY,
             ApiError::CheckerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::LlmError(e) => match e {
+                LlmError::RateLimited { .. } | LlmError::Unavailable { .. } | LlmError::Network(_) => {
+                    StatusCode::SERVICE_UNAVAILABLE
+                }
+                LlmError::ContextExceeded { .. } => StatusCode::BAD_REQUEST,
+                LlmError::AuthenticationFailed(_) | LlmError::TokenExpired => StatusCode::UNAUTHORIZED,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            },
             ApiError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -104,14 +148,32 @@ impl ResponseError for ApiError {
         let code = match self {
             ApiError::NotFound(_) => "NOT_FOUND",
             ApiError::BadRequest(_) => "BAD_REQUEST",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Conflict(_) => "CONFLICT",
+            ApiError::Unprocessable(_) => "UNPROCESSABLE",
+            ApiError::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
             ApiError::GitError(_) => "GIT_ERROR",
             ApiError::CheckerError(_) => "CHECKER_ERROR",
+            ApiError::LlmError(e) => match e {
+                LlmError::RateLimited { .. } => "LLM_RATE_LIMITED",
+                LlmError::Unavailable { .. } | LlmError::Network(_) => "LLM_UNAVAILABLE",
+                LlmError::ContextExceeded { .. } => "LLM_CONTEXT_EXCEEDED",
+                LlmError::AuthenticationFailed(_) | LlmError::TokenExpired => "LLM_AUTH_FAILED",
+                _ => "LLM_ERROR",
+            },
             ApiError::InternalError(_) => "INTERNAL_ERROR",
         };
-        HttpResponse::build(self.status_code()).json(ErrorResponse {
+        let retry_after_ms = self.retry_after_ms();
+        let mut response = HttpResponse::build(self.status_code());
+        if let Some(ms) = retry_after_ms {
+            // Whole seconds, rounded up so clients don't retry early
+            response.insert_header((header::RETRY_AFTER, ms.div_ceil(1000).to_string()));
+        }
+        response.json(ErrorResponse {
             error: self.to_string(),
             code: code.to_string(),
             details: None,
+            retry_after_ms,
         })
     }
 }
@@ -142,5 +204,53 @@ mod tests {
 
         let bad_request = ApiError::BadRequest("missing field".to_string());
         assert_eq!(bad_request.status_code(), StatusCode::BAD_REQUEST);
+
+        let conflict = ApiError::Conflict("review busy".to_string());
+        assert_eq!(conflict.status_code(), StatusCode::CONFLICT);
+
+        let queue_full = ApiError::ServiceUnavailable("queue full".to_string());
+        assert_eq!(queue_full.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            queue_full.error_response().headers().get(header::RETRY_AFTER).unwrap(),
+            "30"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_llm_error_responses() {
+        let body = |error: &ApiError| {
+            let response = error.error_response();
+            async move {
+                let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let rate_limited = ApiError::from(LlmError::RateLimited { retry_after_ms: 1500 });
+        assert_eq!(rate_limited.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            rate_limited.error_response().headers().get(header::RETRY_AFTER).unwrap(),
+            "2"
+        );
+        let json = body(&rate_limited).await;
+        assert_eq!(json["code"], "LLM_RATE_LIMITED");
+        assert_eq!(json["retry_after_ms"], 1500);
+        assert_eq!(json["error"], "LLM error: rate limit exceeded: retry after 1500ms");
+
+        let unavailable = ApiError::from(LlmError::Unavailable { provider: "anthropic".to_string() });
+        assert_eq!(unavailable.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(unavailable.error_response().headers().get(header::RETRY_AFTER).is_none());
+        assert!(body(&unavailable).await.get("retry_after_ms").is_none());
+
+        let too_long = ApiError::from(LlmError::ContextExceeded { used: 300_000, limit: 200_000 });
+        assert_eq!(too_long.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(body(&too_long).await["code"], "LLM_CONTEXT_EXCEEDED");
+
+        let auth = ApiError::from(LlmError::AuthenticationFailed("bad key".to_string()));
+        assert_eq!(auth.status_code(), StatusCode::UNAUTHORIZED);
+
+        let invalid = ApiError::from(LlmError::InvalidResponse("not json".to_string()));
+        assert_eq!(invalid.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body(&invalid).await["code"], "LLM_ERROR");
     }
 }
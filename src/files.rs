@@ -0,0 +1,505 @@
+//! File collection helpers shared by checkers and AI context builders
+//!
+//! Minified bundles, lockfiles, source maps and generated code produce
+//! thousands of meaningless diagnostics and waste LLM context, so they are
+//! skipped based on their name and the first kilobyte of content.
+//!
+//! Files loaded into memory for LLM context are read through [`read_capped`],
+//! which bounds both each file and the total held per review or grade.
+//! [`RepoTree`] lists every collected path, so prompts still show files whose
+//! content didn't fit.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Bytes read from the start of a file for the content heuristics
+pub const HEAD_BYTES: usize = 1024;
+
+/// Files larger than this are never treated as hand-written source
+pub const MAX_SOURCE_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Average line length above which a file is considered minified
+const MINIFIED_AVG_LINE_LEN: usize = 500;
+
+/// Lines at the top of a file searched for generated-code markers
+const MARKER_LINES: usize = 10;
+
+const GENERATED_SUFFIXES: &[&str] = &[".min.js", ".min.css", ".map", ".lock"];
+
+const GENERATED_FILE_NAMES: &[&str] =
+    &["package-lock.json", "pnpm-lock.yaml", "npm-shrinkwrap.json"];
+
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT", "Code generated by"];
+
+const GENERATED_MARKERS_LOWERCASE: &[&str] = &["auto-generated", "autogenerated"];
+
+/// Byte caps for source files loaded into an LLM context
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLimits {
+    /// Bytes read from the start of each file
+    pub max_file_bytes: usize,
+    /// Bytes held across all files of one context
+    pub max_total_bytes: usize,
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: 256 * 1024,
+            max_total_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// Characters of the repository tree included in a prompt
+pub const TREE_MAX_CHARS: usize = 4000;
+
+/// Failure reason of grades and reviews of repositories without source code
+pub const NO_SOURCE_ERROR: &str = "repository contains no gradeable source code";
+
+/// Source lines a repository needs before it is graded or reviewed
+pub const DEFAULT_MIN_SOURCE_LINES: usize = 5;
+
+/// Markdown and plain text, which don't count as source lines
+const PROSE_EXTENSIONS: &[&str] = &["md", "mdx", "markdown", "txt", "rst"];
+
+/// Directory levels shown in the repository tree
+const TREE_MAX_DEPTH: usize = 6;
+
+/// Files whose lines are counted for the tree; the rest are only tallied
+const TREE_MAX_ENTRIES: usize = 1000;
+
+/// A listed file and its line count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    pub path: String,
+    pub lines: usize,
+}
+
+/// Paths and line counts of all collected files, including those whose
+/// content was not loaded into context
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoTree {
+    /// Entries sorted by path
+    pub entries: Vec<TreeEntry>,
+    /// Collected files beyond `TREE_MAX_ENTRIES`
+    pub omitted: usize,
+}
+
+impl RepoTree {
+    /// List `paths` relative to `repo_path`, counting lines of each file
+    pub fn collect<'a>(repo_path: &Path, paths: impl IntoIterator<Item = &'a Path>) -> Self {
+        let mut tree = Self::default();
+        for path in paths {
+            if tree.entries.len() >= TREE_MAX_ENTRIES {
+                tree.omitted += 1;
+                continue;
+            }
+            tree.entries.push(TreeEntry {
+                path: relative_path(repo_path, path),
+                lines: count_lines(path),
+            });
+        }
+        tree.entries.sort_by(|a, b| a.path.cmp(&b.path));
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.omitted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lines of listed files other than markdown and plain text
+    pub fn source_lines(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                let ext = entry.path.rsplit_once('.').map_or("", |(_, ext)| ext);
+                !PROSE_EXTENSIONS.contains(&ext)
+            })
+            .map(|entry| entry.lines)
+            .sum()
+    }
+
+    /// Whether there is anything to grade: at least one file and
+    /// `min_lines` source lines. Trees past `TREE_MAX_ENTRIES` always are.
+    pub fn has_source(&self, min_lines: usize) -> bool {
+        self.omitted > 0 || (!self.is_empty() && self.source_lines() >= min_lines)
+    }
+
+    /// Indented listing with line counts, marking `included` files with `*`.
+    /// Stops before `max_chars`, ending with "…and N more files".
+    pub fn render<'a>(&self, included: impl IntoIterator<Item = &'a str>, max_chars: usize) -> String {
+        let included: HashSet<&str> = included.into_iter().collect();
+        let mut out = String::new();
+        let mut open_dirs: Vec<&str> = Vec::new();
+        let mut listed = 0;
+
+        for entry in &self.entries {
+            let parts: Vec<&str> = entry.path.split('/').collect();
+            let (file_name, dirs) = parts.split_last().expect("split yields a part");
+            if dirs.len() > TREE_MAX_DEPTH {
+                continue;
+            }
+
+            let common = open_dirs
+                .iter()
+                .zip(dirs.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let mut lines = String::new();
+            for (depth, dir) in dirs.iter().enumerate().skip(common) {
+                lines.push_str(&format!("{}{}/\n", "  ".repeat(depth), dir));
+            }
+            let marker = if included.contains(entry.path.as_str()) { " *" } else { "" };
+            lines.push_str(&format!(
+                "{}{} ({} lines){}\n",
+                "  ".repeat(dirs.len()),
+                file_name,
+                entry.lines,
+                marker
+            ));
+
+            if out.len() + lines.len() > max_chars {
+                break;
+            }
+            out.push_str(&lines);
+            open_dirs = dirs.to_vec();
+            listed += 1;
+        }
+
+        let remaining = self.len() - listed;
+        if remaining > 0 {
+            out.push_str(&format!("…and {} more files\n", remaining));
+        }
+        out
+    }
+}
+
+fn count_lines(path: &Path) -> usize {
+    File::open(path)
+        .map(|file| {
+            BufReader::new(file.take(MAX_SOURCE_FILE_BYTES))
+                .split(b'\n')
+                .count()
+        })
+        .unwrap_or(0)
+}
+
+/// Whether a file looks generated, minified or vendored, judging by its name
+/// and the first kilobyte of its content
+pub fn is_generated_or_vendored(path: &Path, head: &str) -> bool {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if GENERATED_SUFFIXES
+        .iter()
+        .any(|suffix| file_name.ends_with(suffix))
+        || GENERATED_FILE_NAMES.contains(&file_name)
+    {
+        return true;
+    }
+
+    let marker_region: String = head
+        .lines()
+        .take(MARKER_LINES)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let marker_region_lower = marker_region.to_lowercase();
+    if GENERATED_MARKERS.iter().any(|m| marker_region.contains(m))
+        || GENERATED_MARKERS_LOWERCASE
+            .iter()
+            .any(|m| marker_region_lower.contains(m))
+    {
+        return true;
+    }
+
+    let line_count = head.lines().count().max(1);
+    head.len() / line_count > MINIFIED_AVG_LINE_LEN
+}
+
+/// Read up to [`HEAD_BYTES`] from the start of a file
+fn read_head(path: &Path) -> Option<String> {
+    let mut buffer = Vec::with_capacity(HEAD_BYTES);
+    File::open(path)
+        .ok()?
+        .take(HEAD_BYTES as u64)
+        .read_to_end(&mut buffer)
+        .ok()?;
+    Some(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// Read at most `max_bytes` from the start of a file as UTF-8. A multi-byte
+/// character cut at the limit is dropped; otherwise invalid UTF-8 is an error,
+/// like `read_to_string`.
+pub fn read_prefix(path: &Path, max_bytes: usize) -> io::Result<String> {
+    let mut buffer = Vec::new();
+    File::open(path)?
+        .take(max_bytes as u64)
+        .read_to_end(&mut buffer)?;
+
+    match String::from_utf8(buffer) {
+        Ok(content) => Ok(content),
+        Err(e) => {
+            let valid_up_to = e.utf8_error().valid_up_to();
+            // Only an incomplete character at the very end is acceptable
+            if e.utf8_error().error_len().is_none() {
+                let mut bytes = e.into_bytes();
+                bytes.truncate(valid_up_to);
+                Ok(String::from_utf8(bytes).expect("prefix is valid UTF-8"))
+            } else {
+                Err(io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+        }
+    }
+}
+
+/// `path` relative to `repo_path` with `/` separators, as reported to
+/// clients; `.` for the repository itself
+pub fn relative_path(repo_path: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(repo_path).unwrap_or(path);
+    if relative.as_os_str().is_empty() {
+        return ".".to_string();
+    }
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Read files as `(repo-relative path, content)` pairs within `limits`,
+/// stopping once the total budget is used up. Unreadable or non-UTF-8 files
+/// are skipped.
+pub fn read_capped(
+    repo_path: &Path,
+    paths: impl IntoIterator<Item = PathBuf>,
+    limits: ReadLimits,
+) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    let mut remaining = limits.max_total_bytes;
+
+    for path in paths {
+        if remaining == 0 {
+            break;
+        }
+        let Ok(content) = read_prefix(&path, limits.max_file_bytes.min(remaining)) else {
+            continue;
+        };
+        remaining -= content.len();
+
+        files.push((relative_path(repo_path, &path), content));
+    }
+
+    files
+}
+
+/// Which files checkers and context collectors leave out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFilter {
+    /// Skip generated, minified and vendored files; turning this off is
+    /// useful for debugging
+    pub skip_generated: bool,
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        Self { skip_generated: true }
+    }
+}
+
+impl FileFilter {
+    /// Whether collectors should skip this file. Always `false` when
+    /// `skip_generated` is off.
+    pub fn skips(&self, path: &Path) -> bool {
+        self.skip_generated && is_generated_file(path)
+    }
+}
+
+fn is_generated_file(path: &Path) -> bool {
+    let oversized = fs::metadata(path)
+        .map(|m| m.len() > MAX_SOURCE_FILE_BYTES)
+        .unwrap_or(false);
+    if oversized {
+        return true;
+    }
+
+    read_head(path)
+        .map(|head| is_generated_or_vendored(path, &head))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Minified JavaScript as emitted by a production bundler
+    const MINIFIED_SAMPLE: &str = r#"var n,l,u,t,i,o,r,f,e,c,s,a,h={},p=[],y=/acit|ex(?:s|g|n|p|$)|rph|grid|ows|mnc|ntw|ine[ch]|zoo|^ord|itera/i,d=Array.isArray;function w(n,l){for(var u in l)n[u]=l[u];return n}function _(n){n&&n.parentNode&&n.parentNode.removeChild(n)}function g(l,u,t){var i,o,r,f={};for(r in u)"key"==r?i=u[r]:"ref"==r?o=u[r]:f[r]=u[r];if(arguments.length>2&&(f.children=arguments.length>3?n.call(arguments,2):t),"function"==typeof l&&null!=l.defaultProps)for(r in l.defaultProps)void 0===f[r]&&(f[r]=l.defaultProps[r]);return m(l,f,i,o,null)}function m(n,t,i,o,r){var f={type:n,props:t,key:i,ref:o,__k:null,__:null,__b:0,__e:null,__d:void 0,__c:null,constructor:void 0,__v:null==r?++u:r,__i:-1,__u:0};return null==r&&null!=l.vnode&&l.vnode(f),f}function b(){return{current:null}}function k(n){return n.children}function x(n,l){this.props=n,this.context=l}function C(n,l){if(null==l)return n.__?C(n.__,n.__i+1):null;for(var u;l<n.__k.length;l++)if(null!=(u=n.__k[l])&&null!=u.__e)return u.__e;return"function"==typeof n.type?C(n):null}function S(n){var l,u;if(null!=(n=n.__)&&null!=n.__c){for(n.__e=n.__c.base=null,l=0;l<n.__k.length;l++)if(null!=(u=n.__k[l])&&null!=u.__e){n.__e=n.__c.base=u.__e;break}return S(n)}}function M(n){(!n.__d&&(n.__d=!0)&&i.push(n)&&!P.__r++||o!==l.debounceRendering)&&((o=l.debounceRendering)||r)(P)}"#;
+
+    #[test]
+    fn test_minified_sample_detected() {
+        let head = &MINIFIED_SAMPLE[..HEAD_BYTES];
+        assert!(is_generated_or_vendored(Path::new("static/app.js"), head));
+    }
+
+    #[test]
+    fn test_generated_names_and_markers() {
+        assert!(is_generated_or_vendored(Path::new("vendor.min.js"), ""));
+        assert!(is_generated_or_vendored(Path::new("app.js.map"), ""));
+        assert!(is_generated_or_vendored(Path::new("Cargo.lock"), ""));
+        assert!(is_generated_or_vendored(Path::new("package-lock.json"), ""));
+        assert!(is_generated_or_vendored(
+            Path::new("api.pb.go"),
+            "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n"
+        ));
+        assert!(is_generated_or_vendored(
+            Path::new("schema.ts"),
+            "/**\n * This file was auto-generated by openapi-typescript.\n */\n"
+        ));
+    }
+
+    #[test]
+    fn test_handwritten_source_kept() {
+        let source = "import React from 'react';\n\nexport function App() {\n  return <div>Hello</div>;\n}\n";
+        assert!(!is_generated_or_vendored(Path::new("src/App.tsx"), source));
+        // A marker far below the header is ordinary code, not a generated file
+        let late_marker = format!("{}// DO NOT EDIT below\n", "let x = 1;\n".repeat(20));
+        assert!(!is_generated_or_vendored(
+            Path::new("src/x.js"),
+            &late_marker
+        ));
+    }
+
+    #[test]
+    fn test_read_prefix_caps_large_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("data.json");
+        let file = File::create(&path).unwrap();
+        // Sparse 50 MB file; only the prefix may be loaded
+        file.set_len(50 * 1024 * 1024).unwrap();
+
+        let content = read_prefix(&path, 4096).unwrap();
+        assert_eq!(content.len(), 4096);
+    }
+
+    #[test]
+    fn test_read_prefix_utf8_boundary() {
+        let dir = TempDir::new().unwrap();
+        let text = dir.path().join("ko.md");
+        fs::write(&text, "가나다").unwrap();
+        // Each character is 3 bytes; the cut one is dropped
+        assert_eq!(read_prefix(&text, 4).unwrap(), "가");
+
+        let binary = dir.path().join("image.json");
+        fs::write(&binary, [0xff, 0xfe, b'a', b'b']).unwrap();
+        assert!(read_prefix(&binary, 4).is_err());
+    }
+
+    #[test]
+    fn test_repo_tree_render() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src/api")).unwrap();
+        fs::create_dir(dir.path().join("tests")).unwrap();
+        let paths: Vec<PathBuf> = [
+            ("src/main.rs", "fn main() {}\n"),
+            ("tests/app_test.rs", "#[test]\nfn ok() {}\n"),
+            ("src/api/routes.rs", "a\nb\nc"),
+            ("README.md", ""),
+        ]
+        .iter()
+        .map(|(name, content)| {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            path
+        })
+        .collect();
+
+        let tree = RepoTree::collect(dir.path(), paths.iter().map(PathBuf::as_path));
+        assert_eq!(
+            tree.render(["src/main.rs"], TREE_MAX_CHARS),
+            "README.md (0 lines)\n\
+             src/\n\
+             \x20 api/\n\
+             \x20   routes.rs (3 lines)\n\
+             \x20 main.rs (1 lines) *\n\
+             tests/\n\
+             \x20 app_test.rs (2 lines)\n"
+        );
+        assert_eq!(
+            tree.render([], 60),
+            "README.md (0 lines)\nsrc/\n  api/\n    routes.rs (3 lines)\n…and 2 more files\n"
+        );
+    }
+
+    #[test]
+    fn test_repo_tree_has_source() {
+        let entry = |path: &str, lines| TreeEntry {
+            path: path.to_string(),
+            lines,
+        };
+        let tree = RepoTree {
+            entries: vec![entry("README.md", 200), entry("notes.txt", 40), entry("src/main.rs", 3)],
+            omitted: 0,
+        };
+        assert_eq!(tree.source_lines(), 3);
+        assert!(tree.has_source(3));
+        assert!(!tree.has_source(DEFAULT_MIN_SOURCE_LINES));
+
+        // With the threshold off, only an empty tree has nothing to grade
+        let docs_only = RepoTree {
+            entries: vec![entry("README.md", 200)],
+            omitted: 0,
+        };
+        assert!(docs_only.has_source(0));
+        assert!(!RepoTree::default().has_source(0));
+    }
+
+    #[test]
+    fn test_read_capped_total_budget() {
+        let dir = TempDir::new().unwrap();
+        let paths: Vec<PathBuf> = ["a.rs", "b.rs", "c.rs"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                fs::write(&path, "x".repeat(100)).unwrap();
+                path
+            })
+            .collect();
+        let limits = ReadLimits {
+            max_file_bytes: 60,
+            max_total_bytes: 150,
+        };
+
+        let files = read_capped(dir.path(), paths, limits);
+
+        let sizes: Vec<(&str, usize)> = files.iter().map(|(p, c)| (p.as_str(), c.len())).collect();
+        assert_eq!(sizes, vec![("a.rs", 60), ("b.rs", 60), ("c.rs", 30)]);
+    }
+
+    #[test]
+    fn test_generated_file_on_disk() {
+        let dir = TempDir::new().unwrap();
+        let bundle = dir.path().join("bundle.js");
+        fs::write(&bundle, MINIFIED_SAMPLE).unwrap();
+        let large = dir.path().join("large.js");
+        fs::write(&large, "let x = 1;\n".repeat(100_000)).unwrap();
+        let source = dir.path().join("main.js");
+        fs::write(&source, "console.log('hi');\n").unwrap();
+
+        assert!(is_generated_file(&bundle));
+        assert!(is_generated_file(&large));
+        assert!(!is_generated_file(&source));
+
+        assert!(FileFilter::default().skips(&bundle));
+        assert!(!FileFilter::default().skips(&source));
+        assert!(!FileFilter { skip_generated: false }.skips(&bundle));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let repo = Path::new("/tmp/repo");
+        assert_eq!(relative_path(repo, &repo.join("src").join("main.rs")), "src/main.rs");
+        assert_eq!(relative_path(repo, repo), ".");
+        assert_eq!(relative_path(repo, Path::new("other/file.rs")), "other/file.rs");
+    }
+}
@@ -1,11 +1,14 @@
 use crate::error::ApiError;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 use tokio::time::{timeout, Duration};
 
 const CLONE_TIMEOUT_SECS: u64 = 300;
 const VALIDATION_TIMEOUT_SECS: u64 = 10;
 
+/// libgit2's fetch depth that converts a shallow clone into a full one
+const FETCH_DEPTH_UNSHALLOW: i32 = i32::MAX;
+
 async fn validate_github_repo(url: &str) -> Result<(), ApiError> {
     let github_api_url = if let Some(captures) = extract_github_info(url) {
         format!("https://api.github.com/repos/{}/{}", captures.0, captures.1)
@@ -60,13 +63,93 @@ pub fn extract_github_info(url: &str) -> Option<(String, String)> {
     None
 }
 
+/// Check that a URL looks like a cloneable remote (`http(s)://host/path` or `git@host:path`)
+pub fn is_valid_repo_url(url: &str) -> bool {
+    if let Some(rest) = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+    {
+        return match rest.split_once('/') {
+            Some((host, path)) => !host.is_empty() && !path.trim_matches('/').is_empty(),
+            None => false,
+        };
+    }
+
+    if let Some(rest) = url.strip_prefix("git@") {
+        return match rest.split_once(':') {
+            Some((host, path)) => !host.is_empty() && !path.is_empty(),
+            None => false,
+        };
+    }
+
+    false
+}
+
+/// A file changed on HEAD since it diverged from a base branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedFile {
+    pub path: String,
+    pub added_lines: Vec<String>,
+    pub removed_lines: Vec<String>,
+}
+
+/// What a caller needs from a clone; `ClonedRepo::from_url_with_options`
+/// picks the fetch depth from it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloneOptions {
+    /// Commits of history to fetch, 0 for all; overrides the depth chosen
+    /// from the other options
+    pub depth: Option<u32>,
+    /// Branch that will be diffed against HEAD, which needs their history
+    /// back to the merge base
+    pub base_branch: Option<String>,
+    /// Commit to check out instead of the default branch's HEAD, e.g. to
+    /// re-grade exactly the code that was graded before
+    pub commit: Option<String>,
+}
+
+impl CloneOptions {
+    pub fn with_depth(mut self, depth: Option<u32>) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_base_branch(mut self, base_branch: Option<String>) -> Self {
+        self.base_branch = base_branch;
+        self
+    }
+
+    pub fn with_commit(mut self, commit: Option<String>) -> Self {
+        self.commit = commit;
+        self
+    }
+
+    /// Depth passed to libgit2, where 0 fetches the full history. Without
+    /// a `depth`, only a diff against `base_branch` needs more than HEAD.
+    pub fn fetch_depth(&self) -> i32 {
+        match self.depth {
+            Some(depth) => i32::try_from(depth).unwrap_or(FETCH_DEPTH_UNSHALLOW),
+            None if self.base_branch.is_some() => 0,
+            None => 1,
+        }
+    }
+}
+
 pub struct ClonedRepo {
     pub path: PathBuf,
+    /// Branch `HEAD` pointed at when the repository was cloned or opened
+    default_branch: Option<String>,
     _temp_dir: Option<TempDir>,
 }
 
 impl ClonedRepo {
     pub async fn from_url(url: &str) -> Result<Self, ApiError> {
+        Self::from_url_with_options(url, CloneOptions::default()).await
+    }
+
+    /// Clone a repository with as much history as `options` needs, checking
+    /// out `options.commit` when set
+    pub async fn from_url_with_options(url: &str, options: CloneOptions) -> Result<Self, ApiError> {
         validate_github_repo(url).await?;
 
         let temp_dir = TempDir::new()
@@ -74,28 +157,41 @@ impl ClonedRepo {
 
         let path = temp_dir.path().to_path_buf();
         let url = url.to_string();
+        let depth = options.fetch_depth();
 
         let clone_result = timeout(
             Duration::from_secs(CLONE_TIMEOUT_SECS),
             tokio::task::spawn_blocking(move || {
                 let mut builder = git2::build::RepoBuilder::new();
                 let mut fetch_opts = git2::FetchOptions::new();
-                fetch_opts.depth(1);
+                fetch_opts.depth(depth);
                 builder.fetch_options(fetch_opts);
-                builder.clone(&url, &path)
+                builder.clone(&url, &path).map(|repo| head_branch(&repo))
             }),
         )
         .await;
 
-        match clone_result {
-            Ok(Ok(Ok(_))) => Ok(Self {
+        let cloned = match clone_result {
+            Ok(Ok(Ok(default_branch))) => Self {
                 path: temp_dir.path().to_path_buf(),
+                default_branch,
                 _temp_dir: Some(temp_dir),
-            }),
-            Ok(Ok(Err(e))) => Err(ApiError::GitError(format!("Clone failed: {}", e))),
-            Ok(Err(e)) => Err(ApiError::GitError(format!("Clone task failed: {}", e))),
-            Err(_) => Err(ApiError::GitError("Clone timed out".to_string())),
+            },
+            Ok(Ok(Err(e))) => return Err(ApiError::GitError(format!("Clone failed: {}", e))),
+            Ok(Err(e)) => return Err(ApiError::GitError(format!("Clone task failed: {}", e))),
+            Err(_) => return Err(ApiError::GitError("Clone timed out".to_string())),
+        };
+
+        if let Some(commit) = options.commit {
+            let path = cloned.path.clone();
+            tokio::task::spawn_blocking(move || {
+                checkout_commit(&path, &commit)
+                    .map_err(|e| ApiError::GitError(format!("Checkout of {} failed: {}", commit, e)))
+            })
+            .await
+            .map_err(|e| ApiError::GitError(format!("Checkout task failed: {}", e)))??;
         }
+        Ok(cloned)
     }
 
     pub fn from_local(path: PathBuf) -> Result<Self, ApiError> {
@@ -105,30 +201,164 @@ impl ClonedRepo {
                 path
             )));
         }
+        let default_branch = git2::Repository::open(&path)
+            .ok()
+            .and_then(|repo| head_branch(&repo));
         Ok(Self {
             path,
+            default_branch,
             _temp_dir: None,
         })
     }
 
-    /// Get the short (7-char) HEAD commit hash
-    pub fn head_commit_short(&self) -> Option<String> {
+    /// The repository's default branch (e.g. `main` or `master`), or `None`
+    /// when `HEAD` was detached
+    pub fn default_branch(&self) -> Option<&str> {
+        self.default_branch.as_deref()
+    }
+
+    /// Get the full HEAD commit hash
+    pub fn head_commit(&self) -> Option<String> {
         let repo = git2::Repository::open(&self.path).ok()?;
         let head = repo.head().ok()?;
         let commit = head.peel_to_commit().ok()?;
-        let full_hash = commit.id().to_string();
+        Some(commit.id().to_string())
+    }
+
+    /// Get the short (7-char) HEAD commit hash
+    pub fn head_commit_short(&self) -> Option<String> {
+        let full_hash = self.head_commit()?;
         Some(full_hash[..7.min(full_hash.len())].to_string())
     }
 
-    /// Generate a cache key for this repo: "owner/repo:branch:commit"
+    /// Files with added lines between the merge base of HEAD and
+    /// `base_branch` and HEAD. Fetches the full history of both branches
+    /// when a shallow clone doesn't reach the merge base. Blocking; call
+    /// from `spawn_blocking`.
+    pub fn diff_from_base(&self, base_branch: &str) -> Result<Vec<ChangedFile>, ApiError> {
+        diff_against_base(&self.path, base_branch).map_err(|e| {
+            ApiError::GitError(format!("Diff against {} failed: {}", base_branch, e))
+        })
+    }
+
+    /// Generate a cache key for this repo: "owner/repo:branch:commit", using
+    /// the default branch when `branch` is `None`
     pub fn cache_key(&self, repo_url: &str, branch: Option<&str>) -> Option<String> {
         let (owner, repo) = extract_github_info(repo_url)?;
         let commit = self.head_commit_short()?;
-        let branch = branch.unwrap_or("main");
+        let branch = branch.or(self.default_branch()).unwrap_or("HEAD");
         Some(format!("{}:{}:{}:{}", owner, repo, branch, commit))
     }
 }
 
+/// Branch name `HEAD` symbolically refers to, e.g. `refs/heads/master` →
+/// `master`
+fn head_branch(repo: &git2::Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    let target = head.symbolic_target()?;
+    target.strip_prefix("refs/heads/").map(str::to_string)
+}
+
+/// Check out `commit` in a (possibly shallow) clone, fetching it from
+/// `origin` when it is not present locally
+fn checkout_commit(path: &Path, commit: &str) -> Result<(), git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let oid = git2::Oid::from_str(commit)?;
+
+    if repo.find_commit(oid).is_err() {
+        let mut fetch_opts = git2::FetchOptions::new();
+        fetch_opts.depth(1);
+        repo.find_remote("origin")?
+            .fetch(&[commit], Some(&mut fetch_opts), None)?;
+    }
+
+    let target = repo.find_commit(oid)?;
+    repo.checkout_tree(
+        target.as_object(),
+        Some(git2::build::CheckoutBuilder::new().force()),
+    )?;
+    repo.set_head_detached(oid)
+}
+
+fn diff_against_base(path: &Path, base_branch: &str) -> Result<Vec<ChangedFile>, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let (_, merge_base) = find_base_commit(&repo, head.id(), base_branch)?;
+
+    let base_tree = repo.find_commit(merge_base)?.tree()?;
+    let head_tree = head.tree()?;
+    let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), None)?;
+
+    let mut changed: Vec<ChangedFile> = Vec::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |delta, _, line| {
+            let Some(path) = delta.new_file().path() else {
+                return true;
+            };
+            let path = path.to_string_lossy().replace('\\', "/");
+            if changed.last().map(|file| &file.path) != Some(&path) {
+                changed.push(ChangedFile {
+                    path,
+                    added_lines: Vec::new(),
+                    removed_lines: Vec::new(),
+                });
+            }
+            let file = changed.last_mut().expect("pushed above");
+            let content = String::from_utf8_lossy(line.content())
+                .trim_end_matches(['\n', '\r'])
+                .to_string();
+            match line.origin() {
+                '+' => file.added_lines.push(content),
+                '-' => file.removed_lines.push(content),
+                _ => {}
+            }
+            true
+        }),
+    )?;
+
+    changed.retain(|file| !file.added_lines.is_empty());
+    Ok(changed)
+}
+
+/// Resolve `base_branch` locally or as `origin/<base_branch>` along with its
+/// merge base with `head`, fetching the branch when it is missing and
+/// deepening a shallow clone whose history doesn't reach the merge base
+fn find_base_commit(
+    repo: &git2::Repository,
+    head: git2::Oid,
+    base_branch: &str,
+) -> Result<(git2::Oid, git2::Oid), git2::Error> {
+    let remote_ref = format!("origin/{}", base_branch);
+    let resolve = || {
+        let base = repo
+            .revparse_single(base_branch)
+            .or_else(|_| repo.revparse_single(&remote_ref))
+            .and_then(|object| object.peel_to_commit())?
+            .id();
+        Ok((base, repo.merge_base(head, base)?))
+    };
+
+    if let Ok(found) = resolve() {
+        return Ok(found);
+    }
+
+    let mut refspecs = vec![format!("+refs/heads/{0}:refs/remotes/origin/{0}", base_branch)];
+    if let Some(head_branch) = repo.head()?.shorthand().filter(|name| *name != "HEAD") {
+        refspecs.push(format!("+refs/heads/{0}:refs/remotes/origin/{0}", head_branch));
+    }
+    let mut fetch_opts = git2::FetchOptions::new();
+    if repo.is_shallow() {
+        fetch_opts.depth(FETCH_DEPTH_UNSHALLOW);
+    }
+    repo.find_remote("origin")?
+        .fetch(&refspecs, Some(&mut fetch_opts), None)?;
+
+    resolve()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,6 +369,168 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_checkout_commit() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+
+        let commit_file = |content: &str| {
+            std::fs::write(dir.path().join("main.py"), content).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new("main.py")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parents: Vec<git2::Commit> = repo
+                .head()
+                .ok()
+                .and_then(|h| h.peel_to_commit().ok())
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            repo.commit(Some("HEAD"), &signature, &signature, content, &tree, &parents)
+                .unwrap()
+        };
+        let first = commit_file("v1");
+        commit_file("v2");
+
+        checkout_commit(dir.path(), &first.to_string()).unwrap();
+
+        let cloned = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
+        assert_eq!(cloned.head_commit(), Some(first.to_string()));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("main.py")).unwrap(),
+            "v1"
+        );
+    }
+
+    #[test]
+    fn test_default_branch_in_cache_key() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init_opts(
+            dir.path(),
+            git2::RepositoryInitOptions::new().initial_head("master"),
+        )
+        .unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        let short = &commit.to_string()[..7];
+
+        let cloned = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
+        assert_eq!(cloned.default_branch(), Some("master"));
+        assert_eq!(
+            cloned.cache_key("https://github.com/owner/repo", None),
+            Some(format!("owner:repo:master:{}", short))
+        );
+        assert_eq!(
+            cloned.cache_key("https://github.com/owner/repo", Some("dev")),
+            Some(format!("owner:repo:dev:{}", short))
+        );
+
+        repo.set_head_detached(commit).unwrap();
+        let detached = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
+        assert_eq!(detached.default_branch(), None);
+    }
+
+    #[test]
+    fn test_diff_from_base() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+
+        let commit_files = |files: &[(&str, &str)]| {
+            let mut index = repo.index().unwrap();
+            for (name, content) in files {
+                std::fs::write(dir.path().join(name), content).unwrap();
+                index.add_path(Path::new(name)).unwrap();
+            }
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &signature, &signature, "commit", &tree, &parents)
+                .unwrap()
+        };
+        let base = commit_files(&[("app.py", "import os\n"), ("setup.py", "setup()\n")]);
+        repo.branch("starter", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+        commit_files(&[
+            ("app.py", "import sys\nprint(sys.argv)\n"),
+            ("solution.py", "def solve():\n    return 42\n"),
+        ]);
+
+        let cloned = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
+        let changed = cloned.diff_from_base("starter").unwrap();
+
+        assert_eq!(
+            changed,
+            vec![
+                ChangedFile {
+                    path: "app.py".to_string(),
+                    added_lines: vec!["import sys".to_string(), "print(sys.argv)".to_string()],
+                    removed_lines: vec!["import os".to_string()],
+                },
+                ChangedFile {
+                    path: "solution.py".to_string(),
+                    added_lines: vec!["def solve():".to_string(), "    return 42".to_string()],
+                    removed_lines: vec![],
+                },
+            ]
+        );
+        assert!(matches!(
+            cloned.diff_from_base("missing"),
+            Err(ApiError::GitError(_))
+        ));
+    }
+
+    #[test]
+    fn test_clone_options_fetch_depth() {
+        let options = CloneOptions::default();
+        assert_eq!(options.fetch_depth(), 1);
+        assert_eq!(options.clone().with_commit(Some("abc1234".to_string())).fetch_depth(), 1);
+
+        let diff = options.with_base_branch(Some("starter".to_string()));
+        assert_eq!(diff.fetch_depth(), 0);
+        assert_eq!(diff.clone().with_depth(Some(50)).fetch_depth(), 50);
+        assert_eq!(diff.with_depth(Some(u32::MAX)).fetch_depth(), FETCH_DEPTH_UNSHALLOW);
+    }
+
+    #[tokio::test]
+    async fn test_clone_with_base_branch_history() {
+        let origin = TempDir::new().unwrap();
+        let repo = git2::Repository::init(origin.path()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let commit_file = |name: &str, content: &str| {
+            std::fs::write(origin.path().join(name), content).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new(name)).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &signature, &signature, name, &tree, &parents)
+                .unwrap()
+        };
+        let base = commit_file("starter.py", "pass\n");
+        repo.branch("starter", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+        let head = commit_file("solution.py", "print(42)\n");
+
+        let options = CloneOptions::default().with_base_branch(Some("starter".to_string()));
+        let url = origin.path().to_string_lossy();
+        let cloned = ClonedRepo::from_url_with_options(&url, options.clone()).await.unwrap();
+
+        assert_eq!(cloned.head_commit(), Some(head.to_string()));
+        let changed = cloned.diff_from_base("starter").unwrap();
+        assert_eq!(changed.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), ["solution.py"]);
+
+        let pinned = ClonedRepo::from_url_with_options(&url, options.with_commit(Some(base.to_string())))
+            .await
+            .unwrap();
+        assert_eq!(pinned.head_commit(), Some(base.to_string()));
+    }
+
     #[test]
     fn test_from_local_exists() {
         let result = ClonedRepo::from_local(PathBuf::from("."));
@@ -163,6 +555,15 @@ mod tests {
         assert_eq!(result, Some(("junhoyeo".to_string(), "tokscale".to_string())));
     }
 
+    #[test]
+    fn test_is_valid_repo_url() {
+        assert!(is_valid_repo_url("https://github.com/junhoyeo/tokscale"));
+        assert!(is_valid_repo_url("git@github.com:junhoyeo/tokscale.git"));
+        assert!(!is_valid_repo_url("https://github.com"));
+        assert!(!is_valid_repo_url("github.com/junhoyeo/tokscale"));
+        assert!(!is_valid_repo_url(""));
+    }
+
     #[test]
     fn test_extract_github_info_non_github() {
         let result = extract_github_info("https://gitlab.com/owner/repo");
@@ -1,23 +1,44 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
-use tokio::sync::{broadcast, RwLock, Semaphore};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock, Semaphore};
 use tokio::time::{interval, Duration};
+use futures::future::ready;
+use futures::stream::{self, Stream, StreamExt};
+use tracing::Instrument;
 
-use crate::ai::graders::{CriteriaChecker, GradeContext, Grader};
-use crate::config::ProvidersConfig;
-use crate::error::ApiError;
-use crate::git::ClonedRepo;
-use crate::llm::anthropic::AnthropicClient;
-use crate::llm::openai::OpenAIClient;
-use crate::llm::opencode::OpenCodeClient;
-use crate::llm::ModelClient;
+use crate::analysis::similarity::{similar_pairs, SimilarityFingerprint};
+use crate::ai::graders::{
+    merge_provided_files, CriteriaChecker, DiagnosticsSummary, GradeContext, Grader, DEFAULT_LANGUAGE,
+};
+use crate::checkers::{all_checkers, checkers_for};
+use crate::config::{ProvidersConfig, DEFAULT_MAX_CONCURRENT_LLM_REQUESTS, DEFAULT_MAX_STORED_JOBS};
+use crate::db::write_retry::WRITE_RETRY_INTERVAL_SECS;
+use crate::db::{
+    AuditEntry, AuditRepository, IdempotencyRepository, PendingWrite, RegradeSubscription, SubscriptionRepository,
+    WriteRetryQueue,
+};
+use crate::error::{ApiError, LlmError};
+use crate::files::{read_capped, FileFilter, ReadLimits, RepoTree, NO_SOURCE_ERROR};
+use crate::git::{extract_github_info, is_valid_repo_url, CloneOptions, ClonedRepo};
+use crate::idempotency::IdempotencyKeys;
+use crate::prompts::PromptRegistry;
+use crate::queue::JobQueue;
+use crate::orchestrator::{lru_evictions, unix_millis, watch_events, EventLog};
+use crate::llm::{
+    allowed_models, estimate_tokens_from_chars, ModelClient, MultiProviderClient, ProviderOptions,
+};
+use crate::profile::RepoProfile;
+use crate::test_runner::{self, TestRunResult, TestRunnerConfig};
+use crate::utils::{PiiRedactor, PriorityFileSelector};
+use crate::webhook::PushEvent;
 use crate::types::{
-    CriterionResult, GradeConfig, GradeEvent, GradeMetadata, GradeReport, GradeRequest,
-    GradeStatus, GradeTask, TaskGradeResult, TaskStatus,
+    CalibrationReport, Criterion, DEFAULT_RUBRIC_CATEGORY, CriterionDryRunEstimate, CriterionOverride, CriterionOverrideRequest, CriterionOutcome, CriterionResult, DryRunRequest, DryRunResult, GradeConfig, GradeEvent, GradeMetadata, GradeReport, GradeRequest,
+    backfill_seq, EventEnvelope, GradeStatus, GradeTask, ModelHint, NoSourcePolicy, RubricCategory, SimilarPair, SimilarityReport, TaskDryRunEstimate, TaskGradeResult, TaskStatus, UnverifiablePolicy,
 };
-use secrecy::ExposeSecret;
 
 pub struct GradeState {
     pub id: String,
@@ -25,47 +46,319 @@ pub struct GradeState {
     pub repo_url: String,
     pub tasks: Vec<GradeTask>,
     pub task_results: Vec<TaskGradeResult>,
+    pub rubric_breakdown: BTreeMap<String, RubricCategory>,
     pub overall_score: f32,
     pub percentage: u32,
     pub grade: String,
     pub summary: String,
     pub error: Option<String>,
     pub metadata: Option<GradeMetadata>,
+    pub language: String,
+    pub minutes_late: Option<i64>,
+    /// Commit graded; set before cloning for re-grades to pin the code
+    pub commit_sha: Option<String>,
+    /// Requested branch, or the default branch resolved after cloning
+    pub branch: Option<String>,
+    /// Time budget ran out before every criterion was checked
+    pub partial: bool,
+    pub regrade_of: Option<String>,
+    /// Model used for criterion checks, set once the client is created
+    pub model: Option<String>,
+    /// 1-based position while waiting in the job queue
+    pub queue_position: Option<usize>,
     pub created_at: u64,
+    /// When criterion checks began, for measuring LLM latency
+    pub started_grading_at: Option<u64>,
+    pub completed_at: Option<u64>,
     pub duration_ms: u64,
-    event_sender: broadcast::Sender<GradeEvent>,
+    /// Final report saved to MongoDB, directly or by a retry
+    pub persisted: bool,
+    /// Batch the grade was created in, if any
+    pub batch_id: Option<String>,
+    /// Student files' fingerprint, when `similarity_fingerprint` is set
+    pub fingerprint: Option<SimilarityFingerprint>,
+    /// Unix millis of the last read, for evicting the least recently used
+    last_accessed: AtomicU64,
+    events: EventLog<GradeEvent>,
+}
+
+pub enum GradeSubscription {
+    /// Events from now until the grade finishes
+    Live(broadcast::Receiver<EventEnvelope<GradeEvent>>),
+    /// The grade already finished with this event
+    Finished(EventEnvelope<GradeEvent>),
+}
+
+/// Evidence recorded for criteria skipped after the error budget ran out
+const ABORTED_EVIDENCE: &str = "Grading aborted due to LLM errors";
+
+/// Evidence recorded for criteria skipped after the time budget ran out
+const TIME_BUDGET_EVIDENCE: &str = "Not evaluated: time budget exhausted";
+
+/// Counts LLM failures during a grade run so a dead provider stops the run
+/// instead of failing every remaining criterion one by one
+pub struct ErrorBudget {
+    max_failures: usize,
+    current: AtomicUsize,
+}
+
+impl ErrorBudget {
+    pub fn new(max_failures: usize) -> Self {
+        Self {
+            max_failures,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record a failure, returning whether the budget is now exhausted
+    pub fn record_failure(&self) -> bool {
+        self.current.fetch_add(1, Ordering::SeqCst) + 1 >= self.max_failures
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.failures() >= self.max_failures
+    }
+
+    pub fn failures(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
+}
+
+/// Wall-clock limit for a grade run. Criteria reached after the deadline are
+/// skipped so the grade completes with partial results instead of hanging.
+pub struct TimeBudget {
+    deadline: Instant,
+    skipped: AtomicUsize,
+}
+
+impl TimeBudget {
+    pub fn new(start: Instant, max_duration: Duration) -> Self {
+        Self {
+            deadline: start + max_duration,
+            skipped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Time left before the deadline, zero once it has passed
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    pub fn record_skip(&self) {
+        self.skipped.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Criteria left unchecked because the budget ran out
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::SeqCst)
+    }
+}
+
+/// Times a criterion is retried after being rate limited before it fails
+pub const MAX_RATE_LIMIT_RETRIES: usize = 3;
+
+/// Pause when a rate-limited response has no usable `Retry-After`
+const MIN_RATE_LIMIT_PAUSE: Duration = Duration::from_secs(1);
+
+/// Pauses all of a grade's criterion checks after a rate-limited response,
+/// so they back off together instead of each running into the limit
+#[derive(Default)]
+pub struct RateLimitGate {
+    resume_at: std::sync::Mutex<Option<Instant>>,
+    pauses: AtomicUsize,
+}
+
+impl RateLimitGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold checks back for `retry_after`, extending any pause in progress
+    pub fn pause(&self, retry_after: Duration) {
+        let until = Instant::now() + retry_after.max(MIN_RATE_LIMIT_PAUSE);
+        let mut resume_at = self.resume_at.lock().unwrap();
+        if resume_at.is_none_or(|current| current < until) {
+            *resume_at = Some(until);
+        }
+        self.pauses.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Wait out the current pause, or until the time budget runs out
+    pub async fn wait(&self, time_budget: &TimeBudget) {
+        loop {
+            let Some(resume_at) = *self.resume_at.lock().unwrap() else {
+                return;
+            };
+            let remaining = resume_at.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || time_budget.is_exhausted() {
+                return;
+            }
+            // Checked again afterwards: another check may have extended it
+            tokio::time::sleep(remaining.min(time_budget.remaining())).await;
+        }
+    }
+
+    /// Rate-limited responses seen so far
+    pub fn pauses(&self) -> usize {
+        self.pauses.load(Ordering::SeqCst)
+    }
 }
 
+/// Seconds since the Unix epoch
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Fixed per-grade events: started, cloning x2, analysis x2, completed (+ headroom)
+const BASE_EVENT_COUNT: usize = 8;
+
+/// Largest event buffer a grade gets, whatever its request asks for; the
+/// buffer is allocated up front
+pub const MAX_EVENT_BUFFER_SIZE: usize = 10_000;
+
+/// Largest `max_files` a grade config may set
+pub const MAX_FILES_LIMIT: usize = 1_000;
+
+/// Largest `max_chars_per_file` a grade config may set
+pub const MAX_CHARS_PER_FILE_LIMIT: usize = 200_000;
+
+/// Largest `max_total_chars` a grade config may set
+pub const MAX_TOTAL_CHARS_LIMIT: usize = 2_000_000;
+
 impl GradeState {
     pub fn new(id: String, request: &GradeRequest) -> Self {
-        let (event_sender, _) = broadcast::channel(100);
+        let config = request.config_or(&GradeConfig::default());
+        Self::with_capacity(id, request, Self::event_capacity(request, &config))
+    }
+
+    pub fn with_capacity(id: String, request: &GradeRequest, capacity: usize) -> Self {
         Self {
             id,
             status: GradeStatus::Pending,
             repo_url: request.repo_url.clone(),
             tasks: request.tasks.clone(),
             task_results: Vec::new(),
+            rubric_breakdown: BTreeMap::new(),
             overall_score: 0.0,
             percentage: 0,
             grade: String::new(),
             summary: String::new(),
             error: None,
             metadata: request.metadata.clone(),
-            created_at: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            minutes_late: request.metadata.as_ref().and_then(|m| m.minutes_late()),
+            commit_sha: None,
+            branch: request.branch.clone(),
+            partial: false,
+            regrade_of: None,
+            model: None,
+            queue_position: None,
+            language: request
+                .language
+                .clone()
+                .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string()),
+            created_at: unix_now(),
+            started_grading_at: None,
+            completed_at: None,
             duration_ms: 0,
-            event_sender,
+            persisted: false,
+            batch_id: None,
+            fingerprint: None,
+            last_accessed: AtomicU64::new(unix_millis()),
+            events: EventLog::new(capacity),
         }
     }
 
-    pub fn subscribe(&self) -> broadcast::Receiver<GradeEvent> {
-        self.event_sender.subscribe()
+    /// Channel capacity large enough to hold every event of the grade, up
+    /// to `MAX_EVENT_BUFFER_SIZE`
+    pub fn event_capacity(request: &GradeRequest, config: &GradeConfig) -> usize {
+        let total_criteria: usize = request
+            .tasks
+            .iter()
+            .map(|t| t.acceptance_criteria.len())
+            .sum();
+        // Started and completed per checker
+        let checks = if config.run_checkers { all_checkers().len() * 2 } else { 0 };
+        let expected = BASE_EVENT_COUNT
+            .saturating_add(config.max_files)
+            .saturating_add(request.tasks.len() * 2)
+            .saturating_add(total_criteria)
+            .saturating_add(checks);
+        expected.max(config.event_buffer_size).min(MAX_EVENT_BUFFER_SIZE)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope<GradeEvent>> {
+        self.events.subscribe()
     }
 
     pub fn emit(&self, event: GradeEvent) {
-        let _ = self.event_sender.send(event);
+        self.events.send(event);
+    }
+
+    /// Events emitted so far, oldest first
+    pub fn events(&self) -> Vec<EventEnvelope<GradeEvent>> {
+        self.events.events()
+    }
+
+    /// Event a finished grade ended with, for late subscribers. Taken from
+    /// the log, or rebuilt (unlogged) when it has been dropped from it.
+    fn terminal_event(&self) -> Option<EventEnvelope<GradeEvent>> {
+        let event = self.rebuilt_terminal_event()?;
+        let logged = self
+            .events()
+            .into_iter()
+            .rev()
+            .find(|envelope| envelope.event.is_terminal());
+        Some(logged.unwrap_or_else(|| EventEnvelope::new(0, unix_millis(), event)))
+    }
+
+    fn rebuilt_terminal_event(&self) -> Option<GradeEvent> {
+        match self.status {
+            GradeStatus::Completed => Some(GradeEvent::GradeCompleted {
+                overall_score: self.overall_score,
+                percentage: self.percentage,
+                grade: self.grade.clone(),
+                summary: self.summary.clone(),
+                duration_ms: self.duration_ms,
+                partial: self.partial,
+                reason: None,
+            }),
+            GradeStatus::Failed => Some(GradeEvent::GradeFailed {
+                error: self.error.clone().unwrap_or_default(),
+                recoverable: true,
+            }),
+            _ => None,
+        }
+    }
+
+    fn touch(&self) {
+        self.last_accessed.store(unix_millis(), Ordering::Relaxed);
+    }
+
+    /// When the grade was last read, or `None` while it is still running
+    /// and must not be evicted
+    fn evictable_since(&self) -> Option<u64> {
+        matches!(self.status, GradeStatus::Completed | GradeStatus::Failed)
+            .then(|| self.last_accessed.load(Ordering::Relaxed))
+    }
+
+    /// Record a new queue position, emitting `Queued` when it changed
+    fn update_queue_position(&mut self, position: usize) {
+        if self.status == GradeStatus::Queued && self.queue_position != Some(position) {
+            self.queue_position = Some(position);
+            self.emit(GradeEvent::Queued { position });
+        }
+    }
+
+    /// Log for emitting events without holding the state lock
+    pub fn event_sender(&self) -> EventLog<GradeEvent> {
+        self.events.clone()
     }
 
     pub fn to_report(&self) -> GradeReport {
@@ -77,21 +370,100 @@ impl GradeState {
             percentage: self.percentage,
             grade: self.grade.clone(),
             tasks: self.task_results.clone(),
+            rubric_breakdown: self.rubric_breakdown.clone(),
             summary: self.summary.clone(),
             duration_ms: self.duration_ms,
             error: self.error.clone(),
             metadata: self.metadata.clone(),
+            language: self.language.clone(),
+            is_late: self.minutes_late.is_some(),
+            minutes_late: self.minutes_late,
+            commit_sha: self.commit_sha.clone(),
+            partial: self.partial,
+            branch: self.branch.clone(),
+            regrade_of: self.regrade_of.clone(),
+            model: self.model.clone(),
+            queue_position: self.queue_position,
+            created_at: self.created_at,
+            started_grading_at: self.started_grading_at,
+            completed_at: self
+                .completed_at
+                .filter(|_| self.status == GradeStatus::Completed),
+            persisted: self.persisted,
+            batch_id: self.batch_id.clone(),
+        }
+    }
+}
+
+/// Overall result of a grade, from [`GradeStore::calculate_final_score`]
+struct FinalScore {
+    overall_score: f32,
+    percentage: u32,
+    grade: String,
+    summary: String,
+    rubric_breakdown: BTreeMap<String, RubricCategory>,
+}
+
+/// Grader system prompt plus task/criterion text, approximated for dry runs
+const PROMPT_OVERHEAD_CHARS: usize = 2000;
+
+/// Thinking budget used when `GradeConfig::use_extended_thinking` is set
+const EXTENDED_THINKING_BUDGET: u32 = 4096;
+
+/// The grade's default client plus any used for criteria's `model_hint`s
+struct GraderClients {
+    default: Box<dyn ModelClient>,
+    cheap: Option<Box<dyn ModelClient>>,
+    strong: Option<Box<dyn ModelClient>>,
+}
+
+impl GraderClients {
+    fn new(default: Box<dyn ModelClient>) -> Self {
+        Self {
+            default,
+            cheap: None,
+            strong: None,
         }
     }
+
+    /// Client for `criterion`'s hint, or the default when there is no hint
+    /// or no model configured for it
+    fn for_criterion(&self, criterion: &Criterion) -> &dyn ModelClient {
+        let hinted = match criterion.model_hint {
+            Some(ModelHint::Cheap) => self.cheap.as_deref(),
+            Some(ModelHint::Strong) => self.strong.as_deref(),
+            None => None,
+        };
+        hinted.unwrap_or(self.default.as_ref())
+    }
 }
 
+/// Each grade has its own lock so concurrent grades don't contend on the map
+type SharedGradeState = Arc<RwLock<GradeState>>;
+
+type GradeMap = Arc<RwLock<HashMap<String, SharedGradeState>>>;
+
 #[derive(Clone)]
 pub struct GradeStore {
-    grades: Arc<RwLock<HashMap<String, GradeState>>>,
-    ttl_secs: u64,
+    grades: GradeMap,
+    /// Grades held in memory before the least recently used finished ones
+    /// are evicted
+    max_entries: Arc<AtomicUsize>,
     providers_config: Option<ProvidersConfig>,
     default_config: GradeConfig,
     grade_repo: Option<Arc<crate::db::GradeRepository>>,
+    /// Grade writes that failed and are retried in the background
+    write_retry: Option<WriteRetryQueue>,
+    audit_repo: Option<Arc<AuditRepository>>,
+    /// Grades re-run when their GitHub branch is pushed to
+    subscription_repo: Option<Arc<SubscriptionRepository>>,
+    prompts: Arc<PromptRegistry>,
+    queue: JobQueue,
+    /// Caps LLM requests across every grade job, not just within one
+    global_llm_semaphore: Arc<Semaphore>,
+    idempotency_keys: IdempotencyKeys,
+    file_filter: FileFilter,
+    test_runner: TestRunnerConfig,
 }
 
 impl GradeStore {
@@ -101,506 +473,2990 @@ impl GradeStore {
         default_config: GradeConfig,
         grade_repo: Option<Arc<crate::db::GradeRepository>>,
     ) -> Self {
+        let max_llm_requests = providers_config
+            .as_ref()
+            .map_or(DEFAULT_MAX_CONCURRENT_LLM_REQUESTS, |p| p.max_concurrent_llm_requests);
+        let grades: GradeMap = Arc::new(RwLock::new(HashMap::new()));
+        let write_retry = grade_repo
+            .as_ref()
+            .map(|repo| Self::spawn_write_retry(Arc::clone(repo), grades.clone()));
         let store = Self {
-            grades: Arc::new(RwLock::new(HashMap::new())),
-            ttl_secs,
+            grades,
+            max_entries: Arc::new(AtomicUsize::new(DEFAULT_MAX_STORED_JOBS)),
             providers_config,
             default_config,
             grade_repo,
+            write_retry,
+            audit_repo: None,
+            subscription_repo: None,
+            prompts: Arc::default(),
+            queue: JobQueue::default(),
+            global_llm_semaphore: Arc::new(Semaphore::new(max_llm_requests.max(1))),
+            idempotency_keys: IdempotencyKeys::new("grade"),
+            file_filter: FileFilter::default(),
+            test_runner: TestRunnerConfig::default(),
         };
 
-        Self::spawn_cleanup_task(store.grades.clone(), ttl_secs);
+        Self::spawn_cleanup_task(store.grades.clone(), ttl_secs, store.max_entries.clone());
+        store.spawn_queue_watcher();
         store
     }
 
-    fn spawn_cleanup_task(grades: Arc<RwLock<HashMap<String, GradeState>>>, ttl_secs: u64) {
+    pub fn with_audit_repo(mut self, audit_repo: Option<Arc<AuditRepository>>) -> Self {
+        self.audit_repo = audit_repo;
+        self
+    }
+
+    pub fn with_idempotency_repo(mut self, repo: Option<Arc<IdempotencyRepository>>) -> Self {
+        self.idempotency_keys = self.idempotency_keys.with_repo(repo);
+        self
+    }
+
+    pub fn idempotency_keys(&self) -> &IdempotencyKeys {
+        &self.idempotency_keys
+    }
+
+    pub fn with_prompts(mut self, prompts: Arc<PromptRegistry>) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Which files checkers and the grading context leave out
+    pub fn with_file_filter(mut self, file_filter: FileFilter) -> Self {
+        self.file_filter = file_filter;
+        self
+    }
+
+    /// Whether `run_tests` tasks run the submission's tests, and for how long
+    /// at most
+    pub fn with_test_runner(mut self, test_runner: TestRunnerConfig) -> Self {
+        self.test_runner = test_runner;
+        self
+    }
+
+    /// Keep at most `max` grades in memory, evicting the least recently read
+    /// finished ones first. Running grades are never evicted.
+    pub fn with_max_entries(self, max: usize) -> Self {
+        self.max_entries.store(max, Ordering::Relaxed);
+        self
+    }
+
+    pub fn with_subscription_repo(mut self, subscription_repo: Option<Arc<SubscriptionRepository>>) -> Self {
+        self.subscription_repo = subscription_repo;
+        self
+    }
+
+    /// Run at most `workers` grades at once, with up to `capacity` waiting
+    pub fn with_job_limits(mut self, workers: usize, capacity: usize) -> Self {
+        self.queue = JobQueue::new(workers, capacity);
+        self.spawn_queue_watcher();
+        self
+    }
+
+    /// Emit `Queued` events as waiting grades move up the queue
+    fn spawn_queue_watcher(&self) {
+        let grades = self.grades.clone();
+        let mut waiting = self.queue.subscribe();
         tokio::spawn(async move {
-            let mut cleanup_interval = interval(Duration::from_secs(60));
-            loop {
-                cleanup_interval.tick().await;
-                Self::cleanup_expired(&grades, ttl_secs).await;
+            while waiting.changed().await.is_ok() {
+                let ids = waiting.borrow_and_update().clone();
+                let states: Vec<_> = {
+                    let grades = grades.read().await;
+                    ids.iter().map(|id| grades.get(id).cloned()).collect()
+                };
+                for (index, state) in states.into_iter().enumerate() {
+                    if let Some(state) = state {
+                        state.write().await.update_queue_position(index + 1);
+                    }
+                }
             }
         });
     }
 
-    async fn cleanup_expired(grades: &Arc<RwLock<HashMap<String, GradeState>>>, ttl_secs: u64) {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+    /// The config of `request` over this store's defaults
+    pub fn config_for(&self, request: &GradeRequest) -> GradeConfig {
+        request.config_or(&self.default_config)
+    }
 
-        let mut grades = grades.write().await;
-        grades.retain(|_, state| now - state.created_at < ttl_secs);
+    /// How to clone the repository of `request`, with enough history for
+    /// its `base_branch`
+    pub fn clone_options(&self, request: &GradeRequest) -> CloneOptions {
+        let config = self.config_for(request);
+        CloneOptions::default()
+            .with_depth(config.clone_depth)
+            .with_base_branch(request.base_branch.clone())
     }
 
-    fn create_llm_client(&self) -> Option<Box<dyn ModelClient>> {
-        let config = self.providers_config.as_ref()?;
+    fn grader(&self, config: &GradeConfig) -> CriteriaChecker {
+        CriteriaChecker::with_limits(config.max_files, config.max_chars_per_file, config.max_total_chars)
+            .with_evidence_mode(config.evidence_mode)
+            .with_prompts(Arc::clone(&self.prompts))
+    }
 
-        // Priority: Anthropic > OpenAI > OpenCode
-        if let Some(ref api_key) = config.anthropic_api_key {
-            return Some(Box::new(AnthropicClient::with_api_key(
-                api_key.expose_secret(),
-            )));
+    /// Record an audit entry in the background; failures are only logged
+    pub fn audit(&self, entry: AuditEntry) {
+        if let Some(repo) = self.audit_repo.clone() {
+            tokio::spawn(async move {
+                if let Err(e) = repo.record(entry).await {
+                    tracing::warn!("Failed to record audit entry: {}", e);
+                }
+            });
         }
+    }
 
-        if let Some(ref api_key) = config.openai_api_key {
-            return Some(Box::new(OpenAIClient::with_api_key(
-                api_key.expose_secret(),
-            )));
-        }
+    /// Retry failed grade writes, marking grades persisted once their report
+    /// is stored
+    fn spawn_write_retry(repo: Arc<crate::db::GradeRepository>, grades: GradeMap) -> WriteRetryQueue {
+        let (persisted_tx, mut persisted_rx) = mpsc::unbounded_channel::<String>();
+        let queue = WriteRetryQueue::start(
+            repo,
+            Duration::from_secs(WRITE_RETRY_INTERVAL_SECS),
+            persisted_tx,
+        );
+        tokio::spawn(async move {
+            while let Some(id) = persisted_rx.recv().await {
+                let state = grades.read().await.get(&id).cloned();
+                if let Some(state) = state {
+                    state.write().await.persisted = true;
+                }
+            }
+        });
+        queue
+    }
 
-        if let Some(ref api_key) = config.opencode_api_key {
-            let base_url = config.opencode_base_url.clone();
-            return Some(Box::new(OpenCodeClient::new(
-                base_url,
-                Some(api_key.expose_secret().to_string()),
-            )));
+    /// Apply a grade write, queueing it for retry if MongoDB is unavailable.
+    /// Never fails the grade.
+    async fn write(&self, write: PendingWrite) {
+        let Some(repo) = &self.grade_repo else {
+            return;
+        };
+        match write.apply(repo).await {
+            Ok(()) => {
+                if write.stores_report() {
+                    if let Some(state) = self.state(write.grade_id()).await {
+                        state.write().await.persisted = true;
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!(
+                    "MongoDB write for grade {} failed, retrying in the background: {}",
+                    write.grade_id(),
+                    e
+                );
+                if let Some(retry) = &self.write_retry {
+                    retry.push(write);
+                }
+            }
         }
-
-        None
     }
 
-    pub async fn create_grade(&self, request: GradeRequest) -> String {
-        let id = uuid::Uuid::new_v4().to_string();
-        let state = GradeState::new(id.clone(), &request);
+    fn spawn_cleanup_task(grades: GradeMap, ttl_secs: u64, max_entries: Arc<AtomicUsize>) {
+        tokio::spawn(async move {
+            let mut cleanup_interval = interval(Duration::from_secs(60));
+            loop {
+                cleanup_interval.tick().await;
+                Self::cleanup_expired(&grades, ttl_secs, max_entries.load(Ordering::Relaxed)).await;
+            }
+        });
+    }
 
-        let total_criteria: usize = request
-            .tasks
-            .iter()
-            .map(|t| t.acceptance_criteria.len())
-            .sum();
+    /// Drop grades past their TTL, then the least recently used ones over
+    /// `max_entries`
+    async fn cleanup_expired(grades: &GradeMap, ttl_secs: u64, max_entries: usize) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
 
-        state.emit(GradeEvent::GradeStarted {
-            grade_id: id.clone(),
-            repo_url: request.repo_url.clone(),
-            task_count: request.tasks.len(),
-            total_criteria,
+        let mut grades = grades.write().await;
+        // A grade being updated right now is not expired; keep it for the next pass
+        grades.retain(|_, state| {
+            state
+                .try_read()
+                .map_or(true, |state| now - state.created_at < ttl_secs)
         });
+        Self::evict_lru(&mut grades, max_entries);
+    }
 
-        let mut grades = self.grades.write().await;
-        grades.insert(id.clone(), state);
-
-        if let Some(ref repo) = self.grade_repo {
-            if let Err(e) = repo
-                .save_grade_job(
-                    &request,
-                    request.curriculum_id.clone(),
-                    request.task_id.clone(),
-                )
-                .await
-            {
-                tracing::error!("Failed to save grade job to MongoDB: {}", e);
-            }
+    fn evict_lru(grades: &mut HashMap<String, SharedGradeState>, max_entries: usize) {
+        // Grades locked for an update are in use; leave them for the next pass
+        let evicted = lru_evictions(
+            grades
+                .iter()
+                .map(|(id, state)| (id, state.try_read().ok().and_then(|s| s.evictable_since()))),
+            max_entries,
+        );
+        for id in evicted {
+            grades.remove(&id);
         }
-
-        id
     }
 
-    pub async fn get_grade(&self, id: &str) -> Option<GradeReport> {
+    /// Look up a grade's state, holding the map lock only for the lookup
+    async fn state(&self, id: &str) -> Option<SharedGradeState> {
         let grades = self.grades.read().await;
-        grades.get(id).map(|state| state.to_report())
+        let state = grades.get(id).cloned()?;
+        // A grade locked for an update is running, and never evicted anyway
+        if let Ok(state) = state.try_read() {
+            state.touch();
+        }
+        Some(state)
     }
 
-    pub async fn subscribe(&self, id: &str) -> Option<broadcast::Receiver<GradeEvent>> {
-        let grades = self.grades.read().await;
-        grades.get(id).map(|state| state.subscribe())
+    async fn event_sender(&self, id: &str) -> Option<EventLog<GradeEvent>> {
+        let state = self.state(id).await?;
+        let state = state.read().await;
+        Some(state.event_sender())
     }
 
-    pub async fn run_grade(&self, id: &str, request: GradeRequest) -> Result<(), ApiError> {
-        let start = Instant::now();
-        let config = request.config.clone().unwrap_or(self.default_config.clone());
+    fn create_llm_client(
+        &self,
+        grade_config: &GradeConfig,
+        model: Option<&str>,
+    ) -> Option<Box<dyn ModelClient>> {
+        let config = self.providers_config.as_ref()?;
+        let options = ProviderOptions {
+            model,
+            thinking_budget: grade_config
+                .use_extended_thinking
+                .then_some(EXTENDED_THINKING_BUDGET),
+        };
+        let client = MultiProviderClient::from_config_with(config, options)?
+            .with_logging(config.llm_log, config.secrets());
+        Some(Box::new(client))
+    }
 
-        {
-            let mut grades = self.grades.write().await;
-            if let Some(state) = grades.get_mut(id) {
-                state.status = GradeStatus::Cloning;
-                state.emit(GradeEvent::CloningStarted);
-            } else {
-                return Err(ApiError::NotFound(format!("Grade {} not found", id)));
-            }
+    /// Clients for grading `request`. Criteria use `LLM_MODEL_GRADER` when set;
+    /// hinted ones get the configured cheap or strong model, unless the
+    /// request pins one model for everything.
+    fn grader_clients(&self, config: &GradeConfig, request: &GradeRequest) -> Option<GraderClients> {
+        let providers = self.providers_config.as_ref()?;
+        let model = request.model.as_deref().or(providers.grader_model.as_deref());
+        let mut clients = GraderClients::new(self.create_llm_client(config, model)?);
+        if request.model.is_some() {
+            return Some(clients);
         }
 
-        let clone_start = Instant::now();
-        let cloned_repo = ClonedRepo::from_url(&request.repo_url).await?;
-        let repo_path = cloned_repo.path.clone();
+        let hinted = |hint: ModelHint, model: &Option<String>| {
+            let used = request
+                .tasks
+                .iter()
+                .flat_map(|task| &task.acceptance_criteria)
+                .any(|criterion| criterion.model_hint == Some(hint));
+            model
+                .as_deref()
+                .filter(|_| used)
+                .and_then(|model| self.create_llm_client(config, Some(model)))
+        };
+        clients.cheap = hinted(ModelHint::Cheap, &providers.cheap_model);
+        clients.strong = hinted(ModelHint::Strong, &providers.strong_model);
+        Some(clients)
+    }
 
-        {
-            let mut grades = self.grades.write().await;
-            if let Some(state) = grades.get_mut(id) {
-                state.emit(GradeEvent::CloningCompleted {
-                    duration_ms: clone_start.elapsed().as_millis() as u64,
-                });
-                state.status = GradeStatus::Analyzing;
-                state.emit(GradeEvent::AnalysisStarted);
-            }
+    /// Reject a pinned model the configured provider doesn't allow. Without a
+    /// provider there is nothing to check; the grade fails when it runs.
+    pub fn validate_model(&self, model: Option<&str>) -> Result<(), ApiError> {
+        let Some(model) = model else {
+            return Ok(());
+        };
+        let Some(client) = self.create_llm_client(&self.default_config, None) else {
+            return Ok(());
+        };
+        match allowed_models(client.provider()) {
+            Some(allowed) if !allowed.contains(&model) => Err(ApiError::BadRequest(format!(
+                "Model '{}' is not allowed for provider {}; allowed: {}",
+                model,
+                client.provider(),
+                allowed.join(", ")
+            ))),
+            _ => Ok(()),
         }
+    }
 
-        let files = Self::read_source_files(&repo_path, config.max_files);
-        let total_lines: usize = files.iter().map(|(_, c)| c.lines().count()).sum();
+    pub async fn create_grade(&self, request: GradeRequest) -> String {
+        self.create_grade_with_id(uuid::Uuid::new_v4().to_string(), request).await
+    }
 
-        {
-            let mut grades = self.grades.write().await;
-            if let Some(state) = grades.get_mut(id) {
-                state.emit(GradeEvent::AnalysisCompleted {
-                    file_count: files.len(),
-                    total_lines,
-                });
-                state.status = GradeStatus::Grading;
-            }
-        }
+    /// Create a grade under an id reserved beforehand, e.g. by an
+    /// idempotency key
+    pub async fn create_grade_with_id(&self, id: String, request: GradeRequest) -> String {
+        self.insert_grade(id, request, None, None).await
+    }
 
-        let llm_client = self.create_llm_client().ok_or_else(|| {
-            ApiError::InternalError("No LLM provider configured".to_string())
+    /// Create a grade of a persisted job's commit with replacement tasks.
+    /// Returns the new grade id and its request, ready for [`Self::run_grade`].
+    pub async fn create_regrade(
+        &self,
+        original_id: &str,
+        tasks: Vec<GradeTask>,
+    ) -> Result<(String, GradeRequest), ApiError> {
+        let repo = self.grade_repo.as_ref().ok_or_else(|| {
+            ApiError::BadRequest("Re-grading requires MongoDB persistence".to_string())
         })?;
 
-        let grader = CriteriaChecker::with_limits(config.max_files, config.max_chars_per_file);
-        let task_results = self
-            .process_tasks_parallel(
-                id,
-                &request.tasks,
-                &files,
-                &request.repo_url,
-                llm_client.as_ref(),
-                &grader,
-                &config,
+        let job = repo
+            .find_by_grade_id(original_id)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to load grade job: {}", e)))?
+            .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", original_id)))?;
+
+        let mut request: GradeRequest = bson::from_document(job.request).map_err(|e| {
+            ApiError::InternalError(format!("Stored grade request is invalid: {}", e))
+        })?;
+        request.tasks = tasks;
+
+        // Without a recorded commit (e.g. the original failed early) the
+        // current HEAD is graded
+        let id = self
+            .insert_grade(
+                uuid::Uuid::new_v4().to_string(),
+                request.clone(),
+                Some(original_id.to_string()),
+                job.commit_sha,
             )
             .await;
+        Ok((id, request))
+    }
 
-        let (overall_score, percentage, grade, summary) =
-            Self::calculate_final_score(&task_results);
-
-        {
-            let mut grades = self.grades.write().await;
-            if let Some(state) = grades.get_mut(id) {
-                state.task_results = task_results;
-                state.overall_score = overall_score;
-                state.percentage = percentage;
-                state.grade = grade.clone();
-                state.summary = summary.clone();
-                state.status = GradeStatus::Completed;
-                state.duration_ms = start.elapsed().as_millis() as u64;
+    /// Record an instructor's verdict on a criterion of a completed,
+    /// persisted grade and rescore it. Returns the rescored report.
+    pub async fn override_criterion(
+        &self,
+        id: &str,
+        task_index: usize,
+        criterion_index: usize,
+        verdict: CriterionOverrideRequest,
+    ) -> Result<GradeReport, ApiError> {
+        let repo = self.grade_repo.as_ref().ok_or_else(|| {
+            ApiError::BadRequest("Overriding criteria requires MongoDB persistence".to_string())
+        })?;
 
-                state.emit(GradeEvent::GradeCompleted {
-                    overall_score,
-                    percentage,
-                    grade,
-                    summary,
-                    duration_ms: state.duration_ms,
+        let job = repo
+            .find_by_grade_id(id)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to load grade job: {}", e)))?
+            .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", id)))?;
+        let request: GradeRequest = bson::from_document(job.request).map_err(|e| {
+            ApiError::InternalError(format!("Stored grade request is invalid: {}", e))
+        })?;
+        let config = self.config_for(&request);
+        let verdict = CriterionOverride {
+            passed: verdict.passed,
+            note: verdict.note,
+            overridden_at: unix_now(),
+        };
+        let not_completed = || ApiError::Conflict(format!("Grade {} has not completed", id));
+
+        // Rescore in memory when the grade is still there, since its report
+        // may be ahead of a write that is being retried
+        let (report, events) = match self.state(id).await {
+            Some(state) => {
+                let mut state = state.write().await;
+                if state.status != GradeStatus::Completed {
+                    return Err(not_completed());
+                }
+                let late_penalty = state.minutes_late.and(config.late_penalty_percentage).unwrap_or(0);
+                let tasks = state.tasks.clone();
+                let score = Self::apply_override(
+                    &tasks,
+                    &mut state.task_results,
+                    task_index,
+                    criterion_index,
+                    verdict,
+                    config.unverifiable_policy,
+                    late_penalty,
+                )?;
+                state.overall_score = score.overall_score;
+                state.percentage = score.percentage;
+                state.grade = score.grade.clone();
+                state.summary = score.summary.clone();
+                state.rubric_breakdown = score.rubric_breakdown;
+                state.emit(GradeEvent::GradeRescored {
+                    task_index,
+                    criterion_index,
+                    overall_score: score.overall_score,
+                    percentage: score.percentage,
+                    grade: score.grade,
+                    summary: score.summary,
                 });
+                (state.to_report(), state.events())
+            }
+            None => {
+                let mut report: GradeReport = bson::from_document(job.result.ok_or_else(not_completed)?)
+                    .map_err(|e| ApiError::InternalError(format!("Stored grade report is invalid: {}", e)))?;
+                if report.status != GradeStatus::Completed {
+                    return Err(not_completed());
+                }
+                let late_penalty = report.minutes_late.and(config.late_penalty_percentage).unwrap_or(0);
+                let score = Self::apply_override(
+                    &request.tasks,
+                    &mut report.tasks,
+                    task_index,
+                    criterion_index,
+                    verdict,
+                    config.unverifiable_policy,
+                    late_penalty,
+                )?;
+                report.overall_score = score.overall_score;
+                report.percentage = score.percentage;
+                report.grade = score.grade;
+                report.summary = score.summary;
+                report.rubric_breakdown = score.rubric_breakdown;
+                (report, job.events)
             }
+        };
+
+        self.write(PendingWrite::UpdateGradeJob {
+            report: Box::new(report.clone()),
+            events,
+            fingerprint: None,
+        })
+        .await;
+        if let (Some(curriculum_id), Some(task_id)) = (job.curriculum_id, job.task_id) {
+            self.write(PendingWrite::UpdateTaskGrade {
+                curriculum_id,
+                task_id,
+                report: Box::new(report.clone()),
+            })
+            .await;
         }
+        Ok(report)
+    }
 
-        if let Some(ref repo) = self.grade_repo {
-            let report = self.get_grade(id).await;
-            if let Some(report) = report {
-                if let Err(e) = repo.update_grade_job(id, &report).await {
-                    tracing::error!("Failed to update grade job in MongoDB: {}", e);
-                }
+    /// How often instructor overrides agreed with the grader, per confidence
+    /// bucket, across every persisted grade
+    pub async fn calibration(&self) -> Result<CalibrationReport, ApiError> {
+        let repo = self.grade_repo.as_ref().ok_or_else(|| {
+            ApiError::BadRequest("Calibration requires MongoDB persistence".to_string())
+        })?;
+        let counts = repo
+            .override_calibration()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to aggregate overrides: {}", e)))?;
+        Ok(CalibrationReport::from_counts(counts))
+    }
 
-                if let (Some(curriculum_id), Some(task_id)) =
-                    (&request.curriculum_id, &request.task_id)
-                {
-                    if let Err(e) = repo.update_task_grade(curriculum_id, task_id, &report).await {
-                        tracing::error!("Failed to update task grade in MongoDB: {}", e);
-                    }
-                }
+    /// Expected duration of `request`, from completed grades with as many
+    /// tasks. `None` without MongoDB or enough history.
+    pub async fn estimate_duration_ms(&self, request: &GradeRequest) -> Option<u64> {
+        let repo = self.grade_repo.as_ref()?;
+        match repo.average_duration_by_task_count(request.tasks.len()).await {
+            Ok(estimate) => estimate,
+            Err(e) => {
+                tracing::warn!("Failed to estimate grade duration: {}", e);
+                None
             }
         }
-
-        Ok(())
     }
 
-    async fn process_tasks_parallel(
+    /// Pairs of fingerprinted submissions in the course titled `course_title`
+    /// that are at least `threshold` similar, comparing the latest grade of
+    /// each repository
+    pub async fn similarity(
+        &self,
+        course_title: &str,
+        threshold: f32,
+        limit: usize,
+    ) -> Result<SimilarityReport, ApiError> {
+        let repo = self.grade_repo.as_ref().ok_or_else(|| {
+            ApiError::BadRequest("Similarity detection requires MongoDB persistence".to_string())
+        })?;
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(ApiError::BadRequest("threshold must be between 0 and 1".to_string()));
+        }
+        let mut jobs = repo
+            .find_fingerprinted(course_title)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to load grade jobs: {}", e)))?;
+
+        // Newest first, so re-grades shadow earlier grades of the same repository
+        let mut seen = HashSet::new();
+        jobs.retain(|job| seen.insert(job.repo_url.clone()));
+        let jobs: Vec<_> = jobs
+            .into_iter()
+            .filter_map(|job| Some((job.grade_id?, job.repo_url, job.fingerprint?)))
+            .collect();
+
+        let submissions = jobs.len();
+
+        // Comparing every pair is quadratic, so it stays off the async workers
+        let (total_pairs, pairs) = tokio::task::spawn_blocking(move || {
+            let fingerprints: Vec<_> = jobs.iter().map(|(_, _, fingerprint)| fingerprint).collect();
+            let pairs = similar_pairs(&fingerprints, threshold);
+            let total_pairs = pairs.len();
+            let pairs = pairs
+                .into_iter()
+                .take(limit)
+                .map(|(i, j, similarity)| SimilarPair {
+                    grade_ids: (jobs[i].0.clone(), jobs[j].0.clone()),
+                    repo_urls: (jobs[i].1.clone(), jobs[j].1.clone()),
+                    similarity,
+                    overlapping_files: jobs[i].2.shared_files(&jobs[j].2),
+                })
+                .collect();
+            (total_pairs, pairs)
+        })
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Similarity comparison failed: {}", e)))?;
+
+        Ok(SimilarityReport {
+            course_title: course_title.to_string(),
+            threshold,
+            submissions,
+            total_pairs,
+            pairs,
+        })
+    }
+
+    /// Re-grade a persisted grade's branch with the same request whenever
+    /// it is pushed to. Subscribing again replaces the subscription.
+    pub async fn subscribe_regrades(&self, grade_id: &str) -> Result<(), ApiError> {
+        let (Some(grade_repo), Some(subscriptions)) = (&self.grade_repo, &self.subscription_repo) else {
+            return Err(ApiError::BadRequest(
+                "Auto re-grading requires MongoDB persistence".to_string(),
+            ));
+        };
+
+        let job = grade_repo
+            .find_by_grade_id(grade_id)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to load grade job: {}", e)))?
+            .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", grade_id)))?;
+        let (owner, repo) = extract_github_info(&job.repo_url).ok_or_else(|| {
+            ApiError::BadRequest("Auto re-grading only supports GitHub repositories".to_string())
+        })?;
+
+        let subscription = RegradeSubscription {
+            id: None,
+            repo: format!("{}/{}", owner, repo).to_lowercase(),
+            branch: job.branch,
+            grade_id: grade_id.to_string(),
+            latest_grade_id: grade_id.to_string(),
+            request: job.request,
+            created_at: bson::DateTime::now(),
+        };
+        subscriptions
+            .subscribe(&subscription)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to save subscription: {}", e)))
+    }
+
+    /// Stop auto re-grading the subscription `grade_id` started or last re-graded
+    pub async fn unsubscribe_regrades(&self, grade_id: &str) -> Result<(), ApiError> {
+        let Some(subscriptions) = &self.subscription_repo else {
+            return Err(ApiError::BadRequest(
+                "Auto re-grading requires MongoDB persistence".to_string(),
+            ));
+        };
+
+        let removed = subscriptions
+            .unsubscribe(grade_id)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to remove subscription: {}", e)))?;
+        if !removed {
+            return Err(ApiError::NotFound(format!("Grade {} has no subscription", grade_id)));
+        }
+        Ok(())
+    }
+
+    /// Create a re-grade of the pushed commit for every subscription on the
+    /// pushed branch. Returns the new grades, ready for [`Self::enqueue_grade`].
+    pub async fn regrade_on_push(&self, push: &PushEvent) -> Result<Vec<(String, GradeRequest)>, ApiError> {
+        let Some(subscriptions) = &self.subscription_repo else {
+            return Ok(Vec::new());
+        };
+
+        let matching = subscriptions
+            .find_by_repo(&push.repo())
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to load subscriptions: {}", e)))?
+            .into_iter()
+            .filter(|subscription| push.updates_branch(subscription.branch.as_deref()));
+
+        let mut regrades = Vec::new();
+        for subscription in matching {
+            let request: GradeRequest = match bson::from_document(subscription.request) {
+                Ok(request) => request,
+                Err(e) => {
+                    tracing::warn!("Subscription of grade {} has an invalid request: {}", subscription.grade_id, e);
+                    continue;
+                }
+            };
+            let id = self
+                .insert_grade(
+                    uuid::Uuid::new_v4().to_string(),
+                    request.clone(),
+                    Some(subscription.latest_grade_id),
+                    Some(push.after.clone()),
+                )
+                .await;
+            if let Some(subscription_id) = subscription.id {
+                if let Err(e) = subscriptions.set_latest_grade(subscription_id, &id).await {
+                    tracing::warn!("Failed to link re-grade {} to its subscription: {}", id, e);
+                }
+            }
+            regrades.push((id, request));
+        }
+        Ok(regrades)
+    }
+
+    /// Create one grade per request under a new batch id, all inserted under
+    /// a single lock. Returns the batch id and the grade ids in request order.
+    pub async fn create_grade_batch(&self, requests: &[GradeRequest]) -> (String, Vec<String>) {
+        let batch_id = uuid::Uuid::new_v4().to_string();
+        let states: Vec<GradeState> = requests
+            .iter()
+            .map(|request| {
+                let mut state = self.new_grade_state(uuid::Uuid::new_v4().to_string(), request, None, None);
+                state.batch_id = Some(batch_id.clone());
+                state
+            })
+            .collect();
+        let ids: Vec<String> = states.iter().map(|state| state.id.clone()).collect();
+
+        {
+            let mut grades = self.grades.write().await;
+            for state in states {
+                grades.insert(state.id.clone(), Arc::new(RwLock::new(state)));
+            }
+            Self::evict_lru(&mut grades, self.max_entries.load(Ordering::Relaxed));
+        }
+
+        for (id, request) in ids.iter().zip(requests) {
+            self.write(PendingWrite::SaveGradeJob {
+                grade_id: id.clone(),
+                regrade_of: None,
+                request: Box::new(request.clone()),
+            })
+            .await;
+        }
+
+        (batch_id, ids)
+    }
+
+    /// Reports of a batch's grades still in memory, oldest first
+    pub async fn batch_grades(&self, batch_id: &str) -> Vec<GradeReport> {
+        let states: Vec<SharedGradeState> = self.grades.read().await.values().cloned().collect();
+        let mut reports = Vec::new();
+        for state in states {
+            let state = state.read().await;
+            if state.batch_id.as_deref() == Some(batch_id) {
+                reports.push(state.to_report());
+            }
+        }
+        reports.sort_by(|a, b| (a.created_at, &a.repo_url).cmp(&(b.created_at, &b.repo_url)));
+        reports
+    }
+
+    /// Grades that can be queued before new ones are rejected
+    pub fn queue_free_slots(&self) -> usize {
+        self.queue.free_slots()
+    }
+
+    async fn insert_grade(
+        &self,
+        id: String,
+        request: GradeRequest,
+        regrade_of: Option<String>,
+        commit_sha: Option<String>,
+    ) -> String {
+        let state = self.new_grade_state(id.clone(), &request, regrade_of.clone(), commit_sha);
+
+        {
+            let mut grades = self.grades.write().await;
+            grades.insert(id.clone(), Arc::new(RwLock::new(state)));
+            Self::evict_lru(&mut grades, self.max_entries.load(Ordering::Relaxed));
+        }
+
+        self.write(PendingWrite::SaveGradeJob {
+            grade_id: id.clone(),
+            regrade_of,
+            request: Box::new(request),
+        })
+        .await;
+
+        id
+    }
+
+    /// State of a new grade with its `GradeStarted` event emitted
+    fn new_grade_state(
+        &self,
+        id: String,
+        request: &GradeRequest,
+        regrade_of: Option<String>,
+        commit_sha: Option<String>,
+    ) -> GradeState {
+        let config = self.config_for(request);
+        let capacity = GradeState::event_capacity(request, &config);
+        let mut state = GradeState::with_capacity(id.clone(), request, capacity);
+        state.regrade_of = regrade_of;
+        state.commit_sha = commit_sha;
+
+        let total_criteria: usize = request
+            .tasks
+            .iter()
+            .map(|t| t.acceptance_criteria.len())
+            .sum();
+
+        state.emit(GradeEvent::GradeStarted {
+            grade_id: id,
+            repo_url: request.repo_url.clone(),
+            task_count: request.tasks.len(),
+            total_criteria,
+        });
+        state
+    }
+
+    /// Validate a grade request and estimate its LLM usage without cloning the
+    /// repository, calling an LLM or storing any state
+    pub fn dry_run(&self, request: &GradeRequest) -> DryRunResult {
+        let config = self.config_for(request);
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        if !is_valid_repo_url(&request.repo_url) {
+            errors.push(format!("invalid repo_url: '{}'", request.repo_url));
+        }
+
+        if request.tasks.is_empty() {
+            errors.push("tasks cannot be empty".to_string());
+        }
+
+        for (index, task) in request.tasks.iter().enumerate() {
+            if task.acceptance_criteria.is_empty() {
+                warnings.push(format!(
+                    "task {} ('{}') has no acceptance criteria",
+                    index, task.title
+                ));
+                continue;
+            }
+
+            if task
+                .acceptance_criteria
+                .iter()
+                .any(|c| c.description.trim().is_empty())
+            {
+                errors.push(format!(
+                    "task {} ('{}') has a criterion with an empty description",
+                    index, task.title
+                ));
+            }
+
+            if task.acceptance_criteria.iter().any(|c| c.weight < 0.0) {
+                errors.push(format!(
+                    "task {} ('{}') has a criterion with a negative weight",
+                    index, task.title
+                ));
+            }
+
+            let total_weight: f32 = task.acceptance_criteria.iter().map(|c| c.weight).sum();
+            if total_weight <= 0.0 {
+                errors.push(format!(
+                    "task {} ('{}'): criterion weights must sum to a positive value",
+                    index, task.title
+                ));
+            }
+        }
+
+        Self::validate_config(&config, &mut errors, &mut warnings);
+
+        let estimated_llm_calls: usize = request
+            .tasks
+            .iter()
+            .map(|t| t.acceptance_criteria.len())
+            .sum();
+        let chars_per_call = Self::max_code_chars(&config).saturating_add(PROMPT_OVERHEAD_CHARS);
+        let estimated_prompt_chars = estimated_llm_calls.saturating_mul(chars_per_call);
+        let estimated_prompt_tokens = estimate_tokens_from_chars(estimated_prompt_chars);
+
+        DryRunResult {
+            valid: errors.is_empty(),
+            errors,
+            warnings,
+            estimated_llm_calls,
+            estimated_cost_usd: Self::estimate_cost(&config, estimated_prompt_tokens),
+            estimated_prompt_chars,
+            estimated_prompt_tokens,
+            files: Vec::new(),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Dry run that collects files exactly as `run_grade` would (cloning the
+    /// repo unless `skip_clone` is set) and builds per-criterion prompt estimates.
+    /// No LLM is called and no state is stored.
+    pub async fn dry_run_with_context(
+        &self,
+        request: &DryRunRequest,
+    ) -> Result<DryRunResult, ApiError> {
+        let mut result = self.dry_run(&request.grade);
+        if !result.valid {
+            return Ok(result);
+        }
+
+        let grade = &request.grade;
+        let config = self.config_for(grade);
+
+        let mut placeholder_chars = 0;
+        let (files, tree, profile) = if request.skip_clone {
+            let (files, chars) = Self::placeholder_files(request, &config);
+            placeholder_chars = chars;
+            let profile = RepoProfile::from_paths(files.iter().map(|(path, _)| Path::new(path)));
+            (files, RepoTree::default(), profile)
+        } else {
+            // Cloned on the grade queue so dry runs share its worker limit
+            let (sender, receiver) = oneshot::channel();
+            let store = self.clone();
+            let job_request = grade.clone();
+            let job_config = config.clone();
+            self.queue.try_enqueue(&format!("dry-run-{}", uuid::Uuid::new_v4()), async move {
+                let collected = store.collect_dry_run_files(&job_request, job_config).await;
+                let _ = sender.send(collected);
+            })?;
+            let (files, tree, profile) = receiver
+                .await
+                .map_err(|_| ApiError::InternalError("Dry run job did not finish".to_string()))??;
+            result.files = files.iter().map(|(path, _)| path.clone()).collect();
+            (files, tree, profile)
+        };
+        let files = Arc::new(merge_provided_files(&grade.context_files, files));
+        let tree = Arc::new(tree);
+
+        let grader = self.grader(&config);
+        let mut total_chars = 0;
+
+        result.tasks = grade
+            .tasks
+            .iter()
+            .map(|task| {
+                let context = GradeContext::new(grade.repo_url.clone(), task.clone())
+                    .with_files(Arc::clone(&files))
+                    .with_tree(Arc::clone(&tree))
+                    .with_language(grade.language.as_deref().unwrap_or(DEFAULT_LANGUAGE))
+                    .with_profile(profile.clone());
+                let criteria: Vec<CriterionDryRunEstimate> = task
+                    .acceptance_criteria
+                    .iter()
+                    .map(|criterion| {
+                        let prompt_chars =
+                            grader.prompt_chars(&context, criterion).saturating_add(placeholder_chars);
+                        let prompt_tokens = estimate_tokens_from_chars(prompt_chars);
+                        total_chars = prompt_chars.saturating_add(total_chars);
+                        CriterionDryRunEstimate {
+                            criterion: criterion.description.clone(),
+                            prompt_chars,
+                            prompt_tokens,
+                            estimated_cost_usd: Self::estimate_cost(&config, prompt_tokens),
+                        }
+                    })
+                    .collect();
+                TaskDryRunEstimate {
+                    task_title: task.title.clone(),
+                    llm_calls: criteria.len(),
+                    criteria,
+                }
+            })
+            .collect();
+
+        result.estimated_prompt_chars = total_chars;
+        result.estimated_prompt_tokens = estimate_tokens_from_chars(total_chars);
+        result.estimated_cost_usd = Self::estimate_cost(&config, result.estimated_prompt_tokens);
+
+        Ok(result)
+    }
+
+    /// Clone the repository of `request` and collect its redacted files as
+    /// `run_grade` would, walking the checkout on a blocking thread
+    async fn collect_dry_run_files(
+        &self,
+        request: &GradeRequest,
+        config: GradeConfig,
+    ) -> Result<(Vec<(String, String)>, RepoTree, RepoProfile), ApiError> {
+        let options = self.clone_options(request);
+        let cloned_repo = ClonedRepo::from_url_with_options(&request.repo_url, options).await?;
+        let changed = Self::changed_paths(&cloned_repo.path, request.base_branch.as_deref()).await?;
+        let redactor = Self::redactor(request, &config)?;
+        let path = cloned_repo.path.clone();
+        let filter = self.file_filter;
+        tokio::task::spawn_blocking(move || {
+            let profile = RepoProfile::detect(&path, filter);
+            let (files, tree) =
+                Self::read_source_files(&path, &config, &profile, changed.as_ref(), filter);
+            let files = match redactor {
+                Some(redactor) => redactor.redact_files(files),
+                None => files,
+            };
+            (files, tree, profile)
+        })
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Reading source files failed: {}", e)))
+    }
+
+    fn estimate_cost(config: &GradeConfig, tokens: usize) -> Option<f32> {
+        config
+            .cost_per_1k_tokens_usd
+            .map(|rate| tokens as f32 / 1000.0 * rate)
+    }
+
+    /// Synthetic file set for `skip_clone` dry runs: empty files named like
+    /// the estimated ones, plus the chars of code they would add to a prompt.
+    /// Content is capped at `max_chars_per_file` since anything beyond that
+    /// is truncated anyway.
+    fn placeholder_files(request: &DryRunRequest, config: &GradeConfig) -> (Vec<(String, String)>, usize) {
+        let count = request
+            .estimated_file_count
+            .unwrap_or(config.max_files)
+            .min(config.max_files);
+        let chars = request
+            .estimated_file_chars
+            .unwrap_or(config.max_chars_per_file)
+            .min(config.max_chars_per_file);
+
+        let files = (0..count).map(|i| (format!("file_{}", i), String::new())).collect();
+        (files, count.saturating_mul(chars).min(config.max_total_chars))
+    }
+
+    /// Most chars of code a prompt can include under `config`
+    fn max_code_chars(config: &GradeConfig) -> usize {
+        config.max_files.saturating_mul(config.max_chars_per_file).min(config.max_total_chars)
+    }
+
+    /// Redactor for the submission's files, when `redact_pii` is set
+    fn redactor(request: &GradeRequest, config: &GradeConfig) -> Result<Option<PiiRedactor>, ApiError> {
+        if !config.redact_pii {
+            return Ok(None);
+        }
+        let student_id = request.metadata.as_ref().and_then(|m| m.student_id.as_deref());
+        PiiRedactor::new(config.student_id_pattern.as_deref(), student_id)
+            .map(Some)
+            .map_err(|e| ApiError::BadRequest(format!("Invalid student_id_pattern: {}", e)))
+    }
+
+    /// Errors in the effective config of `request`, as a dry run reports them
+    pub fn config_errors(&self, request: &GradeRequest) -> Vec<String> {
+        let mut errors = Vec::new();
+        Self::validate_config(&self.config_for(request), &mut errors, &mut Vec::new());
+        errors
+    }
+
+    fn validate_config(config: &GradeConfig, errors: &mut Vec<String>, warnings: &mut Vec<String>) {
+        let required = [
+            ("max_parallel_tasks", config.max_parallel_tasks),
+            ("max_parallel_criteria", config.max_parallel_criteria),
+            ("max_files", config.max_files),
+            ("max_chars_per_file", config.max_chars_per_file),
+            ("max_total_chars", config.max_total_chars),
+            ("max_llm_failures", config.max_llm_failures),
+            ("criterion_timeout_secs", config.criterion_timeout_secs as usize),
+            ("test_timeout_secs", config.test_timeout_secs as usize),
+            ("max_file_bytes", config.max_file_bytes),
+            ("max_context_bytes", config.max_context_bytes),
+            ("max_total_duration_secs", config.max_total_duration_secs as usize),
+        ];
+        for (name, value) in required {
+            if value == 0 {
+                errors.push(format!("config.{} must be greater than 0", name));
+            }
+        }
+
+        let bounded = [
+            ("max_files", config.max_files, MAX_FILES_LIMIT),
+            ("max_chars_per_file", config.max_chars_per_file, MAX_CHARS_PER_FILE_LIMIT),
+            ("max_total_chars", config.max_total_chars, MAX_TOTAL_CHARS_LIMIT),
+            ("event_buffer_size", config.event_buffer_size, MAX_EVENT_BUFFER_SIZE),
+        ];
+        for (name, value, max) in bounded {
+            if value > max {
+                errors.push(format!("config.{} cannot exceed {}", name, max));
+            }
+        }
+
+        if Self::max_code_chars(config) > 1_000_000 {
+            warnings.push(format!(
+                "config allows up to {} chars of code per prompt, which may exceed the model context",
+                Self::max_code_chars(config)
+            ));
+        }
+
+        if config.late_penalty_percentage.is_some_and(|p| p > 100) {
+            errors.push("config.late_penalty_percentage cannot exceed 100".to_string());
+        }
+
+        if config.cost_per_1k_tokens_usd.is_some_and(|rate| rate < 0.0) {
+            errors.push("config.cost_per_1k_tokens_usd cannot be negative".to_string());
+        }
+
+        if let Err(e) = PiiRedactor::new(config.student_id_pattern.as_deref(), None) {
+            errors.push(format!("config.student_id_pattern is not a valid regex: {}", e));
+        }
+    }
+
+    /// Status and queue position of a grade in memory, or else of its
+    /// MongoDB job
+    pub async fn grade_status(&self, id: &str) -> Option<(GradeStatus, Option<usize>)> {
+        if let Some(state) = self.state(id).await {
+            let state = state.read().await;
+            return Some((state.status, state.queue_position));
+        }
+        match self.grade_repo.as_ref()?.find_by_grade_id(id).await {
+            Ok(job) => job.map(|job| (job.status, None)),
+            Err(e) => {
+                tracing::warn!("Failed to load grade job {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    pub async fn get_grade(&self, id: &str) -> Option<GradeReport> {
+        let state = self.state(id).await?;
+        let state = state.read().await;
+        Some(state.to_report())
+    }
+
+    pub async fn subscribe(&self, id: &str) -> Option<broadcast::Receiver<EventEnvelope<GradeEvent>>> {
+        let state = self.state(id).await?;
+        let state = state.read().await;
+        Some(state.subscribe())
+    }
+
+    /// Event log of a completed or failed grade, from memory or its
+    /// MongoDB job
+    pub async fn grade_events(&self, id: &str) -> Result<Vec<EventEnvelope<GradeEvent>>, ApiError> {
+        let not_found = || ApiError::NotFound(format!("Grade {} not found", id));
+        let finished = |status| matches!(status, GradeStatus::Completed | GradeStatus::Failed);
+        let not_finished = || ApiError::Conflict(format!("Grade {} has not finished", id));
+
+        if let Some(state) = self.state(id).await {
+            let state = state.read().await;
+            if !finished(state.status) {
+                return Err(not_finished());
+            }
+            return Ok(state.events());
+        }
+
+        let repo = self.grade_repo.as_ref().ok_or_else(not_found)?;
+        let job = repo
+            .find_by_grade_id(id)
+            .await
+            .map_err(|e| ApiError::InternalError(format!("Failed to load grade job: {}", e)))?
+            .ok_or_else(not_found)?;
+        if !finished(job.status) {
+            return Err(not_finished());
+        }
+        let mut events = job.events;
+        backfill_seq(&mut events);
+        Ok(events)
+    }
+
+    /// Subscribe to a grade, or get the terminal event it already finished
+    /// with. Checked under one lock so the terminal event can't be missed.
+    pub async fn subscribe_or_finished(&self, id: &str) -> Option<GradeSubscription> {
+        let state = self.state(id).await?;
+        let state = state.read().await;
+        Some(match state.terminal_event() {
+            Some(event) => GradeSubscription::Finished(event),
+            None => GradeSubscription::Live(state.subscribe()),
+        })
+    }
+
+    /// Follow a grade's events until it completes or fails. A grade that
+    /// already finished yields just its terminal event.
+    pub async fn watch_grade(&self, id: &str) -> Result<impl Stream<Item = EventEnvelope<GradeEvent>>, ApiError> {
+        let subscription = self
+            .subscribe_or_finished(id)
+            .await
+            .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", id)))?;
+        Ok(match subscription {
+            GradeSubscription::Live(receiver) => {
+                watch_events(receiver, |count| GradeEvent::EventsDropped { count }, GradeEvent::is_terminal)
+                    .left_stream()
+            }
+            GradeSubscription::Finished(event) => stream::once(ready(event)).right_stream(),
+        })
+    }
+
+    /// Queue a created grade to run, returning its queue position. When the
+    /// queue is full the grade is failed and dropped from memory.
+    pub async fn enqueue_grade(&self, id: &str, request: GradeRequest) -> Result<usize, ApiError> {
+        let store = self.clone();
+        let job_id = id.to_string();
+        self.enqueue_job(id, async move {
+            if let Err(e) = store.run_grade(&job_id, request).await {
+                tracing::error!("Grade {} failed: {}", job_id, e);
+                store.mark_failed(&job_id, e.to_string()).await;
+            }
+        })
+        .await
+    }
+
+    /// Queue `job` to run a created grade, as `enqueue_grade` does for
+    /// `run_grade`
+    pub async fn enqueue_job(
+        &self,
+        id: &str,
+        job: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<usize, ApiError> {
+        let state = self
+            .state(id)
+            .await
+            .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", id)))?;
+        state.write().await.status = GradeStatus::Queued;
+
+        let queued = self.queue.try_enqueue(id, job);
+
+        match queued {
+            Ok(position) => {
+                // A worker may already have started it or moved it up
+                let mut state = state.write().await;
+                if let Some(current) = self.queue.position(id) {
+                    state.update_queue_position(current);
+                }
+                Ok(position)
+            }
+            Err(e) => {
+                self.mark_failed(id, e.to_string()).await;
+                self.persist_report(id).await;
+                self.grades.write().await.remove(id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Cancel a grade still waiting in the queue
+    pub async fn cancel_grade(&self, id: &str) -> Result<(), ApiError> {
+        if self.state(id).await.is_none() {
+            return Err(ApiError::NotFound(format!("Grade {} not found", id)));
+        }
+        if !self.queue.cancel(id) {
+            return Err(ApiError::Conflict(format!("Grade {} is not queued", id)));
+        }
+        self.mark_failed(id, "Cancelled while queued".to_string()).await;
+        self.persist_report(id).await;
+        Ok(())
+    }
+
+    /// Save a grade's current report to its MongoDB job, if persisted
+    async fn persist_report(&self, id: &str) {
+        let Some(state) = self.state(id).await else {
+            return;
+        };
+        let (report, events, fingerprint) = {
+            let state = state.read().await;
+            (state.to_report(), state.events(), state.fingerprint.clone())
+        };
+        self.write(PendingWrite::UpdateGradeJob {
+            report: Box::new(report),
+            events,
+            fingerprint: fingerprint.map(Box::new),
+        })
+        .await;
+    }
+
+    pub async fn mark_failed(&self, id: &str, error: String) {
+        if let Some(state) = self.state(id).await {
+            let mut state = state.write().await;
+            state.status = GradeStatus::Failed;
+            state.error = Some(error.clone());
+            state.queue_position = None;
+            state.emit(GradeEvent::GradeFailed {
+                error,
+                recoverable: true,
+            });
+        }
+    }
+
+    /// Mark a grade as cloning and emit `CloningStarted`
+    pub async fn begin_cloning(&self, id: &str) -> Result<(), ApiError> {
+        if let Some(state) = self.state(id).await {
+            let mut state = state.write().await;
+            state.status = GradeStatus::Cloning;
+            state.queue_position = None;
+            state.emit(GradeEvent::CloningStarted);
+            Ok(())
+        } else {
+            Err(ApiError::NotFound(format!("Grade {} not found", id)))
+        }
+    }
+
+    pub async fn run_grade(&self, id: &str, request: GradeRequest) -> Result<(), ApiError> {
+        let start = Instant::now();
+        self.begin_cloning(id).await?;
+
+        let pinned_commit = match self.state(id).await {
+            Some(state) => state.read().await.commit_sha.clone(),
+            None => None,
+        };
+        let options = self.clone_options(&request).with_commit(pinned_commit);
+        let cloned_repo = ClonedRepo::from_url_with_options(&request.repo_url, options).await?;
+        self.run_grade_with_repo(id, request, &cloned_repo, start).await
+    }
+
+    /// Grade an already cloned repository. `start` is when cloning began and
+    /// is used for both the clone and total durations.
+    #[tracing::instrument(name = "grade", skip_all, fields(grade_id = %id))]
+    pub async fn run_grade_with_repo(
+        &self,
+        id: &str,
+        request: GradeRequest,
+        cloned_repo: &ClonedRepo,
+        start: Instant,
+    ) -> Result<(), ApiError> {
+        let config = self.config_for(&request);
+        let repo_path = cloned_repo.path.clone();
+        let commit_sha = cloned_repo.head_commit();
+
+        let shared_state = self
+            .state(id)
+            .await
+            .ok_or_else(|| ApiError::NotFound(format!("Grade {} not found", id)))?;
+
+        {
+            let mut state = shared_state.write().await;
+            state.commit_sha = commit_sha;
+            if state.branch.is_none() {
+                state.branch = cloned_repo.default_branch().map(str::to_string);
+            }
+            state.emit(GradeEvent::CloningCompleted {
+                duration_ms: start.elapsed().as_millis() as u64,
+            });
+            state.status = GradeStatus::Analyzing;
+            state.emit(GradeEvent::AnalysisStarted);
+        }
+
+        let changed = Self::changed_paths(&repo_path, request.base_branch.as_deref()).await?;
+        let redactor = Self::redactor(&request, &config)?;
+        let (profile, files, tree, fingerprint) = tokio::task::spawn_blocking({
+            let path = repo_path.clone();
+            let config = config.clone();
+            let filter = self.file_filter;
+            move || {
+                let profile = RepoProfile::detect(&path, filter);
+                let (files, tree) =
+                    Self::read_source_files(&path, &config, &profile, changed.as_ref(), filter);
+                let fingerprint = config
+                    .similarity_fingerprint
+                    .then(|| SimilarityFingerprint::from_files(&files));
+                let files = match redactor {
+                    Some(redactor) => redactor.redact_files(files),
+                    None => files,
+                };
+                (profile, files, tree, fingerprint)
+            }
+        })
+        .await
+        .map_err(|e| ApiError::InternalError(format!("Reading source files failed: {}", e)))?;
+        let total_lines: usize = files.iter().map(|(_, c)| c.lines().count()).sum();
+        let file_count = files.len();
+        {
+            let state = shared_state.read().await;
+            for (file_index, (path, _)) in files.iter().enumerate() {
+                state.emit(GradeEvent::FileLoaded {
+                    path: path.clone(),
+                    file_index,
+                    total_files: file_count,
+                });
+            }
+        }
+        let files = merge_provided_files(&request.context_files, files);
+        let (files, tree) = (Arc::new(files), Arc::new(tree));
+
+        // Wrong URLs (empty repos, documents only) end here instead of
+        // failing every criterion with the LLM
+        let no_source = !tree.has_source(config.min_source_lines);
+        let diagnostics = if config.run_checkers && !no_source {
+            Some(Arc::new(Self::run_checkers(&shared_state, &repo_path, &profile, self.file_filter).await))
+        } else {
+            None
+        };
+
+        {
+            let mut state = shared_state.write().await;
+            state.fingerprint = fingerprint;
+            state.emit(GradeEvent::AnalysisCompleted {
+                file_count,
+                total_lines,
+                skipped_files: profile.skipped_files,
+                profile: profile.clone(),
+            });
+            if let Some(minutes_late) = state.minutes_late {
+                state.emit(GradeEvent::LateSubmission { minutes_late });
+            }
+        }
+
+        if no_source {
+            tracing::info!("Grade {} has no gradeable source code", id);
+            if config.no_source_policy == NoSourcePolicy::Fail {
+                {
+                    let mut state = shared_state.write().await;
+                    state.status = GradeStatus::Failed;
+                    state.error = Some(NO_SOURCE_ERROR.to_string());
+                    state.duration_ms = start.elapsed().as_millis() as u64;
+                    state.emit(GradeEvent::GradeFailed {
+                        error: NO_SOURCE_ERROR.to_string(),
+                        recoverable: false,
+                    });
+                }
+                self.persist_report(id).await;
+                return Ok(());
+            }
+        }
+
+        {
+            let mut state = shared_state.write().await;
+            state.status = GradeStatus::Grading;
+            state.started_grading_at = Some(unix_now());
+        }
+
+        let error_budget = ErrorBudget::new(config.max_llm_failures);
+        let time_budget = TimeBudget::new(start, Duration::from_secs(config.max_total_duration_secs));
+        let rate_limit = RateLimitGate::new();
+        let task_results = if no_source {
+            Self::no_source_results(&request.tasks, config.unverifiable_policy)
+        } else {
+            let clients = self
+                .grader_clients(&config, &request)
+                .ok_or_else(|| ApiError::InternalError("No LLM provider configured".to_string()))?;
+            shared_state.write().await.model = Some(clients.default.model().to_string());
+
+            let wants_tests = request.tasks.iter().any(|task| task.run_tests);
+            if wants_tests && !self.test_runner.enabled {
+                tracing::info!("Skipping tests of grade {}: the test runner is disabled", id);
+            }
+            let test_run = if wants_tests && self.test_runner.enabled {
+                let path = repo_path.clone();
+                let timeout = self.test_runner.timeout(config.test_timeout_secs);
+                tokio::task::spawn_blocking(move || test_runner::run_tests(&path, timeout))
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("Test run for grade {} panicked: {}", id, e);
+                        None
+                    })
+            } else {
+                None
+            };
+
+            let grader = self.grader(&config);
+            self.process_tasks_parallel(
+                id,
+                &request,
+                &files,
+                &tree,
+                &profile,
+                test_run.as_ref(),
+                diagnostics,
+                &clients,
+                &grader,
+                &config,
+                &error_budget,
+                &time_budget,
+                &rate_limit,
+            )
+            .await
+        };
+
+        {
+            let mut state = shared_state.write().await;
+            let late_penalty = state
+                .minutes_late
+                .and(config.late_penalty_percentage)
+                .unwrap_or(0);
+            let FinalScore {
+                overall_score,
+                percentage,
+                grade,
+                mut summary,
+                rubric_breakdown,
+            } = Self::calculate_final_score(&request.tasks, &task_results, late_penalty, config.unverifiable_policy);
+            let partial = time_budget.skipped() > 0;
+            if partial {
+                tracing::warn!(
+                    "Grade {} ran out of its {}s time budget; {} criteria were not checked",
+                    id,
+                    config.max_total_duration_secs,
+                    time_budget.skipped()
+                );
+                summary.push_str(&format!(", 시간 초과로 기준 {}개 미평가", time_budget.skipped()));
+            }
+
+            state.task_results = task_results;
+            state.rubric_breakdown = rubric_breakdown;
+            state.partial = partial;
+            state.overall_score = overall_score;
+            state.percentage = percentage;
+            state.grade = grade.clone();
+            state.summary = summary.clone();
+            state.duration_ms = start.elapsed().as_millis() as u64;
+
+            if error_budget.is_exhausted() {
+                let error = format!(
+                    "Grading aborted after {} LLM failures; remaining criteria were not checked",
+                    error_budget.failures()
+                );
+                state.status = GradeStatus::Failed;
+                state.error = Some(error.clone());
+                state.emit(GradeEvent::GradeFailed {
+                    error,
+                    recoverable: true,
+                });
+            } else {
+                state.status = GradeStatus::Completed;
+                state.completed_at = Some(unix_now());
+                state.emit(GradeEvent::GradeCompleted {
+                    overall_score,
+                    percentage,
+                    grade,
+                    summary,
+                    duration_ms: state.duration_ms,
+                    partial,
+                    reason: no_source.then(|| NO_SOURCE_ERROR.to_string()),
+                });
+            }
+        }
+
+        self.persist_report(id).await;
+        if let (Some(curriculum_id), Some(task_id), Some(report)) =
+            (&request.curriculum_id, &request.task_id, self.get_grade(id).await)
+        {
+            self.write(PendingWrite::UpdateTaskGrade {
+                curriculum_id: curriculum_id.clone(),
+                task_id: task_id.clone(),
+                report: Box::new(report),
+            })
+            .await;
+        }
+
+        Ok(())
+    }
+
+    /// Every criterion failed with `NO_SOURCE_ERROR`, for zero-graded
+    /// repositories without source code
+    fn no_source_results(tasks: &[GradeTask], policy: UnverifiablePolicy) -> Vec<TaskGradeResult> {
+        tasks
+            .iter()
+            .enumerate()
+            .map(|(task_index, task)| {
+                let criteria_results: Vec<CriterionResult> = task
+                    .acceptance_criteria
+                    .iter()
+                    .map(|criterion| CriterionResult {
+                        criterion: criterion.description.clone(),
+                        criterion_id: criterion.id.clone(),
+                        task_index,
+                        passed: false,
+                        outcome: CriterionOutcome::Failed,
+                        confidence: 1.0,
+                        evidence: NO_SOURCE_ERROR.to_string(),
+                        code_references: vec![],
+                        weight: criterion.weight,
+                        human_override: None,
+                    })
+                    .collect();
+                let (score, status, passed_count) = Self::calculate_task_score(&criteria_results, policy);
+                TaskGradeResult {
+                    task_title: task.title.clone(),
+                    score,
+                    status,
+                    criteria_results,
+                    passed_count,
+                    total_count: task.acceptance_criteria.len(),
+                }
+            })
+            .collect()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn process_tasks_parallel(
         &self,
         grade_id: &str,
+        request: &GradeRequest,
+        files: &Arc<Vec<(String, String)>>,
+        tree: &Arc<RepoTree>,
+        profile: &RepoProfile,
+        test_run: Option<&TestRunResult>,
+        diagnostics: Option<Arc<DiagnosticsSummary>>,
+        clients: &GraderClients,
+        grader: &CriteriaChecker,
+        config: &GradeConfig,
+        error_budget: &ErrorBudget,
+        time_budget: &TimeBudget,
+        rate_limit: &RateLimitGate,
+    ) -> Vec<TaskGradeResult> {
+        let tasks = &request.tasks;
+        let language = request.language.as_deref().unwrap_or(DEFAULT_LANGUAGE);
+        let task_semaphore = Arc::new(Semaphore::new(config.max_parallel_tasks));
+        let criteria_semaphore = Arc::new(Semaphore::new(config.max_parallel_criteria));
+
+        let mut task_results = Vec::with_capacity(tasks.len());
+        let events = self.event_sender(grade_id).await;
+        let emit = |event| {
+            if let Some(events) = &events {
+                events.send(event);
+            }
+        };
+
+        for (task_index, task) in tasks.iter().enumerate() {
+            let _permit = task_semaphore.acquire().await.unwrap();
+
+            emit(GradeEvent::TaskStarted {
+                task_index,
+                task_title: task.title.clone(),
+                criteria_count: task.acceptance_criteria.len(),
+            });
+
+            let context = GradeContext::new(request.repo_url.clone(), task.clone())
+                .with_files(Arc::clone(files))
+                .with_tree(Arc::clone(tree))
+                .with_language(language)
+                .with_profile(profile.clone())
+                .with_test_run(test_run.filter(|_| task.run_tests).cloned())
+                .with_diagnostics(diagnostics.clone());
+            let criteria_results = self
+                .process_criteria_parallel(
+                    grade_id,
+                    task_index,
+                    task,
+                    &context,
+                    clients,
+                    grader,
+                    &criteria_semaphore,
+                    config,
+                    error_budget,
+                    time_budget,
+                    rate_limit,
+                )
+                .instrument(tracing::info_span!("task", task_index, task = %task.title))
+                .await;
+
+            let (score, status, passed_count) =
+                Self::calculate_task_score(&criteria_results, config.unverifiable_policy);
+
+            let task_result = TaskGradeResult {
+                task_title: task.title.clone(),
+                score,
+                status,
+                criteria_results,
+                passed_count,
+                total_count: task.acceptance_criteria.len(),
+            };
+
+            emit(GradeEvent::TaskCompleted {
+                task_index,
+                task_title: task.title.clone(),
+                score,
+                status,
+                passed_count,
+                total_count: task.acceptance_criteria.len(),
+            });
+
+            task_results.push(task_result);
+        }
+
+        task_results
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn process_criteria_parallel(
+        &self,
+        grade_id: &str,
+        task_index: usize,
+        task: &GradeTask,
+        context: &GradeContext,
+        clients: &GraderClients,
+        grader: &CriteriaChecker,
+        semaphore: &Arc<Semaphore>,
+        config: &GradeConfig,
+        error_budget: &ErrorBudget,
+        time_budget: &TimeBudget,
+        rate_limit: &RateLimitGate,
+    ) -> Vec<CriterionResult> {
+        let mut results = Vec::with_capacity(task.acceptance_criteria.len());
+        let criterion_timeout = Duration::from_secs(config.criterion_timeout_secs);
+        let events = self.event_sender(grade_id).await;
+
+        for (criterion_index, criterion) in task.acceptance_criteria.iter().enumerate() {
+            let _permit = semaphore.acquire().await.unwrap();
+
+            let failed = |evidence: String| CriterionResult {
+                criterion: criterion.description.clone(),
+                criterion_id: criterion.id.clone(),
+                task_index,
+                passed: false,
+                outcome: CriterionOutcome::Failed,
+                confidence: 0.0,
+                evidence,
+                code_references: vec![],
+                weight: criterion.weight,
+                human_override: None,
+            };
+
+            let result = if error_budget.is_exhausted() {
+                failed(ABORTED_EVIDENCE.to_string())
+            } else if time_budget.is_exhausted() {
+                time_budget.record_skip();
+                failed(TIME_BUDGET_EVIDENCE.to_string())
+            } else {
+                let mut rate_limited = 0;
+                loop {
+                    rate_limit.wait(time_budget).await;
+                    if time_budget.is_exhausted() {
+                        time_budget.record_skip();
+                        break failed(TIME_BUDGET_EVIDENCE.to_string());
+                    }
+                    // Waiting for a global permit doesn't count toward the timeout
+                    let _llm_permit = self.global_llm_semaphore.acquire().await.unwrap();
+                    let span = tracing::info_span!(
+                        "criterion",
+                        criterion_index,
+                        criterion_id = criterion.id.as_deref(),
+                    );
+                    // An in-flight check is abandoned when the time budget runs out
+                    let result = match tokio::time::timeout(
+                        criterion_timeout.min(time_budget.remaining()),
+                        grader
+                            .check_criterion(clients.for_criterion(criterion), context, criterion)
+                            .instrument(span),
+                    )
+                    .await
+                    {
+                        Ok(Ok(result)) => CriterionResult { task_index, ..result },
+                        // Rate limits pause every check of the grade, then this one is retried
+                        Ok(Err(LlmError::RateLimited { retry_after_ms }))
+                            if rate_limited < MAX_RATE_LIMIT_RETRIES =>
+                        {
+                            rate_limited += 1;
+                            tracing::warn!(
+                                "Rate limited checking criterion '{}', pausing grade {} for {}ms",
+                                criterion.description,
+                                grade_id,
+                                retry_after_ms
+                            );
+                            rate_limit.pause(Duration::from_millis(retry_after_ms));
+                            continue;
+                        }
+                        Ok(Err(e)) => {
+                            tracing::warn!(
+                                "Failed to check criterion '{}': {}",
+                                criterion.description,
+                                e
+                            );
+                            if error_budget.record_failure() {
+                                tracing::error!(
+                                    "Grade {} exceeded its LLM error budget ({} failures)",
+                                    grade_id,
+                                    error_budget.failures()
+                                );
+                            }
+                            failed(format!("Error checking criterion: {}", e))
+                        }
+                        Err(_) if time_budget.is_exhausted() => {
+                            tracing::warn!("Criterion '{}' abandoned at the time budget", criterion.description);
+                            time_budget.record_skip();
+                            failed(TIME_BUDGET_EVIDENCE.to_string())
+                        }
+                        // Timeouts don't count against the error budget
+                        Err(_) => {
+                            tracing::warn!("Criterion '{}' timed out", criterion.description);
+                            failed(format!(
+                                "Criterion check timed out after {}s",
+                                config.criterion_timeout_secs
+                            ))
+                        }
+                    };
+                    break result;
+                }
+            };
+
+            if let Some(events) = &events {
+                events.send(GradeEvent::CriterionChecked {
+                    task_index,
+                    criterion_index,
+                    criterion: criterion.description.clone(),
+                    criterion_id: criterion.id.clone(),
+                    passed: result.passed,
+                    outcome: result.outcome,
+                    confidence: result.confidence,
+                });
+            }
+
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Run the checkers that apply to the repository, streaming their
+    /// progress on the grade's events. A panicking checker is reported and
+    /// left out.
+    async fn run_checkers(
+        state: &Arc<RwLock<GradeState>>,
+        repo_path: &Path,
+        profile: &RepoProfile,
+        filter: FileFilter,
+    ) -> DiagnosticsSummary {
+        let mut results = Vec::new();
+        for checker in checkers_for(profile) {
+            let check_type = checker.check_type();
+            state.read().await.emit(GradeEvent::CheckStarted { check_type });
+
+            let check_start = Instant::now();
+            let path = repo_path.to_path_buf();
+            let event = match tokio::task::spawn_blocking(move || checker.check(&path, filter)).await {
+                Ok(diagnostics) => {
+                    let event = GradeEvent::CheckCompleted {
+                        check_type,
+                        diagnostics_count: diagnostics.len(),
+                        duration_ms: check_start.elapsed().as_millis() as u64,
+                    };
+                    results.push((check_type, diagnostics));
+                    event
+                }
+                Err(e) => {
+                    tracing::warn!("Checker {:?} failed: {}", check_type, e);
+                    GradeEvent::CheckFailed {
+                        check_type,
+                        error: e.to_string(),
+                    }
+                }
+            };
+            state.read().await.emit(event);
+        }
+        DiagnosticsSummary::new(results)
+    }
+
+    /// Weighted score, status and passed count, with not-verifiable criteria
+    /// scored according to `policy`
+    fn calculate_task_score(
+        criteria_results: &[CriterionResult],
+        policy: UnverifiablePolicy,
+    ) -> (f32, TaskStatus, usize) {
+        if criteria_results.is_empty() {
+            return (0.0, TaskStatus::Failed, 0);
+        }
+
+        let scored: Vec<(f32, bool)> = criteria_results
+            .iter()
+            .filter_map(|r| match (r.effective_outcome(), policy) {
+                (CriterionOutcome::NotVerifiable, UnverifiablePolicy::ExcludeFromScore) => None,
+                (CriterionOutcome::NotVerifiable, UnverifiablePolicy::CountAsPassed) => {
+                    Some((r.weight, true))
+                }
+                (outcome, _) => Some((r.weight, outcome == CriterionOutcome::Passed)),
+            })
+            .collect();
+
+        let total_weight: f32 = scored.iter().map(|(weight, _)| weight).sum();
+        let passed_weight: f32 = scored
+            .iter()
+            .filter(|(_, passed)| *passed)
+            .map(|(weight, _)| weight)
+            .sum();
+
+        let score = if total_weight > 0.0 {
+            passed_weight / total_weight
+        } else {
+            0.0
+        };
+
+        let passed_count = scored.iter().filter(|(_, passed)| *passed).count();
+
+        let status = if score >= 1.0 {
+            TaskStatus::Passed
+        } else if score > 0.0 {
+            TaskStatus::Partial
+        } else {
+            TaskStatus::Failed
+        };
+
+        (score, status, passed_count)
+    }
+
+    /// Record `verdict` on one criterion and recompute every task's score and
+    /// the final score, as [`Self::run_grade`] would have with the verdict
+    #[allow(clippy::too_many_arguments)]
+    fn apply_override(
         tasks: &[GradeTask],
-        files: &[(String, String)],
-        repo_url: &str,
-        client: &dyn ModelClient,
-        grader: &CriteriaChecker,
+        task_results: &mut [TaskGradeResult],
+        task_index: usize,
+        criterion_index: usize,
+        verdict: CriterionOverride,
+        policy: UnverifiablePolicy,
+        penalty_percentage: u32,
+    ) -> Result<FinalScore, ApiError> {
+        let result = task_results
+            .get_mut(task_index)
+            .and_then(|task| task.criteria_results.get_mut(criterion_index))
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("Criterion {}/{} not found", task_index, criterion_index))
+            })?;
+        result.human_override = Some(verdict);
+
+        for task in task_results.iter_mut() {
+            let (score, status, passed_count) =
+                Self::calculate_task_score(&task.criteria_results, policy);
+            task.score = score;
+            task.status = status;
+            task.passed_count = passed_count;
+        }
+        Ok(Self::calculate_final_score(tasks, task_results, penalty_percentage, policy))
+    }
+
+    /// Final score with `penalty_percentage` of the overall score deducted,
+    /// and the unpenalized score of each rubric category in `tasks`
+    fn calculate_final_score(
+        tasks: &[GradeTask],
+        task_results: &[TaskGradeResult],
+        penalty_percentage: u32,
+        policy: UnverifiablePolicy,
+    ) -> FinalScore {
+        if task_results.is_empty() {
+            return FinalScore {
+                overall_score: 0.0,
+                percentage: 0,
+                grade: "N/A".to_string(),
+                summary: "No tasks to grade".to_string(),
+                rubric_breakdown: BTreeMap::new(),
+            };
+        }
+
+        let penalty = penalty_percentage.min(100) as f32 / 100.0;
+        let overall_score: f32 = task_results.iter().map(|t| t.score).sum::<f32>()
+            / task_results.len() as f32
+            * (1.0 - penalty);
+        let percentage = (overall_score * 100.0).round() as u32;
+
+        let grade = match percentage {
+            90..=100 => "우수",
+            75..=89 => "양호",
+            60..=74 => "보통",
+            40..=59 => "미흡",
+            _ => "불합격",
+        }
+        .to_string();
+
+        let passed_tasks = task_results
+            .iter()
+            .filter(|t| t.status == TaskStatus::Passed)
+            .count();
+        let total_tasks = task_results.len();
+        let total_criteria: usize = task_results.iter().map(|t| t.total_count).sum();
+        let passed_criteria: usize = task_results.iter().map(|t| t.passed_count).sum();
+
+        let mut summary = format!(
+            "전체 점수: {}점 ({}) - 과제 {}/{} 완료, 기준 {}/{} 충족",
+            percentage, grade, passed_tasks, total_tasks, passed_criteria, total_criteria
+        );
+        if penalty_percentage > 0 {
+            summary.push_str(&format!(", 지각 감점 {}%", penalty_percentage.min(100)));
+        }
+
+        // Listed separately for instructors to review by hand
+        let unverifiable: Vec<String> = task_results
+            .iter()
+            .flat_map(|task| {
+                task.criteria_results
+                    .iter()
+                    .filter(|r| r.effective_outcome() == CriterionOutcome::NotVerifiable)
+                    .map(|r| format!("- [{}] {}", task.task_title, r.criterion))
+            })
+            .collect();
+        if !unverifiable.is_empty() {
+            summary.push_str(&format!(
+                "\n검증 불가 기준 {}개 (수동 검토 필요):\n{}",
+                unverifiable.len(),
+                unverifiable.join("\n")
+            ));
+        }
+
+        FinalScore {
+            overall_score,
+            percentage,
+            grade,
+            summary,
+            rubric_breakdown: Self::rubric_breakdown(tasks, task_results, policy),
+        }
+    }
+
+    /// Criterion results grouped by their criterion's `rubric_category`,
+    /// each group scored like a task
+    fn rubric_breakdown(
+        tasks: &[GradeTask],
+        task_results: &[TaskGradeResult],
+        policy: UnverifiablePolicy,
+    ) -> BTreeMap<String, RubricCategory> {
+        let mut groups: BTreeMap<String, Vec<CriterionResult>> = BTreeMap::new();
+        for (task, results) in tasks.iter().zip(task_results) {
+            // Results are in the order of the task's criteria
+            for (criterion, result) in task.acceptance_criteria.iter().zip(&results.criteria_results) {
+                let category = criterion
+                    .rubric_category
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_RUBRIC_CATEGORY.to_string());
+                groups.entry(category).or_default().push(result.clone());
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(category, results)| {
+                let (score, _, passed_count) = Self::calculate_task_score(&results, policy);
+                let breakdown = RubricCategory {
+                    category: category.clone(),
+                    score,
+                    percentage: (score * 100.0).round() as u32,
+                    criteria_count: results.len(),
+                    passed_count,
+                };
+                (category, breakdown)
+            })
+            .collect()
+    }
+
+    /// Source files within the configured limits, plus a tree listing every
+    /// candidate file. Files matching `file_priority_patterns` are read
+    /// first. With `changed`, only those repo-relative paths are read.
+    fn read_source_files(
+        repo_path: &Path,
         config: &GradeConfig,
-    ) -> Vec<TaskGradeResult> {
-        let task_semaphore = Arc::new(Semaphore::new(config.max_parallel_tasks));
-        let criteria_semaphore = Arc::new(Semaphore::new(config.max_parallel_criteria));
+        profile: &RepoProfile,
+        changed: Option<&HashSet<String>>,
+        filter: FileFilter,
+    ) -> (Vec<(String, String)>, RepoTree) {
+        let extensions = [
+            "rs", "ts", "tsx", "js", "jsx", "py", "go", "java", "kt", "swift", "c", "cpp", "h",
+            "hpp", "cs", "rb", "php", "html", "css", "json", "yaml", "yml", "toml", "md",
+        ];
+
+        let mut entries: Vec<_> = walkdir::WalkDir::new(repo_path)
+            .max_depth(10)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| {
+                // Relative, since clones live in hidden `.tmp*` directories
+                let path = e.path().strip_prefix(repo_path).unwrap_or(e.path());
+                // Skip hidden files and common non-source directories
+                !path
+                    .components()
+                    .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+                    && !path
+                        .components()
+                        .any(|c| ["node_modules", "target", "dist", "build", "__pycache__"].contains(&c.as_os_str().to_str().unwrap_or("")))
+            })
+            .filter(|e| {
+                e.path()
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| extensions.contains(&ext))
+                    .unwrap_or(false)
+            })
+            .filter(|e| !filter.skips(e.path()))
+            .collect();
+        // Priority patterns first, then by language; the stable sort keeps
+        // walk order within each
+        let selector = PriorityFileSelector::new(&config.file_priority_patterns);
+        entries.sort_by_cached_key(|e| {
+            let path = e.path().strip_prefix(repo_path).unwrap_or(e.path());
+            let score = selector.score(&path.to_string_lossy());
+            (std::cmp::Reverse(score), profile.rank(e.path()))
+        });
+        let tree = RepoTree::collect(repo_path, entries.iter().map(|e| e.path()));
+
+        let limits = ReadLimits {
+            max_file_bytes: config.max_file_bytes,
+            max_total_bytes: config.max_context_bytes,
+        };
+        let files = read_capped(
+            repo_path,
+            entries
+                .into_iter()
+                .filter(|e| match changed {
+                    Some(paths) => e
+                        .path()
+                        .strip_prefix(repo_path)
+                        .is_ok_and(|p| paths.contains(p.to_string_lossy().as_ref())),
+                    None => true,
+                })
+                .take(config.max_files)
+                .map(|e| e.into_path()),
+            limits,
+        );
+        (files, tree)
+    }
+
+    /// Paths changed since `base_branch`, or `None` to grade every file
+    async fn changed_paths(
+        repo_path: &Path,
+        base_branch: Option<&str>,
+    ) -> Result<Option<HashSet<String>>, ApiError> {
+        let Some(base_branch) = base_branch else {
+            return Ok(None);
+        };
+        let path = repo_path.to_path_buf();
+        let base_branch = base_branch.to_string();
+        let changed = tokio::task::spawn_blocking(move || {
+            ClonedRepo::from_local(path)?.diff_from_base(&base_branch)
+        })
+        .await
+        .map_err(|e| ApiError::GitError(format!("Diff task failed: {}", e)))??;
+        Ok(Some(changed.into_iter().map(|file| file.path).collect()))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::error::LlmError;
+    use crate::llm::Message;
+    use crate::types::{CheckType, Criterion, GradeConfigOverrides};
+    use async_trait::async_trait;
+
+    /// A request grading `tasks` of a test repository with no options set
+    pub(crate) fn grade_request(tasks: Vec<GradeTask>) -> GradeRequest {
+        GradeRequest {
+            repo_url: "https://github.com/test/repo".to_string(),
+            branch: None,
+            tasks,
+            config: None,
+            metadata: None,
+            curriculum_id: None,
+            task_id: None,
+            language: None,
+            model: None,
+            base_branch: None,
+            context_files: vec![],
+        }
+    }
+
+    struct FailingClient {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ModelClient for FailingClient {
+        async fn chat(&self, _messages: &[Message], _system: Option<&str>) -> Result<String, LlmError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(LlmError::Unavailable {
+                provider: "mock".to_string(),
+            })
+        }
+    }
+
+    /// Rate limited on its first call, passing every criterion afterwards
+    struct RateLimitedOnceClient {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ModelClient for RateLimitedOnceClient {
+        async fn chat(&self, _messages: &[Message], _system: Option<&str>) -> Result<String, LlmError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(LlmError::RateLimited { retry_after_ms: 200 });
+            }
+            Ok(r#"{"outcome": "passed", "confidence": 0.9, "evidence": "ok"}"#.to_string())
+        }
+    }
+
+    /// Records the most requests it ever had in flight at once
+    struct SlowClient {
+        in_flight: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ModelClient for SlowClient {
+        async fn chat(&self, _messages: &[Message], _system: Option<&str>) -> Result<String, LlmError> {
+            let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(r#"{"outcome": "passed", "confidence": 0.9, "evidence": "ok"}"#.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_global_llm_semaphore_serializes_concurrent_grades() {
+        let providers = ProvidersConfig {
+            max_concurrent_llm_requests: 1,
+            ..ProvidersConfig::default()
+        };
+        let store = GradeStore::new(3600, Some(providers), GradeConfig::default(), None);
+        let request = grade_request(vec![GradeTask {
+            title: "Task".to_string(),
+            description: None,
+            acceptance_criteria: (0..2)
+                .map(|i| Criterion {
+                    id: None,
+                    description: format!("criterion {}", i),
+                    weight: 1.0,
+                    examples: vec![],
+                    model_hint: None,
+                    rubric_category: None,
+                })
+                .collect(),
+            estimated_minutes: None,
+            run_tests: false,
+        }]);
+        let config = GradeConfig::default();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let clients = || {
+            GraderClients::new(Box::new(SlowClient {
+                in_flight: Arc::clone(&in_flight),
+                peak: Arc::clone(&peak),
+            }))
+        };
+        let (first_clients, second_clients) = (clients(), clients());
+        let (first_budget, second_budget) = (ErrorBudget::new(10), ErrorBudget::new(10));
+        let (first_gate, second_gate) = (RateLimitGate::new(), RateLimitGate::new());
+        let time_budget = TimeBudget::new(Instant::now(), Duration::from_secs(60));
+        let grader = CriteriaChecker::new();
+        let (files, tree, profile) = (Arc::default(), Arc::default(), RepoProfile::default());
+        let other_store = store.clone();
+
+        // Two grade jobs from separate requests, sharing the store's semaphore
+        let (first, second) = tokio::join!(
+            store.process_tasks_parallel(
+                "first",
+                &request,
+                &files,
+                &tree,
+                &profile,
+                None,
+                None,
+                &first_clients,
+                &grader,
+                &config,
+                &first_budget,
+                &time_budget,
+                &first_gate,
+            ),
+            other_store.process_tasks_parallel(
+                "second",
+                &request,
+                &files,
+                &tree,
+                &profile,
+                None,
+                None,
+                &second_clients,
+                &grader,
+                &config,
+                &second_budget,
+                &time_budget,
+                &second_gate,
+            ),
+        );
+
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert!(first
+            .iter()
+            .chain(&second)
+            .flat_map(|t| &t.criteria_results)
+            .all(|c| c.passed));
+    }
+
+    #[tokio::test]
+    async fn test_error_budget_aborts_after_max_failures() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let criteria: Vec<Criterion> = (0..4)
+            .map(|i| Criterion {
+                id: None,
+                description: format!("criterion {}", i),
+                weight: 1.0,
+                examples: vec![],
+                model_hint: None,
+                rubric_category: None,
+            })
+            .collect();
+        let task = GradeTask {
+            title: "Task".to_string(),
+            description: None,
+            acceptance_criteria: criteria,
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let request = grade_request(vec![task.clone(), task]);
+        let config = GradeConfig::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let clients = GraderClients::new(Box::new(FailingClient {
+            calls: Arc::clone(&calls),
+        }));
+        let budget = ErrorBudget::new(config.max_llm_failures);
+        let time_budget = TimeBudget::new(Instant::now(), Duration::from_secs(60));
+
+        let results = store
+            .process_tasks_parallel(
+                "missing",
+                &request,
+                &Arc::default(),
+                &Arc::default(),
+                &RepoProfile::default(),
+                None,
+                None,
+                &clients,
+                &CriteriaChecker::new(),
+                &config,
+                &budget,
+                &time_budget,
+                &RateLimitGate::new(),
+            )
+            .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+        assert!(budget.is_exhausted());
+
+        let evidence: Vec<&str> = results
+            .iter()
+            .flat_map(|t| t.criteria_results.iter().map(|c| c.evidence.as_str()))
+            .collect();
+        assert_eq!(evidence.len(), 8);
+        assert!(evidence[..5].iter().all(|e| e.starts_with("Error checking criterion")));
+        assert!(evidence[5..].iter().all(|e| *e == ABORTED_EVIDENCE));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_pauses_and_retries() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let task = GradeTask {
+            title: "Task".to_string(),
+            description: None,
+            acceptance_criteria: (0..3)
+                .map(|i| Criterion {
+                    id: None,
+                    description: format!("criterion {}", i),
+                    weight: 1.0,
+                    examples: vec![],
+                    model_hint: None,
+                    rubric_category: None,
+                })
+                .collect(),
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let calls = Arc::new(AtomicUsize::new(0));
+        let clients = GraderClients::new(Box::new(RateLimitedOnceClient {
+            calls: Arc::clone(&calls),
+        }));
+        let budget = ErrorBudget::new(1);
+        let time_budget = TimeBudget::new(Instant::now(), Duration::from_secs(60));
+        let gate = RateLimitGate::new();
+        let start = Instant::now();
+
+        let results = store
+            .process_criteria_parallel(
+                "missing",
+                0,
+                &task,
+                &GradeContext::new("https://github.com/test/repo".to_string(), task.clone()),
+                &clients,
+                &CriteriaChecker::new(),
+                &Arc::new(Semaphore::new(3)),
+                &GradeConfig::default(),
+                &budget,
+                &time_budget,
+                &gate,
+            )
+            .await;
+
+        // The rate-limited criterion is retried after the pause, not failed
+        assert!(results.iter().all(|c| c.passed));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(gate.pauses(), 1);
+        assert_eq!(budget.failures(), 0);
+        assert!(start.elapsed() >= MIN_RATE_LIMIT_PAUSE);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_gate_respects_time_budget() {
+        let gate = RateLimitGate::new();
+        let time_budget = TimeBudget::new(Instant::now(), Duration::from_millis(50));
+        gate.pause(Duration::from_secs(30));
+        gate.pause(Duration::from_millis(10));
+
+        let start = Instant::now();
+        gate.wait(&time_budget).await;
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(time_budget.is_exhausted());
+        assert_eq!(gate.pauses(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_time_budget_skips_remaining_criteria() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let task = GradeTask {
+            title: "Task".to_string(),
+            description: None,
+            acceptance_criteria: vec![Criterion {
+                id: None,
+                description: "criterion".to_string(),
+                weight: 1.0,
+                examples: vec![],
+                model_hint: None,
+                rubric_category: None,
+            }],
+            estimated_minutes: None,
+            run_tests: false,
+        };
+        let request = grade_request(vec![task.clone(), task]);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let clients = GraderClients::new(Box::new(FailingClient {
+            calls: Arc::clone(&calls),
+        }));
+        let time_budget = TimeBudget::new(Instant::now(), Duration::ZERO);
+
+        let results = store
+            .process_tasks_parallel(
+                "missing",
+                &request,
+                &Arc::default(),
+                &Arc::default(),
+                &RepoProfile::default(),
+                None,
+                None,
+                &clients,
+                &CriteriaChecker::new(),
+                &GradeConfig::default(),
+                &ErrorBudget::new(5),
+                &time_budget,
+                &RateLimitGate::new(),
+            )
+            .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(time_budget.skipped(), 2);
+        assert!(results
+            .iter()
+            .flat_map(|t| &t.criteria_results)
+            .all(|c| !c.passed && c.evidence == TIME_BUDGET_EVIDENCE));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_get_grade() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = grade_request(vec![GradeTask {
+            title: "Test Task".to_string(),
+            description: None,
+            acceptance_criteria: vec![Criterion {
+                id: None,
+                description: "Test criterion".to_string(),
+                weight: 1.0,
+                examples: vec![],
+                model_hint: None,
+                rubric_category: None,
+            }],
+            estimated_minutes: None,
+            run_tests: false,
+        }]);
+
+        let id = store.create_grade(request).await;
+        let report = store.get_grade(&id).await;
+
+        assert!(report.is_some());
+        let report = report.unwrap();
+        assert_eq!(report.repo_url, "https://github.com/test/repo");
+        assert_eq!(report.status, GradeStatus::Pending);
+        assert!(report.created_at > 0);
+        assert_eq!(report.started_grading_at, None);
+        assert_eq!(report.completed_at, None);
+
+        // completed_at is only reported once the grade has completed
+        let state = store.state(&id).await.unwrap();
+        {
+            let mut state = state.write().await;
+            state.started_grading_at = Some(report.created_at + 5);
+            state.completed_at = Some(report.created_at + 30);
+            state.status = GradeStatus::Failed;
+        }
+        let failed = store.get_grade(&id).await.unwrap();
+        assert_eq!(failed.started_grading_at, Some(report.created_at + 5));
+        assert_eq!(failed.completed_at, None);
+
+        state.write().await.status = GradeStatus::Completed;
+        let completed = store.get_grade(&id).await.unwrap();
+        assert_eq!(completed.completed_at, Some(report.created_at + 30));
+    }
+
+    #[tokio::test]
+    async fn test_mongo_outage_does_not_fail_grade() {
+        // Nothing listens on port 1, so every write fails fast
+        let client = crate::db::MongoClient::new(
+            "mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=50",
+            "test",
+        )
+        .await
+        .unwrap();
+        let repo = Arc::new(crate::db::GradeRepository::new(client));
+        let store = GradeStore::new(3600, None, GradeConfig::default(), Some(repo));
+        let request = grade_request(vec![]);
+
+        let id = store.create_grade(request).await;
+        store.state(&id).await.unwrap().write().await.status = GradeStatus::Completed;
+        store.persist_report(&id).await;
+
+        let report = store.get_grade(&id).await.unwrap();
+        assert_eq!(report.status, GradeStatus::Completed);
+        assert!(!report.persisted);
+        // The job insert and the report update wait for MongoDB to come back
+        assert_eq!(store.write_retry.as_ref().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_grades_lock_independently() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = grade_request(vec![]);
+        let busy = store.create_grade(request.clone()).await;
+        let other = store.create_grade(request).await;
+
+        // A long update of one grade must not block reads of another
+        let busy_state = store.state(&busy).await.unwrap();
+        let _guard = busy_state.write().await;
+        let report = tokio::time::timeout(Duration::from_secs(1), store.get_grade(&other))
+            .await
+            .expect("other grade should not be blocked");
+        assert!(report.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let mut request = GradeRequest {
+            config: Some(GradeConfigOverrides::from(GradeConfig {
+                max_files: 10,
+                max_chars_per_file: 1000,
+                cost_per_1k_tokens_usd: Some(1.0),
+                ..GradeConfig::default()
+            })),
+            ..grade_request(vec![GradeTask {
+                title: "Task".to_string(),
+                description: None,
+                acceptance_criteria: vec![
+                    Criterion {
+                        id: None,
+                        description: "A".to_string(),
+                        weight: 1.0,
+                        examples: vec![],
+                        model_hint: None,
+                        rubric_category: None,
+                    },
+                    Criterion {
+                        id: None,
+                        description: "B".to_string(),
+                        weight: 2.0,
+                        examples: vec![],
+                        model_hint: None,
+                        rubric_category: None,
+                    },
+                ],
+                estimated_minutes: None,
+                run_tests: false,
+            }])
+        };
+
+        let result = store.dry_run(&request);
+        assert!(result.valid, "{:?}", result.errors);
+        assert_eq!(result.estimated_llm_calls, 2);
+        // (10 * 1000 + 2000) / 4 = 3000 tokens per call, two calls
+        assert_eq!(result.estimated_prompt_tokens, 6000);
+        assert!((result.estimated_cost_usd.unwrap() - 6.0).abs() < 0.01);
+
+        let dry_run = DryRunRequest {
+            grade: request.clone(),
+            skip_clone: true,
+            estimated_file_count: Some(2),
+            estimated_file_chars: Some(500),
+        };
+        let result = store.dry_run_with_context(&dry_run).await.unwrap();
+        assert!(result.valid);
+        assert_eq!(result.tasks.len(), 1);
+        assert_eq!(result.tasks[0].llm_calls, 2);
+        let criterion = &result.tasks[0].criteria[0];
+        assert!(criterion.prompt_chars > 1000);
+        assert_eq!(criterion.prompt_tokens, criterion.prompt_chars.div_ceil(4));
+        assert_eq!(
+            result.estimated_prompt_chars,
+            result.tasks[0].criteria.iter().map(|c| c.prompt_chars).sum::<usize>()
+        );
+
+        // Cloning dry runs go through the grade queue, so a full queue rejects them
+        let busy = GradeStore::new(3600, None, GradeConfig::default(), None).with_job_limits(1, 1);
+        let (release, held) = oneshot::channel::<()>();
+        busy.queue.try_enqueue("running", async move { let _ = held.await; }).unwrap();
+        while busy.queue.position("running").is_some() {
+            tokio::task::yield_now().await;
+        }
+        busy.queue.try_enqueue("waiting", async {}).unwrap();
+        let cloning = DryRunRequest { skip_clone: false, ..dry_run };
+        assert!(matches!(
+            busy.dry_run_with_context(&cloning).await,
+            Err(ApiError::ServiceUnavailable(_))
+        ));
+        drop(release);
+
+        request.repo_url = "not a url".to_string();
+        for criterion in &mut request.tasks[0].acceptance_criteria {
+            criterion.weight = 0.0;
+        }
+        let result = store.dry_run(&request);
+        assert!(!result.valid);
+        assert_eq!(result.errors.len(), 2);
+        assert!(store.get_grade("any").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_rejects_oversized_config() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = GradeRequest {
+            config: Some(GradeConfigOverrides::from(GradeConfig {
+                max_files: usize::MAX,
+                max_chars_per_file: usize::MAX,
+                max_total_chars: usize::MAX,
+                ..GradeConfig::default()
+            })),
+            ..grade_request(vec![GradeTask {
+                title: "Task".to_string(),
+                description: None,
+                acceptance_criteria: vec![Criterion {
+                    id: None,
+                    description: "Works".to_string(),
+                    weight: 1.0,
+                    examples: vec![],
+                    model_hint: None,
+                    rubric_category: None,
+                }],
+                estimated_minutes: None,
+                run_tests: false,
+            }])
+        };
 
-        let mut task_results = Vec::with_capacity(tasks.len());
+        let result = store.dry_run(&request);
+        assert!(!result.valid);
+        assert_eq!(result.errors.len(), 3, "{:?}", result.errors);
+        assert_eq!(result.estimated_prompt_chars, usize::MAX);
+        assert_eq!(store.config_errors(&request), result.errors);
+    }
 
-        for (task_index, task) in tasks.iter().enumerate() {
-            let _permit = task_semaphore.acquire().await.unwrap();
+    #[test]
+    fn test_validate_config_requires_llm_failure_budget() {
+        let config = GradeConfig { max_llm_failures: 0, ..GradeConfig::default() };
+        let mut errors = Vec::new();
+        GradeStore::validate_config(&config, &mut errors, &mut Vec::new());
+        assert_eq!(errors, vec!["config.max_llm_failures must be greater than 0".to_string()]);
+    }
 
-            {
-                let grades = self.grades.read().await;
-                if let Some(state) = grades.get(grade_id) {
-                    state.emit(GradeEvent::TaskStarted {
-                        task_index,
-                        task_title: task.title.clone(),
-                        criteria_count: task.acceptance_criteria.len(),
-                    });
-                }
-            }
+    #[tokio::test]
+    async fn test_subscribe() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = grade_request(vec![]);
 
-            let context = GradeContext::new(repo_url.to_string(), task.clone())
-                .with_files(files.to_vec());
-            let criteria_results = self
-                .process_criteria_parallel(
-                    grade_id,
-                    task_index,
-                    task,
-                    &context,
-                    client,
-                    grader,
-                    &criteria_semaphore,
-                )
-                .await;
+        let id = store.create_grade(request).await;
+        let receiver = store.subscribe(&id).await;
 
-            let (score, status, passed_count) = Self::calculate_task_score(&criteria_results);
+        assert!(receiver.is_some());
+    }
 
-            let task_result = TaskGradeResult {
-                task_title: task.title.clone(),
-                score,
-                status,
-                criteria_results,
-                passed_count,
-                total_count: task.acceptance_criteria.len(),
-            };
+    #[tokio::test]
+    async fn test_begin_cloning_and_mark_failed() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = grade_request(vec![]);
 
-            {
-                let grades = self.grades.read().await;
-                if let Some(state) = grades.get(grade_id) {
-                    state.emit(GradeEvent::TaskCompleted {
-                        task_index,
-                        task_title: task.title.clone(),
-                        score,
-                        status,
-                        passed_count,
-                        total_count: task.acceptance_criteria.len(),
-                    });
-                }
-            }
+        let id = store.create_grade(request).await;
+        store.begin_cloning(&id).await.unwrap();
+        assert_eq!(store.get_grade(&id).await.unwrap().status, GradeStatus::Cloning);
 
-            task_results.push(task_result);
-        }
+        store.mark_failed(&id, "clone failed".to_string()).await;
+        let report = store.get_grade(&id).await.unwrap();
+        assert_eq!(report.status, GradeStatus::Failed);
+        assert_eq!(report.error.as_deref(), Some("clone failed"));
 
-        task_results
+        assert!(store.begin_cloning("missing").await.is_err());
     }
 
-    async fn process_criteria_parallel(
-        &self,
-        grade_id: &str,
-        task_index: usize,
-        task: &GradeTask,
-        context: &GradeContext,
-        client: &dyn ModelClient,
-        grader: &CriteriaChecker,
-        semaphore: &Arc<Semaphore>,
-    ) -> Vec<CriterionResult> {
-        let mut results = Vec::with_capacity(task.acceptance_criteria.len());
-
-        for (criterion_index, criterion) in task.acceptance_criteria.iter().enumerate() {
-            let _permit = semaphore.acquire().await.unwrap();
+    #[tokio::test]
+    async fn test_watch_grade_ends_with_terminal_event() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = grade_request(vec![]);
 
-            let result = match grader.check_criterion(client, context, criterion).await {
-                Ok(result) => result,
-                Err(e) => {
-                    tracing::warn!(
-                        "Failed to check criterion '{}': {}",
-                        criterion.description,
-                        e
-                    );
-                    CriterionResult {
-                        criterion: criterion.description.clone(),
-                        passed: false,
-                        confidence: 0.0,
-                        evidence: format!("Error checking criterion: {}", e),
-                        code_references: vec![],
-                        weight: criterion.weight,
-                    }
-                }
-            };
+        let id = store.create_grade(request).await;
+        let live = store.watch_grade(&id).await.unwrap();
+        store.begin_cloning(&id).await.unwrap();
+        store.mark_failed(&id, "clone failed".to_string()).await;
+        let events: Vec<EventEnvelope<GradeEvent>> = live.collect().await;
+        assert!(matches!(events.last().unwrap().event, GradeEvent::GradeFailed { .. }));
+
+        // A finished grade yields its logged terminal event and ends
+        let finished: Vec<EventEnvelope<GradeEvent>> = store.watch_grade(&id).await.unwrap().collect().await;
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].seq, events.last().unwrap().seq);
+        assert!(finished[0].event.is_terminal());
+
+        assert!(store.watch_grade("missing").await.is_err());
+    }
 
-            {
-                let grades = self.grades.read().await;
-                if let Some(state) = grades.get(grade_id) {
-                    state.emit(GradeEvent::CriterionChecked {
-                        task_index,
-                        criterion_index,
-                        criterion: criterion.description.clone(),
-                        passed: result.passed,
-                        confidence: result.confidence,
-                    });
-                }
-            }
+    #[tokio::test]
+    async fn test_grade_without_source_code() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("README.md"), "# Course website\n".repeat(50)).unwrap();
+        std::fs::write(dir.path().join("main.py"), "print('hi')\n").unwrap();
+        let repo = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
 
-            results.push(result);
-        }
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = |no_source_policy| GradeRequest {
+            config: Some(GradeConfigOverrides::from(GradeConfig {
+                no_source_policy,
+                ..GradeConfig::default()
+            })),
+            ..grade_request(vec![GradeTask {
+                title: "Auth".to_string(),
+                description: None,
+                acceptance_criteria: vec![Criterion {
+                    id: None,
+                    description: "Login works".to_string(),
+                    weight: 1.0,
+                    examples: vec![],
+                    model_hint: None,
+                    rubric_category: None,
+                }],
+                estimated_minutes: None,
+                run_tests: false,
+            }])
+        };
 
-        results
+        // No LLM provider is configured, so reaching the criteria would error
+        let id = store.create_grade(request(NoSourcePolicy::Fail)).await;
+        let request_fail = request(NoSourcePolicy::Fail);
+        store.run_grade_with_repo(&id, request_fail, &repo, Instant::now()).await.unwrap();
+        let report = store.get_grade(&id).await.unwrap();
+        assert_eq!(report.status, GradeStatus::Failed);
+        assert_eq!(report.error.as_deref(), Some(NO_SOURCE_ERROR));
+        assert_eq!(report.started_grading_at, None);
+        let events = store.grade_events(&id).await.unwrap();
+        assert!(matches!(
+            &events.last().unwrap().event,
+            GradeEvent::GradeFailed { error, recoverable: false } if error == NO_SOURCE_ERROR
+        ));
+
+        let id = store.create_grade(request(NoSourcePolicy::ZeroGrade)).await;
+        let request_zero = request(NoSourcePolicy::ZeroGrade);
+        store.run_grade_with_repo(&id, request_zero, &repo, Instant::now()).await.unwrap();
+        let report = store.get_grade(&id).await.unwrap();
+        assert_eq!(report.status, GradeStatus::Completed);
+        assert_eq!(report.percentage, 0);
+        let criterion = &report.tasks[0].criteria_results[0];
+        assert_eq!(criterion.outcome, CriterionOutcome::Failed);
+        assert_eq!(criterion.evidence, NO_SOURCE_ERROR);
+        let events = store.grade_events(&id).await.unwrap();
+        assert!(matches!(
+            &events.last().unwrap().event,
+            GradeEvent::GradeCompleted { reason: Some(reason), percentage: 0, .. } if reason == NO_SOURCE_ERROR
+        ));
     }
 
-    fn calculate_task_score(criteria_results: &[CriterionResult]) -> (f32, TaskStatus, usize) {
-        if criteria_results.is_empty() {
-            return (0.0, TaskStatus::Failed, 0);
-        }
-
-        let total_weight: f32 = criteria_results.iter().map(|r| r.weight).sum();
-        let passed_weight: f32 = criteria_results
+    #[tokio::test]
+    async fn test_run_checkers_emits_check_events() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("app.js"), "var x = 1;\nif (x == 2) { console.log(x); }\n").unwrap();
+        let profile = RepoProfile::detect(dir.path(), FileFilter::default());
+        let request = grade_request(vec![]);
+        let state = Arc::new(RwLock::new(GradeState::new("g1".to_string(), &request)));
+
+        let summary = GradeStore::run_checkers(&state, dir.path(), &profile, FileFilter::default()).await;
+
+        assert_eq!(summary.checks, checkers_for(&profile).iter().map(|c| c.check_type()).collect::<Vec<_>>());
+        assert!(summary.counts.iter().any(|(check_type, rule, ..)| {
+            *check_type == CheckType::Lint && rule == "strict-equality"
+        }));
+        let events = state.read().await.events();
+        let lint = events.iter().find_map(|e| match e.event {
+            GradeEvent::CheckCompleted {
+                check_type: CheckType::Lint,
+                diagnostics_count,
+                ..
+            } => Some(diagnostics_count),
+            _ => None,
+        });
+        assert!(lint.unwrap() > 0);
+        let started = events
             .iter()
-            .filter(|r| r.passed)
-            .map(|r| r.weight)
-            .sum();
+            .filter(|e| matches!(e.event, GradeEvent::CheckStarted { .. }))
+            .count();
+        assert_eq!(started, summary.checks.len());
+    }
 
-        let score = if total_weight > 0.0 {
-            passed_weight / total_weight
-        } else {
-            0.0
+    #[tokio::test]
+    async fn test_redactor_uses_metadata_student_id() {
+        let mut request = GradeRequest {
+            metadata: Some(GradeMetadata {
+                student_id: Some("s-4821".to_string()),
+                ..Default::default()
+            }),
+            ..grade_request(vec![])
         };
+        let mut config = GradeConfig::default();
+        assert!(GradeStore::redactor(&request, &config).unwrap().is_none());
+
+        config.redact_pii = true;
+        let redactor = GradeStore::redactor(&request, &config).unwrap().unwrap();
+        let files = redactor.redact_files(vec![("app.js".to_string(), "// s-4821 a@b.io
+".to_string())]);
+        assert_eq!(files[0].1, "// [REDACTED] [REDACTED]\n");
+
+        config.student_id_pattern = Some("[".to_string());
+        request.config = Some(config.clone().into());
+        assert!(matches!(GradeStore::redactor(&request, &config), Err(ApiError::BadRequest(_))));
+        let dry_run = GradeStore::new(3600, None, GradeConfig::default(), None).dry_run(&request);
+        assert!(dry_run.errors.iter().any(|e| e.starts_with("config.student_id_pattern")));
+    }
 
-        let passed_count = criteria_results.iter().filter(|r| r.passed).count();
+    #[tokio::test]
+    async fn test_grade_similarity_fingerprint() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("main.py"), "def add(a, b):\n    return a + b\n".repeat(5)).unwrap();
+        let repo = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
 
-        let status = if score >= 1.0 {
-            TaskStatus::Passed
-        } else if score > 0.0 {
-            TaskStatus::Partial
-        } else {
-            TaskStatus::Failed
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = |similarity_fingerprint| GradeRequest {
+            config: Some(GradeConfigOverrides::from(GradeConfig {
+                similarity_fingerprint,
+                ..GradeConfig::default()
+            })),
+            context_files: vec![("starter.py".to_string(), "print('provided')".to_string())],
+            ..grade_request(vec![])
         };
 
-        (score, status, passed_count)
+        for enabled in [false, true] {
+            let id = store.create_grade(request(enabled)).await;
+            // Without an LLM provider the grade fails after analysis
+            let _ = store.run_grade_with_repo(&id, request(enabled), &repo, Instant::now()).await;
+            let state = store.state(&id).await.unwrap();
+            let fingerprint = state.read().await.fingerprint.clone();
+            if enabled {
+                // Provided files aren't the student's work
+                let paths: Vec<_> = fingerprint.unwrap().files.into_iter().map(|file| file.path).collect();
+                assert_eq!(paths, vec!["main.py".to_string()]);
+            } else {
+                assert!(fingerprint.is_none());
+            }
+        }
+        assert!(store.similarity("Web 101", 0.8, 10).await.is_err());
     }
 
-    fn calculate_final_score(task_results: &[TaskGradeResult]) -> (f32, u32, String, String) {
-        if task_results.is_empty() {
-            return (0.0, 0, "N/A".to_string(), "No tasks to grade".to_string());
+    #[tokio::test]
+    async fn test_file_loaded_events() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        for name in ["src/app.js", "src/db.js", "index.js"] {
+            std::fs::write(dir.path().join(name), "export const x = 1;\n".repeat(5)).unwrap();
         }
+        let repo = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = GradeRequest {
+            context_files: vec![("spec.md".to_string(), "# Spec".to_string())],
+            ..grade_request(vec![])
+        };
 
-        let overall_score: f32 =
-            task_results.iter().map(|t| t.score).sum::<f32>() / task_results.len() as f32;
-        let percentage = (overall_score * 100.0).round() as u32;
-
-        let grade = match percentage {
-            90..=100 => "우수",
-            75..=89 => "양호",
-            60..=74 => "보통",
-            40..=59 => "미흡",
-            _ => "불합격",
-        }
-        .to_string();
+        let id = store.create_grade(request.clone()).await;
+        // Without an LLM provider the grade fails after analysis
+        let _ = store.run_grade_with_repo(&id, request, &repo, Instant::now()).await;
 
-        let passed_tasks = task_results
+        let events = store.state(&id).await.unwrap().read().await.events();
+        let loaded: Vec<_> = events
             .iter()
-            .filter(|t| t.status == TaskStatus::Passed)
-            .count();
-        let total_tasks = task_results.len();
-        let total_criteria: usize = task_results.iter().map(|t| t.total_count).sum();
-        let passed_criteria: usize = task_results.iter().map(|t| t.passed_count).sum();
+            .filter_map(|e| match &e.event {
+                GradeEvent::FileLoaded { path, file_index, total_files } => Some((path.clone(), *file_index, *total_files)),
+                _ => None,
+            })
+            .collect();
+        let file_count = events.iter().find_map(|e| match e.event {
+            GradeEvent::AnalysisCompleted { file_count, .. } => Some(file_count),
+            _ => None,
+        });
+        assert_eq!(file_count, Some(3));
+        assert_eq!(loaded.len(), 3);
+        assert!(loaded.iter().enumerate().all(|(i, (_, index, total))| *index == i && *total == 3));
+        let mut paths: Vec<_> = loaded.into_iter().map(|(path, ..)| path).collect();
+        paths.sort();
+        assert_eq!(paths, ["index.js", "src/app.js", "src/db.js"]);
+    }
 
-        let summary = format!(
-            "전체 점수: {}점 ({}) - 과제 {}/{} 완료, 기준 {}/{} 충족",
-            percentage, grade, passed_tasks, total_tasks, passed_criteria, total_criteria
+    #[tokio::test]
+    async fn test_regrade_links_original() {
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        let request = grade_request(vec![]);
+
+        let id = store
+            .insert_grade(
+                "g1".to_string(),
+                request,
+                Some("original".to_string()),
+                Some("abc123".to_string()),
+            )
+            .await;
+        let report = store.get_grade(&id).await.unwrap();
+        assert_eq!(report.regrade_of.as_deref(), Some("original"));
+        assert_eq!(report.commit_sha.as_deref(), Some("abc123"));
+
+        // Loading the original job needs MongoDB
+        let result = store.create_regrade("original", vec![]).await;
+        assert!(matches!(result, Err(ApiError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_model_hints_route_to_configured_models() {
+        let providers = ProvidersConfig {
+            anthropic_api_key: Some("sk-ant-test".to_string().into()),
+            cheap_model: Some("claude-3-5-haiku-latest".to_string()),
+            ..ProvidersConfig::default()
+        };
+        let store = GradeStore::new(3600, Some(providers), GradeConfig::default(), None);
+        let criterion = |model_hint| Criterion {
+            id: None,
+            description: "README exists".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint,
+            rubric_category: None,
+        };
+        let mut request = grade_request(vec![GradeTask {
+            title: "Task".to_string(),
+            description: None,
+            acceptance_criteria: vec![
+                criterion(Some(ModelHint::Cheap)),
+                criterion(Some(ModelHint::Strong)),
+            ],
+            estimated_minutes: None,
+            run_tests: false,
+        }]);
+        let config = GradeConfig::default();
+
+        let clients = store.grader_clients(&config, &request).unwrap();
+        let default_model = clients.default.model().to_string();
+        let model_for = |hint| clients.for_criterion(&criterion(hint)).model().to_string();
+        assert_eq!(model_for(Some(ModelHint::Cheap)), "claude-3-5-haiku-latest");
+        // No strong model configured
+        assert_eq!(model_for(Some(ModelHint::Strong)), default_model);
+        assert_eq!(model_for(None), default_model);
+
+        // A pinned model overrides hints
+        request.model = Some("claude-opus-4-20250514".to_string());
+        let clients = store.grader_clients(&config, &request).unwrap();
+        assert_eq!(
+            clients.for_criterion(&criterion(Some(ModelHint::Cheap))).model(),
+            "claude-opus-4-20250514"
         );
 
-        (overall_score, percentage, grade, summary)
+        // The grader model replaces the provider default for unhinted criteria
+        let providers = ProvidersConfig {
+            anthropic_api_key: Some("sk-ant-test".to_string().into()),
+            grader_model: Some("claude-sonnet-4-20250514".to_string()),
+            ..ProvidersConfig::default()
+        };
+        let store = GradeStore::new(3600, Some(providers), GradeConfig::default(), None);
+        request.model = None;
+        let clients = store.grader_clients(&config, &request).unwrap();
+        assert_eq!(clients.for_criterion(&criterion(None)).model(), "claude-sonnet-4-20250514");
     }
 
-    fn read_source_files(repo_path: &Path, max_files: usize) -> Vec<(String, String)> {
-        let mut files = Vec::new();
-        let extensions = [
-            "rs", "ts", "tsx", "js", "jsx", "py", "go", "java", "kt", "swift", "c", "cpp", "h",
-            "hpp", "cs", "rb", "php", "html", "css", "json", "yaml", "yml", "toml", "md",
-        ];
+    #[tokio::test]
+    async fn test_validate_model_against_provider() {
+        let providers = ProvidersConfig {
+            anthropic_api_key: Some("sk-ant-test".to_string().into()),
+            ..ProvidersConfig::default()
+        };
+        let store = GradeStore::new(3600, Some(providers), GradeConfig::default(), None);
 
-        for entry in walkdir::WalkDir::new(repo_path)
-            .max_depth(10)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .filter(|e| {
-                let path = e.path();
-                // Skip hidden files and common non-source directories
-                !path
-                    .components()
-                    .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
-                    && !path
-                        .components()
-                        .any(|c| ["node_modules", "target", "dist", "build", "__pycache__"].contains(&c.as_os_str().to_str().unwrap_or("")))
-            })
-            .filter(|e| {
-                e.path()
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(|ext| extensions.contains(&ext))
-                    .unwrap_or(false)
-            })
-            .take(max_files)
-        {
-            if let Ok(content) = std::fs::read_to_string(entry.path()) {
-                let relative_path = entry
-                    .path()
-                    .strip_prefix(repo_path)
-                    .unwrap_or(entry.path())
-                    .to_string_lossy()
-                    .to_string();
-                files.push((relative_path, content));
-            }
-        }
+        assert!(store.validate_model(None).is_ok());
+        assert!(store.validate_model(Some("claude-sonnet-4-20250514")).is_ok());
+        assert!(matches!(
+            store.validate_model(Some("gpt-4o")),
+            Err(ApiError::BadRequest(_))
+        ));
 
-        files
+        // Nothing to check against without a provider
+        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
+        assert!(store.validate_model(Some("gpt-4o")).is_ok());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::Criterion;
+    #[test]
+    fn test_read_source_files_only_changed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("starter.py"), "print('hi')\n").unwrap();
+        std::fs::write(dir.path().join("solution.py"), "def solve(): pass\n").unwrap();
+        let profile = RepoProfile::detect(dir.path(), FileFilter::default());
+        let changed: HashSet<String> = ["solution.py".to_string()].into();
+
+        let (files, tree) = GradeStore::read_source_files(
+            dir.path(),
+            &GradeConfig::default(),
+            &profile,
+            Some(&changed),
+            FileFilter::default(),
+        );
 
-    #[tokio::test]
-    async fn test_create_and_get_grade() {
-        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
-        let request = GradeRequest {
-            repo_url: "https://github.com/test/repo".to_string(),
-            branch: None,
-            tasks: vec![GradeTask {
-                title: "Test Task".to_string(),
-                description: None,
-                acceptance_criteria: vec![Criterion {
-                    id: None,
-                    description: "Test criterion".to_string(),
-                    weight: 1.0,
-                }],
-                estimated_minutes: None,
-            }],
-            config: None,
-            metadata: None,
-            curriculum_id: None,
-            task_id: None,
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["solution.py"]);
+        // The tree still lists the starter code
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    fn test_read_source_files_priority_patterns() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("lib")).unwrap();
+        std::fs::write(dir.path().join("lib/helpers.py"), "def h(): pass\n").unwrap();
+        std::fs::write(dir.path().join("app.py"), "print('app')\n").unwrap();
+        let profile = RepoProfile::detect(dir.path(), FileFilter::default());
+        let config = GradeConfig {
+            max_files: 1,
+            file_priority_patterns: vec!["helpers.*".to_string()],
+            ..GradeConfig::default()
         };
 
-        let id = store.create_grade(request).await;
-        let report = store.get_grade(&id).await;
+        let (files, tree) =
+            GradeStore::read_source_files(dir.path(), &config, &profile, None, FileFilter::default());
 
-        assert!(report.is_some());
-        let report = report.unwrap();
-        assert_eq!(report.repo_url, "https://github.com/test/repo");
-        assert_eq!(report.status, GradeStatus::Pending);
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["lib/helpers.py"]);
+        assert_eq!(tree.len(), 2);
     }
 
-    #[tokio::test]
-    async fn test_subscribe() {
-        let store = GradeStore::new(3600, None, GradeConfig::default(), None);
-        let request = GradeRequest {
-            repo_url: "https://github.com/test/repo".to_string(),
-            branch: None,
+    #[test]
+    fn test_event_capacity_scales_with_criteria() {
+        let criterion = Criterion {
+            id: None,
+            description: "c".to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: None,
+        };
+        let request = grade_request(vec![GradeTask {
+            title: "Task".to_string(),
+            description: None,
+            acceptance_criteria: vec![criterion; 300],
+            estimated_minutes: None,
+            run_tests: false,
+        }]);
+
+        let config = GradeConfig::default();
+        // Base events, one per file, task start/complete and criterion
+        assert_eq!(GradeState::event_capacity(&request, &config), 8 + 30 + 2 + 300);
+
+        let small = GradeRequest {
             tasks: vec![],
-            config: None,
-            metadata: None,
-            curriculum_id: None,
-            task_id: None,
+            ..request
         };
+        assert_eq!(GradeState::event_capacity(&small, &config), 100);
 
-        let id = store.create_grade(request).await;
-        let receiver = store.subscribe(&id).await;
-
-        assert!(receiver.is_some());
+        let huge = GradeConfig {
+            event_buffer_size: usize::MAX,
+            max_files: usize::MAX,
+            ..GradeConfig::default()
+        };
+        assert_eq!(GradeState::event_capacity(&small, &huge), MAX_EVENT_BUFFER_SIZE);
+        let oversized = GradeConfig { event_buffer_size: usize::MAX, ..GradeConfig::default() };
+        let mut errors = Vec::new();
+        GradeStore::validate_config(&oversized, &mut errors, &mut Vec::new());
+        assert!(errors.iter().any(|e| e.contains("event_buffer_size")), "{:?}", errors);
     }
 
     #[test]
@@ -608,28 +3464,129 @@ mod tests {
         let results = vec![
             CriterionResult {
                 criterion: "A".to_string(),
+                criterion_id: None,
+                task_index: 0,
                 passed: true,
+                outcome: CriterionOutcome::Passed,
                 confidence: 0.9,
                 evidence: "".to_string(),
                 code_references: vec![],
                 weight: 1.0,
+                human_override: None,
             },
             CriterionResult {
                 criterion: "B".to_string(),
+                criterion_id: None,
+                task_index: 0,
                 passed: false,
+                outcome: CriterionOutcome::Failed,
                 confidence: 0.8,
                 evidence: "".to_string(),
                 code_references: vec![],
                 weight: 1.0,
+                human_override: None,
             },
         ];
 
-        let (score, status, passed_count) = GradeStore::calculate_task_score(&results);
+        let (score, status, passed_count) =
+            GradeStore::calculate_task_score(&results, UnverifiablePolicy::default());
         assert!((score - 0.5).abs() < 0.01);
         assert_eq!(status, TaskStatus::Partial);
         assert_eq!(passed_count, 1);
     }
 
+    #[test]
+    fn test_unverifiable_policy() {
+        let result = |criterion: &str, outcome: CriterionOutcome| CriterionResult {
+            criterion: criterion.to_string(),
+            criterion_id: None,
+            task_index: 0,
+            passed: outcome == CriterionOutcome::Passed,
+            outcome,
+            confidence: 0.9,
+            evidence: String::new(),
+            code_references: vec![],
+            weight: 1.0,
+            human_override: None,
+        };
+        let results = vec![
+            result("A", CriterionOutcome::Passed),
+            result("Responds within 200ms", CriterionOutcome::NotVerifiable),
+        ];
+        let score = |policy| GradeStore::calculate_task_score(&results, policy).0;
+
+        assert!((score(UnverifiablePolicy::ExcludeFromScore) - 1.0).abs() < 0.01);
+        assert!((score(UnverifiablePolicy::CountAsFailed) - 0.5).abs() < 0.01);
+        assert!((score(UnverifiablePolicy::CountAsPassed) - 1.0).abs() < 0.01);
+
+        let task_results = vec![TaskGradeResult {
+            task_title: "Performance".to_string(),
+            score: 1.0,
+            status: TaskStatus::Passed,
+            criteria_results: results.clone(),
+            passed_count: 1,
+            total_count: 2,
+        }];
+        let summary = GradeStore::calculate_final_score(&[], &task_results, 0, UnverifiablePolicy::default()).summary;
+        assert!(summary.ends_with("\n검증 불가 기준 1개 (수동 검토 필요):\n- [Performance] Responds within 200ms"));
+    }
+
+    #[test]
+    fn test_apply_override() {
+        let result = |criterion: &str, outcome: CriterionOutcome| CriterionResult {
+            criterion: criterion.to_string(),
+            criterion_id: None,
+            task_index: 0,
+            passed: outcome == CriterionOutcome::Passed,
+            outcome,
+            confidence: 0.9,
+            evidence: String::new(),
+            code_references: vec![],
+            weight: 1.0,
+            human_override: None,
+        };
+        let mut task_results = vec![TaskGradeResult {
+            task_title: "Login".to_string(),
+            score: 1.0,
+            status: TaskStatus::Passed,
+            criteria_results: vec![
+                result("Form exists", CriterionOutcome::Passed),
+                result("Responds within 200ms", CriterionOutcome::NotVerifiable),
+            ],
+            passed_count: 1,
+            total_count: 2,
+        }];
+        let verdict = |passed| CriterionOverride {
+            passed,
+            note: Some("checked by hand".to_string()),
+            overridden_at: 0,
+        };
+
+        let score = GradeStore::apply_override(
+            &[],
+            &mut task_results,
+            0,
+            1,
+            verdict(false),
+            UnverifiablePolicy::ExcludeFromScore,
+            0,
+        )
+        .unwrap();
+        assert!((score.overall_score - 0.5).abs() < 0.01);
+        assert_eq!(score.percentage, 50);
+        assert!(!score.summary.contains("검증 불가"));
+        assert_eq!(task_results[0].status, TaskStatus::Partial);
+        // The grader's own verdict is kept next to the override
+        let overridden = &task_results[0].criteria_results[1];
+        assert_eq!(overridden.outcome, CriterionOutcome::NotVerifiable);
+        assert_eq!(overridden.human_override.as_ref().unwrap().note.as_deref(), Some("checked by hand"));
+
+        assert!(matches!(
+            GradeStore::apply_override(&[], &mut task_results, 0, 2, verdict(true), UnverifiablePolicy::default(), 0),
+            Err(ApiError::NotFound(_))
+        ));
+    }
+
     #[test]
     fn test_calculate_final_score() {
         let task_results = vec![
@@ -651,9 +3608,74 @@ mod tests {
             },
         ];
 
-        let (score, percentage, grade, _summary) = GradeStore::calculate_final_score(&task_results);
-        assert!((score - 0.75).abs() < 0.01);
-        assert_eq!(percentage, 75);
-        assert_eq!(grade, "양호");
+        let policy = UnverifiablePolicy::default();
+        let score = GradeStore::calculate_final_score(&[], &task_results, 0, policy);
+        assert!((score.overall_score - 0.75).abs() < 0.01);
+        assert_eq!(score.percentage, 75);
+        assert_eq!(score.grade, "양호");
+
+        let score = GradeStore::calculate_final_score(&[], &task_results, 20, policy);
+        assert!((score.overall_score - 0.6).abs() < 0.01);
+        assert_eq!(score.percentage, 60);
+        assert_eq!(score.grade, "보통");
+        assert!(score.summary.ends_with("지각 감점 20%"));
+    }
+
+    #[test]
+    fn test_rubric_breakdown() {
+        let criterion = |description: &str, rubric_category: Option<&str>| Criterion {
+            id: None,
+            description: description.to_string(),
+            weight: 1.0,
+            examples: vec![],
+            model_hint: None,
+            rubric_category: rubric_category.map(str::to_string),
+        };
+        let tasks = vec![GradeTask {
+            title: "Login".to_string(),
+            description: None,
+            acceptance_criteria: vec![
+                criterion("Form exists", Some("Correctness")),
+                criterion("Password is validated", Some("Correctness")),
+                criterion("Login has unit tests", Some("Tests")),
+                criterion("README explains setup", None),
+            ],
+            estimated_minutes: None,
+            run_tests: false,
+        }];
+        let result = |criterion: &str, passed: bool| CriterionResult {
+            criterion: criterion.to_string(),
+            criterion_id: None,
+            task_index: 0,
+            passed,
+            outcome: if passed { CriterionOutcome::Passed } else { CriterionOutcome::Failed },
+            confidence: 0.9,
+            evidence: String::new(),
+            code_references: vec![],
+            weight: 1.0,
+            human_override: None,
+        };
+        let task_results = vec![TaskGradeResult {
+            task_title: "Login".to_string(),
+            score: 0.5,
+            status: TaskStatus::Partial,
+            criteria_results: vec![
+                result("Form exists", true),
+                result("Password is validated", false),
+                result("Login has unit tests", true),
+                result("README explains setup", false),
+            ],
+            passed_count: 2,
+            total_count: 4,
+        }];
+
+        let breakdown =
+            GradeStore::calculate_final_score(&tasks, &task_results, 20, UnverifiablePolicy::default()).rubric_breakdown;
+        assert_eq!(breakdown.keys().collect::<Vec<_>>(), ["Correctness", DEFAULT_RUBRIC_CATEGORY, "Tests"]);
+        let correctness = &breakdown["Correctness"];
+        assert_eq!((correctness.percentage, correctness.criteria_count, correctness.passed_count), (50, 2, 1));
+        // The late penalty only applies to the overall score
+        assert_eq!(breakdown["Tests"].percentage, 100);
+        assert_eq!(breakdown[DEFAULT_RUBRIC_CATEGORY].passed_count, 0);
     }
 }
@@ -0,0 +1,359 @@
+//! Idempotency keys for the create endpoints
+//!
+//! A client that retries `POST /api/grade` or `POST /api/review` with the
+//! same `Idempotency-Key` gets the job created by the first attempt instead
+//! of a duplicate. Keys are claimed before the job is created, under a
+//! per-key lock in memory and with an atomic upsert in MongoDB, so
+//! concurrent retries can't both create one.
+
+use actix_web::HttpRequest;
+use bson::DateTime as BsonDateTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::db::{IdempotencyRecord, IdempotencyRepository};
+use crate::error::ApiError;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Set on responses that return the job of an earlier request
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// How long a key keeps pointing at its job
+pub const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A create request with an optional `idempotency_key` body field
+#[derive(Debug, Clone, Deserialize)]
+pub struct Idempotent<T> {
+    #[serde(flatten)]
+    pub request: T,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Claim {
+    /// The key is new and its job was created with this id
+    New(String),
+    /// The key was used before with the same request and its job exists
+    Existing(String),
+}
+
+struct Entry {
+    request_hash: String,
+    job_id: String,
+    created_at: Instant,
+}
+
+/// Keys of one kind of job, e.g. grades
+#[derive(Clone)]
+pub struct IdempotencyKeys {
+    scope: &'static str,
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+    /// Held by the request claiming a key, so only retries of the same key
+    /// wait on each other
+    claiming: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+    repo: Option<Arc<IdempotencyRepository>>,
+}
+
+impl IdempotencyKeys {
+    pub fn new(scope: &'static str) -> Self {
+        Self {
+            scope,
+            entries: Arc::default(),
+            claiming: Arc::default(),
+            repo: None,
+        }
+    }
+
+    pub fn with_repo(mut self, repo: Option<Arc<IdempotencyRepository>>) -> Self {
+        self.repo = repo;
+        self
+    }
+
+    /// Claim `key` for a request hashing to `request_hash`, calling `create`
+    /// with a new job id when the key is new. A key whose job no longer
+    /// `exists` is claimed again; one used with a different request is a
+    /// conflict.
+    pub async fn claim<E, EFut, C, CFut>(
+        &self,
+        key: &str,
+        request_hash: &str,
+        exists: E,
+        create: C,
+    ) -> Result<Claim, ApiError>
+    where
+        E: Fn(String) -> EFut,
+        EFut: Future<Output = bool>,
+        C: FnOnce(String) -> CFut,
+        CFut: Future,
+    {
+        let lock = Arc::clone(self.claiming.lock().unwrap().entry(key.to_string()).or_default());
+        // Held until the job is created so concurrent retries find it
+        let claimed = {
+            let _claiming = lock.lock().await;
+            self.claim_locked(key, request_hash, exists, create).await
+        };
+
+        let mut claiming = self.claiming.lock().unwrap();
+        // Only this map and `lock` left means nobody else is waiting
+        if Arc::strong_count(&lock) == 2 {
+            claiming.remove(key);
+        }
+        claimed
+    }
+
+    async fn claim_locked<E, EFut, C, CFut>(
+        &self,
+        key: &str,
+        request_hash: &str,
+        exists: E,
+        create: C,
+    ) -> Result<Claim, ApiError>
+    where
+        E: Fn(String) -> EFut,
+        EFut: Future<Output = bool>,
+        C: FnOnce(String) -> CFut,
+        CFut: Future,
+    {
+        let cached = {
+            let mut entries = self.entries.lock().unwrap();
+            entries.retain(|_, entry| entry.created_at.elapsed() < IDEMPOTENCY_KEY_TTL);
+            entries.get(key).map(|entry| (entry.request_hash.clone(), entry.job_id.clone()))
+        };
+        if let Some((stored_hash, job_id)) = cached {
+            Self::check_request(key, &stored_hash, request_hash)?;
+            if exists(job_id.clone()).await {
+                return Ok(Claim::Existing(job_id));
+            }
+            self.entries.lock().unwrap().remove(key);
+            self.release_stored(key).await;
+        }
+
+        let record = IdempotencyRecord {
+            id: format!("{}:{}", self.scope, key),
+            request_hash: request_hash.to_string(),
+            job_id: uuid::Uuid::new_v4().to_string(),
+            created_at: BsonDateTime::now(),
+        };
+        let (record, existing) = match self.claim_stored(&record, &exists).await {
+            Some(stored) => (stored, true),
+            None => (record, false),
+        };
+
+        self.entries.lock().unwrap().insert(
+            key.to_string(),
+            Entry {
+                request_hash: record.request_hash.clone(),
+                job_id: record.job_id.clone(),
+                created_at: Instant::now(),
+            },
+        );
+        Self::check_request(key, &record.request_hash, request_hash)?;
+        if existing {
+            return Ok(Claim::Existing(record.job_id));
+        }
+        create(record.job_id.clone()).await;
+        Ok(Claim::New(record.job_id))
+    }
+
+    /// Forget `key`, e.g. when its job couldn't be queued, so a retry
+    /// creates a new one
+    pub async fn release(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+        self.release_stored(key).await;
+    }
+
+    /// Store `record` in MongoDB. Returns the live record another request
+    /// stored first, if any.
+    async fn claim_stored<E, EFut>(&self, record: &IdempotencyRecord, exists: &E) -> Option<IdempotencyRecord>
+    where
+        E: Fn(String) -> EFut,
+        EFut: Future<Output = bool>,
+    {
+        let repo = self.repo.as_ref()?;
+        let stored = match repo.claim(record).await {
+            Ok(stored) => stored?,
+            Err(e) => {
+                tracing::warn!("Failed to store idempotency key {}: {}", record.id, e);
+                return None;
+            }
+        };
+
+        if stored.request_hash == record.request_hash && !exists(stored.job_id.clone()).await {
+            // Its job expired; take the key over
+            if let Err(e) = repo.release(&record.id).await {
+                tracing::warn!("Failed to release idempotency key {}: {}", record.id, e);
+            }
+            return match repo.claim(record).await {
+                Ok(stored) => stored,
+                Err(e) => {
+                    tracing::warn!("Failed to store idempotency key {}: {}", record.id, e);
+                    None
+                }
+            };
+        }
+        Some(stored)
+    }
+
+    async fn release_stored(&self, key: &str) {
+        if let Some(repo) = &self.repo {
+            let id = format!("{}:{}", self.scope, key);
+            if let Err(e) = repo.release(&id).await {
+                tracing::warn!("Failed to release idempotency key {}: {}", id, e);
+            }
+        }
+    }
+
+    fn check_request(key: &str, stored_hash: &str, request_hash: &str) -> Result<(), ApiError> {
+        if stored_hash != request_hash {
+            return Err(ApiError::Conflict(format!(
+                "Idempotency-Key {} was already used with a different request",
+                key
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The `Idempotency-Key` header, or else the body's `idempotency_key`
+pub fn idempotency_key(req: &HttpRequest, body_key: Option<&str>) -> Result<Option<String>, ApiError> {
+    let header_key = req
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .map_err(|_| ApiError::BadRequest("Idempotency-Key must be visible ASCII".to_string()))
+        })
+        .transpose()?;
+    if let (Some(header_key), Some(body_key)) = (header_key, body_key) {
+        if header_key != body_key {
+            return Err(ApiError::BadRequest(
+                "Idempotency-Key header and idempotency_key differ".to_string(),
+            ));
+        }
+    }
+
+    let Some(key) = header_key.or(body_key) else {
+        return Ok(None);
+    };
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "Idempotency-Key must be 1 to {} characters",
+            MAX_IDEMPOTENCY_KEY_LEN
+        )));
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// SHA-256 of a request body, to tell retries from reuse of a key
+pub fn request_hash<T: Serialize>(request: &T) -> String {
+    let body = serde_json::to_vec(request).unwrap_or_default();
+    hex::encode(Sha256::digest(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[tokio::test]
+    async fn test_claim_replays_and_conflicts() {
+        let keys = IdempotencyKeys::new("grade");
+        let live = |_: String| async { true };
+        let created = std::sync::Mutex::new(Vec::new());
+        let create = |id: String| async { created.lock().unwrap().push(id) };
+
+        let Claim::New(id) = keys.claim("k1", "hash-a", live, create).await.unwrap() else {
+            panic!("first claim should be new");
+        };
+        assert_eq!(keys.claim("k1", "hash-a", live, create).await.unwrap(), Claim::Existing(id.clone()));
+        assert!(matches!(keys.claim("k1", "hash-b", live, create).await, Err(ApiError::Conflict(_))));
+
+        // A key whose job is gone starts over
+        let Claim::New(second) = keys.claim("k1", "hash-a", |_| async { false }, create).await.unwrap() else {
+            panic!("expired job should be replaced");
+        };
+        assert_ne!(second, id);
+
+        keys.release("k1").await;
+        assert!(matches!(keys.claim("k1", "hash-b", live, create).await.unwrap(), Claim::New(_)));
+        assert_eq!(created.lock().unwrap().len(), 3);
+        assert_eq!(created.lock().unwrap()[..2], [id, second]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_claims_create_one_job() {
+        let keys = IdempotencyKeys::new("review");
+        let jobs = std::sync::Mutex::new(std::collections::HashSet::new());
+        let exists = |id: String| {
+            let jobs = &jobs;
+            async move { jobs.lock().unwrap().contains(&id) }
+        };
+        let create = |id: String| {
+            let jobs = &jobs;
+            async move {
+                // Let the other requests run while the job is being created
+                tokio::task::yield_now().await;
+                jobs.lock().unwrap().insert(id);
+            }
+        };
+
+        let claims =
+            futures::future::join_all((0..8).map(|_| keys.claim("k", "hash", exists, create))).await;
+
+        assert_eq!(jobs.lock().unwrap().len(), 1);
+        let new = claims.iter().filter(|c| matches!(c, Ok(Claim::New(_)))).count();
+        assert_eq!(new, 1);
+        let ids: std::collections::HashSet<_> = claims
+            .into_iter()
+            .map(|c| match c.unwrap() {
+                Claim::New(id) | Claim::Existing(id) => id,
+            })
+            .collect();
+        assert_eq!(ids.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_claims_of_different_keys_run_concurrently() {
+        let keys = IdempotencyKeys::new("grade");
+        let live = |_: String| async { true };
+        let (created_tx, created_rx) = tokio::sync::oneshot::channel::<()>();
+
+        // Creating k1's job waits for k2's claim to finish
+        let slow = keys.claim("k1", "hash", live, |_| async move { created_rx.await.unwrap() });
+        let fast = async {
+            let claim = keys.claim("k2", "hash", live, |_| async {}).await;
+            created_tx.send(()).unwrap();
+            claim
+        };
+        let (slow, fast) = tokio::time::timeout(Duration::from_secs(1), futures::future::join(slow, fast))
+            .await
+            .expect("claims of different keys should not wait on each other");
+
+        assert!(matches!(slow.unwrap(), Claim::New(_)));
+        assert!(matches!(fast.unwrap(), Claim::New(_)));
+        assert!(keys.claiming.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_idempotency_key_sources() {
+        let req = TestRequest::default().insert_header((IDEMPOTENCY_KEY_HEADER, "abc")).to_http_request();
+        assert_eq!(idempotency_key(&req, None).unwrap().as_deref(), Some("abc"));
+        assert_eq!(idempotency_key(&req, Some("abc")).unwrap().as_deref(), Some("abc"));
+        assert!(idempotency_key(&req, Some("xyz")).is_err());
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(idempotency_key(&req, Some("xyz")).unwrap().as_deref(), Some("xyz"));
+        assert_eq!(idempotency_key(&req, None).unwrap(), None);
+        assert!(idempotency_key(&req, Some("")).is_err());
+        assert!(idempotency_key(&req, Some(&"k".repeat(MAX_IDEMPOTENCY_KEY_LEN + 1))).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Import extraction for JS/TS, powered by OXC
+//!
+//! Gives AI reviewers a compact view of which modules each file depends on,
+//! so architecture suggestions can reference concrete relationships.
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{
+    CallExpression, ExportAllDeclaration, ExportNamedDeclaration, Expression, ImportDeclaration,
+    ImportExpression,
+};
+use oxc_ast::visit::walk;
+use oxc_ast::Visit;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+/// Characters of dependency summary included in reviewer prompts
+pub const DEPENDENCY_SUMMARY_MAX_CHARS: usize = 3000;
+
+const JS_TS_EXTENSIONS: [&str; 6] = ["js", "jsx", "mjs", "cjs", "ts", "tsx"];
+
+/// Modules imported by one file, in source order without duplicates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileImports {
+    pub path: String,
+    pub imports: Vec<String>,
+}
+
+/// Per-file imports of a repository's JS/TS files
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    pub files: Vec<FileImports>,
+}
+
+impl DependencyGraph {
+    /// Imports of every JS/TS file in `files`; other languages are skipped
+    pub fn from_files(files: &[(String, String)]) -> Self {
+        let files = files
+            .iter()
+            .filter(|(path, _)| is_js_ts(path))
+            .map(|(path, source)| FileImports {
+                path: path.clone(),
+                imports: extract_imports(path, source),
+            })
+            .filter(|file| !file.imports.is_empty())
+            .collect();
+        Self { files }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// One `path -> module, module` line per file, cut off at `max_chars`
+    /// with a count of the files left out
+    pub fn render(&self, max_chars: usize) -> String {
+        let mut out = String::new();
+        for (index, file) in self.files.iter().enumerate() {
+            let line = format!("{} -> {}\n", file.path, file.imports.join(", "));
+            if out.len() + line.len() > max_chars {
+                out.push_str(&format!("…and {} more files\n", self.files.len() - index));
+                break;
+            }
+            out.push_str(&line);
+        }
+        out
+    }
+}
+
+fn is_js_ts(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| JS_TS_EXTENSIONS.contains(&ext))
+}
+
+/// Modules imported by `source`: static and dynamic imports, re-exports and
+/// `require` calls with a literal specifier. Unparseable code yields whatever
+/// was recovered.
+pub fn extract_imports(path: &str, source: &str) -> Vec<String> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::from_path(path).unwrap_or_default();
+    let ret = Parser::new(&allocator, source, source_type).parse();
+
+    let mut visitor = ImportVisitor::default();
+    visitor.visit_program(&ret.program);
+    visitor.imports
+}
+
+#[derive(Default)]
+struct ImportVisitor {
+    imports: Vec<String>,
+}
+
+impl ImportVisitor {
+    fn add(&mut self, module: &str) {
+        if !self.imports.iter().any(|m| m == module) {
+            self.imports.push(module.to_string());
+        }
+    }
+}
+
+impl<'a> Visit<'a> for ImportVisitor {
+    fn visit_import_declaration(&mut self, it: &ImportDeclaration<'a>) {
+        self.add(&it.source.value);
+    }
+
+    fn visit_export_all_declaration(&mut self, it: &ExportAllDeclaration<'a>) {
+        self.add(&it.source.value);
+    }
+
+    fn visit_export_named_declaration(&mut self, it: &ExportNamedDeclaration<'a>) {
+        if let Some(source) = &it.source {
+            self.add(&source.value);
+        }
+        walk::walk_export_named_declaration(self, it);
+    }
+
+    fn visit_import_expression(&mut self, it: &ImportExpression<'a>) {
+        if let Expression::StringLiteral(source) = &it.source {
+            self.add(&source.value);
+        }
+        walk::walk_import_expression(self, it);
+    }
+
+    fn visit_call_expression(&mut self, it: &CallExpression<'a>) {
+        if let Some(source) = it.common_js_require() {
+            self.add(&source.value);
+        }
+        walk::walk_call_expression(self, it);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_imports() {
+        let source = r#"
+import React from "react";
+import type { User } from "./types";
+import { api } from "./api";
+import "./styles.css";
+export { Button } from "./components/Button";
+export * from "./utils";
+const fs = require("fs");
+const again = require("react");
+
+export async function load() {
+    const { chart } = await import("./chart");
+    return chart;
+}
+"#;
+        assert_eq!(
+            extract_imports("src/app.tsx", source),
+            vec![
+                "react",
+                "./types",
+                "./api",
+                "./styles.css",
+                "./components/Button",
+                "./utils",
+                "fs",
+                "./chart",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependency_graph_render() {
+        let files = vec![
+            ("src/app.ts".to_string(), "import { api } from './api';".to_string()),
+            ("src/api.ts".to_string(), "import axios from 'axios';".to_string()),
+            ("src/constants.ts".to_string(), "export const A = 1;".to_string()),
+            ("main.py".to_string(), "import os".to_string()),
+        ];
+        let graph = DependencyGraph::from_files(&files);
+
+        assert_eq!(graph.render(1000), "src/app.ts -> ./api\nsrc/api.ts -> axios\n");
+        assert_eq!(graph.render(25), "src/app.ts -> ./api\n…and 1 more files\n");
+    }
+}
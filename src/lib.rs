@@ -1,12 +1,25 @@
 pub mod ai;
+pub mod analysis;
 pub mod api;
+pub mod auth;
 pub mod checkers;
 pub mod config;
+pub mod cors;
 pub mod db;
+pub mod diff;
 pub mod error;
+pub mod files;
 pub mod git;
+pub mod idempotency;
+pub mod imports;
 pub mod grade_orchestrator;
 pub mod llm;
 pub mod orchestrator;
+pub mod profile;
+pub mod prompts;
+pub mod queue;
 pub mod shutdown;
+pub mod test_runner;
 pub mod types;
+pub mod utils;
+pub mod webhook;
@@ -1,15 +1,26 @@
+use crate::config::AuthorizationHeader;
 use crate::error::LlmError;
-use crate::llm::{Message, ModelClient, Role};
+use crate::llm::{default_http_client, http_client, retry_after_from_headers, Message, ModelClient, Role};
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const ANTHROPIC_VERSION: &str = "2023-06-01";
 const OAUTH_BETA_FEATURES: &str = "oauth-2025-04-20,interleaved-thinking-2025-05-14";
+const THINKING_BETA_FEATURES: &str = "interleaved-thinking-2025-05-14";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
 const OAUTH_USER_AGENT: &str = "claude-cli/2.1.2 (external, cli)";
 const TOOL_PREFIX: &str = "mcp_";
+/// Models that grades may pin
+pub const ALLOWED_MODELS: &[&str] = &[
+    "claude-sonnet-4-20250514",
+    "claude-opus-4-20250514",
+    "claude-3-7-sonnet-latest",
+    "claude-3-5-haiku-latest",
+];
 const CLAUDE_CODE_IDENTITY: &str = "You are Claude Code, Anthropic's official CLI for Claude.";
 
 enum AuthMode {
@@ -21,27 +32,31 @@ pub struct AnthropicClient {
     client: Client,
     auth: AuthMode,
     model: String,
-    base_url: String,
+    /// Messages endpoint replacing the public API, e.g. an enterprise proxy
+    base_url: Option<String>,
+    thinking_budget: Option<u32>,
 }
 
 impl AnthropicClient {
     pub fn with_api_key(api_key: impl Into<String>) -> Self {
         Self {
-            client: Client::new(),
+            client: default_http_client(),
             auth: AuthMode::ApiKey(SecretString::from(api_key.into())),
             model: "claude-sonnet-4-20250514".to_string(),
-            base_url: ANTHROPIC_API_URL.to_string(),
+            base_url: None,
+            thinking_budget: None,
         }
     }
 
     pub fn with_oauth(access_token: impl Into<String>) -> Self {
         Self {
-            client: Client::new(),
+            client: default_http_client(),
             auth: AuthMode::OAuth {
                 access_token: SecretString::from(access_token.into()),
             },
             model: "claude-sonnet-4-20250514".to_string(),
-            base_url: ANTHROPIC_API_URL.to_string(),
+            base_url: None,
+            thinking_budget: None,
         }
     }
 
@@ -50,16 +65,73 @@ impl AnthropicClient {
         self
     }
 
+    /// Send requests to `url` instead of the public Messages API, e.g. a
+    /// Bedrock or Azure gateway. The URL is used as-is.
+    pub fn with_custom_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = Some(url.into());
+        self
+    }
+
+    /// Fail requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = http_client(timeout);
+        self
+    }
+
+    /// Enable extended thinking with the given token budget
+    pub fn with_thinking_budget(mut self, tokens: u32) -> Self {
+        self.thinking_budget = Some(tokens);
+        self
+    }
+
+    /// Use `model` with extended thinking at the given token budget
+    pub fn with_model_and_budget(self, model: impl Into<String>, tokens: u32) -> Self {
+        self.with_model(model).with_thinking_budget(tokens)
+    }
+
     fn is_oauth(&self) -> bool {
         matches!(self.auth, AuthMode::OAuth { .. })
     }
 
     fn get_endpoint(&self) -> String {
-        if self.is_oauth() {
-            format!("{}?beta=true", self.base_url)
-        } else {
-            self.base_url.clone()
+        match &self.base_url {
+            Some(url) => url.clone(),
+            None if self.is_oauth() => format!("{}?beta=true", ANTHROPIC_API_URL),
+            None => ANTHROPIC_API_URL.to_string(),
+        }
+    }
+
+    /// POST to the endpoint with version and auth headers, without a body
+    fn request_builder(&self) -> RequestBuilder {
+        let mut req_builder = self
+            .client
+            .post(self.get_endpoint())
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json");
+
+        match &self.auth {
+            AuthMode::ApiKey(key) => {
+                req_builder = req_builder.header("x-api-key", key.expose_secret());
+                if self.thinking_budget.is_some() {
+                    req_builder = req_builder.header("anthropic-beta", THINKING_BETA_FEATURES);
+                }
+            }
+            AuthMode::OAuth { access_token } => {
+                req_builder = req_builder
+                    .header("Authorization", access_token.to_authorization_header("Bearer"))
+                    .header("anthropic-beta", OAUTH_BETA_FEATURES)
+                    .header("anthropic-product", "claude-code")
+                    .header("user-agent", OAUTH_USER_AGENT);
+            }
         }
+        req_builder
+    }
+
+    fn thinking_config(&self) -> Option<ThinkingConfig> {
+        self.thinking_budget.map(|budget_tokens| ThinkingConfig {
+            thinking_type: "enabled".to_string(),
+            budget_tokens,
+        })
     }
 
     fn sanitize_for_oauth(text: &str) -> String {
@@ -75,21 +147,50 @@ struct ApiRequest {
     messages: Vec<ApiMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     system: Option<SystemPrompt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<ThinkingConfig>,
+}
+
+#[derive(Serialize)]
+struct ThinkingConfig {
+    #[serde(rename = "type")]
+    thinking_type: String,
+    budget_tokens: u32,
 }
 
 #[derive(Serialize)]
 #[serde(untagged)]
 enum SystemPrompt {
     Text(String),
-    Blocks(Vec<SystemBlock>),
+    Blocks(Vec<TextBlock>),
 }
 
 #[derive(Serialize)]
-struct SystemBlock {
+struct TextBlock {
     #[serde(rename = "type")]
     block_type: String,
     text: String,
-    cache_control: CacheControl,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<CacheControl>,
+}
+
+impl TextBlock {
+    fn new(text: impl Into<String>) -> Self {
+        Self {
+            block_type: "text".to_string(),
+            text: text.into(),
+            cache_control: None,
+        }
+    }
+
+    fn cached(text: impl Into<String>) -> Self {
+        Self {
+            cache_control: Some(CacheControl {
+                cache_type: "ephemeral".to_string(),
+            }),
+            ..Self::new(text)
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -101,12 +202,48 @@ struct CacheControl {
 #[derive(Serialize)]
 struct ApiMessage {
     role: String,
-    content: String,
+    content: MessageContent,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Blocks(Vec<TextBlock>),
+}
+
+impl MessageContent {
+    /// Plain text, or a cached block for the message's cacheable prefix
+    /// followed by the rest
+    fn from_message(message: &Message) -> Self {
+        match message.cache_breakpoint {
+            Some(at) if at < message.content.len() => {
+                let (prefix, rest) = message.content.split_at(at);
+                Self::Blocks(vec![TextBlock::cached(prefix), TextBlock::new(rest)])
+            }
+            Some(_) => Self::Blocks(vec![TextBlock::cached(message.content.as_str())]),
+            None => Self::Text(message.content.clone()),
+        }
+    }
 }
 
 #[derive(Deserialize)]
 struct ApiResponse {
     content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Usage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
 }
 
 #[derive(Deserialize)]
@@ -121,23 +258,10 @@ struct ContentBlock {
 impl ModelClient for AnthropicClient {
     async fn chat(&self, messages: &[Message], system: Option<&str>) -> Result<String, LlmError> {
         let system_prompt = if self.is_oauth() {
-            let mut blocks = vec![SystemBlock {
-                block_type: "text".to_string(),
-                text: CLAUDE_CODE_IDENTITY.to_string(),
-                cache_control: CacheControl {
-                    cache_type: "ephemeral".to_string(),
-                },
-            }];
+            let mut blocks = vec![TextBlock::cached(CLAUDE_CODE_IDENTITY)];
 
             if let Some(sys) = system {
-                let sanitized = Self::sanitize_for_oauth(sys);
-                blocks.push(SystemBlock {
-                    block_type: "text".to_string(),
-                    text: sanitized,
-                    cache_control: CacheControl {
-                        cache_type: "ephemeral".to_string(),
-                    },
-                });
+                blocks.push(TextBlock::cached(Self::sanitize_for_oauth(sys)));
             }
             Some(SystemPrompt::Blocks(blocks))
         } else {
@@ -153,45 +277,30 @@ impl ModelClient for AnthropicClient {
                     Role::Assistant => "assistant".to_string(),
                     Role::System => "user".to_string(),
                 },
-                content: m.content.clone(),
+                content: MessageContent::from_message(m),
             })
             .collect();
 
+        // max_tokens must exceed the thinking budget, so the budget is added on top
         let request = ApiRequest {
             model: self.model.clone(),
-            max_tokens: 4096,
+            max_tokens: DEFAULT_MAX_TOKENS + self.thinking_budget.unwrap_or(0),
             messages: api_messages,
             system: system_prompt,
+            thinking: self.thinking_config(),
         };
 
-        let mut req_builder = self
-            .client
-            .post(self.get_endpoint())
-            .header("anthropic-version", ANTHROPIC_VERSION)
-            .header("Content-Type", "application/json");
-
-        match &self.auth {
-            AuthMode::ApiKey(key) => {
-                req_builder = req_builder.header("x-api-key", key.expose_secret());
-            }
-            AuthMode::OAuth { access_token } => {
-                req_builder = req_builder
-                    .header(
-                        "Authorization",
-                        format!("Bearer {}", access_token.expose_secret()),
-                    )
-                    .header("anthropic-beta", OAUTH_BETA_FEATURES)
-                    .header("anthropic-product", "claude-code")
-                    .header("user-agent", OAUTH_USER_AGENT);
-            }
-        }
-
-        let response = req_builder.json(&request).send().await.map_err(LlmError::Network)?;
+        let response = self
+            .request_builder()
+            .json(&request)
+            .send()
+            .await
+            .map_err(LlmError::Network)?;
 
         let status = response.status();
         if status.as_u16() == 429 {
             return Err(LlmError::RateLimited {
-                retry_after_ms: 60000,
+                retry_after_ms: retry_after_from_headers(response.headers()),
             });
         }
 
@@ -208,13 +317,16 @@ impl ModelClient for AnthropicClient {
             .await
             .map_err(|e| LlmError::InvalidResponse(format!("Invalid response: {}", e)))?;
 
-        let text = api_response
-            .content
-            .iter()
-            .filter(|b| b.block_type == "text")
-            .map(|b| b.text.as_str())
-            .collect::<Vec<_>>()
-            .join("");
+        let usage = &api_response.usage;
+        tracing::info!(
+            input_tokens = usage.input_tokens,
+            output_tokens = usage.output_tokens,
+            cache_creation_input_tokens = usage.cache_creation_input_tokens,
+            cache_read_input_tokens = usage.cache_read_input_tokens,
+            "Anthropic token usage"
+        );
+
+        let text = extract_text(&api_response.content);
 
         if text.is_empty() {
             Err(LlmError::InvalidResponse(
@@ -224,6 +336,24 @@ impl ModelClient for AnthropicClient {
             Ok(text)
         }
     }
+
+    fn provider(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+/// Join the text blocks of a response, dropping `thinking` and other non-text blocks
+fn extract_text(content: &[ContentBlock]) -> String {
+    content
+        .iter()
+        .filter(|b| b.block_type == "text")
+        .map(|b| b.text.as_str())
+        .collect::<Vec<_>>()
+        .join("")
 }
 
 pub fn prefix_tool_name(name: &str) -> String {
@@ -239,6 +369,7 @@ pub fn strip_tool_prefix(name: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use actix_web::{web, App, HttpResponse};
 
     #[test]
     fn test_sanitize_for_oauth() {
@@ -247,10 +378,121 @@ mod tests {
         assert_eq!(output, "This is Claude Code running Claude commands");
     }
 
+    #[test]
+    fn test_custom_base_url() {
+        let url = |client: AnthropicClient| client.request_builder().build().unwrap().url().to_string();
+
+        assert_eq!(url(AnthropicClient::with_api_key("key")), ANTHROPIC_API_URL);
+        assert_eq!(
+            url(AnthropicClient::with_oauth("sk-ant-oat-token")),
+            format!("{}?beta=true", ANTHROPIC_API_URL)
+        );
+
+        let proxy = "https://llm-gateway.example.com/anthropic/v1/messages";
+        assert_eq!(url(AnthropicClient::with_api_key("key").with_custom_base_url(proxy)), proxy);
+        // Custom endpoints get no `?beta=true`, even with OAuth
+        assert_eq!(url(AnthropicClient::with_oauth("sk-ant-oat-token").with_custom_base_url(proxy)), proxy);
+    }
+
+    #[test]
+    fn test_extract_text_strips_thinking_blocks() {
+        let response: ApiResponse = serde_json::from_str(
+            r#"{"content": [
+                {"type": "thinking", "thinking": "Let me check the files...", "signature": "abc"},
+                {"type": "text", "text": "{\"passed\": true}"}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(extract_text(&response.content), r#"{"passed": true}"#);
+    }
+
+    #[test]
+    fn test_thinking_config_serialization() {
+        let client = AnthropicClient::with_api_key("key").with_model_and_budget("claude-3-7-sonnet-latest", 2048);
+        assert_eq!(client.thinking_budget, Some(2048));
+        assert_eq!(client.model, "claude-3-7-sonnet-latest");
+
+        let request = ApiRequest {
+            model: client.model.clone(),
+            max_tokens: DEFAULT_MAX_TOKENS + 2048,
+            messages: vec![],
+            system: None,
+            thinking: client.thinking_config(),
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["thinking"]["type"], "enabled");
+        assert_eq!(json["thinking"]["budget_tokens"], 2048);
+    }
+
     #[test]
     fn test_tool_prefix() {
         assert_eq!(prefix_tool_name("read_file"), "mcp_read_file");
         assert_eq!(strip_tool_prefix("mcp_read_file"), "read_file");
         assert_eq!(strip_tool_prefix("read_file"), "read_file");
     }
+
+    #[actix_web::test]
+    async fn test_cached_prefix_sent_as_content_blocks() {
+        use std::sync::{Arc, Mutex};
+
+        let captured = Arc::new(Mutex::new(Vec::<serde_json::Value>::new()));
+        let server = actix_test::start({
+            let captured = captured.clone();
+            move || {
+                let captured = captured.clone();
+                App::new().default_service(web::to(move |body: web::Json<serde_json::Value>| {
+                    let captured = captured.clone();
+                    async move {
+                        captured.lock().unwrap().push(body.into_inner());
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "content": [{"type": "text", "text": "ok"}],
+                            "usage": {
+                                "input_tokens": 12,
+                                "output_tokens": 3,
+                                "cache_creation_input_tokens": 0,
+                                "cache_read_input_tokens": 2048
+                            }
+                        }))
+                    }
+                }))
+            }
+        });
+
+        let client = AnthropicClient::with_api_key("key").with_custom_base_url(server.url("/v1/messages"));
+        let messages = [
+            Message::user_with_cached_prefix("## Submitted Code\n...", "## Criterion\nc"),
+            Message::user("plain"),
+        ];
+        assert_eq!(client.chat(&messages, Some("system")).await.unwrap(), "ok");
+
+        let request = captured.lock().unwrap().pop().unwrap();
+        assert_eq!(
+            request["messages"][0]["content"],
+            serde_json::json!([
+                {"type": "text", "text": "## Submitted Code\n...", "cache_control": {"type": "ephemeral"}},
+                {"type": "text", "text": "## Criterion\nc"}
+            ])
+        );
+        assert_eq!(request["messages"][1]["content"], "plain");
+        assert_eq!(request["system"], "system");
+    }
+
+    #[actix_web::test]
+    async fn test_request_timeout() {
+        let server = actix_test::start(|| {
+            App::new().default_service(web::to(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                HttpResponse::Ok().finish()
+            }))
+        });
+
+        let client = AnthropicClient::with_api_key("key")
+            .with_timeout(Duration::from_millis(100))
+            .with_custom_base_url(server.url("/v1/messages"));
+        match client.chat(&[Message::user("hi")], None).await {
+            Err(e @ LlmError::Network(_)) => assert!(e.is_retryable()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,169 @@
+//! Structured logging of LLM calls
+//!
+//! [`LoggingClient`] wraps a [`ModelClient`] and records each `chat` call in an
+//! `llm_call` span. The span nests under whatever review, grade, task or
+//! criterion span is current, so the ids are attached without threading them
+//! through every grader and validator.
+
+use super::{Message, ModelClient};
+use crate::config::{LlmLogConfig, LlmLogLevel};
+use crate::error::LlmError;
+use async_trait::async_trait;
+use std::time::Instant;
+use tracing::Instrument;
+
+const REDACTED: &str = "[REDACTED]";
+
+pub struct LoggingClient {
+    inner: Box<dyn ModelClient>,
+    config: LlmLogConfig,
+    secrets: Vec<String>,
+}
+
+impl LoggingClient {
+    pub fn new(inner: Box<dyn ModelClient>, config: LlmLogConfig) -> Self {
+        Self {
+            inner,
+            config,
+            secrets: Vec::new(),
+        }
+    }
+
+    /// Values scrubbed from prompts and responses logged at `full`
+    pub fn with_secrets(mut self, secrets: impl IntoIterator<Item = String>) -> Self {
+        self.secrets = secrets.into_iter().filter(|s| !s.is_empty()).collect();
+        self
+    }
+
+    /// Redact secrets, then cut to the configured length
+    fn loggable(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for secret in &self.secrets {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+        match text.char_indices().nth(self.config.max_chars) {
+            Some((end, _)) => format!("{}… ({} chars)", &text[..end], text.chars().count()),
+            None => text,
+        }
+    }
+}
+
+/// Wrap `client` for logging unless LLM logging is off
+pub fn with_logging(
+    client: Box<dyn ModelClient>,
+    config: LlmLogConfig,
+    secrets: impl IntoIterator<Item = String>,
+) -> Box<dyn ModelClient> {
+    match config.level {
+        LlmLogLevel::Off => client,
+        _ => Box::new(LoggingClient::new(client, config).with_secrets(secrets)),
+    }
+}
+
+fn prompt_chars(messages: &[Message], system: Option<&str>) -> usize {
+    let messages: usize = messages.iter().map(|m| m.content.chars().count()).sum();
+    messages + system.map_or(0, |s| s.chars().count())
+}
+
+#[async_trait]
+impl ModelClient for LoggingClient {
+    async fn chat(&self, messages: &[Message], system: Option<&str>) -> Result<String, LlmError> {
+        let span = tracing::info_span!(
+            "llm_call",
+            provider = self.inner.provider(),
+            model = self.inner.model(),
+        );
+
+        async {
+            if self.config.level == LlmLogLevel::Full {
+                if let Some(system) = system {
+                    tracing::info!(system = %self.loggable(system), "LLM system prompt");
+                }
+                for message in messages {
+                    tracing::info!(
+                        role = ?message.role,
+                        content = %self.loggable(&message.content),
+                        "LLM prompt message"
+                    );
+                }
+            }
+
+            let start = Instant::now();
+            let result = self.inner.chat(messages, system).await;
+            let latency_ms = start.elapsed().as_millis() as u64;
+            let prompt_chars = prompt_chars(messages, system);
+
+            match &result {
+                Ok(response) => {
+                    tracing::info!(
+                        prompt_chars,
+                        response_chars = response.chars().count(),
+                        latency_ms,
+                        "LLM call completed"
+                    );
+                    if self.config.level == LlmLogLevel::Full {
+                        tracing::info!(response = %self.loggable(response), "LLM response");
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(prompt_chars, latency_ms, error = %e, "LLM call failed");
+                }
+            }
+
+            result
+        }
+        .instrument(span)
+        .await
+    }
+
+    fn provider(&self) -> &'static str {
+        self.inner.provider()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoClient;
+
+    #[async_trait]
+    impl ModelClient for EchoClient {
+        async fn chat(&self, messages: &[Message], _system: Option<&str>) -> Result<String, LlmError> {
+            Ok(messages[0].content.clone())
+        }
+    }
+
+    fn full(max_chars: usize) -> LlmLogConfig {
+        LlmLogConfig {
+            level: LlmLogLevel::Full,
+            max_chars,
+        }
+    }
+
+    #[test]
+    fn test_loggable_redacts_and_truncates() {
+        let client = LoggingClient::new(Box::new(EchoClient), full(20))
+            .with_secrets(["sk-secret".to_string(), String::new()]);
+
+        assert_eq!(client.loggable("key=sk-secret"), "key=[REDACTED]");
+        assert_eq!(
+            client.loggable("key=sk-secret and some more text"),
+            "key=[REDACTED] and s… (33 chars)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logging_client_passes_through() {
+        let client = with_logging(Box::new(EchoClient), full(10), []);
+
+        let response = client.chat(&[Message::user("hello")], None).await.unwrap();
+
+        assert_eq!(response, "hello");
+        assert_eq!(client.provider(), "unknown");
+    }
+}
@@ -2,24 +2,71 @@ mod retry;
 mod tokens;
 
 pub mod anthropic;
+pub mod logging;
+pub mod multi_provider;
 pub mod openai;
 pub mod opencode;
 
-pub use retry::{with_retry, RetryConfig};
-pub use tokens::OAuthTokens;
+pub use logging::{with_logging, LoggingClient};
+pub use multi_provider::{MultiProviderClient, ProviderOptions};
+pub use retry::{retry_after_from_headers, with_retry, RetryConfig};
+pub use tokens::{estimate_tokens, estimate_tokens_from_chars, OAuthTokens};
 
+use crate::config::DEFAULT_LLM_TIMEOUT_SECS;
 use crate::error::LlmError;
 use async_trait::async_trait;
+use reqwest::Client;
+use std::time::Duration;
+
+/// Seconds allowed to connect to a provider, however long the request may run
+const CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// HTTP client for provider requests. Requests exceeding `timeout` fail with
+/// a retryable `LlmError::Network`.
+fn http_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+}
+
+fn default_http_client() -> Client {
+    http_client(Duration::from_secs(DEFAULT_LLM_TIMEOUT_SECS))
+}
+
+/// Models a request may pin, by provider. `None` means the provider is a
+/// proxy whose model names can't be known in advance.
+pub fn allowed_models(provider: &str) -> Option<&'static [&'static str]> {
+    match provider {
+        "anthropic" => Some(anthropic::ALLOWED_MODELS),
+        "openai" => Some(openai::ALLOWED_MODELS),
+        _ => None,
+    }
+}
 
 #[async_trait]
 pub trait ModelClient: Send + Sync {
     async fn chat(&self, messages: &[Message], system: Option<&str>) -> Result<String, LlmError>;
+
+    /// Provider name recorded in LLM call logs
+    fn provider(&self) -> &'static str {
+        "unknown"
+    }
+
+    /// Model name recorded in LLM call logs
+    fn model(&self) -> &str {
+        "unknown"
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Message {
     pub role: Role,
     pub content: String,
+    /// Byte offset ending a prefix of `content` that is identical across
+    /// calls. Providers with prompt caching cache it; others ignore it.
+    pub cache_breakpoint: Option<usize>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -34,6 +81,19 @@ impl Message {
         Self {
             role: Role::User,
             content: content.into(),
+            cache_breakpoint: None,
+        }
+    }
+
+    /// User message whose `prefix` is marked cacheable, followed by `rest`
+    pub fn user_with_cached_prefix(prefix: impl Into<String>, rest: &str) -> Self {
+        let mut content = prefix.into();
+        let breakpoint = content.len();
+        content.push_str(rest);
+        Self {
+            role: Role::User,
+            content,
+            cache_breakpoint: Some(breakpoint).filter(|&b| b > 0),
         }
     }
 
@@ -41,6 +101,7 @@ impl Message {
         Self {
             role: Role::Assistant,
             content: content.into(),
+            cache_breakpoint: None,
         }
     }
 
@@ -48,6 +109,7 @@ impl Message {
         Self {
             role: Role::System,
             content: content.into(),
+            cache_breakpoint: None,
         }
     }
 }
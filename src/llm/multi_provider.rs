@@ -0,0 +1,240 @@
+//! Provider fallback
+//!
+//! [`MultiProviderClient`] holds every configured provider in priority order
+//! and moves on to the next one when a provider is down or rate limited, so a
+//! single provider outage doesn't fail every review and grade.
+
+use super::anthropic::AnthropicClient;
+use super::openai::OpenAIClient;
+use super::opencode::OpenCodeClient;
+use super::{with_logging, Message, ModelClient};
+use crate::config::{LlmLogConfig, ProvidersConfig};
+use crate::error::LlmError;
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+use std::time::Duration;
+
+/// Per-request settings for the clients built by
+/// [`MultiProviderClient::from_config_with`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProviderOptions<'a> {
+    /// Model for the primary provider; fallbacks keep their default model,
+    /// since model names don't carry across providers
+    pub model: Option<&'a str>,
+    /// Extended thinking budget for Anthropic
+    pub thinking_budget: Option<u32>,
+}
+
+pub struct MultiProviderClient {
+    clients: Vec<Box<dyn ModelClient>>,
+}
+
+impl MultiProviderClient {
+    pub fn new(clients: Vec<Box<dyn ModelClient>>) -> Self {
+        Self { clients }
+    }
+
+    /// Every configured provider, Anthropic > OpenAI > OpenCode. `None` when
+    /// no provider is configured.
+    pub fn from_config(config: &ProvidersConfig) -> Option<Self> {
+        Self::from_config_with(config, ProviderOptions::default())
+    }
+
+    pub fn from_config_with(config: &ProvidersConfig, options: ProviderOptions) -> Option<Self> {
+        let mut clients: Vec<Box<dyn ModelClient>> = Vec::new();
+        let timeout = Duration::from_secs(config.default_timeout_secs);
+
+        if let Some(ref api_key) = config.anthropic_api_key {
+            let key = api_key.expose_secret();
+            // Detect OAuth token vs API key
+            let mut client = if key.starts_with("sk-ant-oat") {
+                AnthropicClient::with_oauth(key)
+            } else {
+                AnthropicClient::with_api_key(key)
+            };
+            if let Some(model) = options.model {
+                client = client.with_model(model);
+            }
+            if let Some(budget) = options.thinking_budget {
+                client = client.with_thinking_budget(budget);
+            }
+            if let Some(ref url) = config.anthropic_base_url {
+                client = client.with_custom_base_url(url);
+            }
+            clients.push(Box::new(client.with_timeout(timeout)));
+        }
+
+        if let Some(ref api_key) = config.openai_api_key {
+            let mut client = OpenAIClient::with_api_key(api_key.expose_secret()).with_timeout(timeout);
+            if let Some(model) = options.model.filter(|_| clients.is_empty()) {
+                client = client.with_model(model);
+            }
+            clients.push(Box::new(client));
+        }
+
+        if let Some(ref api_key) = config.opencode_api_key {
+            let base_url = config.opencode_base_url.clone();
+            let mut client = OpenCodeClient::new(base_url, Some(api_key.expose_secret().to_string()))
+                .with_headers(config.opencode_extra_headers.clone())
+                .with_timeout(timeout);
+            if let Some(model) = options.model.filter(|_| clients.is_empty()) {
+                client = client.with_model(model);
+            }
+            clients.push(Box::new(client));
+        }
+
+        (!clients.is_empty()).then(|| Self::new(clients))
+    }
+
+    /// Log each provider's calls separately, so fallbacks show up in the logs
+    pub fn with_logging(self, config: LlmLogConfig, secrets: Vec<String>) -> Self {
+        let clients = self
+            .clients
+            .into_iter()
+            .map(|client| with_logging(client, config, secrets.clone()))
+            .collect();
+        Self::new(clients)
+    }
+
+    fn primary(&self) -> Option<&dyn ModelClient> {
+        self.clients.first().map(|client| client.as_ref())
+    }
+}
+
+#[async_trait]
+impl ModelClient for MultiProviderClient {
+    /// Try each provider in order, moving on after unavailable, rate limited
+    /// and network errors. Returns the last error once every provider failed.
+    async fn chat(&self, messages: &[Message], system: Option<&str>) -> Result<String, LlmError> {
+        let mut last_error = LlmError::InvalidResponse("no LLM provider configured".to_string());
+        for client in &self.clients {
+            match client.chat(messages, system).await {
+                Err(e @ (LlmError::Unavailable { .. } | LlmError::RateLimited { .. } | LlmError::Network(_))) => {
+                    tracing::warn!("LLM provider {} failed, trying next: {}", client.provider(), e);
+                    last_error = e;
+                }
+                result => return result,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn provider(&self) -> &'static str {
+        self.primary().map_or("unknown", |client| client.provider())
+    }
+
+    fn model(&self) -> &str {
+        self.primary().map_or("unknown", |client| client.model())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct StubClient {
+        name: &'static str,
+        result: fn() -> Result<String, LlmError>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl StubClient {
+        fn boxed(
+            name: &'static str,
+            result: fn() -> Result<String, LlmError>,
+            calls: &Arc<AtomicUsize>,
+        ) -> Box<dyn ModelClient> {
+            Box::new(Self {
+                name,
+                result,
+                calls: Arc::clone(calls),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl ModelClient for StubClient {
+        async fn chat(&self, _: &[Message], _: Option<&str>) -> Result<String, LlmError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            (self.result)()
+        }
+
+        fn provider(&self) -> &'static str {
+            self.name
+        }
+    }
+
+    fn unavailable() -> Result<String, LlmError> {
+        Err(LlmError::Unavailable {
+            provider: "anthropic".to_string(),
+        })
+    }
+
+    fn rate_limited() -> Result<String, LlmError> {
+        Err(LlmError::RateLimited { retry_after_ms: 1000 })
+    }
+
+    fn ok() -> Result<String, LlmError> {
+        Ok("fallback answer".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_next_provider() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = MultiProviderClient::new(vec![
+            StubClient::boxed("anthropic", unavailable, &calls),
+            StubClient::boxed("openai", rate_limited, &calls),
+            StubClient::boxed("opencode", ok, &calls),
+        ]);
+
+        let reply = client.chat(&[Message::user("hi")], None).await.unwrap();
+        assert_eq!(reply, "fallback answer");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(client.provider(), "anthropic");
+    }
+
+    #[tokio::test]
+    async fn test_returns_last_error_when_exhausted() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = MultiProviderClient::new(vec![
+            StubClient::boxed("anthropic", unavailable, &calls),
+            StubClient::boxed("openai", rate_limited, &calls),
+        ]);
+
+        let err = client.chat(&[Message::user("hi")], None).await.unwrap_err();
+        assert!(matches!(err, LlmError::RateLimited { retry_after_ms: 1000 }));
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_do_not_fall_back() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = MultiProviderClient::new(vec![
+            StubClient::boxed("anthropic", || Err(LlmError::AuthenticationFailed("bad key".to_string())), &calls),
+            StubClient::boxed("openai", ok, &calls),
+        ]);
+
+        let err = client.chat(&[Message::user("hi")], None).await.unwrap_err();
+        assert!(matches!(err, LlmError::AuthenticationFailed(_)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_from_config_priority() {
+        assert!(MultiProviderClient::from_config(&ProvidersConfig::default()).is_none());
+
+        let config = ProvidersConfig {
+            openai_api_key: Some("sk-openai".to_string().into()),
+            opencode_api_key: Some("sk-opencode".to_string().into()),
+            ..ProvidersConfig::default()
+        };
+        let options = ProviderOptions {
+            model: Some("gpt-4o-mini"),
+            thinking_budget: None,
+        };
+        let client = MultiProviderClient::from_config_with(&config, options).unwrap();
+        let providers: Vec<_> = client.clients.iter().map(|c| (c.provider(), c.model())).collect();
+        assert_eq!(providers, vec![("openai", "gpt-4o-mini"), ("opencode", "default")]);
+    }
+}
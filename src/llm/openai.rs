@@ -1,12 +1,17 @@
+use crate::config::AuthorizationHeader;
 use crate::error::LlmError;
-use crate::llm::{Message, ModelClient, Role};
+use crate::llm::{default_http_client, http_client, retry_after_from_headers, Message, ModelClient, Role};
 use async_trait::async_trait;
 use reqwest::Client;
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{Mutex, RwLock};
 
+/// Models that grades may pin
+pub const ALLOWED_MODELS: &[&str] = &["gpt-4o", "gpt-4o-mini", "gpt-4.1", "gpt-4.1-mini", "o3-mini"];
+
 const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
 const OPENAI_OAUTH_TOKEN_URL: &str = "https://auth.openai.com/oauth/token";
 const CODEX_CLIENT_ID: &str = "app_EMoamEEZ73f0CkXaXp7hrann";
@@ -26,17 +31,19 @@ pub struct OpenAIClient {
     credential: Arc<RwLock<CredentialSource>>,
     refresh_lock: Arc<Mutex<()>>,
     model: String,
+    base_url: String,
 }
 
 impl OpenAIClient {
     pub fn with_api_key(api_key: impl Into<String>) -> Self {
         Self {
-            client: Client::new(),
+            client: default_http_client(),
             credential: Arc::new(RwLock::new(CredentialSource::ApiKey(SecretString::from(
                 api_key.into(),
             )))),
             refresh_lock: Arc::new(Mutex::new(())),
             model: "gpt-4o".to_string(),
+            base_url: OPENAI_API_URL.to_string(),
         }
     }
 
@@ -46,7 +53,7 @@ impl OpenAIClient {
         expires_at: Option<i64>,
     ) -> Self {
         Self {
-            client: Client::new(),
+            client: default_http_client(),
             credential: Arc::new(RwLock::new(CredentialSource::OAuth {
                 access_token: SecretString::from(access_token.into()),
                 refresh_token: refresh_token.map(SecretString::from),
@@ -54,6 +61,7 @@ impl OpenAIClient {
             })),
             refresh_lock: Arc::new(Mutex::new(())),
             model: "gpt-4o".to_string(),
+            base_url: OPENAI_API_URL.to_string(),
         }
     }
 
@@ -62,6 +70,12 @@ impl OpenAIClient {
         self
     }
 
+    /// Fail requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = http_client(timeout);
+        self
+    }
+
     fn is_token_expired(expires_at: Option<i64>) -> bool {
         match expires_at {
             Some(exp) => {
@@ -167,10 +181,8 @@ impl OpenAIClient {
     async fn get_auth_header(&self) -> Result<String, LlmError> {
         let credential = self.credential.read().await;
         match &*credential {
-            CredentialSource::ApiKey(key) => Ok(format!("Bearer {}", key.expose_secret())),
-            CredentialSource::OAuth { access_token, .. } => {
-                Ok(format!("Bearer {}", access_token.expose_secret()))
-            }
+            CredentialSource::ApiKey(key) => Ok(key.to_authorization_header("Bearer")),
+            CredentialSource::OAuth { access_token, .. } => Ok(access_token.to_authorization_header("Bearer")),
         }
     }
 }
@@ -237,7 +249,7 @@ impl ModelClient for OpenAIClient {
 
         let response = self
             .client
-            .post(OPENAI_API_URL)
+            .post(&self.base_url)
             .header("Authorization", auth_header)
             .header("Content-Type", "application/json")
             .json(&request)
@@ -248,7 +260,7 @@ impl ModelClient for OpenAIClient {
         let status = response.status();
         if status.as_u16() == 429 {
             return Err(LlmError::RateLimited {
-                retry_after_ms: 60000,
+                retry_after_ms: retry_after_from_headers(response.headers()),
             });
         }
 
@@ -271,4 +283,35 @@ impl ModelClient for OpenAIClient {
             .map(|c| c.message.content.clone())
             .ok_or_else(|| LlmError::InvalidResponse("No choices in response".to_string()))
     }
+
+    fn provider(&self) -> &'static str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_request_timeout() {
+        let server = actix_test::start(|| {
+            App::new().default_service(web::to(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                HttpResponse::Ok().finish()
+            }))
+        });
+
+        let mut client = OpenAIClient::with_api_key("key").with_timeout(Duration::from_millis(100));
+        client.base_url = server.url("/v1/chat/completions");
+        match client.chat(&[Message::user("hi")], None).await {
+            Err(e @ LlmError::Network(_)) => assert!(e.is_retryable()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
@@ -1,9 +1,12 @@
+use crate::config::AuthorizationHeader;
 use crate::error::LlmError;
-use crate::llm::{Message, ModelClient, Role};
+use crate::llm::{default_http_client, http_client, retry_after_from_headers, Message, ModelClient, Role};
 use async_trait::async_trait;
 use reqwest::Client;
-use secrecy::{ExposeSecret, SecretString};
+use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
 
 const DEFAULT_OPENCODE_URL: &str = "http://localhost:8000/v1/chat/completions";
 
@@ -12,15 +15,18 @@ pub struct OpenCodeClient {
     api_key: Option<SecretString>,
     base_url: String,
     model: String,
+    /// Sent with every request, e.g. gateway routing or org headers
+    headers: HashMap<String, String>,
 }
 
 impl OpenCodeClient {
     pub fn new(base_url: Option<String>, api_key: Option<String>) -> Self {
         Self {
-            client: Client::new(),
+            client: default_http_client(),
             api_key: api_key.map(SecretString::from),
             base_url: base_url.unwrap_or_else(|| DEFAULT_OPENCODE_URL.to_string()),
             model: "default".to_string(),
+            headers: HashMap::new(),
         }
     }
 
@@ -28,6 +34,62 @@ impl OpenCodeClient {
         self.model = model.into();
         self
     }
+
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Fail requests that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = http_client(timeout);
+        self
+    }
+
+    /// Map an OpenAI-style `{"error": {"code", "type", "message"}}` body to
+    /// a typed error, falling back to the raw body
+    fn error_from_body(&self, status: reqwest::StatusCode, retry_after_ms: u64, body: &str) -> LlmError {
+        let detail = serde_json::from_str::<ErrorResponse>(body).ok().map(|r| r.error);
+        let kinds: Vec<&str> = detail
+            .iter()
+            .flat_map(|d| [d.code.as_deref(), d.error_type.as_deref()])
+            .flatten()
+            .collect();
+        let is = |names: &[&str]| kinds.iter().any(|k| names.contains(k));
+        let message = detail
+            .as_ref()
+            .and_then(|d| d.message.clone())
+            .unwrap_or_else(|| body.to_string());
+
+        if is(&["model_not_found"]) {
+            LlmError::ModelNotFound {
+                model: self.model.clone(),
+            }
+        } else if status.as_u16() == 429
+            || is(&["insufficient_quota", "rate_limit_exceeded", "quota_exceeded"])
+        {
+            LlmError::RateLimited { retry_after_ms }
+        } else if is(&["content_filter", "content_policy_violation"]) {
+            LlmError::ContentFiltered { reason: message }
+        } else if status.as_u16() == 401 || is(&["invalid_api_key", "authentication_error"]) {
+            LlmError::AuthenticationFailed(message)
+        } else {
+            LlmError::InvalidResponse(format!("API error ({}): {}", status, body))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: Option<String>,
+    code: Option<String>,
+    #[serde(rename = "type")]
+    error_type: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -92,24 +154,19 @@ impl ModelClient for OpenCodeClient {
             .header("Content-Type", "application/json");
 
         if let Some(api_key) = &self.api_key {
-            req_builder = req_builder.header("Authorization", format!("Bearer {}", api_key.expose_secret()));
+            req_builder = req_builder.header("Authorization", api_key.to_authorization_header("Bearer"));
+        }
+        for (name, value) in &self.headers {
+            req_builder = req_builder.header(name, value);
         }
 
         let response = req_builder.json(&request).send().await.map_err(LlmError::Network)?;
 
         let status = response.status();
-        if status.as_u16() == 429 {
-            return Err(LlmError::RateLimited {
-                retry_after_ms: 60000,
-            });
-        }
-
         if !status.is_success() {
+            let retry_after_ms = retry_after_from_headers(response.headers());
             let body = response.text().await.unwrap_or_default();
-            return Err(LlmError::InvalidResponse(format!(
-                "API error ({}): {}",
-                status, body
-            )));
+            return Err(self.error_from_body(status, retry_after_ms, &body));
         }
 
         let chat_response: ChatResponse = response
@@ -123,4 +180,140 @@ impl ModelClient for OpenCodeClient {
             .map(|c| c.message.content.clone())
             .ok_or_else(|| LlmError::InvalidResponse("No choices in response".to_string()))
     }
+
+    fn provider(&self) -> &'static str {
+        "opencode"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse};
+
+    /// Serve every request with `status`, `body` and optional `Retry-After`
+    fn mock_server(status: u16, body: &'static str, retry_after: Option<&'static str>) -> actix_test::TestServer {
+        actix_test::start(move || {
+            App::new().default_service(web::to(move || async move {
+                let mut response = HttpResponse::build(
+                    actix_web::http::StatusCode::from_u16(status).unwrap(),
+                );
+                if let Some(secs) = retry_after {
+                    response.insert_header(("Retry-After", secs));
+                }
+                response.content_type("application/json").body(body)
+            }))
+        })
+    }
+
+    async fn chat(server: &actix_test::TestServer) -> Result<String, LlmError> {
+        OpenCodeClient::new(Some(server.url("/v1/chat/completions")), None)
+            .with_model("gpt-x")
+            .chat(&[Message::user("hi")], None)
+            .await
+    }
+
+    #[actix_web::test]
+    async fn test_model_not_found() {
+        let server = mock_server(
+            404,
+            r#"{"error":{"message":"The model `gpt-x` does not exist","type":"invalid_request_error","code":"model_not_found"}}"#,
+            None,
+        );
+        match chat(&server).await {
+            Err(LlmError::ModelNotFound { model }) => assert_eq!(model, "gpt-x"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_quota_exceeded_is_rate_limited() {
+        let server = mock_server(
+            403,
+            r#"{"error":{"message":"You exceeded your current quota","type":"insufficient_quota","code":null}}"#,
+            None,
+        );
+        assert!(matches!(chat(&server).await, Err(LlmError::RateLimited { .. })));
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limit_honors_retry_after() {
+        let server = mock_server(429, r#"{"error":{"message":"slow down"}}"#, Some("7"));
+        match chat(&server).await {
+            Err(LlmError::RateLimited { retry_after_ms }) => assert_eq!(retry_after_ms, 7000),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_content_policy_violation() {
+        let server = mock_server(
+            400,
+            r#"{"error":{"message":"Request was rejected by the safety system","type":"invalid_request_error","code":"content_policy_violation"}}"#,
+            None,
+        );
+        match chat(&server).await {
+            Err(LlmError::ContentFiltered { reason }) => {
+                assert_eq!(reason, "Request was rejected by the safety system")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_error_keeps_body() {
+        let server = mock_server(500, "upstream exploded", None);
+        match chat(&server).await {
+            Err(LlmError::InvalidResponse(message)) => assert!(message.contains("upstream exploded")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_extra_headers_are_sent() {
+        let server = actix_test::start(|| {
+            App::new().default_service(web::to(|req: HttpRequest| async move {
+                let org = req
+                    .headers()
+                    .get("X-Org-Id")
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or("missing")
+                    .to_string();
+                HttpResponse::Ok().json(serde_json::json!({
+                    "choices": [{ "message": { "content": org } }]
+                }))
+            }))
+        });
+
+        let headers = HashMap::from([("X-Org-Id".to_string(), "org-42".to_string())]);
+        let reply = OpenCodeClient::new(Some(server.url("/v1/chat/completions")), None)
+            .with_headers(headers)
+            .chat(&[Message::user("hi")], None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "org-42");
+    }
+
+    #[actix_web::test]
+    async fn test_request_timeout() {
+        let server = actix_test::start(|| {
+            App::new().default_service(web::to(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                HttpResponse::Ok().finish()
+            }))
+        });
+
+        let result = OpenCodeClient::new(Some(server.url("/v1/chat/completions")), None)
+            .with_timeout(Duration::from_millis(100))
+            .chat(&[Message::user("hi")], None)
+            .await;
+        match result {
+            Err(LlmError::Network(e)) => assert!(e.is_timeout()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
@@ -1,10 +1,55 @@
 use crate::error::LlmError;
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
 use std::time::Duration;
 use tokio::time::sleep;
 
 const MAX_RETRIES: u32 = 3;
 const BASE_DELAY_MS: u64 = 1000;
 const MAX_DELAY_MS: u64 = 60000;
+/// Used when a 429 response carries no usable retry headers
+const DEFAULT_RETRY_AFTER_MS: u64 = 60000;
+
+const ANTHROPIC_RESET_HEADERS: &[&str] = &[
+    "anthropic-ratelimit-requests-reset",
+    "anthropic-ratelimit-tokens-reset",
+    "anthropic-ratelimit-input-tokens-reset",
+    "anthropic-ratelimit-output-tokens-reset",
+];
+
+/// Delay to wait after a 429, read from the response headers.
+///
+/// Checks `retry-after-ms`, then `retry-after` (seconds or HTTP-date), then
+/// the Anthropic `anthropic-ratelimit-*-reset` RFC 3339 timestamps.
+pub fn retry_after_from_headers(headers: &HeaderMap) -> u64 {
+    parse_retry_after(headers, Utc::now()).unwrap_or(DEFAULT_RETRY_AFTER_MS)
+}
+
+fn parse_retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<u64> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+    let millis_until = |at: DateTime<Utc>| (at - now).num_milliseconds().max(0) as u64;
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        return Some(ms.max(0.0) as u64);
+    }
+
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<f64>() {
+            return Some((secs.max(0.0) * 1000.0) as u64);
+        }
+        if let Ok(at) = DateTime::parse_from_rfc2822(value) {
+            return Some(millis_until(at.with_timezone(&Utc)));
+        }
+    }
+
+    // The exhausted limit is unknown, so wait for the latest reset
+    ANTHROPIC_RESET_HEADERS
+        .iter()
+        .filter_map(|name| header(name))
+        .filter_map(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|at| millis_until(at.with_timezone(&Utc)))
+        .max()
+}
 
 pub struct RetryConfig {
     pub max_retries: u32,
@@ -63,6 +108,49 @@ mod tests {
     use std::sync::atomic::{AtomicU32, Ordering};
     use std::sync::Arc;
 
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_parse_retry_after_seconds() {
+        let now = Utc::now();
+        assert_eq!(parse_retry_after(&headers(&[("retry-after", "7")]), now), Some(7000));
+        assert_eq!(parse_retry_after(&headers(&[("retry-after-ms", "250")]), now), Some(250));
+    }
+
+    #[test]
+    fn test_parse_retry_after_http_date() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:27:30Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let map = headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]);
+        assert_eq!(parse_retry_after(&map, now), Some(30_000));
+    }
+
+    #[test]
+    fn test_parse_anthropic_reset_headers() {
+        let now = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let map = headers(&[
+            ("anthropic-ratelimit-requests-reset", "2025-01-01T00:00:05Z"),
+            ("anthropic-ratelimit-tokens-reset", "2025-01-01T00:00:12Z"),
+        ]);
+        assert_eq!(parse_retry_after(&map, now), Some(12_000));
+    }
+
+    #[test]
+    fn test_retry_after_default() {
+        assert_eq!(retry_after_from_headers(&HeaderMap::new()), DEFAULT_RETRY_AFTER_MS);
+        let now = Utc::now();
+        assert_eq!(parse_retry_after(&headers(&[("retry-after", "soon")]), now), None);
+    }
+
     #[tokio::test]
     async fn test_retry_succeeds_first_try() {
         let config = RetryConfig::default();
@@ -1,7 +1,20 @@
+use crate::config::authorization_header;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
 const TOKEN_REFRESH_BUFFER_SECS: i64 = 300;
+/// Rough chars-per-token ratio for English text and source code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Approximate token count for a prompt of `chars` characters (chars/4 heuristic)
+pub fn estimate_tokens_from_chars(chars: usize) -> usize {
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Approximate token count for a piece of text (chars/4 heuristic)
+pub fn estimate_tokens(text: &str) -> usize {
+    estimate_tokens_from_chars(text.chars().count())
+}
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct OAuthTokens {
@@ -35,6 +48,11 @@ impl OAuthTokens {
             token_type: "Bearer".to_string(),
         }
     }
+
+    /// `Authorization` header value for the access token, using `token_type`
+    pub fn to_authorization_header(&self) -> String {
+        authorization_header(&self.token_type, &self.access_token)
+    }
 }
 
 impl std::fmt::Debug for OAuthTokens {
@@ -68,6 +86,14 @@ mod tests {
         assert!(token.is_expired());
     }
 
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens_from_chars(4000), 1000);
+    }
+
     #[test]
     fn test_token_no_expiry() {
         let token = OAuthTokens {
@@ -78,4 +104,16 @@ mod tests {
         };
         assert!(!token.is_expired());
     }
+
+    #[test]
+    fn test_authorization_header() {
+        let mut token = OAuthTokens::new("abc123".to_string(), None, None);
+        assert_eq!(token.to_authorization_header(), "Bearer abc123");
+
+        token.token_type = "bearer".to_string();
+        assert_eq!(token.to_authorization_header(), "Bearer abc123");
+
+        token.token_type = "MAC".to_string();
+        assert_eq!(token.to_authorization_header(), "MAC abc123");
+    }
 }
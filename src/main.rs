@@ -1,11 +1,20 @@
 use actix_web::{App, HttpServer, middleware, web};
+use api_server::ai::PreviewLimits;
 use api_server::api;
+use api_server::auth::BearerAuth;
 use api_server::config::AppConfig;
-use api_server::db::{GradeRepository, MongoClient, ReviewCacheRepository};
+use api_server::cors::build_cors;
+use api_server::files::{FileFilter, ReadLimits};
+use api_server::db::{
+    AuditRepository, GradeRepository, IdempotencyRepository, MongoClient, ReviewCacheRepository,
+    ReviewRepository, SubscriptionRepository,
+};
 use api_server::grade_orchestrator::GradeStore;
+use api_server::idempotency::IDEMPOTENCY_KEY_TTL;
 use api_server::orchestrator::ReviewStore;
+use api_server::prompts::PromptRegistry;
 use api_server::shutdown::shutdown_signal;
-use api_server::types::GradeConfig;
+use api_server::webhook::GithubWebhook;
 use secrecy::ExposeSecret;
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -21,37 +30,104 @@ async fn main() -> std::io::Result<()> {
         .init();
 
     let config = AppConfig::from_env().expect("Failed to load configuration");
+    let prompts = Arc::new(PromptRegistry::from_env().expect("Failed to load prompt templates"));
+    for id in prompts.overridden() {
+        tracing::info!("Using custom prompt template for {}", id);
+    }
 
-    let (grade_repo, review_cache_repo) = if let Some(ref mongodb_url) = config.mongo.mongodb_url {
+    let (grade_repo, review_repo, review_cache_repo, audit_repo, subscription_repo, idempotency_repo) = if let Some(ref mongodb_url) = config.mongo.mongodb_url {
         match MongoClient::new(mongodb_url.expose_secret(), &config.mongo.mongodb_db_name).await {
             Ok(client) => {
-                tracing::info!("MongoDB connected for grade persistence and review cache");
+                tracing::info!("MongoDB connected for grade and review persistence, review cache and audit log");
                 let grade_repo = Arc::new(GradeRepository::new(client.clone()));
-                let review_cache_repo = Arc::new(ReviewCacheRepository::new(client));
-                (Some(grade_repo), Some(review_cache_repo))
+                let review_repo = Arc::new(ReviewRepository::new(client.clone()));
+                let review_cache_repo = Arc::new(ReviewCacheRepository::new(client.clone()));
+                let subscription_repo = Arc::new(SubscriptionRepository::new(client.clone()));
+                let idempotency_repo = Arc::new(IdempotencyRepository::new(client.clone()));
+                let audit_repo = Arc::new(AuditRepository::new(client));
+                if let Err(e) = review_repo.ensure_indexes().await {
+                    tracing::warn!("Failed to create review job indexes: {}", e);
+                }
+                if let Err(e) = audit_repo.ensure_indexes().await {
+                    tracing::warn!("Failed to create audit log indexes: {}", e);
+                }
+                if let Err(e) = subscription_repo.ensure_indexes().await {
+                    tracing::warn!("Failed to create grade subscription indexes: {}", e);
+                }
+                if let Err(e) = idempotency_repo.ensure_indexes(IDEMPOTENCY_KEY_TTL).await {
+                    tracing::warn!("Failed to create idempotency key indexes: {}", e);
+                }
+                (
+                    Some(grade_repo),
+                    Some(review_repo),
+                    Some(review_cache_repo),
+                    Some(audit_repo),
+                    Some(subscription_repo),
+                    Some(idempotency_repo),
+                )
             }
             Err(e) => {
-                tracing::warn!("Failed to connect to MongoDB: {}. Grade persistence and review cache disabled.", e);
-                (None, None)
+                tracing::warn!("Failed to connect to MongoDB: {}. Grade and review persistence, review cache and audit log disabled.", e);
+                (None, None, None, None, None, None)
             }
         }
     } else {
-        tracing::info!("MongoDB not configured. Grade persistence and review cache disabled.");
-        (None, None)
+        tracing::info!("MongoDB not configured. Grade and review persistence, review cache and audit log disabled.");
+        (None, None, None, None, None, None)
     };
 
+    let file_filter = FileFilter {
+        skip_generated: config.review.skip_generated_files,
+    };
     let review_store = ReviewStore::new(
         config.review.review_ttl_secs,
         Some(config.providers.clone()),
         review_cache_repo,
-    );
+    )
+    .with_review_repo(review_repo)
+    .with_audit_repo(audit_repo.clone())
+    .with_idempotency_repo(idempotency_repo.clone())
+    .with_preview_limits(PreviewLimits {
+        max_files: config.review.ai_preview_files,
+        max_chars_per_file: config.review.ai_preview_chars,
+    })
+    .with_read_limits(ReadLimits {
+        max_file_bytes: config.review.max_file_bytes,
+        max_total_bytes: config.review.max_context_bytes,
+    })
+    .with_file_filter(file_filter)
+    .with_prompts(Arc::clone(&prompts))
+    .with_min_suggestion_confidence(config.review.min_suggestion_confidence)
+    .with_max_suggestions(config.review.max_suggestions)
+    .with_job_limits(config.review.max_concurrent_jobs, config.review.max_queued_jobs)
+    .with_max_entries(config.review.max_stored_jobs);
 
     let grade_store = GradeStore::new(
         config.review.review_ttl_secs,
         Some(config.providers.clone()),
-        GradeConfig::default(),
+        config.grade.clone(),
         grade_repo,
-    );
+    )
+    .with_audit_repo(audit_repo)
+    .with_idempotency_repo(idempotency_repo)
+    .with_subscription_repo(subscription_repo)
+    .with_prompts(prompts)
+    .with_file_filter(file_filter)
+    .with_test_runner(config.test_runner)
+    .with_job_limits(config.review.max_concurrent_jobs, config.review.max_queued_jobs)
+    .with_max_entries(config.review.max_stored_jobs);
+
+    let cors_origins = config.server.cors_origins.clone();
+    let cors_max_age_secs = config.server.cors_max_age_secs;
+    let auth = BearerAuth::new(config.server.api_auth_token.clone());
+    if config.server.api_auth_token.is_none() {
+        tracing::warn!("API_AUTH_TOKEN not set. The API is open to anyone who can reach it.");
+    }
+    let github_webhook = config
+        .server
+        .github_webhook_secret
+        .clone()
+        .map(|secret| web::Data::new(GithubWebhook::new(secret)));
 
     let bind_addr = format!("{}:{}", config.server.host, config.server.port);
     tracing::info!("Starting server at http://{}", bind_addr);
@@ -59,9 +135,16 @@ async fn main() -> std::io::Result<()> {
     let server = HttpServer::new(move || {
         App::new()
             .wrap(middleware::Logger::default())
+            .wrap(auth.clone())
             .wrap(middleware::Compress::default())
+            .wrap(build_cors(&cors_origins, cors_max_age_secs))
             .app_data(web::Data::new(review_store.clone()))
             .app_data(web::Data::new(grade_store.clone()))
+            .configure(|cfg| {
+                if let Some(webhook) = &github_webhook {
+                    cfg.app_data(webhook.clone());
+                }
+            })
             .configure(api::configure)
     })
     .bind(&bind_addr)?
@@ -1,60 +1,348 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
+use futures::stream::{self, Stream, StreamExt};
 use tokio::sync::{broadcast, RwLock};
 use tokio::time::{interval, Duration};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
 use crate::ai::validators::{CommentValidator, Prioritizer, TypoValidator};
-use crate::ai::reviewers::{CodeOracle, ProductIdeasReviewer};
-use crate::ai::{CodeContext, Reviewer, Validator};
-use crate::checkers::run_all_checkers;
-use crate::config::ProvidersConfig;
-use crate::db::ReviewCacheRepository;
-use crate::error::ApiError;
+use crate::ai::reviewers::{AccessibilityReviewer, CodeOracle, ProductIdeasReviewer, TestCoverageReviewer};
+use crate::ai::{CodeContext, PreviewLimits, Reviewer, Validator};
+use crate::analysis::extract_product_context;
+use crate::checkers::checkers_for;
+use crate::config::{
+    ProvidersConfig, DEFAULT_MAX_STORED_JOBS, DEFAULT_MAX_SUGGESTIONS, DEFAULT_MIN_SUGGESTION_CONFIDENCE,
+};
+use crate::db::{
+    AuditEntry, AuditRepository, CachedReview, IdempotencyRepository, ReviewCacheRepository, ReviewJob,
+    ReviewRepository,
+};
+use crate::error::{ApiError, LlmError};
+use crate::files::{
+    read_capped, FileFilter, ReadLimits, RepoTree, DEFAULT_MIN_SOURCE_LINES, NO_SOURCE_ERROR,
+};
 use crate::git::ClonedRepo;
-use crate::llm::openai::OpenAIClient;
-use crate::llm::anthropic::AnthropicClient;
-use crate::llm::opencode::OpenCodeClient;
-use crate::llm::ModelClient;
-use crate::types::{Diagnostic, ReviewEvent, ReviewStatus, ReviewSummary, SeverityCounts, Suggestion};
-use secrecy::ExposeSecret;
+use crate::idempotency::IdempotencyKeys;
+use crate::imports::DependencyGraph;
+use crate::prompts::PromptRegistry;
+use crate::queue::JobQueue;
+use crate::llm::{ModelClient, MultiProviderClient, ProviderOptions};
+use crate::profile::{Language, RepoProfile};
+use crate::types::{
+    backfill_seq, rank_suggestions, sort_diagnostics, CheckType, Diagnostic, EventEnvelope, ReviewCacheMode, ReviewEvent,
+    ReviewStatus, ReviewSummary, SeverityCounts, Suggestion,
+};
+use bson::DateTime as BsonDateTime;
+
+/// Reviews emit a few events per check, validator and reviewer, plus one
+/// per file read
+const REVIEW_EVENT_CAPACITY: usize = 200;
+
+/// Reviews returned by a repository history query
+const REVIEW_HISTORY_LIMIT: usize = 50;
+
+/// Files always collected for reviewers that only list paths
+const MIN_CONTEXT_FILES: usize = 20;
+
+/// `CheckFailed` error of a checker cancelled through `abort_checker`
+const CHECKER_ABORTED_ERROR: &str = "Aborted by user";
+
+/// Most files a review request may ask AI reviewers to see
+pub const MAX_PREVIEW_FILES: usize = 100;
+
+/// Most chars per file a review request may ask AI reviewers to see
+pub const MAX_PREVIEW_CHARS: usize = 20_000;
 
 pub struct ReviewState {
     pub id: String,
     pub status: ReviewStatus,
     pub repo_url: String,
     pub results: Vec<Diagnostic>,
+    /// Checker output before AI validation, kept so validators can be re-run
+    pub checker_results: Vec<Diagnostic>,
+    /// Check type of each rule in `checker_results`, for `CheckFiltered` events
+    check_types: HashMap<String, CheckType>,
     pub suggestions: Vec<Suggestion>,
     pub error: Option<String>,
+    /// 1-based position while waiting in the job queue
+    pub queue_position: Option<usize>,
     pub created_at: u64,
-    event_sender: broadcast::Sender<ReviewEvent>,
+    pub preview: PreviewLimits,
+    /// Wall-clock limit for the run, if the request set one
+    pub max_duration: Option<Duration>,
+    pub cache_mode: ReviewCacheMode,
+    /// Source lines the repository needs to be reviewed
+    pub min_source_lines: usize,
+    /// Languages detected in the repository, once analyzed
+    pub profile: Option<RepoProfile>,
+    /// Results were served from the review cache
+    pub cached: bool,
+    /// A fresh review is running to replace the cached results
+    pub refreshing: bool,
+    /// Unix millis of the last read, for evicting the least recently used
+    last_accessed: AtomicU64,
+    events: EventLog<ReviewEvent>,
+    revalidating: Arc<Mutex<bool>>,
+    /// Cancels each checker while it runs, for `abort_checker`
+    checker_tokens: HashMap<CheckType, CancellationToken>,
 }
 
 impl ReviewState {
-    pub fn new(id: String, repo_url: String) -> Self {
-        let (event_sender, _) = broadcast::channel(100);
+    pub fn new(id: String, repo_url: String, preview: PreviewLimits) -> Self {
         Self {
             id,
             status: ReviewStatus::Pending,
             repo_url,
             results: Vec::new(),
+            checker_results: Vec::new(),
+            check_types: HashMap::new(),
             suggestions: Vec::new(),
             error: None,
+            queue_position: None,
             created_at: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-            event_sender,
+            preview,
+            max_duration: None,
+            cache_mode: ReviewCacheMode::default(),
+            min_source_lines: DEFAULT_MIN_SOURCE_LINES,
+            profile: None,
+            cached: false,
+            refreshing: false,
+            last_accessed: AtomicU64::new(unix_millis()),
+            events: EventLog::new(REVIEW_EVENT_CAPACITY),
+            revalidating: Arc::new(Mutex::new(false)),
+            checker_tokens: HashMap::new(),
         }
     }
 
-    pub fn subscribe(&self) -> broadcast::Receiver<ReviewEvent> {
-        self.event_sender.subscribe()
+    /// Completed review restored from the MongoDB cache, keyed by its cache key
+    fn from_cached(cached: CachedReview) -> Self {
+        let mut state = Self::new(cached.cache_key, cached.repo_url, PreviewLimits::default());
+        state.status = ReviewStatus::Completed;
+        state.checker_results = cached.results.clone();
+        state.results = cached.results;
+        state.suggestions = cached.suggestions;
+        state.created_at = (cached.created_at.timestamp_millis() / 1000) as u64;
+        state.cached = true;
+        state
+    }
+
+    /// Review restored from its persisted MongoDB job
+    fn from_job(job: ReviewJob) -> Self {
+        let mut state = Self::new(job.review_id, job.repo_url, PreviewLimits::default());
+        state.status = job.status;
+        state.results = job.results;
+        state.checker_results = job.checker_results;
+        state.suggestions = job.suggestions;
+        state.error = job.error;
+        state.created_at = (job.created_at.timestamp_millis() / 1000) as u64;
+        state.events = EventLog::restore(REVIEW_EVENT_CAPACITY, job.events);
+        state
+    }
+
+    fn snapshot(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            status: self.status,
+            repo_url: self.repo_url.clone(),
+            results: self.results.clone(),
+            checker_results: self.checker_results.clone(),
+            check_types: self.check_types.clone(),
+            suggestions: self.suggestions.clone(),
+            error: self.error.clone(),
+            queue_position: self.queue_position,
+            created_at: self.created_at,
+            preview: self.preview,
+            max_duration: self.max_duration,
+            cache_mode: self.cache_mode,
+            min_source_lines: self.min_source_lines,
+            profile: self.profile.clone(),
+            cached: self.cached,
+            refreshing: self.refreshing,
+            last_accessed: AtomicU64::new(self.last_accessed.load(Ordering::Relaxed)),
+            events: self.events.clone(),
+            revalidating: self.revalidating.clone(),
+            checker_tokens: self.checker_tokens.clone(),
+        }
+    }
+
+    fn touch(&self) {
+        self.last_accessed.store(unix_millis(), Ordering::Relaxed);
+    }
+
+    /// When the review was last read, or `None` while it is still running
+    /// (or refreshing) and must not be evicted
+    fn evictable_since(&self) -> Option<u64> {
+        let finished = matches!(self.status, ReviewStatus::Completed | ReviewStatus::Failed);
+        (finished && !self.refreshing).then(|| self.last_accessed.load(Ordering::Relaxed))
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope<ReviewEvent>> {
+        self.events.subscribe()
+    }
+
+    /// Events emitted so far, oldest first
+    pub fn events(&self) -> Vec<EventEnvelope<ReviewEvent>> {
+        self.events.events()
+    }
+
+    /// Record a new queue position, emitting `Queued` when it changed
+    fn update_queue_position(&mut self, position: usize) {
+        if self.status == ReviewStatus::Queued && self.queue_position != Some(position) {
+            self.queue_position = Some(position);
+            self.emit(ReviewEvent::Queued { position });
+        }
     }
 
     pub fn emit(&self, event: ReviewEvent) {
-        let _ = self.event_sender.send(event);
+        self.events.send(event);
+    }
+}
+
+pub(crate) fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Ids to evict so that at most `max` of `entries` remain, least recently
+/// accessed first. Entries whose access time is `None` are never evicted, so
+/// more than `max` may remain while many jobs are running.
+pub(crate) fn lru_evictions<'a>(
+    entries: impl ExactSizeIterator<Item = (&'a String, Option<u64>)>,
+    max: usize,
+) -> Vec<String> {
+    let excess = entries.len().saturating_sub(max);
+    if excess == 0 {
+        return Vec::new();
+    }
+    let mut evictable: Vec<_> = entries
+        .filter_map(|(id, accessed)| accessed.map(|accessed| (accessed, id)))
+        .collect();
+    evictable.sort_unstable();
+    evictable.into_iter().take(excess).map(|(_, id)| id.clone()).collect()
+}
+
+/// Events kept in a job's event log; older ones are dropped first
+pub const MAX_EVENT_HISTORY: usize = 2_000;
+
+struct EventHistory<E> {
+    events: VecDeque<EventEnvelope<E>>,
+    next_seq: u64,
+}
+
+/// A job's event channel. Every event is stamped with its sequence number
+/// and emission time, broadcast to subscribers and kept in a bounded log.
+#[derive(Clone)]
+pub struct EventLog<E> {
+    sender: broadcast::Sender<EventEnvelope<E>>,
+    history: Arc<Mutex<EventHistory<E>>>,
+}
+
+impl<E: Clone> EventLog<E> {
+    /// Log whose subscribers may fall `capacity` events behind
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            sender,
+            history: Arc::new(Mutex::new(EventHistory {
+                events: VecDeque::new(),
+                next_seq: 1,
+            })),
+        }
+    }
+
+    /// Log continuing from `events`, e.g. restored from a persisted job
+    pub fn restore(capacity: usize, mut events: Vec<EventEnvelope<E>>) -> Self {
+        backfill_seq(&mut events);
+        let log = Self::new(capacity);
+        {
+            let mut history = log.history.lock().unwrap();
+            history.next_seq = events.last().map_or(1, |last| last.seq + 1);
+            history.events = events.into();
+        }
+        log
+    }
+
+    pub fn send(&self, event: E) {
+        // Held while broadcasting so subscribers see the log's order
+        let mut history = self.history.lock().unwrap();
+        let envelope = EventEnvelope::new(history.next_seq, unix_millis(), event);
+        history.next_seq += 1;
+        if history.events.len() == MAX_EVENT_HISTORY {
+            history.events.pop_front();
+        }
+        history.events.push_back(envelope.clone());
+        let _ = self.sender.send(envelope);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope<E>> {
+        self.sender.subscribe()
+    }
+
+    /// Logged events, oldest first
+    pub fn events(&self) -> Vec<EventEnvelope<E>> {
+        self.history.lock().unwrap().events.iter().cloned().collect()
+    }
+}
+
+/// Events from `receiver` up to and including the first terminal one. A
+/// lagged subscriber gets a synthetic `events_dropped` event (`seq` 0) in
+/// place of the events it missed; their numbers are skipped in `seq`.
+pub(crate) fn watch_events<E>(
+    receiver: broadcast::Receiver<EventEnvelope<E>>,
+    events_dropped: fn(u64) -> E,
+    is_terminal: fn(&E) -> bool,
+) -> impl Stream<Item = EventEnvelope<E>>
+where
+    E: Clone + Send + 'static,
+{
+    let events = BroadcastStream::new(receiver).map(move |result| match result {
+        Ok(envelope) => envelope,
+        Err(BroadcastStreamRecvError::Lagged(count)) => {
+            tracing::warn!("Event subscriber lagged, {} events dropped", count);
+            EventEnvelope::new(0, unix_millis(), events_dropped(count))
+        }
+    });
+    // Ends right after the terminal event rather than waiting for another
+    stream::unfold((events, false), move |(mut events, finished)| async move {
+        if finished {
+            return None;
+        }
+        let envelope = events.next().await?;
+        let finished = is_terminal(&envelope.event);
+        Some((envelope, (events, finished)))
+    })
+}
+
+/// Holds a review's busy flag and clears it on drop, so a cancelled request
+/// does not leave the review locked
+struct BusyFlag(Arc<Mutex<bool>>);
+
+impl BusyFlag {
+    fn acquire(flag: Arc<Mutex<bool>>) -> Option<Self> {
+        let mut busy = flag.lock().unwrap();
+        if *busy {
+            return None;
+        }
+        *busy = true;
+        drop(busy);
+        Some(Self(flag))
+    }
+}
+
+impl Drop for BusyFlag {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap() = false;
     }
 }
 
@@ -63,8 +351,21 @@ impl ReviewState {
 pub struct ReviewStore {
     reviews: Arc<RwLock<HashMap<String, ReviewState>>>,
     ttl_secs: u64,
+    /// Reviews held in memory before the least recently used finished ones
+    /// are evicted
+    max_entries: Arc<AtomicUsize>,
     providers_config: Option<ProvidersConfig>,
     cache_repo: Option<Arc<ReviewCacheRepository>>,
+    review_repo: Option<Arc<ReviewRepository>>,
+    audit_repo: Option<Arc<AuditRepository>>,
+    preview: PreviewLimits,
+    read_limits: ReadLimits,
+    file_filter: FileFilter,
+    prompts: Arc<PromptRegistry>,
+    min_suggestion_confidence: f32,
+    max_suggestions: usize,
+    queue: JobQueue,
+    idempotency_keys: IdempotencyKeys,
 }
 
 impl ReviewStore {
@@ -76,51 +377,176 @@ impl ReviewStore {
         let store = Self {
             reviews: Arc::new(RwLock::new(HashMap::new())),
             ttl_secs,
+            max_entries: Arc::new(AtomicUsize::new(DEFAULT_MAX_STORED_JOBS)),
             providers_config,
             cache_repo,
+            review_repo: None,
+            audit_repo: None,
+            preview: PreviewLimits::default(),
+            read_limits: ReadLimits::default(),
+            file_filter: FileFilter::default(),
+            prompts: Arc::default(),
+            min_suggestion_confidence: DEFAULT_MIN_SUGGESTION_CONFIDENCE,
+            max_suggestions: DEFAULT_MAX_SUGGESTIONS,
+            queue: JobQueue::default(),
+            idempotency_keys: IdempotencyKeys::new("review"),
         };
+        store.spawn_queue_watcher();
 
         let reviews = store.reviews.clone();
+        let max_entries = store.max_entries.clone();
         let ttl = ttl_secs;
         tokio::spawn(async move {
             let mut cleanup_interval = interval(Duration::from_secs(60));
             loop {
                 cleanup_interval.tick().await;
-                Self::cleanup_expired(&reviews, ttl).await;
+                Self::cleanup_expired(&reviews, ttl, max_entries.load(Ordering::Relaxed)).await;
             }
         });
 
         store
     }
 
-    /// Create an LLM client based on available configuration
-    fn create_llm_client(&self) -> Option<Box<dyn ModelClient>> {
-        let config = self.providers_config.as_ref()?;
+    pub fn with_audit_repo(mut self, audit_repo: Option<Arc<AuditRepository>>) -> Self {
+        self.audit_repo = audit_repo;
+        self
+    }
 
-        // Priority: Anthropic > OpenAI > OpenCode
-        if let Some(ref api_key) = config.anthropic_api_key {
-            let key = api_key.expose_secret();
-            // Detect OAuth token vs API key
-            if key.starts_with("sk-ant-oat") {
-                return Some(Box::new(AnthropicClient::with_oauth(key)));
-            } else {
-                return Some(Box::new(AnthropicClient::with_api_key(key)));
+    pub fn with_idempotency_repo(mut self, repo: Option<Arc<IdempotencyRepository>>) -> Self {
+        self.idempotency_keys = self.idempotency_keys.with_repo(repo);
+        self
+    }
+
+    pub fn idempotency_keys(&self) -> &IdempotencyKeys {
+        &self.idempotency_keys
+    }
+
+    /// Keep at most `max` reviews in memory, evicting the least recently
+    /// read finished ones first. Running reviews are never evicted.
+    pub fn with_max_entries(self, max: usize) -> Self {
+        self.max_entries.store(max, Ordering::Relaxed);
+        self
+    }
+
+    /// Persist every review by id so history outlives the in-memory TTL
+    pub fn with_review_repo(mut self, review_repo: Option<Arc<ReviewRepository>>) -> Self {
+        self.review_repo = review_repo;
+        self
+    }
+
+    /// Default file preview budget for AI reviewers
+    pub fn with_preview_limits(mut self, preview: PreviewLimits) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    /// Byte caps for source files loaded into the AI review context
+    pub fn with_read_limits(mut self, read_limits: ReadLimits) -> Self {
+        self.read_limits = read_limits;
+        self
+    }
+
+    /// Which files checkers and the AI review context leave out
+    pub fn with_file_filter(mut self, file_filter: FileFilter) -> Self {
+        self.file_filter = file_filter;
+        self
+    }
+
+    /// Prompt templates for the AI reviewers that support them
+    pub fn with_prompts(mut self, prompts: Arc<PromptRegistry>) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Drop AI suggestions whose confidence is below `min`
+    pub fn with_min_suggestion_confidence(mut self, min: f32) -> Self {
+        self.min_suggestion_confidence = min;
+        self
+    }
+
+    /// Keep at most `max` AI suggestions per review, highest priority first
+    pub fn with_max_suggestions(mut self, max: usize) -> Self {
+        self.max_suggestions = max;
+        self
+    }
+
+    /// Run at most `workers` reviews at once, with up to `capacity` waiting
+    pub fn with_job_limits(mut self, workers: usize, capacity: usize) -> Self {
+        self.queue = JobQueue::new(workers, capacity);
+        self.spawn_queue_watcher();
+        self
+    }
+
+    /// Emit `Queued` events as waiting reviews move up the queue
+    fn spawn_queue_watcher(&self) {
+        let reviews = self.reviews.clone();
+        let mut waiting = self.queue.subscribe();
+        tokio::spawn(async move {
+            while waiting.changed().await.is_ok() {
+                let mut reviews = reviews.write().await;
+                let ids = waiting.borrow_and_update().clone();
+                for (index, id) in ids.iter().enumerate() {
+                    if let Some(state) = reviews.get_mut(id) {
+                        state.update_queue_position(index + 1);
+                    }
+                }
             }
-        }
+        });
+    }
 
-        if let Some(ref api_key) = config.openai_api_key {
-            return Some(Box::new(OpenAIClient::with_api_key(api_key.expose_secret())));
+    /// Store defaults with any per-request overrides applied, capped at
+    /// `MAX_PREVIEW_FILES` and `MAX_PREVIEW_CHARS`
+    pub fn preview_limits(&self, max_files: Option<usize>, max_chars_per_file: Option<usize>) -> PreviewLimits {
+        PreviewLimits {
+            max_files: max_files.map_or(self.preview.max_files, |n| n.min(MAX_PREVIEW_FILES)),
+            max_chars_per_file: max_chars_per_file
+                .map_or(self.preview.max_chars_per_file, |n| n.min(MAX_PREVIEW_CHARS)),
         }
+    }
 
-        if let Some(ref api_key) = config.opencode_api_key {
-            let base_url = config.opencode_base_url.clone();
-            return Some(Box::new(OpenCodeClient::new(base_url, Some(api_key.expose_secret().to_string()))));
+    /// Record an audit entry in the background; failures are only logged
+    pub fn audit(&self, entry: AuditEntry) {
+        if let Some(repo) = self.audit_repo.clone() {
+            tokio::spawn(async move {
+                if let Err(e) = repo.record(entry).await {
+                    tracing::warn!("Failed to record audit entry: {}", e);
+                }
+            });
         }
+    }
+
+    /// Create an LLM client over every configured provider, falling back in
+    /// priority order. `model` overrides the primary provider's default.
+    fn create_llm_client(&self, model: Option<&str>) -> Option<Box<dyn ModelClient>> {
+        let config = self.providers_config.as_ref()?;
+        let options = ProviderOptions {
+            model,
+            thinking_budget: None,
+        };
+        let client = MultiProviderClient::from_config_with(config, options)?
+            .with_logging(config.llm_log, config.secrets());
+        Some(Box::new(client))
+    }
+
+    /// Client for AI validators, on `LLM_MODEL_VALIDATOR` when set
+    fn validator_client(&self) -> Option<Box<dyn ModelClient>> {
+        let model = self.providers_config.as_ref()?.validator_model.as_deref();
+        self.create_llm_client(model)
+    }
 
-        None
+    /// Client for AI reviewers, on `LLM_MODEL_REVIEWER` when set
+    fn reviewer_client(&self) -> Option<Box<dyn ModelClient>> {
+        let model = self.providers_config.as_ref()?.reviewer_model.as_deref();
+        self.create_llm_client(model)
     }
 
-    async fn cleanup_expired(reviews: &Arc<RwLock<HashMap<String, ReviewState>>>, ttl_secs: u64) {
+    /// Drop reviews past their TTL, then the least recently used ones over
+    /// `max_entries`
+    async fn cleanup_expired(
+        reviews: &Arc<RwLock<HashMap<String, ReviewState>>>,
+        ttl_secs: u64,
+        max_entries: usize,
+    ) {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -128,79 +554,358 @@ impl ReviewStore {
 
         let mut reviews = reviews.write().await;
         reviews.retain(|_, state| now - state.created_at < ttl_secs);
+        Self::evict_lru(&mut reviews, max_entries);
+    }
+
+    fn evict_lru(reviews: &mut HashMap<String, ReviewState>, max_entries: usize) {
+        let evicted = lru_evictions(
+            reviews.iter().map(|(id, state)| (id, state.evictable_since())),
+            max_entries,
+        );
+        for id in evicted {
+            reviews.remove(&id);
+        }
     }
 
     pub async fn create_review(&self, repo_url: String) -> String {
-        let id = uuid::Uuid::new_v4().to_string();
-        let state = ReviewState::new(id.clone(), repo_url.clone());
+        self.create_review_with_preview(repo_url, self.preview).await
+    }
+
+    pub async fn create_review_with_preview(&self, repo_url: String, preview: PreviewLimits) -> String {
+        self.create_review_with_id(uuid::Uuid::new_v4().to_string(), repo_url, preview)
+            .await
+    }
+
+    /// Create a review under an id reserved beforehand, e.g. by an
+    /// idempotency key
+    pub async fn create_review_with_id(&self, id: String, repo_url: String, preview: PreviewLimits) -> String {
+        let state = ReviewState::new(id.clone(), repo_url.clone(), preview);
 
         state.emit(ReviewEvent::ReviewStarted {
             review_id: id.clone(),
-            repo_url,
+            repo_url: repo_url.clone(),
         });
 
-        let mut reviews = self.reviews.write().await;
-        reviews.insert(id.clone(), state);
+        let created_at = BsonDateTime::from_millis(state.created_at as i64 * 1000);
+        {
+            let mut reviews = self.reviews.write().await;
+            reviews.insert(id.clone(), state);
+            Self::evict_lru(&mut reviews, self.max_entries.load(Ordering::Relaxed));
+        }
+
+        if let Some(ref repo) = self.review_repo {
+            if let Err(e) = repo.save_review_job(&id, &repo_url, created_at).await {
+                tracing::error!("Failed to save review job to MongoDB: {}", e);
+            }
+        }
 
         id
     }
 
+    /// Review by id, falling back to MongoDB for reviews that have expired
+    /// from memory
     pub async fn get_review(&self, id: &str) -> Option<ReviewState> {
+        {
+            let reviews = self.reviews.read().await;
+            if let Some(state) = reviews.get(id) {
+                state.touch();
+                return Some(state.snapshot());
+            }
+        }
+
+        let review_repo = self.review_repo.as_ref()?;
+        match review_repo.find_by_review_id(id).await {
+            Ok(job) => job.map(ReviewState::from_job),
+            Err(e) => {
+                tracing::warn!("Failed to load review {} from MongoDB: {}", id, e);
+                None
+            }
+        }
+    }
+
+    /// Reviews of `repo_url`, newest first. Reads the persisted history when
+    /// MongoDB is configured, otherwise only reviews still in memory.
+    pub async fn list_reviews(&self, repo_url: &str) -> Result<Vec<ReviewState>, ApiError> {
+        if let Some(ref repo) = self.review_repo {
+            let jobs = repo
+                .find_by_repo(repo_url, REVIEW_HISTORY_LIMIT as i64)
+                .await
+                .map_err(|e| ApiError::InternalError(format!("Failed to load reviews: {}", e)))?;
+            return Ok(jobs.into_iter().map(ReviewState::from_job).collect());
+        }
+
+        let reviews = self.reviews.read().await;
+        let mut matching: Vec<ReviewState> = reviews
+            .values()
+            .filter(|state| state.repo_url == repo_url)
+            .map(ReviewState::snapshot)
+            .collect();
+        matching.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+        matching.truncate(REVIEW_HISTORY_LIMIT);
+        Ok(matching)
+    }
+
+    /// Most recent review of `repo_url`, falling back to the MongoDB cache for
+    /// reviews that have expired from memory
+    pub async fn get_latest_review_for_repo(&self, repo_url: &str) -> Option<ReviewState> {
+        let latest = {
+            let reviews = self.reviews.read().await;
+            reviews
+                .values()
+                .filter(|state| state.repo_url == repo_url)
+                .max_by_key(|state| state.created_at)
+                .map(ReviewState::snapshot)
+        };
+        if latest.is_some() {
+            return latest;
+        }
+
+        let cache_repo = self.cache_repo.as_ref()?;
+        match cache_repo.find_latest_for_repo(repo_url).await {
+            Ok(cached) => cached.map(ReviewState::from_cached),
+            Err(e) => {
+                tracing::warn!("Failed to query review cache for {}: {}", repo_url, e);
+                None
+            }
+        }
+    }
+
+    pub async fn subscribe(&self, id: &str) -> Option<broadcast::Receiver<EventEnvelope<ReviewEvent>>> {
         let reviews = self.reviews.read().await;
-        reviews.get(id).map(|state| ReviewState {
-            id: state.id.clone(),
-            status: state.status,
-            repo_url: state.repo_url.clone(),
-            results: state.results.clone(),
-            suggestions: state.suggestions.clone(),
-            error: state.error.clone(),
-            created_at: state.created_at,
-            event_sender: state.event_sender.clone(),
+        reviews.get(id).map(|state| {
+            state.touch();
+            state.subscribe()
         })
     }
 
-    pub async fn subscribe(&self, id: &str) -> Option<broadcast::Receiver<ReviewEvent>> {
+    /// Event log of a completed or failed review, from memory or MongoDB
+    pub async fn review_events(&self, id: &str) -> Result<Vec<EventEnvelope<ReviewEvent>>, ApiError> {
+        let state = self
+            .get_review(id)
+            .await
+            .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", id)))?;
+        if !matches!(state.status, ReviewStatus::Completed | ReviewStatus::Failed) {
+            return Err(ApiError::Conflict(format!("Review {} has not finished", id)));
+        }
+        Ok(state.events())
+    }
+
+    /// Follow a review's events until it completes or fails
+    pub async fn watch_review(&self, id: &str) -> Result<impl Stream<Item = EventEnvelope<ReviewEvent>>, ApiError> {
+        let receiver = self
+            .subscribe(id)
+            .await
+            .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", id)))?;
+        Ok(watch_events(receiver, |count| ReviewEvent::EventsDropped { count }, ReviewEvent::is_terminal))
+    }
+
+    /// Limit how long a created review may run before remaining reviewers
+    /// are skipped
+    pub async fn set_max_duration(&self, id: &str, max_duration: Duration) {
+        if let Some(state) = self.reviews.write().await.get_mut(id) {
+            state.max_duration = Some(max_duration);
+        }
+    }
+
+    pub async fn set_cache_mode(&self, id: &str, cache_mode: ReviewCacheMode) {
+        if let Some(state) = self.reviews.write().await.get_mut(id) {
+            state.cache_mode = cache_mode;
+        }
+    }
+
+    /// Cancel a checker of a running review. The review goes on without
+    /// its diagnostics.
+    pub async fn abort_checker(&self, review_id: &str, check_type: CheckType) -> Result<(), ApiError> {
         let reviews = self.reviews.read().await;
-        reviews.get(id).map(|state| state.subscribe())
+        let state = reviews
+            .get(review_id)
+            .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", review_id)))?;
+        let token = state.checker_tokens.get(&check_type).ok_or_else(|| {
+            ApiError::Conflict(format!(
+                "Checker {:?} is not running for review {}",
+                check_type, review_id
+            ))
+        })?;
+        token.cancel();
+        Ok(())
+    }
+
+    pub async fn set_min_source_lines(&self, id: &str, min_source_lines: usize) {
+        if let Some(state) = self.reviews.write().await.get_mut(id) {
+            state.min_source_lines = min_source_lines;
+        }
+    }
+
+    /// Version of the prompts reviews run with. Cached reviews from another
+    /// version are not served.
+    fn prompt_version(&self) -> String {
+        format!("{}+{}", env!("CARGO_PKG_VERSION"), self.prompts.fingerprint())
+    }
+
+    /// Queue a created review to run, returning its queue position. When the
+    /// queue is full the review is failed and dropped from memory.
+    pub async fn enqueue_review(&self, id: &str) -> Result<usize, ApiError> {
+        {
+            let mut reviews = self.reviews.write().await;
+            let state = reviews
+                .get_mut(id)
+                .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", id)))?;
+            state.status = ReviewStatus::Queued;
+        }
+
+        let store = self.clone();
+        let job_id = id.to_string();
+        let queued = self.queue.try_enqueue(id, async move {
+            if let Err(e) = store.run_review(&job_id).await {
+                tracing::error!("Review {} failed: {}", job_id, e);
+                store.mark_failed(&job_id, e.to_string()).await;
+            }
+        });
+
+        match queued {
+            Ok(position) => {
+                // A worker may already have started it or moved it up
+                let mut reviews = self.reviews.write().await;
+                if let (Some(state), Some(current)) = (reviews.get_mut(id), self.queue.position(id)) {
+                    state.update_queue_position(current);
+                }
+                Ok(position)
+            }
+            Err(e) => {
+                self.mark_failed(id, e.to_string()).await;
+                self.reviews.write().await.remove(id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Cancel a review still waiting in the queue
+    pub async fn cancel_review(&self, id: &str) -> Result<(), ApiError> {
+        if self.get_review(id).await.is_none() {
+            return Err(ApiError::NotFound(format!("Review {} not found", id)));
+        }
+        if !self.queue.cancel(id) {
+            return Err(ApiError::Conflict(format!("Review {} is not queued", id)));
+        }
+        self.mark_failed(id, "Cancelled while queued".to_string()).await;
+        Ok(())
     }
 
     pub async fn mark_failed(&self, id: &str, error: String) {
+        {
+            let mut reviews = self.reviews.write().await;
+            if let Some(state) = reviews.get_mut(id) {
+                if state.refreshing {
+                    // The cached results stay in place
+                    state.refreshing = false;
+                    let error = format!("Refresh failed: {}", error);
+                    state.error = Some(error.clone());
+                    state.emit(ReviewEvent::ReviewFailed { error });
+                } else {
+                    state.status = ReviewStatus::Failed;
+                    state.error = Some(error.clone());
+                    state.queue_position = None;
+                    state.emit(ReviewEvent::ReviewFailed { error });
+                }
+            }
+        }
+        self.persist(id).await;
+    }
+
+    /// Save a review's current status and results to its MongoDB job
+    async fn persist(&self, id: &str) {
+        let (Some(repo), Some(state)) = (&self.review_repo, self.get_review(id).await) else {
+            return;
+        };
+        if let Err(e) = repo
+            .update_review_job(
+                id,
+                state.status,
+                &state.results,
+                &state.checker_results,
+                &state.suggestions,
+                state.error.as_deref(),
+                &state.events(),
+            )
+            .await
+        {
+            tracing::error!("Failed to update review job in MongoDB: {}", e);
+        }
+    }
+
+    /// Mark a review as cloning, returning its repository URL
+    pub async fn begin_cloning(&self, id: &str) -> Result<String, ApiError> {
         let mut reviews = self.reviews.write().await;
         if let Some(state) = reviews.get_mut(id) {
-            state.status = ReviewStatus::Failed;
-            state.error = Some(error.clone());
-            state.emit(ReviewEvent::ReviewFailed { error });
+            state.status = ReviewStatus::Cloning;
+            state.queue_position = None;
+            Ok(state.repo_url.clone())
+        } else {
+            Err(ApiError::NotFound(format!("Review {} not found", id)))
         }
     }
 
     pub async fn run_review(&self, id: &str) -> Result<(), ApiError> {
-        let (repo_url, event_sender) = {
-            let mut reviews = self.reviews.write().await;
-            if let Some(state) = reviews.get_mut(id) {
-                state.status = ReviewStatus::Cloning;
-                (state.repo_url.clone(), state.event_sender.clone())
+        let repo_url = self.begin_cloning(id).await?;
+        let start = std::time::Instant::now();
+
+        let cloned_repo = ClonedRepo::from_url(&repo_url).await?;
+        self.run_review_with_repo(id, &cloned_repo, start).await
+    }
+
+    /// Run checkers and AI review against an already cloned repository, so
+    /// callers that also grade the repo only fetch it once
+    #[tracing::instrument(name = "review", skip_all, fields(review_id = %id))]
+    pub async fn run_review_with_repo(
+        &self,
+        id: &str,
+        cloned_repo: &ClonedRepo,
+        start: std::time::Instant,
+    ) -> Result<(), ApiError> {
+        let (repo_url, preview, max_duration, cache_mode, min_source_lines, event_sender) = {
+            let reviews = self.reviews.read().await;
+            if let Some(state) = reviews.get(id) {
+                (
+                    state.repo_url.clone(),
+                    state.preview,
+                    state.max_duration,
+                    state.cache_mode,
+                    state.min_source_lines,
+                    state.events.clone(),
+                )
             } else {
                 return Err(ApiError::NotFound(format!("Review {} not found", id)));
             }
         };
 
-        let start = std::time::Instant::now();
-
-        let cloned_repo = ClonedRepo::from_url(&repo_url).await?;
         let repo_path = cloned_repo.path.clone();
 
         let cache_key = cloned_repo.cache_key(&repo_url, None);
         let commit_sha = cloned_repo.head_commit_short().unwrap_or_default();
 
-        if let (Some(ref cache_repo), Some(ref key)) = (&self.cache_repo, &cache_key) {
+        let prompt_version = self.prompt_version();
+        let mut refreshing = false;
+
+        if let (Some(ref cache_repo), Some(ref key), true) =
+            (&self.cache_repo, &cache_key, cache_mode != ReviewCacheMode::Bypass)
+        {
             if let Ok(Some(cached)) = cache_repo.get(key).await {
-                tracing::info!("Cache hit for review: {}", key);
-                self.apply_cached_result(id, cached.results, cached.suggestions, start.elapsed().as_millis() as u64).await;
-                return Ok(());
+                if cache_mode.serves(cached.prompt_version.as_deref(), &prompt_version) {
+                    tracing::info!("Cache hit for review: {}", key);
+                    refreshing = cache_mode == ReviewCacheMode::Refresh;
+                    self.apply_cached_result(id, cached.results, cached.suggestions, start.elapsed().as_millis() as u64, refreshing).await;
+                    self.persist(id).await;
+                    if !refreshing {
+                        return Ok(());
+                    }
+                } else {
+                    tracing::info!("Cached review {} is from other prompts, re-running", key);
+                }
             }
         }
 
-        {
+        // While refreshing, the cached results stay visible as completed
+        if !refreshing {
             let mut reviews = self.reviews.write().await;
             if let Some(state) = reviews.get_mut(id) {
                 state.status = ReviewStatus::Running;
@@ -208,48 +913,112 @@ impl ReviewStore {
         }
 
         let mut all_diagnostics: Vec<Diagnostic> = Vec::new();
+        let mut check_types: HashMap<String, CheckType> = HashMap::new();
 
-        let checker_results = tokio::task::spawn_blocking({
+        let profile = tokio::task::spawn_blocking({
             let path = repo_path.clone();
-            move || run_all_checkers(&path)
+            let filter = self.file_filter;
+            move || RepoProfile::detect(&path, filter)
         })
         .await
-        .map_err(|e| ApiError::InternalError(format!("Checker task failed: {}", e)))?;
+        .map_err(|e| ApiError::InternalError(format!("Language detection failed: {}", e)))?;
+        if let Some(state) = self.reviews.write().await.get_mut(id) {
+            state.profile = Some(profile.clone());
+        }
+        event_sender.send(ReviewEvent::AnalysisCompleted {
+            profile: profile.clone(),
+        });
+
+        let (files, tree) = Self::read_source_files(
+            &repo_path,
+            &profile,
+            preview.max_files.max(MIN_CONTEXT_FILES),
+            self.read_limits,
+            self.file_filter,
+            &event_sender,
+        );
+        if !tree.has_source(min_source_lines) {
+            tracing::info!("Review {} has no reviewable source code", id);
+            self.mark_failed(id, NO_SOURCE_ERROR.to_string()).await;
+            return Ok(());
+        }
+
+        // Each checker runs in its own blocking task so progress streams live
+        for checker in checkers_for(&profile) {
+            let check_type = checker.check_type();
+            let token = CancellationToken::new();
+            if let Some(state) = self.reviews.write().await.get_mut(id) {
+                state.checker_tokens.insert(check_type, token.clone());
+            }
+            event_sender.send(ReviewEvent::CheckStarted { check_type });
 
-        for (check_type, diagnostics) in checker_results {
             let check_start = std::time::Instant::now();
-            let _ = event_sender.send(ReviewEvent::CheckStarted { check_type });
+            let task = tokio::task::spawn_blocking({
+                let path = repo_path.clone();
+                let filter = self.file_filter;
+                move || checker.check(&path, filter)
+            });
+            // An aborted checker's thread finishes in the background and its
+            // diagnostics are dropped
+            let outcome = tokio::select! {
+                result = task => Some(result),
+                _ = token.cancelled() => None,
+            };
+            if let Some(state) = self.reviews.write().await.get_mut(id) {
+                state.checker_tokens.remove(&check_type);
+            }
+            let Some(result) = outcome else {
+                tracing::info!("Checker {:?} of review {} aborted", check_type, id);
+                event_sender.send(ReviewEvent::CheckFailed {
+                    check_type,
+                    error: CHECKER_ABORTED_ERROR.to_string(),
+                });
+                continue;
+            };
+            let diagnostics =
+                result.map_err(|e| ApiError::InternalError(format!("Checker task failed: {}", e)))?;
 
-            let _ = event_sender.send(ReviewEvent::CheckCompleted {
+            event_sender.send(ReviewEvent::CheckCompleted {
                 check_type,
                 diagnostics: diagnostics.clone(),
                 duration_ms: check_start.elapsed().as_millis() as u64,
             });
 
+            check_types.extend(diagnostics.iter().map(|d| (d.rule.clone(), check_type)));
             all_diagnostics.extend(diagnostics);
         }
 
+        let checker_diagnostics = all_diagnostics.clone();
         let mut all_suggestions: Vec<Suggestion> = Vec::new();
 
-        if let Some(llm_client) = self.create_llm_client() {
-            let validated_diagnostics = self.run_ai_validators(
-                llm_client.as_ref(),
+        if let (Some(validator_client), Some(reviewer_client)) =
+            (self.validator_client(), self.reviewer_client())
+        {
+            // Unvalidated diagnostics are kept when no validator could run
+            if let Ok(validated_diagnostics) = self.run_ai_validators(
+                validator_client.as_ref(),
                 all_diagnostics.clone(),
+                &check_types,
                 &event_sender,
-            ).await;
-            all_diagnostics = validated_diagnostics;
+            ).await {
+                all_diagnostics = validated_diagnostics;
+            }
 
-            let code_context = self.build_code_context(&repo_url, &repo_path, &all_diagnostics);
+            let code_context = self.build_code_context(&repo_url, &repo_path, files, tree, &profile, preview, &all_diagnostics);
             let suggestions = self.run_ai_reviewers(
-                llm_client.as_ref(),
+                reviewer_client.as_ref(),
                 &code_context,
                 &event_sender,
+                max_duration.map(|max_duration| start + max_duration),
             ).await;
             all_suggestions = suggestions;
+            rank_suggestions(&mut all_suggestions, self.max_suggestions);
         }
 
+        sort_diagnostics(&mut all_diagnostics);
+
         if let (Some(ref cache_repo), Some(ref key)) = (&self.cache_repo, &cache_key) {
-            if let Err(e) = cache_repo.save(key, &repo_url, &commit_sha, &all_diagnostics, &all_suggestions).await {
+            if let Err(e) = cache_repo.save(key, &repo_url, &commit_sha, &all_diagnostics, &all_suggestions, &prompt_version).await {
                 tracing::warn!("Failed to save review cache: {}", e);
             } else {
                 tracing::info!("Saved review to cache: {}", key);
@@ -260,8 +1029,13 @@ impl ReviewStore {
             let mut reviews = self.reviews.write().await;
             if let Some(state) = reviews.get_mut(id) {
                 state.results = all_diagnostics.clone();
+                let filtered = checker_diagnostics.len().saturating_sub(all_diagnostics.len());
+                state.checker_results = checker_diagnostics;
+                state.check_types = check_types;
                 state.suggestions = all_suggestions.clone();
                 state.status = ReviewStatus::Completed;
+                state.cached = false;
+                state.refreshing = false;
                 state.emit(ReviewEvent::ReviewCompleted {
                     summary: ReviewSummary {
                         total_diagnostics: all_diagnostics.len(),
@@ -278,22 +1052,33 @@ impl ReviewStore {
                                 .iter()
                                 .filter(|d| d.severity == crate::types::Severity::Info)
                                 .count(),
+                            filtered,
                         },
                         duration_ms: start.elapsed().as_millis() as u64,
+                        skipped_files: profile.skipped_files,
+                        languages: profile.languages.clone(),
                     },
+                    refreshing: false,
                 });
             }
         }
+        self.persist(id).await;
 
         Ok(())
     }
 
-    async fn apply_cached_result(&self, id: &str, results: Vec<Diagnostic>, suggestions: Vec<Suggestion>, duration_ms: u64) {
+    /// Complete a review with cached results. With `refreshing`, a fresh
+    /// review follows and replaces them.
+    async fn apply_cached_result(&self, id: &str, results: Vec<Diagnostic>, suggestions: Vec<Suggestion>, duration_ms: u64, refreshing: bool) {
         let mut reviews = self.reviews.write().await;
         if let Some(state) = reviews.get_mut(id) {
             state.results = results.clone();
+            // Only validated results are cached; re-validation starts from those
+            state.checker_results = results.clone();
             state.suggestions = suggestions;
             state.status = ReviewStatus::Completed;
+            state.cached = true;
+            state.refreshing = refreshing;
             state.emit(ReviewEvent::ReviewCompleted {
                 summary: ReviewSummary {
                     total_diagnostics: results.len(),
@@ -301,33 +1086,107 @@ impl ReviewStore {
                         error: results.iter().filter(|d| d.severity == crate::types::Severity::Error).count(),
                         warning: results.iter().filter(|d| d.severity == crate::types::Severity::Warning).count(),
                         info: results.iter().filter(|d| d.severity == crate::types::Severity::Info).count(),
+                        // Cached results were validated before they were saved
+                        filtered: 0,
                     },
                     duration_ms,
+                    // Cached results skip analysis, so nothing was scanned
+                    skipped_files: 0,
+                    languages: Vec::new(),
                 },
+                refreshing,
             });
         }
     }
 
+    /// Re-run the AI validators over a completed review's checker output,
+    /// replacing its results. Reviewers and checkers are not re-run.
+    #[tracing::instrument(name = "review", skip_all, fields(review_id = %id))]
+    pub async fn rerun_validation(&self, id: &str) -> Result<(), ApiError> {
+        let (diagnostics, check_types, event_sender, revalidating) = {
+            let reviews = self.reviews.read().await;
+            let state = reviews
+                .get(id)
+                .ok_or_else(|| ApiError::NotFound(format!("Review {} not found", id)))?;
+            if state.status != ReviewStatus::Completed {
+                return Err(ApiError::Conflict(format!("Review {} is not completed", id)));
+            }
+            (
+                state.checker_results.clone(),
+                state.check_types.clone(),
+                state.events.clone(),
+                state.revalidating.clone(),
+            )
+        };
+
+        let _busy = BusyFlag::acquire(revalidating)
+            .ok_or_else(|| ApiError::Conflict(format!("Review {} is already being revalidated", id)))?;
+
+        let llm_client = self
+            .validator_client()
+            .ok_or_else(|| ApiError::BadRequest("No LLM provider configured".to_string()))?;
+
+        let mut validated = self
+            .run_ai_validators(llm_client.as_ref(), diagnostics, &check_types, &event_sender)
+            .await?;
+        sort_diagnostics(&mut validated);
+
+        {
+            let mut reviews = self.reviews.write().await;
+            if let Some(state) = reviews.get_mut(id) {
+                state.results = validated;
+            }
+        }
+        self.persist(id).await;
+
+        Ok(())
+    }
+
+    /// Run each validator in turn, emitting `CheckFiltered` for every check
+    /// whose diagnostics a validator removed. `check_types` maps rules to
+    /// the check that reported them. Fails with the last error only when
+    /// every validator failed.
     async fn run_ai_validators(
         &self,
         client: &dyn ModelClient,
         mut diagnostics: Vec<Diagnostic>,
-        event_sender: &broadcast::Sender<ReviewEvent>,
-    ) -> Vec<Diagnostic> {
+        check_types: &HashMap<String, CheckType>,
+        event_sender: &EventLog<ReviewEvent>,
+    ) -> Result<Vec<Diagnostic>, LlmError> {
         let validators: Vec<Box<dyn Validator>> = vec![
             Box::new(TypoValidator::new()),
             Box::new(CommentValidator::new()),
             Box::new(Prioritizer::new()),
         ];
+        let validator_count = validators.len();
+        let (mut failures, mut last_error) = (0, None);
 
         for validator in validators {
-            let _ = event_sender.send(ReviewEvent::ValidationStarted {
+            event_sender.send(ReviewEvent::ValidationStarted {
                 validator: validator.name().to_string(),
             });
 
-            match validator.validate(client, diagnostics.clone()).await {
+            let span = tracing::info_span!("validator", validator = validator.name());
+            match validator
+                .validate(client, diagnostics.clone())
+                .instrument(span)
+                .await
+            {
                 Ok(validated) => {
-                    let _ = event_sender.send(ReviewEvent::ValidationCompleted {
+                    let before = count_by_check_type(&diagnostics, check_types);
+                    let after = count_by_check_type(&validated, check_types);
+                    for (check_type, original_count) in before {
+                        let remaining = after.get(&check_type).copied().unwrap_or(0);
+                        if remaining < original_count {
+                            event_sender.send(ReviewEvent::CheckFiltered {
+                                check_type,
+                                original_count,
+                                removed_count: original_count - remaining,
+                                validator: validator.name().to_string(),
+                            });
+                        }
+                    }
+                    event_sender.send(ReviewEvent::ValidationCompleted {
                         validator: validator.name().to_string(),
                         results: validated.clone(),
                     });
@@ -335,34 +1194,72 @@ impl ReviewStore {
                 }
                 Err(e) => {
                     tracing::warn!("Validator {} failed: {}", validator.name(), e);
+                    last_error = Some(e);
+                    failures += 1;
                 }
             }
         }
 
-        diagnostics
+        match last_error {
+            Some(e) if failures == validator_count => Err(e),
+            _ => Ok(diagnostics),
+        }
     }
 
+    /// Run each reviewer in turn. Reviewers still running or not yet started
+    /// at `deadline` are skipped.
     async fn run_ai_reviewers(
         &self,
         client: &dyn ModelClient,
         context: &CodeContext,
-        event_sender: &broadcast::Sender<ReviewEvent>,
+        event_sender: &EventLog<ReviewEvent>,
+        deadline: Option<std::time::Instant>,
     ) -> Vec<Suggestion> {
-        let reviewers: Vec<Box<dyn Reviewer>> = vec![
-            Box::new(CodeOracle::new()),
+        let mut reviewers: Vec<Box<dyn Reviewer>> = vec![
+            Box::new(CodeOracle::new().with_prompts(Arc::clone(&self.prompts))),
             Box::new(ProductIdeasReviewer::new()),
+            Box::new(TestCoverageReviewer::new()),
         ];
+        if AccessibilityReviewer::applies_to(context) {
+            reviewers.push(Box::new(AccessibilityReviewer::new()));
+        }
 
         let mut all_suggestions = Vec::new();
 
         for reviewer in reviewers {
-            let _ = event_sender.send(ReviewEvent::ReviewerStarted {
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(std::time::Instant::now()));
+            if remaining.is_some_and(|remaining| remaining.is_zero()) {
+                tracing::warn!("Review time budget exhausted; skipping {} and later reviewers", reviewer.name());
+                break;
+            }
+            event_sender.send(ReviewEvent::ReviewerStarted {
                 reviewer: reviewer.name().to_string(),
             });
 
-            match reviewer.review(client, context).await {
-                Ok(suggestions) => {
-                    let _ = event_sender.send(ReviewEvent::ReviewerCompleted {
+            let span = tracing::info_span!("reviewer", reviewer = reviewer.name());
+            let review = reviewer.review(client, context).instrument(span);
+            let result = match remaining {
+                Some(remaining) => match tokio::time::timeout(remaining, review).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        tracing::warn!("Reviewer {} abandoned at the review time budget", reviewer.name());
+                        break;
+                    }
+                },
+                None => review.await,
+            };
+            match result {
+                Ok(mut suggestions) => {
+                    let count = suggestions.len();
+                    suggestions.retain(|s| s.confidence >= self.min_suggestion_confidence);
+                    if suggestions.len() < count {
+                        tracing::debug!(
+                            "Dropped {} low-confidence suggestions from {}",
+                            count - suggestions.len(),
+                            reviewer.name()
+                        );
+                    }
+                    event_sender.send(ReviewEvent::ReviewerCompleted {
                         reviewer: reviewer.name().to_string(),
                         suggestions: suggestions.clone(),
                     });
@@ -377,49 +1274,256 @@ impl ReviewStore {
         all_suggestions
     }
 
-    fn build_code_context(&self, repo_url: &str, repo_path: &Path, diagnostics: &[Diagnostic]) -> CodeContext {
-        let files = Self::read_source_files(repo_path);
+    #[allow(clippy::too_many_arguments)]
+    fn build_code_context(
+        &self,
+        repo_url: &str,
+        repo_path: &Path,
+        files: Vec<(String, String)>,
+        tree: RepoTree,
+        profile: &RepoProfile,
+        preview: PreviewLimits,
+        diagnostics: &[Diagnostic],
+    ) -> CodeContext {
+        let dependencies = DependencyGraph::from_files(&files);
+        let product_context = extract_product_context(repo_path, &files);
         CodeContext::new(repo_url.to_string())
             .with_files(files)
+            .with_tree(tree)
+            .with_dependencies(dependencies)
             .with_diagnostics(diagnostics.to_vec())
+            .with_profile(profile.clone())
+            .with_preview(preview)
+            .with_product_context(product_context)
     }
 
-    fn read_source_files(repo_path: &Path) -> Vec<(String, String)> {
-        let mut files = Vec::new();
-        let extensions = ["rs", "ts", "tsx", "js", "jsx", "py", "go", "java"];
-
-        for entry in walkdir::WalkDir::new(repo_path)
+    /// Source files within `limits`, plus a tree listing every candidate
+    /// file. Emits `FileProcessed` for each file read.
+    fn read_source_files(
+        repo_path: &Path,
+        profile: &RepoProfile,
+        max_files: usize,
+        limits: ReadLimits,
+        filter: FileFilter,
+        event_sender: &EventLog<ReviewEvent>,
+    ) -> (Vec<(String, String)>, RepoTree) {
+        let mut entries: Vec<_> = walkdir::WalkDir::new(repo_path)
             .max_depth(5)
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file())
+            // Source in any detected language, plus HTML for markup reviews
             .filter(|e| {
-                e.path()
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .map(|ext| extensions.contains(&ext))
-                    .unwrap_or(false)
+                Language::from_path(e.path()).is_some()
+                    || e.path().extension().is_some_and(|ext| ext == "html")
             })
-            .take(20)
-        {
-            if let Ok(content) = std::fs::read_to_string(entry.path()) {
-                let relative_path = entry
-                    .path()
-                    .strip_prefix(repo_path)
-                    .unwrap_or(entry.path())
-                    .to_string_lossy()
-                    .to_string();
-                files.push((relative_path, content));
-            }
+            .filter(|e| !filter.skips(e.path()))
+            .collect();
+        // Stable sort keeps walk order within each language
+        entries.sort_by_key(|e| profile.rank(e.path()));
+        let tree = RepoTree::collect(repo_path, entries.iter().map(|e| e.path()));
+
+        let files = read_capped(
+            repo_path,
+            entries.into_iter().take(max_files).map(|e| e.into_path()),
+            limits,
+        );
+        for (file_index, (path, _)) in files.iter().enumerate() {
+            event_sender.send(ReviewEvent::FileProcessed {
+                path: path.clone(),
+                file_index,
+                total_files: files.len(),
+            });
         }
+        (files, tree)
+    }
+}
 
-        files
+/// Diagnostics per check, skipping rules no checker reported
+fn count_by_check_type(
+    diagnostics: &[Diagnostic],
+    check_types: &HashMap<String, CheckType>,
+) -> HashMap<CheckType, usize> {
+    let mut counts = HashMap::new();
+    for check_type in diagnostics.iter().filter_map(|d| check_types.get(&d.rule)) {
+        *counts.entry(*check_type).or_insert(0) += 1;
     }
+    counts
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::LlmError;
+    use crate::llm::Message;
+    use crate::types::Severity;
+    use async_trait::async_trait;
+
+    /// Answers every validator prompt by flagging the first diagnostic
+    struct FirstIndexClient;
+
+    #[async_trait]
+    impl ModelClient for FirstIndexClient {
+        async fn chat(&self, _: &[Message], _: Option<&str>) -> Result<String, LlmError> {
+            Ok("[1]".to_string())
+        }
+
+        fn provider(&self) -> &'static str {
+            "stub"
+        }
+    }
+
+    struct RateLimitedClient;
+
+    #[async_trait]
+    impl ModelClient for RateLimitedClient {
+        async fn chat(&self, _: &[Message], _: Option<&str>) -> Result<String, LlmError> {
+            Err(LlmError::RateLimited { retry_after_ms: 2000 })
+        }
+
+        fn provider(&self) -> &'static str {
+            "stub"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reviewers_skipped_past_deadline() {
+        let store = ReviewStore::new(3600, None, None);
+        let sender = EventLog::new(16);
+        let mut receiver = sender.subscribe();
+        let context = CodeContext::new("https://github.com/test/repo".to_string());
+
+        let suggestions = store
+            .run_ai_reviewers(&FirstIndexClient, &context, &sender, Some(std::time::Instant::now()))
+            .await;
+
+        assert!(suggestions.is_empty());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_preview_limits_cap_request_overrides() {
+        let store = ReviewStore::new(3600, None, None);
+        let defaults = store.preview_limits(None, None);
+        assert_eq!(defaults.max_files, PreviewLimits::default().max_files);
+
+        let limits = store.preview_limits(Some(5), Some(usize::MAX));
+        assert_eq!(limits.max_files, 5);
+        assert_eq!(limits.max_chars_per_file, MAX_PREVIEW_CHARS);
+        assert_eq!(store.preview_limits(Some(usize::MAX), None).max_files, MAX_PREVIEW_FILES);
+    }
+
+    #[tokio::test]
+    async fn test_validators_emit_check_filtered() {
+        let store = ReviewStore::new(3600, None, None);
+        let diagnostic = |rule: &str| Diagnostic {
+            file: "src/app.js".to_string(),
+            line: 1,
+            column: 1,
+            message: "message".to_string(),
+            rule: rule.to_string(),
+            severity: Severity::Info,
+            suggestion: None,
+        };
+        let check_types = HashMap::from([
+            ("typo".to_string(), CheckType::Typos),
+            ("todo".to_string(), CheckType::Comments),
+            ("no-console".to_string(), CheckType::Lint),
+        ]);
+        let sender = EventLog::new(16);
+        let mut receiver = sender.subscribe();
+
+        let validated = store
+            .run_ai_validators(
+                &FirstIndexClient,
+                vec![diagnostic("typo"), diagnostic("todo"), diagnostic("no-console")],
+                &check_types,
+                &sender,
+            )
+            .await
+            .unwrap();
+        assert_eq!(validated.len(), 1);
+        assert_eq!(validated[0].rule, "no-console");
+
+        let mut filtered = Vec::new();
+        while let Ok(envelope) = receiver.try_recv() {
+            if let ReviewEvent::CheckFiltered { check_type, original_count, removed_count, validator } = envelope.event {
+                filtered.push((check_type, original_count, removed_count, validator));
+            }
+        }
+        assert_eq!(
+            filtered,
+            vec![
+                (CheckType::Typos, 1, 1, "typo_validator".to_string()),
+                (CheckType::Comments, 1, 1, "comment_validator".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validators_fail_when_llm_unavailable() {
+        let store = ReviewStore::new(3600, None, None);
+        let diagnostic = |rule: &str| Diagnostic {
+            file: "src/app.js".to_string(),
+            line: 1,
+            column: 1,
+            message: "message".to_string(),
+            rule: rule.to_string(),
+            severity: Severity::Info,
+            suggestion: None,
+        };
+        let check_types = HashMap::from([
+            ("typo".to_string(), CheckType::Typos),
+            ("todo".to_string(), CheckType::Comments),
+            ("no-console".to_string(), CheckType::Lint),
+        ]);
+
+        let error = store
+            .run_ai_validators(
+                &RateLimitedClient,
+                vec![diagnostic("typo"), diagnostic("todo"), diagnostic("no-console")],
+                &check_types,
+                &EventLog::new(16),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, LlmError::RateLimited { retry_after_ms: 2000 }));
+    }
+
+    #[test]
+    fn test_read_source_files_emits_file_processed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["app.js", "util.js", "style.css", "index.html"] {
+            std::fs::write(dir.path().join(name), "x\n").unwrap();
+        }
+        let profile = RepoProfile::detect(dir.path(), FileFilter::default());
+        let sender = EventLog::new(16);
+
+        let (files, _) =
+            ReviewStore::read_source_files(
+                dir.path(),
+                &profile,
+                10,
+                ReadLimits::default(),
+                FileFilter::default(),
+                &sender,
+            );
+
+        let processed: Vec<_> = sender
+            .events()
+            .into_iter()
+            .filter_map(|e| match e.event {
+                ReviewEvent::FileProcessed { path, file_index, total_files } => Some((path, file_index, total_files)),
+                _ => None,
+            })
+            .collect();
+        assert!(!files.is_empty());
+        assert_eq!(processed.len(), files.len());
+        for (index, (path, file_index, total_files)) in processed.into_iter().enumerate() {
+            assert_eq!(path, files[index].0);
+            assert_eq!((file_index, total_files), (index, files.len()));
+        }
+    }
 
     #[tokio::test]
     async fn test_create_and_get_review() {
@@ -433,6 +1537,158 @@ mod tests {
         assert_eq!(state.unwrap().repo_url, "https://github.com/test/repo");
     }
 
+    #[tokio::test]
+    async fn test_abort_checker() {
+        let store = ReviewStore::new(3600, None, None);
+        let id = store.create_review("https://github.com/test/repo".to_string()).await;
+        assert!(matches!(
+            store.abort_checker(&id, CheckType::Lint).await,
+            Err(ApiError::Conflict(_))
+        ));
+        assert!(matches!(
+            store.abort_checker("missing", CheckType::Lint).await,
+            Err(ApiError::NotFound(_))
+        ));
+
+        let token = CancellationToken::new();
+        store
+            .reviews
+            .write()
+            .await
+            .get_mut(&id)
+            .unwrap()
+            .checker_tokens
+            .insert(CheckType::Lint, token.clone());
+        store.abort_checker(&id, CheckType::Lint).await.unwrap();
+        assert!(token.is_cancelled());
+        assert!(matches!(
+            store.abort_checker(&id, CheckType::Format).await,
+            Err(ApiError::Conflict(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_review_without_source_code() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("README.md"), "# Notes\n".repeat(50)).unwrap();
+        std::fs::write(dir.path().join("slides.pdf"), "%PDF-1.4").unwrap();
+        let repo = ClonedRepo::from_local(dir.path().to_path_buf()).unwrap();
+
+        let store = ReviewStore::new(3600, None, None);
+        let id = store.create_review("https://github.com/test/repo".to_string()).await;
+        store.run_review_with_repo(&id, &repo, std::time::Instant::now()).await.unwrap();
+        let state = store.get_review(&id).await.unwrap();
+        assert_eq!(state.status, ReviewStatus::Failed);
+        assert_eq!(state.error.as_deref(), Some(NO_SOURCE_ERROR));
+        assert!(state.events().iter().all(|e| !matches!(e.event, ReviewEvent::CheckStarted { .. })));
+
+        // A single short file passes once the threshold is lowered
+        std::fs::write(dir.path().join("main.py"), "print('hi')\n").unwrap();
+        let id = store.create_review("https://github.com/test/repo".to_string()).await;
+        store.set_min_source_lines(&id, 1).await;
+        store.run_review_with_repo(&id, &repo, std::time::Instant::now()).await.unwrap();
+        assert_eq!(store.get_review(&id).await.unwrap().status, ReviewStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_max_entries_evicts_least_recently_used() {
+        let store = ReviewStore::new(3600, None, None).with_max_entries(2);
+        let repo = "https://github.com/test/repo";
+        let older = store.create_review(repo.to_string()).await;
+        let newer = store.create_review(repo.to_string()).await;
+        {
+            let mut reviews = store.reviews.write().await;
+            for id in [&older, &newer] {
+                let state = reviews.get_mut(id).unwrap();
+                state.status = ReviewStatus::Completed;
+                state.last_accessed = AtomicU64::new(0);
+            }
+        }
+        // Reading the older review makes the newer one least recently used
+        store.get_review(&older).await.unwrap();
+
+        store.create_review(repo.to_string()).await;
+        assert!(store.get_review(&newer).await.is_none());
+        assert!(store.get_review(&older).await.is_some());
+
+        // Running reviews are kept even over the cap
+        store.create_review(repo.to_string()).await;
+        store.create_review(repo.to_string()).await;
+        let reviews = store.reviews.read().await;
+        assert_eq!(reviews.len(), 3);
+        assert!(!reviews.contains_key(&older));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_keeps_cached_results() {
+        assert!(ReviewCacheMode::Use.serves(Some("v1"), "v1"));
+        assert!(ReviewCacheMode::Refresh.serves(Some("v1"), "v1"));
+        assert!(!ReviewCacheMode::Bypass.serves(Some("v1"), "v1"));
+        assert!(!ReviewCacheMode::Use.serves(Some("v0"), "v1"));
+        assert!(!ReviewCacheMode::Use.serves(None, "v1"));
+
+        let store = ReviewStore::new(3600, None, None);
+        let id = store.create_review("https://github.com/test/repo".to_string()).await;
+        let events = store.watch_review(&id).await.unwrap();
+
+        store.apply_cached_result(&id, vec![], vec![], 5, true).await;
+        let state = store.get_review(&id).await.unwrap();
+        assert_eq!(state.status, ReviewStatus::Completed);
+        assert!(state.cached && state.refreshing);
+
+        // A failed refresh leaves the cached review completed
+        store.mark_failed(&id, "clone failed".to_string()).await;
+        let state = store.get_review(&id).await.unwrap();
+        assert_eq!(state.status, ReviewStatus::Completed);
+        assert!(state.cached && !state.refreshing);
+        assert_eq!(state.error.as_deref(), Some("Refresh failed: clone failed"));
+
+        // The stream stays open past the cached completion
+        let events: Vec<ReviewEvent> = events.map(|envelope| envelope.event).collect().await;
+        assert!(matches!(
+            events.as_slice(),
+            [ReviewEvent::ReviewCompleted { refreshing: true, .. }, ReviewEvent::ReviewFailed { .. }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stage_models() {
+        let providers = ProvidersConfig {
+            anthropic_api_key: Some("sk-ant-test".to_string().into()),
+            validator_model: Some("claude-3-5-haiku-latest".to_string()),
+            ..ProvidersConfig::default()
+        };
+        let store = ReviewStore::new(3600, Some(providers), None);
+        let default_model = store.create_llm_client(None).unwrap().model().to_string();
+
+        assert_eq!(store.validator_client().unwrap().model(), "claude-3-5-haiku-latest");
+        assert_eq!(store.reviewer_client().unwrap().model(), default_model);
+        assert!(ReviewStore::new(3600, None, None).validator_client().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_latest_review_for_repo() {
+        let store = ReviewStore::new(3600, None, None);
+        let repo = "https://github.com/test/repo";
+        let older = store.create_review(repo.to_string()).await;
+        let newer = store.create_review(repo.to_string()).await;
+        store
+            .create_review("https://github.com/test/other".to_string())
+            .await;
+
+        {
+            let mut reviews = store.reviews.write().await;
+            reviews.get_mut(&older).unwrap().created_at -= 60;
+        }
+
+        let latest = store.get_latest_review_for_repo(repo).await.unwrap();
+        assert_eq!(latest.id, newer);
+        assert!(store
+            .get_latest_review_for_repo("https://github.com/test/missing")
+            .await
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_subscribe() {
         let store = ReviewStore::new(3600, None, None);
@@ -443,4 +1699,90 @@ mod tests {
         let receiver = store.subscribe(&id).await;
         assert!(receiver.is_some());
     }
+
+    #[tokio::test]
+    async fn test_list_reviews_without_persistence() {
+        let store = ReviewStore::new(3600, None, None);
+        let older = store
+            .create_review("https://github.com/test/repo".to_string())
+            .await;
+        let newer = store
+            .create_review("https://github.com/test/repo".to_string())
+            .await;
+        store
+            .create_review("https://github.com/test/other".to_string())
+            .await;
+        store.reviews.write().await.get_mut(&older).unwrap().created_at -= 60;
+
+        let ids: Vec<String> = store
+            .list_reviews("https://github.com/test/repo")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|state| state.id)
+            .collect();
+        assert_eq!(ids, vec![newer, older]);
+    }
+
+    #[test]
+    fn test_review_state_from_job() {
+        let job = ReviewJob {
+            id: None,
+            review_id: "review-1".to_string(),
+            repo_url: "https://github.com/test/repo".to_string(),
+            status: ReviewStatus::Failed,
+            results: Vec::new(),
+            checker_results: Vec::new(),
+            suggestions: Vec::new(),
+            // Logged before sequence numbers were recorded
+            events: vec![
+                EventEnvelope::new(0, 0, ReviewEvent::Queued { position: 1 }),
+                EventEnvelope::new(0, 0, ReviewEvent::ReviewFailed {
+                    error: "clone failed".to_string(),
+                }),
+            ],
+            error: Some("clone failed".to_string()),
+            created_at: BsonDateTime::from_millis(1_700_000_000_000),
+            completed_at: None,
+        };
+
+        let state = ReviewState::from_job(job);
+        assert_eq!(state.id, "review-1");
+        assert_eq!(state.status, ReviewStatus::Failed);
+        assert_eq!(state.error.as_deref(), Some("clone failed"));
+        assert_eq!(state.created_at, 1_700_000_000);
+        assert_eq!(state.events().iter().map(|e| e.seq).collect::<Vec<_>>(), [1, 2]);
+
+        // New events continue the restored log
+        state.emit(ReviewEvent::EventsDropped { count: 0 });
+        assert_eq!(state.events().last().unwrap().seq, 3);
+    }
+
+    #[tokio::test]
+    async fn test_rerun_validation_conflicts() {
+        let store = ReviewStore::new(3600, None, None);
+        let id = store
+            .create_review("https://github.com/test/repo".to_string())
+            .await;
+
+        let pending = store.rerun_validation(&id).await;
+        assert!(matches!(pending, Err(ApiError::Conflict(_))));
+
+        let revalidating = {
+            let mut reviews = store.reviews.write().await;
+            let state = reviews.get_mut(&id).unwrap();
+            state.status = ReviewStatus::Completed;
+            state.revalidating.clone()
+        };
+
+        let held = BusyFlag::acquire(revalidating.clone()).unwrap();
+        let busy = store.rerun_validation(&id).await;
+        assert!(matches!(busy, Err(ApiError::Conflict(_))));
+        drop(held);
+
+        // Without a provider the call fails, but the flag is released
+        let unconfigured = store.rerun_validation(&id).await;
+        assert!(matches!(unconfigured, Err(ApiError::BadRequest(_))));
+        assert!(!*revalidating.lock().unwrap());
+    }
 }
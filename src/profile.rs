@@ -0,0 +1,286 @@
+//! Repository language detection
+//!
+//! Builds an extension histogram over a repository's source files so checkers
+//! can be dispatched per language, prompts can state the stack, and file
+//! collection can favour the languages the project is actually written in.
+
+use crate::files::FileFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Directories skipped during detection and file collection
+pub const IGNORED_DIRS: &[&str] = &["node_modules", "target", "dist", "build", "__pycache__"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    Rust,
+    TypeScript,
+    JavaScript,
+    Python,
+    Go,
+    Java,
+    Kotlin,
+    Swift,
+    C,
+    Cpp,
+    CSharp,
+    Ruby,
+    Php,
+    Unknown,
+}
+
+impl Language {
+    /// Every language that can be detected
+    pub const ALL: &'static [Language] = &[
+        Self::Rust,
+        Self::TypeScript,
+        Self::JavaScript,
+        Self::Python,
+        Self::Go,
+        Self::Java,
+        Self::Kotlin,
+        Self::Swift,
+        Self::C,
+        Self::Cpp,
+        Self::CSharp,
+        Self::Ruby,
+        Self::Php,
+    ];
+
+    /// Map a file extension to its language, `None` for non-code files
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let language = match ext {
+            "rs" => Self::Rust,
+            "ts" | "tsx" | "mts" | "cts" => Self::TypeScript,
+            "js" | "jsx" | "mjs" | "cjs" => Self::JavaScript,
+            "py" => Self::Python,
+            "go" => Self::Go,
+            "java" => Self::Java,
+            "kt" => Self::Kotlin,
+            "swift" => Self::Swift,
+            "c" | "h" => Self::C,
+            "cpp" | "cc" | "hpp" => Self::Cpp,
+            "cs" => Self::CSharp,
+            "rb" => Self::Ruby,
+            "php" => Self::Php,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rust => "Rust",
+            Self::TypeScript => "TypeScript",
+            Self::JavaScript => "JavaScript",
+            Self::Python => "Python",
+            Self::Go => "Go",
+            Self::Java => "Java",
+            Self::Kotlin => "Kotlin",
+            Self::Swift => "Swift",
+            Self::C => "C",
+            Self::Cpp => "C++",
+            Self::CSharp => "C#",
+            Self::Ruby => "Ruby",
+            Self::Php => "PHP",
+            Self::Unknown => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Languages used in a repository, most common first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoProfile {
+    pub languages: Vec<(Language, usize)>,
+    pub primary: Language,
+    /// Generated, minified or vendored files left out of analysis
+    #[serde(default)]
+    pub skipped_files: usize,
+}
+
+impl Default for RepoProfile {
+    fn default() -> Self {
+        Self {
+            languages: Vec::new(),
+            primary: Language::Unknown,
+            skipped_files: 0,
+        }
+    }
+}
+
+impl RepoProfile {
+    /// Build a profile from a list of file paths
+    pub fn from_paths<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Self {
+        let mut counts: HashMap<Language, usize> = HashMap::new();
+        for path in paths {
+            if let Some(language) = Language::from_path(path) {
+                *counts.entry(language).or_default() += 1;
+            }
+        }
+
+        let mut languages: Vec<(Language, usize)> = counts.into_iter().collect();
+        // Ties broken by name so the profile is deterministic
+        languages.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name().cmp(b.0.name())));
+
+        let primary = languages
+            .first()
+            .map(|(language, _)| *language)
+            .unwrap_or(Language::Unknown);
+
+        Self {
+            languages,
+            primary,
+            skipped_files: 0,
+        }
+    }
+
+    /// Walk a cloned repository and count source files per language,
+    /// leaving out the files `filter` skips
+    pub fn detect(repo_path: &Path, filter: FileFilter) -> Self {
+        let (skipped, paths): (Vec<_>, Vec<_>) = walkdir::WalkDir::new(repo_path)
+            .max_depth(10)
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() == 0 || !is_ignored_dir_name(&e.file_name().to_string_lossy())
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .filter(|p| Language::from_path(p).is_some())
+            .partition(|p| filter.skips(p));
+
+        Self {
+            skipped_files: skipped.len(),
+            ..Self::from_paths(paths.iter().map(|p| p.as_path()))
+        }
+    }
+
+    pub fn has(&self, language: Language) -> bool {
+        self.languages.iter().any(|(l, _)| *l == language)
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.languages.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Sort key for file collection: files in more common languages first,
+    /// non-code files last
+    pub fn rank(&self, path: &Path) -> usize {
+        Language::from_path(path)
+            .and_then(|language| self.languages.iter().position(|(l, _)| *l == language))
+            .unwrap_or(self.languages.len())
+    }
+
+    /// One-line description of the stack for LLM prompts
+    pub fn describe(&self) -> String {
+        if self.languages.is_empty() {
+            return "No recognized source languages".to_string();
+        }
+        let languages: Vec<String> = self
+            .languages
+            .iter()
+            .map(|(language, count)| format!("{} ({} files)", language, count))
+            .collect();
+        format!(
+            "Primary language: {}. Languages: {}",
+            self.primary,
+            languages.join(", ")
+        )
+    }
+}
+
+/// Whether a path component should be skipped (hidden or vendored)
+pub fn is_ignored_dir_name(name: &str) -> bool {
+    name.starts_with('.') || IGNORED_DIRS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_from_paths_histogram() {
+        let paths = [
+            "src/main.py",
+            "src/util.py",
+            "web/app.ts",
+            "README.md",
+            "setup.py",
+        ];
+        let profile = RepoProfile::from_paths(paths.iter().map(Path::new));
+
+        assert_eq!(profile.primary, Language::Python);
+        assert_eq!(
+            profile.languages,
+            vec![(Language::Python, 3), (Language::TypeScript, 1)]
+        );
+        assert!(profile.has(Language::TypeScript));
+        assert!(!profile.has(Language::JavaScript));
+        assert_eq!(profile.file_count(), 4);
+    }
+
+    #[test]
+    fn test_empty_profile() {
+        let profile = RepoProfile::from_paths(std::iter::empty());
+        assert_eq!(profile.primary, Language::Unknown);
+        assert_eq!(profile.describe(), "No recognized source languages");
+    }
+
+    #[test]
+    fn test_detect_skips_vendored_dirs() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("node_modules/pkg/a.js"), "").unwrap();
+        fs::write(dir.path().join("node_modules/pkg/b.js"), "").unwrap();
+
+        let profile = RepoProfile::detect(dir.path(), FileFilter::default());
+
+        assert_eq!(profile.languages, vec![(Language::Rust, 1)]);
+    }
+
+    #[test]
+    fn test_detect_counts_generated_files() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("bundle.min.js"), "var a=1;").unwrap();
+        fs::write(
+            dir.path().join("api.rs"),
+            "// @generated by build.rs\npub fn f() {}\n",
+        )
+        .unwrap();
+
+        let profile = RepoProfile::detect(dir.path(), FileFilter::default());
+
+        assert_eq!(profile.languages, vec![(Language::Rust, 1)]);
+        assert_eq!(profile.skipped_files, 2);
+    }
+
+    #[test]
+    fn test_rank_prefers_primary_language() {
+        let paths = ["a.py", "b.py", "c.js"];
+        let profile = RepoProfile::from_paths(paths.iter().map(Path::new));
+
+        assert_eq!(profile.rank(Path::new("x.py")), 0);
+        assert_eq!(profile.rank(Path::new("x.js")), 1);
+        assert_eq!(profile.rank(Path::new("x.md")), 2);
+    }
+}
@@ -0,0 +1,273 @@
+//! Customizable LLM prompt templates
+//!
+//! Prompts are [`PromptTemplate`]s with `{{variable}}` placeholders, looked up
+//! by id in a [`PromptRegistry`]. Overrides are read from the TOML file at
+//! `PROMPTS_CONFIG_PATH`, one table per id:
+//!
+//! ```toml
+//! [grader_criterion]
+//! template = """
+//! ## Task
+//! {{task_title}}
+//! ...
+//! """
+//! ```
+//!
+//! Ids without an override use the built-in defaults below.
+
+use crate::error::ConfigError;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Repository context sent ahead of every criterion check, and cached by
+/// providers that support it. Variables: `stack`, `tree`, `diagnostics`,
+/// `code`.
+pub const GRADER_CONTEXT_PROMPT: &str = "grader_context";
+/// User prompt for one criterion check, following the context. Variables:
+/// `task_title`, `task_description`, `criterion`, `examples`, `tests`,
+/// `language`, `evidence`.
+pub const GRADER_CRITERION_PROMPT: &str = "grader_criterion";
+/// System prompt for criterion checks
+pub const GRADER_SYSTEM_PROMPT: &str = "grader_system";
+/// User prompt for CodeOracle. Variables: `tree`, `dependencies`, `files`.
+pub const CODE_ORACLE_PROMPT: &str = "code_oracle";
+/// System prompt for CodeOracle
+pub const CODE_ORACLE_SYSTEM_PROMPT: &str = "code_oracle_system";
+
+const DEFAULT_GRADER_CONTEXT: &str = r#"## Stack
+{{stack}}
+
+{{tree}}{{diagnostics}}## Submitted Code
+{{code}}
+"#;
+
+const DEFAULT_GRADER_CRITERION: &str = r#"## Task
+{{task_title}}
+{{task_description}}
+
+## Acceptance Criterion to Check
+{{criterion}}
+{{examples}}{{tests}}
+Evaluate if this criterion is satisfied. Return JSON only.
+{{evidence}}"#;
+
+const DEFAULT_GRADER_SYSTEM: &str = r#"You are a code grader evaluating student submissions against acceptance criteria.
+
+## Your Role
+Determine if the submitted code satisfies a specific acceptance criterion.
+
+## Evaluation Guidelines
+1. Be Fair: Give credit for working implementations, even if imperfect
+2. Be Thorough: Check for actual implementation, not just presence of code
+3. Be Specific: Cite exact file and line numbers as evidence
+4. Consider Intent: Partial implementations may still satisfy criteria
+
+## Scoring Rules
+- outcome: "passed" - Criterion is clearly satisfied
+- outcome: "failed" - Criterion is NOT satisfied, or the code for it is missing or incomplete
+- outcome: "not_verifiable" - Criterion cannot be checked from source code at all, e.g. response times of the deployed app, uptime, or behavior of external services. Never use it for missing or incomplete code.
+- confidence: Your certainty (0.0 = guess, 1.0 = certain)
+
+## Response Format
+Respond ONLY with valid JSON (no markdown, no explanation):
+{
+    "outcome": "passed"|"failed"|"not_verifiable",
+    "confidence": 0.0-1.0,
+    "evidence": "Detailed explanation with code references",
+    "code_references": [
+        {"file": "path/to/file", "line_start": 10, "line_end": 20, "snippet": "optional"}
+    ]
+}"#;
+
+const DEFAULT_CODE_ORACLE: &str = r#"Analyze this codebase and provide architectural and code quality suggestions.
+
+{{tree}}{{dependencies}}{{files}}
+
+Provide suggestions in this JSON format:
+[{
+  "category": "architecture"|"performance"|"security"|"code_quality",
+  "title": "Brief title",
+  "description": "Detailed description",
+  "file": "path/to/file.rs" (optional),
+  "line": 42 (optional),
+  "priority": "high"|"medium"|"low",
+  "rationale": "Why this matters",
+  "confidence": 0.0-1.0
+}]
+
+Set confidence to how sure you are that the suggestion is correct and worth acting on. Leave out generic advice that doesn't point at this codebase.
+
+Focus on:
+- Architectural patterns and anti-patterns, including module dependencies
+- Error handling improvements
+- Performance optimizations
+- Security concerns
+- Code organization
+
+Return ONLY the JSON array."#;
+
+const DEFAULT_CODE_ORACLE_SYSTEM: &str = "You are a senior software architect reviewing code. \
+    Focus on actionable improvements. Respond ONLY with JSON. \
+    All text content (title, description, rationale) MUST be written in Korean.";
+
+static DEFAULTS: LazyLock<HashMap<&'static str, PromptTemplate>> = LazyLock::new(|| {
+    [
+        (GRADER_CONTEXT_PROMPT, DEFAULT_GRADER_CONTEXT),
+        (GRADER_CRITERION_PROMPT, DEFAULT_GRADER_CRITERION),
+        (GRADER_SYSTEM_PROMPT, DEFAULT_GRADER_SYSTEM),
+        (CODE_ORACLE_PROMPT, DEFAULT_CODE_ORACLE),
+        (CODE_ORACLE_SYSTEM_PROMPT, DEFAULT_CODE_ORACLE_SYSTEM),
+    ]
+    .into_iter()
+    .map(|(id, template)| (id, PromptTemplate::new(template)))
+    .collect()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PromptTemplate {
+    pub template: String,
+}
+
+impl PromptTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    /// Replace each `{{name}}` with its value in `vars`. Placeholders without
+    /// a value are kept as written; values are inserted verbatim, so code
+    /// containing `{{` is never expanded.
+    pub fn render(&self, vars: &HashMap<&str, &str>) -> String {
+        let mut out = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let placeholder = &rest[start..start + 2 + len + 2];
+            let name = placeholder[2..placeholder.len() - 2].trim();
+            out.push_str(&rest[..start]);
+            out.push_str(vars.get(name).copied().unwrap_or(placeholder));
+            rest = &rest[start + placeholder.len()..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Prompt templates by id, with overrides taking precedence over defaults
+#[derive(Debug, Clone, Default)]
+pub struct PromptRegistry {
+    overrides: HashMap<&'static str, PromptTemplate>,
+}
+
+impl PromptRegistry {
+    /// Load overrides from `PROMPTS_CONFIG_PATH`, or use the defaults when it
+    /// is unset
+    pub fn from_env() -> Result<Self, ConfigError> {
+        match std::env::var("PROMPTS_CONFIG_PATH") {
+            Ok(path) => Self::from_file(Path::new(&path)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::InvalidValue(format!("PROMPTS_CONFIG_PATH ({}: {})", path.display(), e))
+        })?;
+        Self::from_toml(&content)
+    }
+
+    /// Parse overrides. Unknown ids are rejected so typos don't silently
+    /// leave the default in place.
+    pub fn from_toml(content: &str) -> Result<Self, ConfigError> {
+        let parsed: HashMap<String, PromptTemplate> = toml::from_str(content)
+            .map_err(|e| ConfigError::InvalidValue(format!("PROMPTS_CONFIG_PATH ({})", e)))?;
+
+        let mut overrides = HashMap::new();
+        for (id, template) in parsed {
+            let Some((&known_id, _)) = DEFAULTS.get_key_value(id.as_str()) else {
+                return Err(ConfigError::InvalidValue(format!(
+                    "PROMPTS_CONFIG_PATH (unknown prompt id '{}')",
+                    id
+                )));
+            };
+            overrides.insert(known_id, template);
+        }
+        Ok(Self { overrides })
+    }
+
+    /// The override for `id`, or its built-in default
+    pub fn get(&self, id: &str) -> &PromptTemplate {
+        self.overrides
+            .get(id)
+            .or_else(|| DEFAULTS.get(id))
+            .unwrap_or_else(|| panic!("unknown prompt id '{}'", id))
+    }
+
+    /// Short hash of every prompt in effect, overrides included, so results
+    /// produced with different prompts can be told apart
+    pub fn fingerprint(&self) -> String {
+        let mut ids: Vec<&str> = DEFAULTS.keys().copied().collect();
+        ids.sort_unstable();
+        let mut hasher = Sha256::new();
+        for id in ids {
+            hasher.update(id);
+            hasher.update([0]);
+            hasher.update(&self.get(id).template);
+            hasher.update([0]);
+        }
+        hex::encode(&hasher.finalize()[..8])
+    }
+
+    /// Ids with an override
+    pub fn overridden(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.overrides.keys().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let template = PromptTemplate::new("Hi {{ name }}, {{missing}} {{code}}");
+        let vars = HashMap::from([("name", "Ada"), ("code", "<p>{{user}}</p>")]);
+
+        assert_eq!(template.render(&vars), "Hi Ada, {{missing}} <p>{{user}}</p>");
+        assert_eq!(PromptTemplate::new("a {{b").render(&vars), "a {{b");
+    }
+
+    #[test]
+    fn test_registry_overrides() {
+        let registry = PromptRegistry::from_toml(
+            r#"
+[code_oracle]
+template = "Review:\n{{files}}"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(registry.get(CODE_ORACLE_PROMPT).template, "Review:\n{{files}}");
+        assert_eq!(
+            registry.get(GRADER_SYSTEM_PROMPT).template,
+            DEFAULT_GRADER_SYSTEM
+        );
+        assert_eq!(registry.overridden().collect::<Vec<_>>(), vec![CODE_ORACLE_PROMPT]);
+
+        assert_ne!(registry.fingerprint(), PromptRegistry::default().fingerprint());
+        assert_eq!(
+            PromptRegistry::default().fingerprint(),
+            PromptRegistry::default().fingerprint()
+        );
+
+        assert!(PromptRegistry::from_toml("[grader_criterio]\ntemplate = \"x\"").is_err());
+        assert!(PromptRegistry::from_toml("code_oracle = 1").is_err());
+    }
+}
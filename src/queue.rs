@@ -0,0 +1,210 @@
+//! Bounded job queue drained by a fixed pool of workers
+//!
+//! Reviews and grades are queued here instead of being spawned unboundedly,
+//! so at most `workers` jobs run at once. Waiting job ids are published in
+//! queue order so stores can report each job's position.
+
+use crate::error::ApiError;
+use futures::future::BoxFuture;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
+
+/// Jobs run concurrently when `MAX_CONCURRENT_JOBS` is unset
+pub const DEFAULT_MAX_CONCURRENT_JOBS: usize = 4;
+
+/// Jobs that may wait when `MAX_QUEUED_JOBS` is unset
+pub const DEFAULT_MAX_QUEUED_JOBS: usize = 100;
+
+/// Seconds clients are told to wait before retrying when the queue is full
+pub const QUEUE_FULL_RETRY_AFTER_SECS: u64 = 30;
+
+struct Job {
+    id: String,
+    run: BoxFuture<'static, ()>,
+}
+
+#[derive(Clone)]
+pub struct JobQueue {
+    sender: mpsc::Sender<Job>,
+    /// Ids of jobs not yet picked up by a worker, oldest first
+    waiting: watch::Sender<Vec<String>>,
+    cancelled: Arc<Mutex<HashSet<String>>>,
+}
+
+impl JobQueue {
+    /// Start `workers` workers sharing a queue of up to `capacity` waiting jobs
+    pub fn new(workers: usize, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let (waiting, _) = watch::channel(Vec::new());
+        let queue = Self {
+            sender,
+            waiting,
+            cancelled: Arc::default(),
+        };
+
+        let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+        for _ in 0..workers.max(1) {
+            tokio::spawn(Self::work(
+                Arc::clone(&receiver),
+                queue.waiting.clone(),
+                Arc::clone(&queue.cancelled),
+            ));
+        }
+        queue
+    }
+
+    async fn work(
+        receiver: Arc<tokio::sync::Mutex<mpsc::Receiver<Job>>>,
+        waiting: watch::Sender<Vec<String>>,
+        cancelled: Arc<Mutex<HashSet<String>>>,
+    ) {
+        loop {
+            let Some(job) = receiver.lock().await.recv().await else {
+                break;
+            };
+            waiting.send_if_modified(|ids| remove_id(ids, &job.id));
+            if cancelled.lock().unwrap().remove(&job.id) {
+                continue;
+            }
+            // Run on its own task so a panicking job doesn't take the worker down
+            if let Err(e) = tokio::spawn(job.run).await {
+                tracing::error!("Job {} panicked: {}", job.id, e);
+            }
+        }
+    }
+
+    /// Queue `job` under `id`, returning its 1-based position. Fails with
+    /// `ServiceUnavailable` when the queue is full.
+    pub fn try_enqueue(
+        &self,
+        id: &str,
+        job: impl Future<Output = ()> + Send + 'static,
+    ) -> Result<usize, ApiError> {
+        // Published before sending so a worker picking the job up at once
+        // always finds it to remove
+        let mut position = 0;
+        self.waiting.send_modify(|ids| {
+            ids.push(id.to_string());
+            position = ids.len();
+        });
+
+        let job = Job {
+            id: id.to_string(),
+            run: Box::pin(job),
+        };
+        if self.sender.try_send(job).is_err() {
+            self.waiting.send_if_modified(|ids| remove_id(ids, id));
+            return Err(ApiError::ServiceUnavailable(
+                "job queue is full, try again later".to_string(),
+            ));
+        }
+        Ok(position)
+    }
+
+    /// Jobs that can be queued before the queue is full
+    pub fn free_slots(&self) -> usize {
+        self.sender.capacity()
+    }
+
+    /// 1-based position of a waiting job
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.waiting.borrow().iter().position(|i| i == id).map(|i| i + 1)
+    }
+
+    /// Cancel a waiting job so it never runs. Returns false once a worker has
+    /// picked it up.
+    pub fn cancel(&self, id: &str) -> bool {
+        let mut cancelled = self.cancelled.lock().unwrap();
+        let removed = self.waiting.send_if_modified(|ids| remove_id(ids, id));
+        if removed {
+            cancelled.insert(id.to_string());
+        }
+        removed
+    }
+
+    /// Waiting job ids, updated whenever a job is queued, started or cancelled
+    pub fn subscribe(&self) -> watch::Receiver<Vec<String>> {
+        self.waiting.subscribe()
+    }
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_JOBS, DEFAULT_MAX_QUEUED_JOBS)
+    }
+}
+
+fn remove_id(ids: &mut Vec<String>, id: &str) -> bool {
+    let before = ids.len();
+    ids.retain(|i| i != id);
+    ids.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn test_more_jobs_than_workers_run_in_order() {
+        let queue = JobQueue::new(1, 10);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        // Hold the only worker until every job is queued
+        let (release, gate) = oneshot::channel::<()>();
+        queue
+            .try_enqueue("blocker", async {
+                let _ = gate.await;
+            })
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+        for i in 0..4 {
+            let order = Arc::clone(&order);
+            let done_tx = done_tx.clone();
+            let position = queue
+                .try_enqueue(&format!("job-{}", i), async move {
+                    order.lock().unwrap().push(i);
+                    let _ = done_tx.send(());
+                })
+                .unwrap();
+            assert_eq!(position, i + 1);
+        }
+        assert_eq!(queue.position("job-2"), Some(3));
+
+        assert!(queue.cancel("job-1"));
+        assert!(!queue.cancel("job-1"));
+        assert_eq!(queue.position("job-2"), Some(2));
+
+        release.send(()).unwrap();
+        for _ in 0..3 {
+            done_rx.recv().await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 2, 3]);
+        assert!(queue.subscribe().borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_is_rejected() {
+        let queue = JobQueue::new(1, 1);
+        let (release, gate) = oneshot::channel::<()>();
+        queue
+            .try_enqueue("running", async {
+                let _ = gate.await;
+            })
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        queue.try_enqueue("waiting", async {}).unwrap();
+        let err = queue.try_enqueue("rejected", async {}).unwrap_err();
+        assert!(matches!(err, ApiError::ServiceUnavailable(_)));
+        assert_eq!(queue.position("rejected"), None);
+        assert_eq!(queue.position("waiting"), Some(1));
+
+        release.send(()).unwrap();
+    }
+}
@@ -0,0 +1,451 @@
+//! Run a submission's own test suite as grading evidence
+//!
+//! The project type is detected from its manifest and the matching test
+//! command runs in a bubblewrap sandbox: no network, the system directories
+//! read-only, an empty home and a cleared environment, so API keys, database
+//! URLs and the server's files are never visible to student code. Only the
+//! repository is writable. Missing toolchains are reported as `None` rather
+//! than as failures.
+//!
+//! Nothing runs unless the server opts in with [`TestRunnerConfig::enabled`].
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// Characters of output kept from the end of the test run
+const OUTPUT_TAIL_CHARS: usize = 3000;
+
+/// Bytes of output a test run may write before it is killed
+const MAX_OUTPUT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Bytes read back from the end of the output for counts and the tail
+const OUTPUT_READ_BYTES: u64 = 256 * 1024;
+
+/// Environment variables passed through to the test command
+const PASSTHROUGH_ENV: &[&str] = &["PATH", "LANG"];
+
+/// Sandbox every test command runs in
+const SANDBOX_PROGRAM: &str = "bwrap";
+
+/// Host directories mounted read-only in the sandbox, when they exist.
+/// Toolchains must be installed under one of them, not in a home directory.
+const SANDBOX_READ_ONLY_DIRS: &[&str] = &["/usr", "/bin", "/sbin", "/lib", "/lib64", "/etc", "/opt"];
+
+/// Empty home directory inside the sandbox
+const SANDBOX_HOME: &str = "/tmp/home";
+
+/// Largest `test_timeout_secs` when `MAX_TEST_TIMEOUT_SECS` is unset
+pub const DEFAULT_MAX_TEST_TIMEOUT_SECS: u64 = 600;
+
+static PASSED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+) (?:passed|passing)").unwrap());
+static FAILED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d+) (?:failed|failing|errors?)\b").unwrap());
+
+/// Server-side settings for running submission tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestRunnerConfig {
+    /// Run the tests of tasks with `run_tests`; off unless the server opts in
+    pub enabled: bool,
+    /// Upper bound on a grade's `test_timeout_secs`
+    pub max_timeout_secs: u64,
+}
+
+impl Default for TestRunnerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_timeout_secs: DEFAULT_MAX_TEST_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl TestRunnerConfig {
+    /// Timeout for a grade asking for `requested_secs`, capped at
+    /// `max_timeout_secs`
+    pub fn timeout(&self, requested_secs: u64) -> Duration {
+        Duration::from_secs(requested_secs.min(self.max_timeout_secs))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestFramework {
+    Npm,
+    Cargo,
+    Pytest,
+}
+
+impl TestFramework {
+    /// Detect the test command from the repository's manifests
+    pub fn detect(repo_path: &Path) -> Option<Self> {
+        if has_npm_test_script(&repo_path.join("package.json")) {
+            return Some(Self::Npm);
+        }
+        if repo_path.join("Cargo.toml").is_file() {
+            return Some(Self::Cargo);
+        }
+        let pytest_markers = ["pytest.ini", "conftest.py", "tests/conftest.py"];
+        let pyproject = fs::read_to_string(repo_path.join("pyproject.toml")).unwrap_or_default();
+        if pytest_markers.iter().any(|m| repo_path.join(m).is_file())
+            || pyproject.contains("[tool.pytest")
+            || has_python_tests(&repo_path.join("tests"))
+        {
+            return Some(Self::Pytest);
+        }
+        None
+    }
+
+    fn program_and_args(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Self::Npm => ("npm", &["test"]),
+            Self::Cargo => ("cargo", &["test"]),
+            Self::Pytest => ("python3", &["-m", "pytest"]),
+        }
+    }
+
+    pub fn command(self) -> String {
+        let (program, args) = self.program_and_args();
+        std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn has_npm_test_script(package_json: &Path) -> bool {
+    let Ok(content) = fs::read_to_string(package_json) else {
+        return false;
+    };
+    serde_json::from_str::<serde_json::Value>(&content)
+        .ok()
+        .and_then(|v| v.get("scripts")?.get("test")?.as_str().map(str::to_string))
+        // npm init's placeholder script always fails
+        .is_some_and(|script| !script.contains("no test specified"))
+}
+
+fn has_python_tests(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.starts_with("test_") && name.ends_with(".py")
+        })
+    })
+}
+
+/// Outcome of running a repository's tests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRunResult {
+    pub framework: TestFramework,
+    pub command: String,
+    /// Whether the command exited successfully within the timeout
+    pub success: bool,
+    pub timed_out: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passed: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed: Option<u32>,
+    pub duration_ms: u64,
+    /// End of the combined stdout/stderr
+    pub output: String,
+}
+
+impl TestRunResult {
+    /// One-line summary used in prompts and evidence
+    pub fn summary(&self) -> String {
+        let outcome = if self.timed_out {
+            "timed out".to_string()
+        } else {
+            match (self.passed, self.failed) {
+                (None, None) if self.success => "succeeded".to_string(),
+                (None, None) => "failed".to_string(),
+                (passed, failed) => format!(
+                    "{} passed, {} failed",
+                    passed.unwrap_or(0),
+                    failed.unwrap_or(0)
+                ),
+            }
+        };
+        format!("`{}`: {}", self.command, outcome)
+    }
+}
+
+/// Run the detected test command in the sandbox, killing it after
+/// `timeout`. Returns `None` when no test setup is found or the toolchain or
+/// sandbox is not installed. Blocking; call from `spawn_blocking`.
+pub fn run_tests(repo_path: &Path, timeout: Duration) -> Option<TestRunResult> {
+    let framework = TestFramework::detect(repo_path)?;
+    let (program, _) = framework.program_and_args();
+    if !on_path(program) {
+        tracing::info!("Skipping `{}`: toolchain not installed", framework.command());
+        return None;
+    }
+    match run_command(repo_path, framework, timeout) {
+        Ok(result) => Some(result),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            tracing::warn!("Skipping `{}`: {} not installed", framework.command(), SANDBOX_PROGRAM);
+            None
+        }
+        Err(e) => {
+            tracing::warn!("Failed to run `{}`: {}", framework.command(), e);
+            None
+        }
+    }
+}
+
+fn run_command(
+    repo_path: &Path,
+    framework: TestFramework,
+    timeout: Duration,
+) -> io::Result<TestRunResult> {
+    let mut log = tempfile::tempfile()?;
+
+    let mut command = Command::new(SANDBOX_PROGRAM);
+    command
+        .args(sandbox_args(repo_path, framework))
+        .env_clear()
+        .env("CI", "true")
+        .env("HOME", SANDBOX_HOME)
+        // Its own process group, so a timeout kills everything it started
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(log.try_clone()?))
+        .stderr(Stdio::from(log.try_clone()?));
+    for key in PASSTHROUGH_ENV {
+        if let Some(value) = std::env::var_os(key) {
+            command.env(key, value);
+        }
+    }
+
+    let start = Instant::now();
+    let mut child = command.spawn()?;
+    let mut output_limited = false;
+    let (status, timed_out) = loop {
+        if let Some(status) = child.try_wait()? {
+            break (Some(status), false);
+        }
+        output_limited = log.metadata()?.len() > MAX_OUTPUT_BYTES;
+        if output_limited || start.elapsed() >= timeout {
+            kill_process_group(child.id());
+            let _ = child.wait();
+            break (None, !output_limited);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+
+    let mut output = read_tail(&mut log, OUTPUT_READ_BYTES)?;
+    if output_limited {
+        output.push_str(&format!("\n[stopped after {} bytes of output]\n", MAX_OUTPUT_BYTES));
+    }
+    let (passed, failed) = parse_counts(framework, &output);
+
+    Ok(TestRunResult {
+        framework,
+        command: framework.command(),
+        success: status.is_some_and(|s| s.success()),
+        timed_out,
+        passed,
+        failed,
+        duration_ms: start.elapsed().as_millis() as u64,
+        output: tail(&output, OUTPUT_TAIL_CHARS),
+    })
+}
+
+/// bubblewrap arguments running `framework`'s command in `repo_path`: every
+/// namespace unshared (so no network), the system directories read-only,
+/// fresh `/tmp`, `/dev` and `/proc`, and only the repository writable.
+/// Everything in the sandbox dies with it.
+fn sandbox_args(repo_path: &Path, framework: TestFramework) -> Vec<OsString> {
+    let mut args: Vec<OsString> = ["--unshare-all", "--die-with-parent", "--new-session"]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+    for dir in SANDBOX_READ_ONLY_DIRS {
+        args.extend(["--ro-bind-try", dir, dir].map(OsString::from));
+    }
+    args.extend(
+        ["--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp", "--dir", SANDBOX_HOME]
+            .map(OsString::from),
+    );
+    let repo = repo_path.as_os_str();
+    args.extend([
+        "--bind".into(),
+        repo.to_os_string(),
+        repo.to_os_string(),
+        "--chdir".into(),
+        repo.to_os_string(),
+        "--".into(),
+    ]);
+
+    let (program, program_args) = framework.program_and_args();
+    args.push(program.into());
+    args.extend(program_args.iter().map(OsString::from));
+    args
+}
+
+/// Whether `program` is a file in one of the `PATH` directories
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| dir.join(program).is_file())
+    })
+}
+
+/// SIGKILL the process group led by `pid`
+fn kill_process_group(pid: u32) {
+    // SAFETY: kill(2) only takes plain integers; a group that has already
+    // exited just returns ESRCH
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+/// The last `max_bytes` of `file`, without reading the rest into memory
+fn read_tail(file: &mut File, max_bytes: u64) -> io::Result<String> {
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_bytes)))?;
+    let mut bytes = Vec::new();
+    file.take(max_bytes).read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn tail(output: &str, max_chars: usize) -> String {
+    let count = output.chars().count();
+    if count <= max_chars {
+        return output.to_string();
+    }
+    let skip = output.char_indices().nth(count - max_chars).map_or(0, |(i, _)| i);
+    format!("[... {} chars omitted]\n{}", count - max_chars, &output[skip..])
+}
+
+fn capture_count(regex: &Regex, line: &str) -> Option<u32> {
+    regex.captures(line)?.get(1)?.as_str().parse().ok()
+}
+
+/// Pass/fail counts from the runner's summary lines
+fn parse_counts(framework: TestFramework, output: &str) -> (Option<u32>, Option<u32>) {
+    let summary_lines: Vec<&str> = match framework {
+        // One "test result:" line per test binary
+        TestFramework::Cargo => output
+            .lines()
+            .filter(|l| l.starts_with("test result:"))
+            .collect(),
+        // Final "=== 3 passed, 1 failed in 0.12s ===" banner
+        TestFramework::Pytest => output
+            .lines()
+            .rev()
+            .find(|l| l.starts_with('=') && (PASSED.is_match(l) || FAILED.is_match(l)))
+            .into_iter()
+            .collect(),
+        // Jest/Vitest "Tests: ..." line, otherwise mocha "N passing" lines
+        TestFramework::Npm => {
+            let tests_line = output
+                .lines()
+                .map(str::trim)
+                .find(|l| l.starts_with("Tests:") || l.starts_with("Tests "));
+            match tests_line {
+                Some(line) => vec![line],
+                None => output.lines().map(str::trim).collect(),
+            }
+        }
+    };
+
+    let sum = |regex: &Regex| {
+        summary_lines
+            .iter()
+            .filter_map(|line| capture_count(regex, line))
+            .reduce(|a, b| a + b)
+    };
+    (sum(&PASSED), sum(&FAILED))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_framework() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(TestFramework::detect(dir.path()), None);
+
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"scripts": {"test": "echo \"Error: no test specified\" && exit 1"}}"#,
+        )
+        .unwrap();
+        assert_eq!(TestFramework::detect(dir.path()), None);
+
+        fs::create_dir(dir.path().join("tests")).unwrap();
+        fs::write(dir.path().join("tests/test_app.py"), "def test_ok(): pass\n").unwrap();
+        assert_eq!(TestFramework::detect(dir.path()), Some(TestFramework::Pytest));
+
+        fs::write(dir.path().join("package.json"), r#"{"scripts": {"test": "jest"}}"#).unwrap();
+        assert_eq!(TestFramework::detect(dir.path()), Some(TestFramework::Npm));
+    }
+
+    #[test]
+    fn test_parse_counts() {
+        let cargo = "running 3 tests\ntest result: ok. 3 passed; 0 failed; 0 ignored\n\
+                     test result: FAILED. 1 passed; 2 failed; 0 ignored\n";
+        assert_eq!(parse_counts(TestFramework::Cargo, cargo), (Some(4), Some(2)));
+
+        let pytest = "tests/test_app.py ..F\n===== 2 passed, 1 failed in 0.12s =====\n";
+        assert_eq!(parse_counts(TestFramework::Pytest, pytest), (Some(2), Some(1)));
+
+        let jest = "Test Suites: 1 failed, 1 passed, 2 total\nTests:       1 failed, 5 passed, 6 total\n";
+        assert_eq!(parse_counts(TestFramework::Npm, jest), (Some(5), Some(1)));
+
+        let mocha = "  3 passing (12ms)\n  1 failing\n";
+        assert_eq!(parse_counts(TestFramework::Npm, mocha), (Some(3), Some(1)));
+    }
+
+    #[test]
+    fn test_summary_and_tail() {
+        let result = TestRunResult {
+            framework: TestFramework::Pytest,
+            command: TestFramework::Pytest.command(),
+            success: false,
+            timed_out: false,
+            passed: Some(2),
+            failed: Some(1),
+            duration_ms: 10,
+            output: String::new(),
+        };
+        assert_eq!(result.summary(), "`python3 -m pytest`: 2 passed, 1 failed");
+        assert_eq!(tail("abcdef", 3), "[... 3 chars omitted]\ndef");
+    }
+
+    #[test]
+    fn test_read_tail() {
+        let mut file = tempfile::tempfile().unwrap();
+        io::Write::write_all(&mut file, b"first line\nlast line\n").unwrap();
+        assert_eq!(read_tail(&mut file, 10).unwrap(), "last line\n");
+        assert_eq!(read_tail(&mut file, 1000).unwrap(), "first line\nlast line\n");
+    }
+
+    #[test]
+    fn test_sandbox_args_isolate_repo() {
+        let args = sandbox_args(Path::new("/tmp/repo"), TestFramework::Npm);
+        let args: Vec<&str> = args.iter().map(|a| a.to_str().unwrap()).collect();
+
+        assert!(args.contains(&"--unshare-all"));
+        assert!(args.windows(3).any(|w| w == ["--bind", "/tmp/repo", "/tmp/repo"]));
+        assert!(!args.iter().any(|a| *a == "/home" || *a == "/root"));
+        assert_eq!(&args[args.len() - 3..], ["--", "npm", "test"]);
+    }
+
+    #[test]
+    fn test_runner_config_caps_timeout() {
+        let config = TestRunnerConfig::default();
+        assert!(!config.enabled);
+        assert_eq!(config.timeout(30), Duration::from_secs(30));
+        assert_eq!(config.timeout(u64::MAX), Duration::from_secs(DEFAULT_MAX_TEST_TIMEOUT_SECS));
+    }
+}
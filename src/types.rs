@@ -1,4 +1,8 @@
+use crate::files::DEFAULT_MIN_SOURCE_LINES;
+use crate::profile::{Language, RepoProfile};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -7,6 +11,21 @@ pub enum ReviewEvent {
         review_id: String,
         repo_url: String,
     },
+    /// Waiting for a worker; re-sent whenever the position changes
+    Queued {
+        position: usize,
+    },
+    /// Repository cloned and its languages detected
+    AnalysisCompleted {
+        profile: RepoProfile,
+    },
+    /// A source file was read for checkers and reviewers; `file_index` is
+    /// 0-based
+    FileProcessed {
+        path: String,
+        file_index: usize,
+        total_files: usize,
+    },
     CheckStarted {
         check_type: CheckType,
     },
@@ -19,6 +38,13 @@ pub enum ReviewEvent {
         check_type: CheckType,
         error: String,
     },
+    /// An AI validator removed some or all of a check's diagnostics
+    CheckFiltered {
+        check_type: CheckType,
+        original_count: usize,
+        removed_count: usize,
+        validator: String,
+    },
     ValidationStarted {
         validator: String,
     },
@@ -33,12 +59,20 @@ pub enum ReviewEvent {
         reviewer: String,
         suggestions: Vec<Suggestion>,
     },
+    /// With `refreshing`, cached results were served and a fresh review is
+    /// running; its own `ReviewCompleted` follows
     ReviewCompleted {
         summary: ReviewSummary,
+        #[serde(default)]
+        refreshing: bool,
     },
     ReviewFailed {
         error: String,
     },
+    /// Subscriber fell behind and missed `count` events; refetch state via GET
+    EventsDropped {
+        count: u64,
+    },
     Ping,
 }
 
@@ -47,6 +81,12 @@ pub struct ReviewSummary {
     pub total_diagnostics: usize,
     pub by_severity: SeverityCounts,
     pub duration_ms: u64,
+    /// Generated, minified or vendored files left out of analysis
+    #[serde(default)]
+    pub skipped_files: usize,
+    /// Source files per detected language, most common first
+    #[serde(default)]
+    pub languages: Vec<(Language, usize)>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +94,9 @@ pub struct SeverityCounts {
     pub error: usize,
     pub warning: usize,
     pub info: usize,
+    /// Checker diagnostics removed by AI validators
+    #[serde(default)]
+    pub filtered: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,9 +110,20 @@ pub struct Suggestion {
     pub line: Option<u32>,
     pub priority: Priority,
     pub rationale: String,
+    /// Reviewer's confidence that the suggestion is worth acting on, 0.0 to 1.0
+    #[serde(default = "default_suggestion_confidence")]
+    pub confidence: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Confidence of suggestions whose reviewer didn't state one
+pub const NEUTRAL_SUGGESTION_CONFIDENCE: f32 = 0.5;
+
+fn default_suggestion_confidence() -> f32 {
+    NEUTRAL_SUGGESTION_CONFIDENCE
+}
+
+/// Ordered as declared, which is the order suggestions are listed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SuggestionCategory {
     Architecture,
@@ -78,9 +132,11 @@ pub enum SuggestionCategory {
     CodeQuality,
     ProductIdea,
     Hardening,
+    TestCoverage,
+    Accessibility,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Priority {
     High,
@@ -92,6 +148,8 @@ pub enum Priority {
 #[serde(rename_all = "snake_case")]
 pub enum ReviewStatus {
     Pending,
+    /// Waiting for a free worker
+    Queued,
     Cloning,
     Running,
     Completed,
@@ -118,27 +176,144 @@ pub enum Severity {
     Info,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+
+    /// Sort rank, most severe first
+    pub fn rank(self) -> u8 {
+        match self {
+            Severity::Error => 0,
+            Severity::Warning => 1,
+            Severity::Info => 2,
+        }
+    }
+}
+
+/// Sort diagnostics by severity (errors first), then file, line and column
+pub fn sort_diagnostics(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by(|a, b| {
+        a.severity
+            .rank()
+            .cmp(&b.severity.rank())
+            .then_with(|| a.file.cmp(&b.file))
+            .then_with(|| a.line.cmp(&b.line))
+            .then_with(|| a.column.cmp(&b.column))
+    });
+}
+
+/// Sort suggestions by priority (high first), then category, and keep the
+/// first `max`. Ties keep their reviewer order, so the result is stable.
+pub fn rank_suggestions(suggestions: &mut Vec<Suggestion>, max: usize) {
+    suggestions.sort_by_key(|s| (s.priority, s.category));
+    suggestions.truncate(max);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CheckType {
     Lint,
     Comments,
     Typos,
     Format,
+    GitHooks,
+    PythonTypes,
+    SqlInjection,
     AiCode,
     AiProduct,
 }
 
+impl CheckType {
+    /// Name as serialized, e.g. `git_hooks`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckType::Lint => "lint",
+            CheckType::Comments => "comments",
+            CheckType::Typos => "typos",
+            CheckType::Format => "format",
+            CheckType::GitHooks => "git_hooks",
+            CheckType::PythonTypes => "python_types",
+            CheckType::SqlInjection => "sql_injection",
+            CheckType::AiCode => "ai_code",
+            CheckType::AiProduct => "ai_product",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewRequest {
     pub repo_url: String,
     #[serde(default)]
     pub branch: Option<String>,
+    /// Override the number of files shown to AI reviewers
+    #[serde(default)]
+    pub preview_files: Option<usize>,
+    /// Override the per-file character budget shown to AI reviewers
+    #[serde(default)]
+    pub preview_chars: Option<usize>,
+    /// Wall-clock limit for the review (seconds); reviewers not reached by
+    /// then are skipped
+    #[serde(default)]
+    pub max_total_duration_secs: Option<u64>,
+    /// How a cached review of the same commit is used
+    #[serde(default)]
+    pub cache_mode: ReviewCacheMode,
+    /// Source lines the repository needs to be reviewed (default 5)
+    #[serde(default)]
+    pub min_source_lines: Option<usize>,
+}
+
+/// How a review uses the review cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewCacheMode {
+    /// Serve a cached result when there is one
+    #[default]
+    Use,
+    /// Always run a fresh review
+    Bypass,
+    /// Serve a cached result right away, then run a fresh review that
+    /// replaces it
+    Refresh,
+}
+
+impl ReviewCacheMode {
+    /// Whether a cache entry saved with `cached_version` may be served.
+    /// Entries from other prompt versions never are.
+    pub fn serves(self, cached_version: Option<&str>, current_version: &str) -> bool {
+        self != Self::Bypass && cached_version == Some(current_version)
+    }
+}
+
+/// Query for looking up the latest review of a repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewByRepoQuery {
+    pub url: String,
+}
+
+/// Query for comparing a review with a later one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewCompareQuery {
+    pub id_a: String,
+    pub id_b: String,
+}
+
+/// Query for a repository's review history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewHistoryQuery {
+    pub repo_url: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateReviewResponse {
     pub review_id: String,
+    /// 1-based position in the job queue
+    pub queue_position: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -150,6 +325,44 @@ pub struct ReviewResponse {
     pub suggestions: Vec<Suggestion>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    /// Results were served from the review cache
+    #[serde(default)]
+    pub cached: bool,
+    /// A fresh review is running to replace the cached results
+    #[serde(default)]
+    pub refreshing: bool,
+    /// Source files per detected language, once the repository is analyzed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<(Language, usize)>,
+}
+
+/// A checker and the languages it runs for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckerCapability {
+    pub check_type: CheckType,
+    /// Empty when it runs on every repository
+    pub languages: Vec<Language>,
+}
+
+/// Response of `GET /api/capabilities`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilitiesResponse {
+    /// Languages recognized by detection
+    pub languages: Vec<Language>,
+    /// Checkers that can run; for a review, only those that applied to it
+    pub checkers: Vec<CheckerCapability>,
+    /// The review's detected languages, when `review_id` was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<RepoProfile>,
+}
+
+/// Query of `GET /api/capabilities`
+#[derive(Debug, Clone, Deserialize)]
+pub struct CapabilitiesQuery {
+    #[serde(default)]
+    pub review_id: Option<String>,
 }
 
 // ============================================================================
@@ -172,6 +385,29 @@ pub struct Criterion {
     /// Optional weight for weighted scoring (default: 1.0)
     #[serde(default = "default_weight")]
     pub weight: f32,
+    /// Snippets of code that would satisfy the criterion, shown to the grader
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<String>,
+    /// Route the check to the cheap or strong configured model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_hint: Option<ModelHint>,
+    /// Rubric category, e.g. "Correctness" or "Tests", for the report's
+    /// `rubric_breakdown`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rubric_category: Option<String>,
+}
+
+/// Category for criteria without a `rubric_category`
+pub const DEFAULT_RUBRIC_CATEGORY: &str = "General";
+
+/// Which configured model checks a criterion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelHint {
+    /// `LLM_CHEAP_MODEL`, for simple criteria like "README exists"
+    Cheap,
+    /// `LLM_STRONG_MODEL`, for criteria that need careful reasoning
+    Strong,
 }
 
 /// Task from planner containing acceptance criteria
@@ -188,6 +424,9 @@ pub struct GradeTask {
     /// Expected time in minutes (for reference only)
     #[serde(default)]
     pub estimated_minutes: Option<u32>,
+    /// Run the repository's test suite and show the results to the grader
+    #[serde(default)]
+    pub run_tests: bool,
 }
 
 /// Grading configuration (can be passed in request or use defaults)
@@ -208,6 +447,73 @@ pub struct GradeConfig {
     /// Max chars per file
     #[serde(default = "default_max_chars_per_file")]
     pub max_chars_per_file: usize,
+    /// Max chars of code across all files; files past the cap are left out
+    #[serde(default = "default_max_total_chars")]
+    pub max_total_chars: usize,
+    /// LLM failures tolerated per grade before remaining criteria are aborted
+    #[serde(default = "default_max_llm_failures")]
+    pub max_llm_failures: usize,
+    /// Minimum SSE event buffer size (scaled up with the criteria count)
+    #[serde(default = "default_event_buffer_size")]
+    pub event_buffer_size: usize,
+    /// Approximate input price (USD per 1K tokens) used for dry-run cost estimates
+    #[serde(default)]
+    pub cost_per_1k_tokens_usd: Option<f32>,
+    /// Enable Anthropic extended thinking for criterion checks
+    #[serde(default)]
+    pub use_extended_thinking: bool,
+    /// Percentage of the overall score deducted for late submissions
+    #[serde(default)]
+    pub late_penalty_percentage: Option<u32>,
+    /// Bytes read from each source file
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: usize,
+    /// Bytes of source held in memory for the whole grade
+    #[serde(default = "default_max_context_bytes")]
+    pub max_context_bytes: usize,
+    /// Timeout for running the repository's tests (seconds)
+    #[serde(default = "default_test_timeout")]
+    pub test_timeout_secs: u64,
+    /// How criteria the grader can't verify from code are scored
+    #[serde(default)]
+    pub unverifiable_policy: UnverifiablePolicy,
+    /// How much evidence the grader writes per criterion
+    #[serde(default)]
+    pub evidence_mode: EvidenceMode,
+    /// Wall-clock limit for the whole grade (seconds); criteria not reached
+    /// by then are skipped and the grade completes as partial
+    #[serde(default = "default_max_total_duration")]
+    pub max_total_duration_secs: u64,
+    /// Glob patterns for files loaded ahead of the rest, e.g. `src/main.*`
+    #[serde(default)]
+    pub file_priority_patterns: Vec<String>,
+    /// Lines outside markdown and plain text a repository needs to be
+    /// graded; 0 accepts any repository with files
+    #[serde(default = "default_min_source_lines")]
+    pub min_source_lines: usize,
+    /// How a repository below `min_source_lines` is graded
+    #[serde(default)]
+    pub no_source_policy: NoSourcePolicy,
+    /// Store a fingerprint of the graded files with the job, for
+    /// `GET /api/grades/similarity`
+    #[serde(default)]
+    pub similarity_fingerprint: bool,
+    /// Run the rule-based checkers during analysis and show their findings
+    /// to the grader
+    #[serde(default)]
+    pub run_checkers: bool,
+    /// Replace email addresses, `student_id_pattern` matches and the
+    /// metadata student id in the submission with `[REDACTED]` before it is
+    /// sent to the LLM
+    #[serde(default)]
+    pub redact_pii: bool,
+    /// Regex for student ids redacted with `redact_pii`, e.g. `\b20\d{6}\b`
+    #[serde(default)]
+    pub student_id_pattern: Option<String>,
+    /// Commits of history to clone, 0 for all; by default only HEAD, or
+    /// the full history when the request has a `base_branch`
+    #[serde(default)]
+    pub clone_depth: Option<u32>,
 }
 
 fn default_max_parallel_tasks() -> usize {
@@ -225,6 +531,30 @@ fn default_max_files() -> usize {
 fn default_max_chars_per_file() -> usize {
     5000
 }
+fn default_max_total_chars() -> usize {
+    80_000
+}
+fn default_max_llm_failures() -> usize {
+    5
+}
+fn default_event_buffer_size() -> usize {
+    100
+}
+fn default_test_timeout() -> u64 {
+    300
+}
+fn default_max_file_bytes() -> usize {
+    256 * 1024
+}
+fn default_max_context_bytes() -> usize {
+    4 * 1024 * 1024
+}
+fn default_max_total_duration() -> u64 {
+    900
+}
+fn default_min_source_lines() -> usize {
+    DEFAULT_MIN_SOURCE_LINES
+}
 
 impl Default for GradeConfig {
     fn default() -> Self {
@@ -234,6 +564,64 @@ impl Default for GradeConfig {
             criterion_timeout_secs: 60,
             max_files: 30,
             max_chars_per_file: 5000,
+            max_total_chars: default_max_total_chars(),
+            max_llm_failures: 5,
+            event_buffer_size: 100,
+            cost_per_1k_tokens_usd: None,
+            use_extended_thinking: false,
+            late_penalty_percentage: None,
+            max_file_bytes: default_max_file_bytes(),
+            max_context_bytes: default_max_context_bytes(),
+            test_timeout_secs: default_test_timeout(),
+            unverifiable_policy: UnverifiablePolicy::default(),
+            evidence_mode: EvidenceMode::default(),
+            max_total_duration_secs: default_max_total_duration(),
+            file_priority_patterns: Vec::new(),
+            min_source_lines: default_min_source_lines(),
+            no_source_policy: NoSourcePolicy::default(),
+            similarity_fingerprint: false,
+            run_checkers: false,
+            redact_pii: false,
+            student_id_pattern: None,
+            clone_depth: None,
+        }
+    }
+}
+
+/// The `GradeConfig` fields a request sets; the rest come from the server's
+/// defaults, so a request setting only `max_files` keeps every other default
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct GradeConfigOverrides(serde_json::Map<String, serde_json::Value>);
+
+impl GradeConfigOverrides {
+    /// `defaults` with the fields set here replaced
+    pub fn apply(&self, defaults: &GradeConfig) -> GradeConfig {
+        let mut merged = match serde_json::to_value(defaults) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => serde_json::Map::new(),
+        };
+        merged.extend(self.0.clone());
+        // The overrides were checked to form a valid config when parsed
+        serde_json::from_value(serde_json::Value::Object(merged)).unwrap_or_else(|_| defaults.clone())
+    }
+}
+
+impl<'de> Deserialize<'de> for GradeConfigOverrides {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = serde_json::Map::deserialize(deserializer)?;
+        GradeConfig::deserialize(serde_json::Value::Object(fields.clone()))
+            .map_err(serde::de::Error::custom)?;
+        Ok(Self(fields))
+    }
+}
+
+/// Every field set, e.g. to pin a full config
+impl From<GradeConfig> for GradeConfigOverrides {
+    fn from(config: GradeConfig) -> Self {
+        match serde_json::to_value(config) {
+            Ok(serde_json::Value::Object(fields)) => Self(fields),
+            _ => Self::default(),
         }
     }
 }
@@ -250,6 +638,25 @@ pub struct GradeMetadata {
     /// Student identifier
     #[serde(default)]
     pub student_id: Option<String>,
+    /// Assignment due date (RFC 3339)
+    #[serde(default)]
+    pub deadline: Option<DateTime<Utc>>,
+    /// When the student submitted (RFC 3339)
+    #[serde(default)]
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+impl GradeMetadata {
+    /// Minutes past the deadline, rounded up; `None` when on time or when
+    /// either timestamp is missing
+    pub fn minutes_late(&self) -> Option<i64> {
+        let (deadline, submitted_at) = (self.deadline?, self.submitted_at?);
+        if submitted_at <= deadline {
+            return None;
+        }
+        let seconds = (submitted_at - deadline).num_seconds();
+        Some((seconds + 59) / 60)
+    }
 }
 
 /// Grading request from external system (planner)
@@ -262,9 +669,9 @@ pub struct GradeRequest {
     pub branch: Option<String>,
     /// Tasks with acceptance criteria
     pub tasks: Vec<GradeTask>,
-    /// Optional grading configuration
+    /// Grading configuration fields overriding the server's defaults
     #[serde(default)]
-    pub config: Option<GradeConfig>,
+    pub config: Option<GradeConfigOverrides>,
     /// Optional metadata
     #[serde(default)]
     pub metadata: Option<GradeMetadata>,
@@ -274,6 +681,43 @@ pub struct GradeRequest {
     /// Optional task ID for linked grading
     #[serde(default)]
     pub task_id: Option<String>,
+    /// BCP-47 language tag for grader evidence (default: "ko")
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Exact model to grade with, so a cohort is graded consistently
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Branch the assignment started from; when set, only files the student
+    /// added to or changed since it are graded
+    #[serde(default)]
+    pub base_branch: Option<String>,
+    /// Assignment materials such as the spec or starter template, as
+    /// `(path, content)`, shown to graders alongside the submission
+    #[serde(default)]
+    pub context_files: Vec<(String, String)>,
+}
+
+impl GradeRequest {
+    /// The request's config over `defaults`
+    pub fn config_or(&self, defaults: &GradeConfig) -> GradeConfig {
+        match &self.config {
+            Some(overrides) => overrides.apply(defaults),
+            None => defaults.clone(),
+        }
+    }
+}
+
+/// Several grade requests created together, e.g. a whole class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchGradeRequest {
+    pub requests: Vec<GradeRequest>,
+}
+
+/// Re-grade of a persisted grade job with corrected tasks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegradeRequest {
+    /// Replacement tasks; everything else is taken from the original request
+    pub tasks: Vec<GradeTask>,
 }
 
 // ----------------------------------------------------------------------------
@@ -288,15 +732,82 @@ pub struct CodeRef {
     pub line_end: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub snippet: Option<String>,
+    /// Whether the file was found among the graded files (lines clamped to it)
+    #[serde(default)]
+    pub verified: bool,
+}
+
+/// Outcome of checking a criterion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CriterionOutcome {
+    Passed,
+    Failed,
+    /// Can't be checked from source code, e.g. runtime performance of a
+    /// deployed app
+    NotVerifiable,
+}
+
+impl CriterionOutcome {
+    /// Mapping for results that only carry a `passed` flag
+    pub fn from_passed(passed: bool) -> Self {
+        if passed {
+            Self::Passed
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+/// How not-verifiable criteria count toward the task score
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnverifiablePolicy {
+    CountAsFailed,
+    /// Leave them out of the weighted score
+    #[default]
+    ExcludeFromScore,
+    CountAsPassed,
+}
+
+/// What a grade does when the repository has no gradeable source code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoSourcePolicy {
+    /// Fail the grade without checking criteria
+    #[default]
+    Fail,
+    /// Complete at 0% with every criterion failed and a `reason`
+    ZeroGrade,
+}
+
+/// Evidence the grader is asked to write for each criterion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvidenceMode {
+    /// No evidence; results carry only the outcome, confidence and references
+    None,
+    /// One short sentence
+    Brief,
+    #[default]
+    Detailed,
 }
 
 /// Result of checking a single criterion
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "CriterionResultRepr")]
 pub struct CriterionResult {
     /// Original criterion description
     pub criterion: String,
+    /// `Criterion.id` from the request, for mapping results back reliably
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub criterion_id: Option<String>,
+    /// Index of the owning task in the request
+    #[serde(default)]
+    pub task_index: usize,
     /// Whether the criterion is satisfied
     pub passed: bool,
+    pub outcome: CriterionOutcome,
     /// Confidence score (0.0 - 1.0)
     pub confidence: f32,
     /// LLM's reasoning/evidence
@@ -306,6 +817,78 @@ pub struct CriterionResult {
     pub code_references: Vec<CodeRef>,
     /// Weight used for scoring
     pub weight: f32,
+    /// Instructor verdict that replaces the grader's when scoring
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub human_override: Option<CriterionOverride>,
+}
+
+impl CriterionResult {
+    /// Outcome used for scoring: the instructor's verdict when overridden
+    pub fn effective_outcome(&self) -> CriterionOutcome {
+        match &self.human_override {
+            Some(verdict) => CriterionOutcome::from_passed(verdict.passed),
+            None => self.outcome,
+        }
+    }
+}
+
+/// Instructor verdict on a criterion, stored next to the grader's result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CriterionOverride {
+    pub passed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Unix timestamp the override was recorded
+    pub overridden_at: u64,
+}
+
+/// Body of `POST /api/grade/{id}/criteria/{task_index}/{criterion_index}/override`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CriterionOverrideRequest {
+    pub passed: bool,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Stored results from before `outcome` only have `passed`
+#[derive(Deserialize)]
+struct CriterionResultRepr {
+    criterion: String,
+    #[serde(default)]
+    criterion_id: Option<String>,
+    #[serde(default)]
+    task_index: usize,
+    #[serde(default)]
+    passed: bool,
+    #[serde(default)]
+    outcome: Option<CriterionOutcome>,
+    confidence: f32,
+    evidence: String,
+    #[serde(default)]
+    code_references: Vec<CodeRef>,
+    weight: f32,
+    #[serde(default)]
+    human_override: Option<CriterionOverride>,
+}
+
+impl From<CriterionResultRepr> for CriterionResult {
+    fn from(repr: CriterionResultRepr) -> Self {
+        let outcome = repr
+            .outcome
+            .unwrap_or_else(|| CriterionOutcome::from_passed(repr.passed));
+        Self {
+            criterion: repr.criterion,
+            criterion_id: repr.criterion_id,
+            task_index: repr.task_index,
+            passed: outcome == CriterionOutcome::Passed,
+            outcome,
+            confidence: repr.confidence,
+            evidence: repr.evidence,
+            code_references: repr.code_references,
+            weight: repr.weight,
+            human_override: repr.human_override,
+        }
+    }
 }
 
 /// Task grading status
@@ -320,6 +903,17 @@ pub enum TaskStatus {
     Failed,
 }
 
+/// Score of the criteria in one rubric category, across all tasks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RubricCategory {
+    pub category: String,
+    /// Weighted score (0.0 - 1.0), before any late penalty
+    pub score: f32,
+    pub percentage: u32,
+    pub criteria_count: usize,
+    pub passed_count: usize,
+}
+
 /// Result of grading a single task
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskGradeResult {
@@ -339,6 +933,8 @@ pub struct TaskGradeResult {
 #[serde(rename_all = "snake_case")]
 pub enum GradeStatus {
     Pending,
+    /// Waiting for a free worker
+    Queued,
     Cloning,
     Analyzing,
     Grading,
@@ -359,12 +955,55 @@ pub struct GradeReport {
     /// Human-readable grade (우수/양호/보통/미흡/불합격)
     pub grade: String,
     pub tasks: Vec<TaskGradeResult>,
+    /// Scores per `Criterion::rubric_category`
+    #[serde(default)]
+    pub rubric_breakdown: BTreeMap<String, RubricCategory>,
     pub summary: String,
     pub duration_ms: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<GradeMetadata>,
+    /// BCP-47 language tag the evidence was written in
+    pub language: String,
+    /// Submitted after the deadline in `metadata`
+    #[serde(default)]
+    pub is_late: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minutes_late: Option<i64>,
+    /// Commit that was graded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
+    /// Time budget ran out before every criterion was checked
+    #[serde(default)]
+    pub partial: bool,
+    /// Requested branch, or the repository's default branch once cloned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Grade this one re-grades, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regrade_of: Option<String>,
+    /// Model the criteria were checked with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 1-based position while waiting in the job queue
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    /// Unix timestamp the grade was created
+    #[serde(default)]
+    pub created_at: u64,
+    /// Unix timestamp criterion checks began
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_grading_at: Option<u64>,
+    /// Unix timestamp the grade completed; unset unless `completed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<u64>,
+    /// Whether the final report was saved to MongoDB
+    #[serde(default)]
+    pub persisted: bool,
+    /// Batch the grade was created in, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
 }
 
 // ----------------------------------------------------------------------------
@@ -381,17 +1020,39 @@ pub enum GradeEvent {
         task_count: usize,
         total_criteria: usize,
     },
+    /// Waiting for a worker; re-sent whenever the position changes
+    Queued { position: usize },
     /// Repository cloning started
     CloningStarted,
     /// Repository cloning completed
     CloningCompleted { duration_ms: u64 },
     /// Code analysis started
     AnalysisStarted,
+    /// A source file was loaded for grading; `file_index` is 0-based
+    FileLoaded {
+        path: String,
+        file_index: usize,
+        total_files: usize,
+    },
+    /// A rule-based checker started, with `run_checkers`
+    CheckStarted { check_type: CheckType },
+    /// A rule-based checker finished; its findings go to the grader prompt
+    CheckCompleted {
+        check_type: CheckType,
+        diagnostics_count: usize,
+        duration_ms: u64,
+    },
+    CheckFailed { check_type: CheckType, error: String },
     /// Code analysis completed
     AnalysisCompleted {
         file_count: usize,
         total_lines: usize,
+        /// Generated, minified or vendored files left out of analysis
+        skipped_files: usize,
+        profile: RepoProfile,
     },
+    /// Submission arrived after the deadline
+    LateSubmission { minutes_late: i64 },
     /// Task grading started
     TaskStarted {
         task_index: usize,
@@ -403,7 +1064,10 @@ pub enum GradeEvent {
         task_index: usize,
         criterion_index: usize,
         criterion: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        criterion_id: Option<String>,
         passed: bool,
+        outcome: CriterionOutcome,
         confidence: f32,
     },
     /// Task grading completed
@@ -422,13 +1086,101 @@ pub enum GradeEvent {
         grade: String,
         summary: String,
         duration_ms: u64,
+        /// Some criteria were skipped when the time budget ran out
+        #[serde(default)]
+        partial: bool,
+        /// Why the grade was decided without checking criteria
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Scores recomputed after an instructor overrode a criterion
+    GradeRescored {
+        task_index: usize,
+        criterion_index: usize,
+        overall_score: f32,
+        percentage: u32,
+        grade: String,
+        summary: String,
     },
     /// Grading failed
     GradeFailed { error: String, recoverable: bool },
+    /// Subscriber fell behind and missed `count` events; refetch state via GET
+    EventsDropped { count: u64 },
     /// Keep-alive ping
     Ping,
 }
 
+impl ReviewEvent {
+    /// Whether the review run ends with this event
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::ReviewCompleted { refreshing: false, .. } | Self::ReviewFailed { .. }
+        )
+    }
+}
+
+impl GradeEvent {
+    /// Whether no further events follow
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::GradeCompleted { .. } | Self::GradeFailed { .. })
+    }
+}
+
+/// An event as streamed and logged: the event's own fields plus its
+/// position in the job's event log and when it was emitted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventEnvelope<E> {
+    /// 1-based position in the job's event log; 0 for synthetic events
+    /// (keep-alives, dropped-event notices) that are not logged
+    #[serde(default)]
+    pub seq: u64,
+    /// Unix millis when the event was emitted
+    #[serde(default)]
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: E,
+}
+
+impl<E> EventEnvelope<E> {
+    pub fn new(seq: u64, timestamp_ms: u64, event: E) -> Self {
+        Self {
+            seq,
+            timestamp_ms,
+            event,
+        }
+    }
+}
+
+/// Number events logged without a `seq` after the event before them, so a
+/// log persisted before sequence numbers existed reads in order
+pub fn backfill_seq<E>(events: &mut [EventEnvelope<E>]) {
+    let mut previous = 0;
+    for envelope in events {
+        if envelope.seq <= previous {
+            envelope.seq = previous + 1;
+        }
+        previous = envelope.seq;
+    }
+}
+
+/// Query for streaming several grades at once: comma-separated grade ids
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GradeStreamQuery {
+    pub ids: String,
+}
+
+/// One grade's event in a merged stream. Unknown grades get a single
+/// envelope with `error` instead of `event`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GradeStreamEnvelope {
+    pub grade_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<EventEnvelope<GradeEvent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 // ----------------------------------------------------------------------------
 // API Responses
 // ----------------------------------------------------------------------------
@@ -437,6 +1189,69 @@ pub enum GradeEvent {
 pub struct CreateGradeResponse {
     pub grade_id: String,
     pub status: GradeStatus,
+    /// 1-based position in the job queue
+    pub queue_position: usize,
+    /// Average duration of past grades with as many tasks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_duration_ms: Option<u64>,
+}
+
+/// Dry-run request: a grade request plus optional clone skipping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunRequest {
+    #[serde(flatten)]
+    pub grade: GradeRequest,
+    /// Skip cloning and estimate from the provided file count/size instead
+    #[serde(default)]
+    pub skip_clone: bool,
+    /// Number of source files in the repo (used with `skip_clone`)
+    #[serde(default)]
+    pub estimated_file_count: Option<usize>,
+    /// Average source file size in chars (used with `skip_clone`)
+    #[serde(default)]
+    pub estimated_file_chars: Option<usize>,
+}
+
+/// Result of validating a grade request without running it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunResult {
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// One LLM call per acceptance criterion
+    pub estimated_llm_calls: usize,
+    /// Only present when `cost_per_1k_tokens_usd` is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost_usd: Option<f32>,
+    /// Total prompt chars across all LLM calls
+    #[serde(default)]
+    pub estimated_prompt_chars: usize,
+    /// Approximate prompt tokens across all LLM calls (chars/4)
+    #[serde(default)]
+    pub estimated_prompt_tokens: usize,
+    /// Files that would be included in the grading context
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<TaskDryRunEstimate>,
+}
+
+/// Per-task dry-run estimate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskDryRunEstimate {
+    pub task_title: String,
+    pub llm_calls: usize,
+    pub criteria: Vec<CriterionDryRunEstimate>,
+}
+
+/// Per-criterion dry-run estimate (one LLM call)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CriterionDryRunEstimate {
+    pub criterion: String,
+    pub prompt_chars: usize,
+    pub prompt_tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost_usd: Option<f32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -448,11 +1263,182 @@ pub struct GradeResponse {
     pub percentage: u32,
     pub grade: String,
     pub tasks: Vec<TaskGradeResult>,
+    pub rubric_breakdown: BTreeMap<String, RubricCategory>,
     pub summary: String,
+    pub language: String,
+    pub is_late: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes_late: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
+    #[serde(default)]
+    pub partial: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regrade_of: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_grading_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<u64>,
+    pub persisted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchGradeResponse {
+    pub batch_id: String,
+    /// Ids of the queued grades, in request order
+    pub grade_ids: Vec<String>,
+    /// Requests whose grade could not be queued
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<BatchGradeFailure>,
+    pub created_at: u64,
+}
+
+/// A batch request whose grade was created but could not be queued
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchGradeFailure {
+    /// Position of the request in the batch
+    pub index: usize,
+    pub error: String,
+}
+
+/// Progress of every grade in a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchStatusResponse {
+    pub batch_id: String,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    /// Grades not yet completed or failed
+    pub in_progress: usize,
+    pub grades: Vec<BatchGradeStatus>,
+}
+
+/// How often instructors agreed with the grader, by grader confidence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    /// Overridden criteria across all persisted grades
+    pub total_overrides: u64,
+    /// Ten buckets of width 0.1, lowest confidence first
+    pub buckets: Vec<CalibrationBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationBucket {
+    pub min_confidence: f32,
+    pub max_confidence: f32,
+    pub overrides: u64,
+    /// Overrides whose verdict matched the grader's
+    pub agreed: u64,
+    /// `agreed / overrides`; `None` without overrides
+    pub agreement_rate: Option<f32>,
+}
+
+/// Query of `GET /api/grades/similarity`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimilarityQuery {
+    #[serde(rename = "metadata.course_title")]
+    pub course_title: String,
+    /// Minimum estimated similarity, 0.0-1.0
+    #[serde(default = "default_similarity_threshold")]
+    pub threshold: f32,
+    /// Most similar pairs returned
+    #[serde(default = "default_similarity_limit")]
+    pub limit: usize,
+}
+
+fn default_similarity_threshold() -> f32 {
+    crate::analysis::similarity::DEFAULT_SIMILARITY_THRESHOLD
+}
+fn default_similarity_limit() -> usize {
+    50
+}
+
+/// Submissions of a cohort that look alike
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarityReport {
+    pub course_title: String,
+    pub threshold: f32,
+    /// Fingerprinted submissions compared, the latest grade per repository
+    pub submissions: usize,
+    /// Pairs at or above the threshold, before `limit` was applied
+    pub total_pairs: usize,
+    /// Most similar first
+    pub pairs: Vec<SimilarPair>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarPair {
+    pub grade_ids: (String, String),
+    pub repo_urls: (String, String),
+    pub similarity: f32,
+    /// Files of the first submission whose content also appears in the second
+    pub overlapping_files: Vec<String>,
+}
+
+/// Confidence buckets in a [`CalibrationReport`]
+pub const CALIBRATION_BUCKETS: usize = 10;
+
+/// Bucket of `confidence`; 1.0 falls in the top bucket
+pub fn calibration_bucket(confidence: f32) -> usize {
+    ((confidence.clamp(0.0, 1.0) * CALIBRATION_BUCKETS as f32) as usize).min(CALIBRATION_BUCKETS - 1)
+}
+
+impl CalibrationReport {
+    /// Report from `(bucket, overrides, agreed)` counts; missing buckets are empty
+    pub fn from_counts(counts: impl IntoIterator<Item = (usize, u64, u64)>) -> Self {
+        let width = 1.0 / CALIBRATION_BUCKETS as f32;
+        let mut buckets: Vec<CalibrationBucket> = (0..CALIBRATION_BUCKETS)
+            .map(|i| CalibrationBucket {
+                min_confidence: i as f32 * width,
+                max_confidence: (i + 1) as f32 * width,
+                overrides: 0,
+                agreed: 0,
+                agreement_rate: None,
+            })
+            .collect();
+        for (bucket, overrides, agreed) in counts {
+            let bucket = &mut buckets[bucket.min(CALIBRATION_BUCKETS - 1)];
+            bucket.overrides += overrides;
+            bucket.agreed += agreed;
+        }
+        for bucket in &mut buckets {
+            bucket.agreement_rate =
+                (bucket.overrides > 0).then(|| bucket.agreed as f32 / bucket.overrides as f32);
+        }
+        Self {
+            total_overrides: buckets.iter().map(|b| b.overrides).sum(),
+            buckets,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchGradeStatus {
+    pub grade_id: String,
+    pub repo_url: String,
+    pub status: GradeStatus,
+    pub percentage: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
 
+/// Review and grade queued to run from a single clone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluateResponse {
+    pub review_id: String,
+    pub grade_id: String,
+    pub queue_position: usize,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -468,6 +1454,88 @@ mod tests {
         assert!(json.contains("test-123"));
     }
 
+    #[test]
+    fn test_check_filtered_serialization() {
+        let event = ReviewEvent::CheckFiltered {
+            check_type: CheckType::Typos,
+            original_count: 3,
+            removed_count: 3,
+            validator: "typo_validator".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "type": "check_filtered",
+                "check_type": "typos",
+                "original_count": 3,
+                "removed_count": 3,
+                "validator": "typo_validator",
+            })
+        );
+
+        // Summaries stored before `filtered` existed still load
+        let counts: SeverityCounts =
+            serde_json::from_str(r#"{"error": 1, "warning": 2, "info": 0}"#).unwrap();
+        assert_eq!(counts.filtered, 0);
+    }
+
+    fn submission(deadline: &str, submitted_at: &str) -> GradeMetadata {
+        serde_json::from_value(serde_json::json!({
+            "deadline": deadline,
+            "submitted_at": submitted_at,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_minutes_late() {
+        let on_time = submission("2024-03-01T23:59:00Z", "2024-03-01T20:15:00+09:00");
+        assert_eq!(on_time.minutes_late(), None);
+
+        let exactly = submission("2024-03-01T23:59:00Z", "2024-03-01T23:59:00Z");
+        assert_eq!(exactly.minutes_late(), None);
+
+        let late = submission("2024-03-01T23:59:00Z", "2024-03-02T00:00:00Z");
+        assert_eq!(late.minutes_late(), Some(1));
+
+        let missing = GradeMetadata {
+            submitted_at: late.submitted_at,
+            ..Default::default()
+        };
+        assert_eq!(missing.minutes_late(), None);
+
+        let json = serde_json::to_value(&late).unwrap();
+        assert_eq!(json["deadline"], "2024-03-01T23:59:00Z");
+    }
+
+    #[test]
+    fn test_event_envelope_serialization() {
+        let envelope = EventEnvelope::new(3, 1_760_688_000_000, GradeEvent::Queued { position: 2 });
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"seq": 3, "timestamp_ms": 1_760_688_000_000u64, "type": "queued", "position": 2})
+        );
+        let parsed: EventEnvelope<GradeEvent> = serde_json::from_value(json).unwrap();
+        assert_eq!((parsed.seq, parsed.timestamp_ms), (3, 1_760_688_000_000));
+        assert!(matches!(parsed.event, GradeEvent::Queued { position: 2 }));
+
+        // Events logged before envelopes existed load with zeroes
+        let legacy: EventEnvelope<GradeEvent> = serde_json::from_str(r#"{"type": "ping"}"#).unwrap();
+        assert_eq!((legacy.seq, legacy.timestamp_ms), (0, 0));
+    }
+
+    #[test]
+    fn test_backfill_seq() {
+        let mut events: Vec<_> = [0, 0, 5, 0, 2]
+            .into_iter()
+            .map(|seq| EventEnvelope::new(seq, 0, GradeEvent::Ping))
+            .collect();
+        backfill_seq(&mut events);
+        let seqs: Vec<u64> = events.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [1, 2, 5, 6, 7]);
+    }
+
     #[test]
     fn test_diagnostic_serialization() {
         let diagnostic = Diagnostic {
@@ -484,6 +1552,77 @@ mod tests {
         assert!(json.contains("src/main.rs"));
     }
 
+    #[test]
+    fn test_rank_suggestions() {
+        let suggestion = |title: &str, category: SuggestionCategory, priority: Priority| Suggestion {
+            category,
+            title: title.to_string(),
+            description: String::new(),
+            file: None,
+            line: None,
+            priority,
+            rationale: String::new(),
+            confidence: 0.5,
+        };
+
+        let mut suggestions = vec![
+            suggestion("a", SuggestionCategory::ProductIdea, Priority::Low),
+            suggestion("b", SuggestionCategory::Security, Priority::High),
+            suggestion("c", SuggestionCategory::Architecture, Priority::Medium),
+            suggestion("d", SuggestionCategory::Architecture, Priority::High),
+            suggestion("e", SuggestionCategory::Architecture, Priority::High),
+            suggestion("f", SuggestionCategory::TestCoverage, Priority::Medium),
+        ];
+        rank_suggestions(&mut suggestions, 4);
+
+        let titles: Vec<_> = suggestions.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, vec!["d", "e", "b", "c"]);
+
+        rank_suggestions(&mut suggestions, 10);
+        assert_eq!(suggestions.len(), 4);
+    }
+
+    #[test]
+    fn test_sort_diagnostics() {
+        let diag = |file: &str, line: u32, column: u32, severity: Severity| Diagnostic {
+            file: file.to_string(),
+            line,
+            column,
+            message: String::new(),
+            rule: "test".to_string(),
+            severity,
+            suggestion: None,
+        };
+
+        let mut diagnostics = vec![
+            diag("b.js", 1, 1, Severity::Info),
+            diag("a.js", 10, 1, Severity::Warning),
+            diag("b.js", 2, 1, Severity::Error),
+            diag("a.js", 2, 5, Severity::Warning),
+            diag("a.js", 2, 3, Severity::Warning),
+            diag("a.js", 1, 1, Severity::Info),
+            diag("b.js", 2, 1, Severity::Error),
+        ];
+        sort_diagnostics(&mut diagnostics);
+
+        let order: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.severity, d.file.as_str(), d.line, d.column))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (Severity::Error, "b.js", 2, 1),
+                (Severity::Error, "b.js", 2, 1),
+                (Severity::Warning, "a.js", 2, 3),
+                (Severity::Warning, "a.js", 2, 5),
+                (Severity::Warning, "a.js", 10, 1),
+                (Severity::Info, "a.js", 1, 1),
+                (Severity::Info, "b.js", 1, 1),
+            ]
+        );
+    }
+
     #[test]
     fn test_check_type_serialization() {
         let check = CheckType::Lint;
@@ -491,6 +1630,23 @@ mod tests {
         assert_eq!(json, "\"lint\"");
     }
 
+    #[test]
+    fn test_calibration_report() {
+        assert_eq!(calibration_bucket(0.0), 0);
+        assert_eq!(calibration_bucket(0.95), 9);
+        assert_eq!(calibration_bucket(1.0), 9);
+        assert_eq!(calibration_bucket(0.55), 5);
+
+        let report = CalibrationReport::from_counts([(9, 10, 8), (5, 4, 1), (9, 5, 4)]);
+        assert_eq!(report.total_overrides, 19);
+        assert_eq!(report.buckets.len(), CALIBRATION_BUCKETS);
+        assert_eq!(report.buckets[9].overrides, 15);
+        assert_eq!(report.buckets[9].agreement_rate, Some(0.8));
+        assert_eq!(report.buckets[5].agreement_rate, Some(0.25));
+        assert_eq!(report.buckets[0].agreement_rate, None);
+        assert!((report.buckets[9].min_confidence - 0.9).abs() < 1e-6);
+    }
+
     #[test]
     fn test_grade_event_serialization() {
         let event = GradeEvent::GradeStarted {
@@ -508,7 +1664,10 @@ mod tests {
     fn test_criterion_result_serialization() {
         let result = CriterionResult {
             criterion: "코드가 실행됨".to_string(),
+            criterion_id: Some("runs".to_string()),
+            task_index: 1,
             passed: true,
+            outcome: CriterionOutcome::Passed,
             confidence: 0.95,
             evidence: "package.json exists".to_string(),
             code_references: vec![CodeRef {
@@ -516,12 +1675,46 @@ mod tests {
                 line_start: 1,
                 line_end: 10,
                 snippet: None,
+                verified: true,
             }],
             weight: 1.0,
+            human_override: None,
         };
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("코드가 실행됨"));
         assert!(json.contains("package.json"));
+        assert!(json.contains(r#""outcome":"passed""#));
+
+        let parsed: CriterionResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.outcome, CriterionOutcome::Passed);
+        assert!(parsed.passed);
+        assert_eq!(parsed.criterion_id.as_deref(), Some("runs"));
+        assert_eq!(parsed.task_index, 1);
+    }
+
+    #[test]
+    fn test_criterion_result_outcome_compat() {
+        let parse = |json: serde_json::Value| serde_json::from_value::<CriterionResult>(json).unwrap();
+        let base = serde_json::json!({
+            "criterion": "Responds within 200ms",
+            "confidence": 0.5,
+            "evidence": "",
+            "weight": 1.0,
+        });
+
+        // Stored before `outcome` existed
+        let mut legacy = base.clone();
+        legacy["passed"] = true.into();
+        assert_eq!(parse(legacy.clone()).outcome, CriterionOutcome::Passed);
+        legacy["passed"] = false.into();
+        assert_eq!(parse(legacy).outcome, CriterionOutcome::Failed);
+
+        let mut current = base;
+        current["passed"] = false.into();
+        current["outcome"] = "not_verifiable".into();
+        let result = parse(current);
+        assert_eq!(result.outcome, CriterionOutcome::NotVerifiable);
+        assert!(!result.passed);
     }
 
     #[test]
@@ -531,4 +1724,37 @@ mod tests {
         assert_eq!(config.max_parallel_criteria, 10);
         assert_eq!(config.criterion_timeout_secs, 60);
     }
+
+    #[test]
+    fn test_grade_config_overrides_merge() {
+        let defaults = GradeConfig {
+            max_files: 80,
+            max_parallel_tasks: 2,
+            late_penalty_percentage: Some(10),
+            ..GradeConfig::default()
+        };
+        let request: GradeRequest = serde_json::from_value(serde_json::json!({
+            "repo_url": "https://github.com/test/repo",
+            "tasks": [],
+            "config": { "max_chars_per_file": 1000, "late_penalty_percentage": null }
+        }))
+        .unwrap();
+
+        let config = request.config_or(&defaults);
+        assert_eq!(config.max_chars_per_file, 1000);
+        assert_eq!(config.late_penalty_percentage, None);
+        // Fields the request leaves out keep the server's defaults
+        assert_eq!(config.max_files, 80);
+        assert_eq!(config.max_parallel_tasks, 2);
+
+        let request = GradeRequest { config: None, ..request };
+        assert_eq!(request.config_or(&defaults).max_files, 80);
+
+        // A full config replaces every field
+        let full = GradeConfigOverrides::from(GradeConfig::default());
+        assert_eq!(full.apply(&defaults).max_files, GradeConfig::default().max_files);
+
+        let invalid = serde_json::from_value::<GradeConfigOverrides>(serde_json::json!({ "max_files": "many" }));
+        assert!(invalid.is_err());
+    }
 }
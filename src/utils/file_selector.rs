@@ -0,0 +1,98 @@
+//! Ordering of source files by configured priority patterns
+//!
+//! Patterns are globs: `*` matches within one path segment, `**` across
+//! segments and `?` one character. A pattern without `/` is matched against
+//! the file name as well as the whole path, so `app.py` and `*.test.*` find
+//! files in any directory.
+
+use regex::Regex;
+
+pub struct PriorityFileSelector {
+    patterns: Vec<Regex>,
+}
+
+impl PriorityFileSelector {
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns.iter().map(|p| glob_regex(p)).collect(),
+        }
+    }
+
+    /// Priority points for `path`: one per matching pattern
+    pub fn score(&self, path: &str) -> usize {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.patterns
+            .iter()
+            .filter(|p| p.is_match(path) || p.is_match(name))
+            .count()
+    }
+
+    /// The `max` highest-scoring files. Files matching a pattern come first;
+    /// ties keep their original order.
+    pub fn select(files: &[(String, String)], patterns: &[String], max: usize) -> Vec<(String, String)> {
+        let selector = Self::new(patterns);
+        let mut scored: Vec<_> = files.iter().map(|f| (selector.score(&f.0), f)).collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().take(max).map(|(_, f)| f.clone()).collect()
+    }
+}
+
+fn glob_regex(pattern: &str) -> Regex {
+    let mut re = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directories at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).expect("escaped glob is a valid regex")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str) -> (String, String) {
+        (path.to_string(), String::new())
+    }
+
+    #[test]
+    fn test_glob_matching() {
+        let selector = PriorityFileSelector::new(&["src/main.*".to_string()]);
+        assert_eq!(selector.score("src/main.rs"), 1);
+        assert_eq!(selector.score("src/bin/main.rs"), 0);
+        assert_eq!(selector.score("main.rs"), 0);
+
+        let selector = PriorityFileSelector::new(&["*.test.*".to_string(), "**/api/*.ts".to_string()]);
+        assert_eq!(selector.score("web/src/app.test.ts"), 1);
+        assert_eq!(selector.score("web/api/app.test.ts"), 2);
+        assert_eq!(selector.score("api/routes.ts"), 1);
+        assert_eq!(selector.score("src/app.ts"), 0);
+    }
+
+    #[test]
+    fn test_select_puts_matches_first() {
+        let files = vec![file("src/util.py"), file("README.md"), file("app.py"), file("tests/test_app.py")];
+        let patterns = vec!["app.py".to_string(), "test_*.py".to_string()];
+
+        let selected = PriorityFileSelector::select(&files, &patterns, 3);
+        let paths: Vec<&str> = selected.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, vec!["app.py", "tests/test_app.py", "src/util.py"]);
+
+        let unchanged = PriorityFileSelector::select(&files, &[], 10);
+        assert_eq!(unchanged, files);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod file_selector;
+pub mod redact;
+
+pub use file_selector::PriorityFileSelector;
+pub use redact::PiiRedactor;
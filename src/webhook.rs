@@ -0,0 +1,122 @@
+//! GitHub webhook receiver support
+//!
+//! Deliveries are authenticated with the `X-Hub-Signature-256` HMAC of the
+//! raw body, since GitHub can't send the API's bearer token. Push events
+//! re-grade the grades subscribed to the pushed branch.
+
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sha2::Sha256;
+
+/// Secret shared with the GitHub webhook configuration
+pub struct GithubWebhook {
+    secret: SecretString,
+}
+
+impl GithubWebhook {
+    pub fn new(secret: SecretString) -> Self {
+        Self { secret }
+    }
+
+    /// Check a `sha256=<hex>` signature header against `body`
+    pub fn verify(&self, body: &[u8], signature: Option<&str>) -> bool {
+        verify_signature(self.secret.expose_secret().as_bytes(), body, signature)
+    }
+}
+
+fn verify_signature(secret: &[u8], body: &[u8], signature: Option<&str>) -> bool {
+    let Some(expected) = signature
+        .and_then(|s| s.strip_prefix("sha256="))
+        .and_then(|hex_digest| hex::decode(hex_digest).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// The parts of a `push` event used for re-grading
+#[derive(Debug, Clone, Deserialize)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    /// Commit the ref points to after the push
+    pub after: String,
+    /// The push deleted the ref
+    #[serde(default)]
+    pub deleted: bool,
+    pub repository: PushRepository,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PushRepository {
+    /// `owner/repo`
+    pub full_name: String,
+    pub default_branch: String,
+}
+
+impl PushEvent {
+    /// Pushed branch; `None` for tags
+    pub fn branch(&self) -> Option<&str> {
+        self.git_ref.strip_prefix("refs/heads/")
+    }
+
+    /// Lowercase `owner/repo`, the key subscriptions are stored under
+    pub fn repo(&self) -> String {
+        self.repository.full_name.to_lowercase()
+    }
+
+    /// Whether a grade of `branch` (`None` = default branch) should be re-run
+    pub fn updates_branch(&self, branch: Option<&str>) -> bool {
+        if self.deleted {
+            return false;
+        }
+        let graded = branch.unwrap_or(&self.repository.default_branch);
+        self.branch() == Some(graded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUSH: &str = include_str!("../tests/fixtures/github/push.json");
+
+    #[test]
+    fn test_verify_signature() {
+        // Example from GitHub's webhook validation docs
+        let secret = b"It's a Secret to Everybody";
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+        assert!(verify_signature(secret, b"Hello, World!", Some(signature)));
+        assert!(!verify_signature(secret, b"Hello, World?", Some(signature)));
+        assert!(!verify_signature(b"other secret", b"Hello, World!", Some(signature)));
+        assert!(!verify_signature(secret, b"Hello, World!", Some(&signature[7..])));
+        assert!(!verify_signature(secret, b"Hello, World!", None));
+    }
+
+    #[test]
+    fn test_push_event_fixture() {
+        let push: PushEvent = serde_json::from_str(PUSH).unwrap();
+
+        assert_eq!(push.branch(), Some("main"));
+        assert_eq!(push.repo(), "student-42/todo-app");
+        assert_eq!(push.after, "9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c");
+        assert!(push.updates_branch(None));
+        assert!(push.updates_branch(Some("main")));
+        assert!(!push.updates_branch(Some("dev")));
+
+        let tag = PushEvent {
+            git_ref: "refs/tags/v1.0".to_string(),
+            ..push.clone()
+        };
+        assert!(!tag.updates_branch(None));
+
+        let deleted = PushEvent { deleted: true, ..push };
+        assert!(!deleted.updates_branch(Some("main")));
+    }
+}
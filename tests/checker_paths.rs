@@ -0,0 +1,44 @@
+//! Checkers report files relative to the repository root
+
+use api_server::checkers::all_checkers;
+use api_server::files::FileFilter;
+use std::fs;
+use tempfile::TempDir;
+
+fn fixture_repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("src/db")).unwrap();
+    fs::write(
+        dir.path().join("src/app.js"),
+        "// TODO: remove\nfunction main() {\n  debugger;\n  var recieve = 1;\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("src/db/query.py"),
+        format!(
+            "import os\n\ndef find(cursor, name):\n    # FIXME: teh query\n    cursor.execute(f\"SELECT * FROM users WHERE name = '{{name}}'\")\n    return {}\n",
+            "x".repeat(200)
+        ),
+    )
+    .unwrap();
+    fs::write(dir.path().join("package.json"), r#"{"name": "app", "scripts": {}}"#).unwrap();
+    dir
+}
+
+#[test]
+fn test_diagnostics_use_repo_relative_paths() {
+    let fixture = fixture_repo();
+    let prefix = fixture.path().to_string_lossy().to_string();
+
+    let diagnostics: Vec<_> = all_checkers()
+        .iter()
+        .flat_map(|checker| checker.check(fixture.path(), FileFilter::default()))
+        .collect();
+
+    assert!(diagnostics.iter().any(|d| d.file.starts_with("src/db/")));
+    for d in &diagnostics {
+        assert!(!d.file.contains(&prefix), "{} contains the repo path", d.file);
+        assert!(!d.file.starts_with('/'), "{} is absolute", d.file);
+        assert!(!d.file.contains('\\'), "{} uses backslashes", d.file);
+    }
+}
@@ -0,0 +1,72 @@
+//! CORS preflight caching and `Vary` headers on the API routes
+
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{test, App};
+use api_server::api;
+use api_server::config::ServerConfig;
+use api_server::cors::build_cors;
+
+const ORIGIN: &str = "https://app.example.com";
+
+#[actix_web::test]
+async fn test_grade_preflight_is_cacheable() {
+    let server = ServerConfig {
+        cors_origins: vec![ORIGIN.to_string()],
+        ..ServerConfig::default()
+    };
+    let app = test::init_service(
+        App::new()
+            .wrap(build_cors(&server.cors_origins, server.cors_max_age_secs))
+            .configure(api::configure),
+    )
+    .await;
+
+    let preflight = test::TestRequest::default()
+        .method(Method::OPTIONS)
+        .uri("/api/grade")
+        .insert_header((header::ORIGIN, ORIGIN))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type, idempotency-key"))
+        .to_request();
+    let response = test::call_service(&app, preflight).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "86400");
+    let mut methods: Vec<_> = headers
+        .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .split(',')
+        .map(str::trim)
+        .collect();
+    methods.sort_unstable();
+    assert_eq!(methods, ["DELETE", "GET", "OPTIONS", "POST"]);
+    assert_eq!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), ORIGIN);
+    assert!(headers.get(header::VARY).unwrap().to_str().unwrap().contains("Origin"));
+
+    // Checker aborts and grade unsubscribes are DELETEs
+    for uri in ["/api/review/r1/checker/security", "/api/grade/g1/subscribe"] {
+        let preflight = test::TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri(uri)
+            .insert_header((header::ORIGIN, ORIGIN))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE"))
+            .to_request();
+        let response = test::call_service(&app, preflight).await;
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        assert_eq!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), ORIGIN);
+    }
+
+    // Plain responses vary on Origin too, with or without one
+    for origin in [Some(ORIGIN), None] {
+        let mut request = test::TestRequest::get().uri("/api/health");
+        if let Some(origin) = origin {
+            request = request.insert_header((header::ORIGIN, origin));
+        }
+        let response = test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::VARY).unwrap().to_str().unwrap().contains("Origin"));
+    }
+}
@@ -0,0 +1,101 @@
+//! Event ordering contract for the review SSE stream
+
+use api_server::config::ProvidersConfig;
+use api_server::git::ClonedRepo;
+use api_server::orchestrator::ReviewStore;
+use api_server::types::{CheckType, EventEnvelope, ReviewEvent};
+use secrecy::SecretString;
+use std::fs;
+use std::time::Instant;
+use tempfile::TempDir;
+
+fn fixture_repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    fs::write(
+        dir.path().join("src/app.js"),
+        "// TODO: remove\nfunction main() {\n  debugger;\n  var recieve = 1;\n}\n",
+    )
+    .unwrap();
+    dir
+}
+
+/// Provider pointing at a closed port: validators start and fail fast
+fn unreachable_provider() -> ProvidersConfig {
+    ProvidersConfig {
+        openai_api_key: None,
+        anthropic_api_key: None,
+        opencode_api_key: Some(SecretString::from("test-key")),
+        opencode_base_url: Some("http://127.0.0.1:1".to_string()),
+        default_timeout_secs: 5,
+        ..ProvidersConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn test_check_events_stream_in_order() {
+    let fixture = fixture_repo();
+    let store = ReviewStore::new(3600, Some(unreachable_provider()), None);
+    let id = store
+        .create_review("https://github.com/test/repo".to_string())
+        .await;
+    let mut receiver = store.subscribe(&id).await.unwrap();
+
+    let repo = ClonedRepo::from_local(fixture.path().to_path_buf()).unwrap();
+    store
+        .run_review_with_repo(&id, &repo, Instant::now())
+        .await
+        .unwrap();
+
+    let mut envelopes = Vec::new();
+    while let Ok(envelope) = receiver.try_recv() {
+        envelopes.push(envelope);
+    }
+    // Stamped in emission order, matching the tail of the review's event log
+    // (it also holds events sent before subscribing)
+    for pair in envelopes.windows(2) {
+        assert_eq!(pair[1].seq, pair[0].seq + 1);
+        assert!(pair[1].timestamp_ms >= pair[0].timestamp_ms);
+    }
+    let seqs = |envelopes: &[EventEnvelope<ReviewEvent>]| envelopes.iter().map(|e| e.seq).collect::<Vec<_>>();
+    let logged = store.get_review(&id).await.unwrap().events();
+    assert!(seqs(&logged).ends_with(&seqs(&envelopes)));
+    let events: Vec<ReviewEvent> = envelopes.into_iter().map(|envelope| envelope.event).collect();
+
+    let checks: Vec<(usize, &ReviewEvent)> = events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| {
+            matches!(
+                e,
+                ReviewEvent::CheckStarted { .. } | ReviewEvent::CheckCompleted { .. }
+            )
+        })
+        .collect();
+    assert!(!checks.is_empty());
+
+    // Every check is a Started/Completed pair for the same check type
+    let mut started: Vec<CheckType> = Vec::new();
+    for pair in checks.chunks(2) {
+        match pair {
+            [(_, ReviewEvent::CheckStarted { check_type: a }), (_, ReviewEvent::CheckCompleted { check_type: b, .. })] =>
+            {
+                assert_eq!(a, b);
+                started.push(*a);
+            }
+            other => panic!("unexpected check events: {:?}", other),
+        }
+    }
+    assert!(started.contains(&CheckType::Lint));
+
+    let last_check = checks.last().unwrap().0;
+    let first_validation = events
+        .iter()
+        .position(|e| matches!(e, ReviewEvent::ValidationStarted { .. }))
+        .expect("validators should run");
+    assert!(last_check < first_validation);
+    assert!(matches!(
+        events.last(),
+        Some(ReviewEvent::ReviewCompleted { .. })
+    ));
+}